@@ -0,0 +1,384 @@
+///
+/// This module contains the auto-clicker that clicks a button at a given
+/// rate on its own thread until it is stopped
+///
+/// The clicks are scheduled by their deadlines rather than by sleeping
+/// between them, so the time that the backends spend in a click (e.g.
+/// the sync of uinput) does not slow down the rate, and the missed
+/// deadlines are skipped instead of being clicked in a burst
+///
+use crate::builder::MouseBuilder;
+use crate::common::{MouseActions, MouseButton};
+use crate::error::Error;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Clicks a button at a given rate
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use mouce::autoclick::AutoClicker;
+/// use mouce::common::MouseButton;
+/// use std::time::Duration;
+///
+/// // Click 12 times a second for 5 seconds, each interval varies by up to 10%
+/// let clicker = AutoClicker::new(MouseButton::Left)
+///     .cps(12.)
+///     .jitter(0.1)
+///     .duration(Duration::from_secs(5))
+///     .start()
+///     .unwrap();
+/// let clicks = clicker.wait().unwrap();
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AutoClicker {
+    button: MouseButton,
+    cps: f64,
+    jitter: f64,
+    duration: Option<Duration>,
+    count: Option<u64>,
+}
+
+impl AutoClicker {
+    /// Create an auto-clicker that clicks the given button 10 times a second until it is stopped
+    pub fn new(button: MouseButton) -> Self {
+        AutoClicker {
+            button,
+            cps: 10.,
+            jitter: 0.,
+            duration: None,
+            count: None,
+        }
+    }
+
+    /// Set the number of clicks per second
+    pub fn cps(mut self, cps: f64) -> Self {
+        self.cps = cps;
+        self
+    }
+
+    /// Vary each interval between the clicks randomly by up to the given
+    /// fraction of it, e.g. `0.1` for 10%, the average rate stays the same
+    pub fn jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Stop clicking once the given duration has passed since the start
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+
+    /// Stop clicking after the given number of clicks
+    pub fn count(mut self, count: u64) -> Self {
+        self.count = Some(count);
+        self
+    }
+
+    /// Start clicking with a mouse manager of the current platform,
+    /// the manager is created on the thread of the auto-clicker
+    pub fn start(self) -> Result<AutoClickerHandle, Error> {
+        self.spawn(|| MouseBuilder::new().build())
+    }
+
+    /// Start clicking with the given mouse manager
+    pub fn start_with(
+        self,
+        manager: Box<dyn MouseActions + Send>,
+    ) -> Result<AutoClickerHandle, Error> {
+        self.spawn(move || Ok(manager))
+    }
+
+    fn spawn<F>(self, manager: F) -> Result<AutoClickerHandle, Error>
+    where
+        F: FnOnce() -> Result<Box<dyn MouseActions>, Error> + Send + 'static,
+    {
+        if !self.cps.is_finite() || self.cps <= 0. {
+            return Err(Error::CustomError(
+                "the clicks per second must be a positive number",
+            ));
+        }
+        if self.jitter.is_nan() {
+            return Err(Error::CustomError("the jitter is not a number"));
+        }
+
+        let (stop, stopped) = mpsc::channel();
+        let clicks = Arc::new(AtomicU64::new(0));
+        let counter = clicks.clone();
+        let thread = thread::spawn(move || {
+            let manager = manager()?;
+            self.run(manager.as_ref(), &stopped, &counter)
+        });
+        Ok(AutoClickerHandle {
+            stop: Some(stop),
+            thread: Some(thread),
+            clicks,
+        })
+    }
+
+    fn run(
+        &self,
+        manager: &dyn MouseActions,
+        stopped: &Receiver<()>,
+        clicks: &AtomicU64,
+    ) -> Result<(), Error> {
+        let interval = Duration::from_secs_f64(1. / self.cps);
+        let jitter = self.jitter.clamp(0., 1.);
+        let mut random = Random::new();
+
+        let start = Instant::now();
+        let end = self.duration.map(|duration| start + duration);
+        let mut deadline = start;
+        loop {
+            let count = clicks.load(Ordering::SeqCst);
+            if self.count.is_some_and(|limit| count >= limit)
+                || end.is_some_and(|end| deadline >= end)
+            {
+                return Ok(());
+            }
+
+            // Wait for the deadline, unless the auto-clicker is stopped first
+            let now = Instant::now();
+            let stop = if deadline > now {
+                !matches!(
+                    stopped.recv_timeout(deadline - now),
+                    Err(RecvTimeoutError::Timeout)
+                )
+            } else {
+                !matches!(stopped.try_recv(), Err(TryRecvError::Empty))
+            };
+            if stop {
+                return Ok(());
+            }
+
+            manager.click_button(&self.button)?;
+            clicks.fetch_add(1, Ordering::SeqCst);
+            deadline = (deadline + jittered(interval, jitter, random.next())).max(Instant::now());
+        }
+    }
+}
+
+/// Vary the given interval by up to `jitter` of it, `random` is in the range from `0.0` to `1.0`
+fn jittered(interval: Duration, jitter: f64, random: f64) -> Duration {
+    interval.mul_f64(1. + jitter * (2. * random - 1.))
+}
+
+/// A xorshift generator, the jitter does not need a better one
+struct Random(u64);
+
+impl Random {
+    fn new() -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_nanos() as u64)
+            .unwrap_or_default();
+        // The state of a xorshift generator must not be zero
+        Random(seed | 1)
+    }
+
+    /// Get a random number in the range from `0.0` to `1.0`
+    fn next(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// A running auto-clicker, dropping the handle stops it as well
+#[derive(Debug)]
+pub struct AutoClickerHandle {
+    /// The auto-clicker stops once this end of the channel is dropped
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<Result<(), Error>>>,
+    clicks: Arc<AtomicU64>,
+}
+
+impl AutoClickerHandle {
+    /// Get the number of clicks so far
+    pub fn clicks(&self) -> u64 {
+        self.clicks.load(Ordering::SeqCst)
+    }
+
+    /// Check if the auto-clicker has stopped by itself, either because
+    /// of its stop conditions or because a click failed
+    pub fn is_finished(&self) -> bool {
+        self.thread
+            .as_ref()
+            .is_none_or(|thread| thread.is_finished())
+    }
+
+    /// Stop clicking and wait for the thread to finish, returns the number of clicks
+    ///
+    /// A click that is in progress is finished first, so no button is left pressed down
+    pub fn stop(mut self) -> Result<u64, Error> {
+        self.stop.take();
+        self.join()
+    }
+
+    /// Wait until the auto-clicker stops by itself, returns the number of clicks
+    ///
+    /// The auto-clicker never stops by itself without a duration or a count
+    pub fn wait(mut self) -> Result<u64, Error> {
+        self.join()
+    }
+
+    fn join(&mut self) -> Result<u64, Error> {
+        if let Some(thread) = self.thread.take() {
+            thread
+                .join()
+                .map_err(|_| Error::CustomError("the auto-clicker thread panicked"))??;
+        }
+        Ok(self.clicks())
+    }
+}
+
+impl Drop for AutoClickerHandle {
+    fn drop(&mut self) {
+        self.stop.take();
+        let _ = self.join();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{jittered, AutoClicker, Random};
+    use crate::common::{
+        CallbackId, EventCallback, MouseActions, MouseButton, ScrollDirection, ScrollUnit,
+    };
+    use crate::error::Error;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    /// A manager that counts the presses, the releases fail after `fail_after` of them
+    struct TestMouseManager {
+        presses: Arc<AtomicU32>,
+        fail_after: u32,
+    }
+
+    impl TestMouseManager {
+        fn boxed(fail_after: u32) -> (Box<TestMouseManager>, Arc<AtomicU32>) {
+            let presses = Arc::new(AtomicU32::new(0));
+            let manager = TestMouseManager {
+                presses: presses.clone(),
+                fail_after,
+            };
+            (Box::new(manager), presses)
+        }
+    }
+
+    impl MouseActions for TestMouseManager {
+        fn move_to(&self, _x: usize, _y: usize) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn get_position(&self) -> Result<(i32, i32), Error> {
+            Ok((0, 0))
+        }
+
+        fn press_button(&self, _button: &MouseButton) -> Result<(), Error> {
+            self.presses.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        fn release_button(&self, _button: &MouseButton) -> Result<(), Error> {
+            if self.presses.load(Ordering::SeqCst) > self.fail_after {
+                return Err(Error::WriteFailed);
+            }
+            Ok(())
+        }
+
+        fn scroll_wheel(
+            &self,
+            _direction: &ScrollDirection,
+            _unit: ScrollUnit,
+            _distance: u32,
+        ) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn hook_with_info(&mut self, _callback: EventCallback) -> Result<CallbackId, Error> {
+            Err(Error::NotImplemented)
+        }
+
+        fn unhook(&mut self, _callback_id: CallbackId) -> Result<(), Error> {
+            Err(Error::NotImplemented)
+        }
+
+        fn unhook_all(&mut self) -> Result<(), Error> {
+            Err(Error::NotImplemented)
+        }
+    }
+
+    #[test]
+    fn stop_conditions() {
+        let (manager, presses) = TestMouseManager::boxed(u32::MAX);
+        let clicker = AutoClicker::new(MouseButton::Left).cps(1000.).count(5);
+        assert_eq!(clicker.start_with(manager).unwrap().wait(), Ok(5));
+        assert_eq!(presses.load(Ordering::SeqCst), 5);
+
+        // The first click is right at the start, the second one would be after the duration
+        let (manager, _) = TestMouseManager::boxed(u32::MAX);
+        let clicker = AutoClicker::new(MouseButton::Left)
+            .cps(0.5)
+            .duration(Duration::from_millis(100));
+        assert_eq!(clicker.start_with(manager).unwrap().wait(), Ok(1));
+
+        let (manager, _) = TestMouseManager::boxed(2);
+        let clicker = AutoClicker::new(MouseButton::Left).cps(1000.);
+        assert_eq!(
+            clicker.start_with(manager).unwrap().wait(),
+            Err(Error::WriteFailed)
+        );
+    }
+
+    #[test]
+    fn stop_while_waiting() {
+        let (manager, presses) = TestMouseManager::boxed(u32::MAX);
+        let handle = AutoClicker::new(MouseButton::Right)
+            .cps(0.1)
+            .start_with(manager)
+            .unwrap();
+        while handle.clicks() == 0 {
+            std::thread::yield_now();
+        }
+
+        // The next click would be ten seconds later
+        let start = Instant::now();
+        assert_eq!(handle.stop(), Ok(1));
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(presses.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn invalid_options() {
+        for clicker in [
+            AutoClicker::new(MouseButton::Left).cps(0.),
+            AutoClicker::new(MouseButton::Left).cps(f64::INFINITY),
+            AutoClicker::new(MouseButton::Left).jitter(f64::NAN),
+        ] {
+            let (manager, _) = TestMouseManager::boxed(u32::MAX);
+            assert!(clicker.start_with(manager).is_err());
+        }
+    }
+
+    #[test]
+    fn jitter_bounds() {
+        let interval = Duration::from_millis(100);
+        assert_eq!(jittered(interval, 0.1, 0.), Duration::from_millis(90));
+        assert_eq!(jittered(interval, 0.1, 0.5), interval);
+        assert_eq!(jittered(interval, 0., 0.9), interval);
+
+        let mut random = Random::new();
+        for _ in 0..1000 {
+            let value = random.next();
+            assert!((0. ..1.).contains(&value));
+        }
+    }
+}
@@ -1,8 +1,16 @@
+use std::fs;
+use std::sync::mpsc::{self, RecvTimeoutError};
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 #[cfg(feature = "cli")]
 use clap::{Arg, Command};
+use mouce::common::{EventInfo, EventMask, MouseButton, MouseEvent, ScrollDirection, ScrollUnit};
+use mouce::recorder::{PlayOptions, Player, Recorder, Session};
+use mouce::script::Script;
+
+/// How often the recorded events are saved while recording
+const SAVE_INTERVAL: Duration = Duration::from_millis(100);
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let app = Command::new("mouce")
@@ -57,10 +65,73 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     .long("direction")
                     .short('d')
                 .takes_value(true))
+                .arg(Arg::new("unit")
+                    .long("unit")
+                    .short('u')
+                    .help("The unit of the distance, line or pixel")
+                    .default_value("line")
+                .takes_value(true))
+                .arg(Arg::new("distance")
+                    .long("distance")
+                    .short('n')
+                    .default_value("1")
+                .takes_value(true))
+        )
+        .subcommand(
+            Command::new("run_script")
+                .about("Run the given commands, e.g. \"move 100 200; click left; sleep 250; scroll down 3\"")
+                .arg(Arg::new("script")
+                    .long("script")
+                    .short('s')
+                .takes_value(true))
         )
         .subcommand(
             Command::new("listen")
                 .about("Listen mouse events and print them to the terminal")
+                .arg(Arg::new("format")
+                    .long("format")
+                    .short('f')
+                    .help("The format of the printed events, json prints an object per line")
+                    .possible_values(["debug", "json"])
+                    .default_value("debug")
+                .takes_value(true))
+                .arg(Arg::new("events")
+                    .long("events")
+                    .short('e')
+                    .help("The comma separated kinds of events to print, move, press, release, scroll or all")
+                    .default_value("all")
+                .takes_value(true))
+        )
+        .subcommand(
+            Command::new("record")
+                .about("Record the mouse events into the given file until the stop button is pressed or Ctrl-C, the events are saved as they are recorded")
+                .arg(Arg::new("output")
+                    .long("output")
+                    .short('o')
+                    .required(true)
+                .takes_value(true))
+                .arg(Arg::new("stop_button")
+                    .long("stop_button")
+                    .short('s')
+                    .help("The button whose press stops the recording, the press is not recorded")
+                .takes_value(true))
+        )
+        .subcommand(
+            Command::new("play")
+                .about("Play the mouse events of a file that is recorded with `record`, with their original timing")
+                .arg(Arg::new("session")
+                    .required(true)
+                .takes_value(true))
+                .arg(Arg::new("speed")
+                    .long("speed")
+                    .help("The multiplier of the playback speed, e.g. 2 plays the events twice as fast")
+                    .default_value("1")
+                .takes_value(true))
+                .arg(Arg::new("repeat")
+                    .long("repeat")
+                    .short('r')
+                    .default_value("1")
+                .takes_value(true))
         );
 
     let mut mouse_manager = mouce::Mouse::new();
@@ -77,34 +148,93 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             println!("{x} {y}");
         }
         Some(("press_button", sub_matches)) => {
-            let button_arg: String = sub_matches.value_of_t_or_exit("button");
-            let button = get_mouse_button(&button_arg)?;
+            let button: MouseButton = sub_matches.value_of_t_or_exit("button");
             mouse_manager.press_button(&button)?;
         }
         Some(("release_button", sub_matches)) => {
-            let button_arg: String = sub_matches.value_of_t_or_exit("button");
-            let button = get_mouse_button(&button_arg)?;
+            let button: MouseButton = sub_matches.value_of_t_or_exit("button");
             mouse_manager.release_button(&button)?;
         }
         Some(("click_button", sub_matches)) => {
-            let button_arg: String = sub_matches.value_of_t_or_exit("button");
-            let button = get_mouse_button(&button_arg)?;
+            let button: MouseButton = sub_matches.value_of_t_or_exit("button");
             mouse_manager.click_button(&button)?;
         }
         Some(("scroll_wheel", sub_matches)) => {
-            let direction_arg: String = sub_matches.value_of_t_or_exit("direction");
-            let direction = get_scroll_direction(&direction_arg)?;
-            mouse_manager.scroll_wheel(&direction)?;
+            let direction: ScrollDirection = sub_matches.value_of_t_or_exit("direction");
+            let unit: ScrollUnit = sub_matches.value_of_t_or_exit("unit");
+            let distance: u32 = sub_matches.value_of_t_or_exit("distance");
+            mouse_manager.scroll_wheel(&direction, unit, distance)?;
+        }
+        Some(("run_script", sub_matches)) => {
+            let script: Script = sub_matches.value_of_t_or_exit("script");
+            script.run(mouse_manager.as_ref())?;
         }
-        Some(("listen", _)) => {
-            mouse_manager.hook(Box::new(|event| {
-                println!("{:?}", event);
-            }))?;
+        Some(("listen", sub_matches)) => {
+            let json = sub_matches.value_of("format") == Some("json");
+            let events: EventMask = sub_matches.value_of_t_or_exit("events");
+            // The backends that filter at the source do not even listen to the other events
+            mouse_manager.hook_filtered(
+                events,
+                Box::new(move |event, info| {
+                    if json {
+                        println!("{}", json_event(event, info));
+                    } else {
+                        println!("{:?}", event);
+                    }
+                }),
+            )?;
             loop {
                 // Call sleep to avoid heavy cpu load
                 sleep(Duration::from_secs(u64::max_value()));
             }
         }
+        Some(("record", sub_matches)) => {
+            let output: String = sub_matches.value_of_t_or_exit("output");
+            let stop_button: Option<MouseButton> = sub_matches
+                .is_present("stop_button")
+                .then(|| sub_matches.value_of_t_or_exit("stop_button"));
+
+            let recorder = Recorder::start(mouse_manager.as_mut())?;
+            let (stop, stopped) = mpsc::channel();
+            if let Some(button) = stop_button {
+                let stop = stop.clone();
+                mouse_manager.hook(Box::new(move |event| {
+                    if *event == MouseEvent::Press(button) {
+                        let _ = stop.send(());
+                    }
+                }))?;
+            }
+
+            // Ctrl-C ends the process, so the events are saved while they are recorded
+            let mut saved = 0;
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(SAVE_INTERVAL) {
+                let session = recorder.session();
+                if session.events.len() != saved {
+                    fs::write(&output, session.to_string())?;
+                    saved = session.events.len();
+                }
+            }
+            drop(stop);
+
+            let mut session = recorder.stop(mouse_manager.as_mut())?;
+            // The press of the stop button is recorded before it stops the recording
+            if let Some(index) = session
+                .events
+                .iter()
+                .rposition(|recorded| stop_button.map(MouseEvent::Press) == Some(recorded.event))
+            {
+                session.events.truncate(index);
+            }
+            fs::write(&output, session.to_string())?;
+        }
+        Some(("play", sub_matches)) => {
+            let path: String = sub_matches.value_of_t_or_exit("session");
+            let session: Session = fs::read_to_string(path)?.parse()?;
+            let options = PlayOptions::new()
+                .speed(sub_matches.value_of_t_or_exit("speed"))
+                .repeat(sub_matches.value_of_t_or_exit("repeat"));
+            Player::new(mouse_manager.as_ref()).play(&session, &options)?;
+        }
         _ => {
             panic!("unknown subcommand, please see mouce --help");
         }
@@ -113,37 +243,55 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-fn get_mouse_button(
-    button: &str,
-) -> Result<mouce::common::MouseButton, Box<dyn std::error::Error>> {
-    match button {
-        "left" => Ok(mouce::common::MouseButton::Left),
-        "right" => Ok(mouce::common::MouseButton::Right),
-        "middle" => Ok(mouce::common::MouseButton::Middle),
-        _ => Err(Box::new(std::io::Error::new(
-            std::io::ErrorKind::InvalidInput,
-            format!(
-                "{} is not accepted as a button, please use left, right or middle",
-                button
-            ),
-        ))),
-    }
-}
-
-fn get_scroll_direction(
-    direction: &str,
-) -> Result<mouce::common::ScrollDirection, Box<dyn std::error::Error>> {
-    match direction {
-        "up" => Ok(mouce::common::ScrollDirection::Up),
-        "down" => Ok(mouce::common::ScrollDirection::Down),
-        "right" => Ok(mouce::common::ScrollDirection::Right),
-        "left" => Ok(mouce::common::ScrollDirection::Left),
-        _ => Err(Box::new(std::io::Error::new(
-            std::io::ErrorKind::InvalidInput,
-            format!(
-                "{} is not accepted as a direction, please use up, down, right or left",
-                direction
-            ),
-        ))),
-    }
+/// Format the given event as a JSON object, the coordinates are `null` if they are not known
+fn json_event(event: &MouseEvent, info: &EventInfo) -> String {
+    let position = |position: Option<(i32, i32)>| match position.or(info.position) {
+        Some((x, y)) => format!("\"x\":{x},\"y\":{y}"),
+        None => String::from("\"x\":null,\"y\":null"),
+    };
+    let fields = match event {
+        MouseEvent::Move {
+            position: event_position,
+            delta: (dx, dy),
+        } => format!(
+            "\"type\":\"move\",{},\"dx\":{dx},\"dy\":{dy}",
+            position(*event_position)
+        ),
+        MouseEvent::Press(button) => {
+            format!("\"type\":\"press\",\"button\":\"{button}\",{}", position(None))
+        }
+        MouseEvent::Release(button) => {
+            format!("\"type\":\"release\",\"button\":\"{button}\",{}", position(None))
+        }
+        MouseEvent::Drag {
+            button,
+            position: event_position,
+            delta: (dx, dy),
+        } => format!(
+            "\"type\":\"drag\",\"button\":\"{button}\",{},\"dx\":{dx},\"dy\":{dy}",
+            position(*event_position)
+        ),
+        MouseEvent::DoubleClick {
+            button,
+            position: event_position,
+        } => format!(
+            "\"type\":\"double_click\",\"button\":\"{button}\",{}",
+            position(*event_position)
+        ),
+        MouseEvent::Scroll {
+            direction,
+            unit,
+            distance,
+        } => format!(
+            "\"type\":\"scroll\",\"direction\":\"{direction}\",\"unit\":\"{unit}\",\"distance\":{distance},{}",
+            position(None)
+        ),
+        MouseEvent::ListenerInterrupted => String::from("\"type\":\"listener_interrupted\""),
+    };
+    // The milliseconds since the Unix epoch, the backends do not share a clock for the events
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    format!("{{{fields},\"timestamp\":{timestamp}}}")
 }
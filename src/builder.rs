@@ -0,0 +1,308 @@
+///
+/// This module contains the builder that configures
+/// the platform specific options of the mouse managers
+///
+/// Options that do not apply to the current platform are ignored
+///
+use crate::common::{
+    Backpressure, CallbackExecution, EventSourceState, MouseActions, MoveCoalescing,
+    ScrollInversion,
+};
+use crate::error::Error;
+use crate::pacing::Pacing;
+use std::time::Duration;
+
+/// The maximum length of the uinput device name, excluding the null terminator
+pub(crate) const MAX_DEVICE_NAME_LENGTH: usize = 79;
+/// The number of the events that the listener queues for the callbacks by default
+const DEFAULT_LISTENER_CAPACITY: usize = 1024;
+/// The longest time that the creation of the uinput device waits for it by default
+const DEFAULT_UINPUT_READY_TIMEOUT: Duration = Duration::from_millis(300);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MouseBuilder {
+    pub(crate) device_name: String,
+    pub(crate) vendor: u16,
+    pub(crate) product: u16,
+    pub(crate) uinput_ready_timeout: Duration,
+    pub(crate) share_uinput_device: bool,
+    pub(crate) x11_display: Option<String>,
+    pub(crate) x11_screen: Option<i32>,
+    pub(crate) tap_listen_only: bool,
+    pub(crate) event_source_state: EventSourceState,
+    pub(crate) post_move_events: bool,
+    pub(crate) hook_module_handle: bool,
+    pub(crate) pacing: Pacing,
+    pub(crate) scroll_inversion: ScrollInversion,
+    pub(crate) callback_execution: CallbackExecution,
+    pub(crate) move_coalescing: MoveCoalescing,
+    pub(crate) listener_capacity: usize,
+    pub(crate) backpressure: Backpressure,
+}
+
+impl MouseBuilder {
+    pub fn new() -> Self {
+        MouseBuilder {
+            device_name: String::from("mouce-library-fake-mouse"),
+            // Random vendor and product
+            vendor: 0x2222,
+            product: 0x3333,
+            uinput_ready_timeout: DEFAULT_UINPUT_READY_TIMEOUT,
+            share_uinput_device: false,
+            x11_display: None,
+            x11_screen: None,
+            tap_listen_only: true,
+            event_source_state: EventSourceState::CombinedSession,
+            post_move_events: true,
+            hook_module_handle: false,
+            pacing: Pacing::new(),
+            scroll_inversion: ScrollInversion::NONE,
+            callback_execution: CallbackExecution::Inline,
+            move_coalescing: MoveCoalescing::None,
+            listener_capacity: DEFAULT_LISTENER_CAPACITY,
+            backpressure: Backpressure::Block,
+        }
+    }
+
+    /// Set the name of the virtual device (uinput only)
+    ///
+    /// The name can be at most 79 bytes long
+    pub fn device_name(mut self, name: &str) -> Self {
+        self.device_name = name.to_string();
+        self
+    }
+
+    /// Set the vendor id of the virtual device (uinput only)
+    pub fn vendor(mut self, vendor: u16) -> Self {
+        self.vendor = vendor;
+        self
+    }
+
+    /// Set the product id of the virtual device (uinput only)
+    pub fn product(mut self, product: u16) -> Self {
+        self.product = product;
+        self
+    }
+
+    /// Set how long the creation of the virtual device waits at most for the
+    /// system to pick the device up (uinput only), 300 milliseconds by default
+    ///
+    /// The events that are injected before that are not noticed by the
+    /// desktop, the creation returns as soon as udev has processed the device
+    pub fn uinput_ready_timeout(mut self, timeout: Duration) -> Self {
+        self.uinput_ready_timeout = timeout;
+        self
+    }
+
+    /// Share the virtual device with the other managers of this process that are
+    /// built with the same name, vendor and product (uinput only, disabled by default)
+    ///
+    /// The shared device is created once and lives as long as any of its managers, so
+    /// building another manager meanwhile does not make the desktop detect a new device.
+    /// The device of another process can be driven with `EvdevMouseManager` instead
+    pub fn share_uinput_device(mut self, shared: bool) -> Self {
+        self.share_uinput_device = shared;
+        self
+    }
+
+    /// Connect to the given X11 display (e.g. `:1`) instead of the one of the `DISPLAY`
+    /// environment variable (X11 only)
+    ///
+    /// The X11 manager is used whenever a display is given, even in the other sessions,
+    /// so e.g. a nested Xephyr server can be driven from a Wayland session
+    pub fn x11_display(mut self, display_name: &str) -> Self {
+        self.x11_display = Some(display_name.to_string());
+        self
+    }
+
+    /// Control the pointer on the given screen of the X11 display instead
+    /// of the default one (X11 only), e.g. on the multi-screen setups
+    pub fn x11_screen(mut self, screen: i32) -> Self {
+        self.x11_screen = Some(screen);
+        self
+    }
+
+    /// Create the event tap as listen only (macOS only, enabled by default)
+    ///
+    /// A listen only tap needs the input monitoring permission, while
+    /// an active tap needs the accessibility permission instead
+    pub fn tap_listen_only(mut self, listen_only: bool) -> Self {
+        self.tap_listen_only = listen_only;
+        self
+    }
+
+    /// Set the state of the event source that posts the injected events (macOS
+    /// only, the combined session state by default)
+    ///
+    /// A manager creates its source once and reuses it for all of its events, the
+    /// source decides e.g. which of the held modifier keys the events carry
+    pub fn event_source_state(mut self, state: EventSourceState) -> Self {
+        self.event_source_state = state;
+        self
+    }
+
+    /// Post a moved event, or a dragged one while a button is held, for the moves
+    /// of the cursor (macOS only, enabled by default)
+    ///
+    /// Otherwise the cursor is only warped, which posts no events, so the hover
+    /// states, the tooltips and the drags of the applications are not updated
+    pub fn post_move_events(mut self, enabled: bool) -> Self {
+        self.post_move_events = enabled;
+        self
+    }
+
+    /// Pass the module handle of the executable while installing the
+    /// low level mouse hook (Windows only, disabled by default)
+    ///
+    /// Some older Windows versions refuse to install the hook without a module handle
+    pub fn hook_module_handle(mut self, module_handle: bool) -> Self {
+        self.hook_module_handle = module_handle;
+        self
+    }
+
+    /// Space out the injected events according to the given pacing (not available
+    /// in the browsers), the events are injected as soon as possible by default
+    pub fn pacing(mut self, pacing: Pacing) -> Self {
+        self.pacing = pacing;
+        self
+    }
+
+    /// Invert the vertical and the horizontal directions of the injected scrolls
+    /// (disabled by default), e.g. to scroll the same way regardless of the
+    /// natural scrolling setting of the system
+    pub fn invert_scroll(mut self, vertical: bool, horizontal: bool) -> Self {
+        self.scroll_inversion.vertical = vertical;
+        self.scroll_inversion.horizontal = horizontal;
+        self
+    }
+
+    /// Invert the scrolls of the devices as well while a rewriting hook is
+    /// hooked (disabled by default), since the hook re-injects them
+    ///
+    /// The axes are the ones of `invert_scroll`, see `MouseActions::hook_rewrite`
+    pub fn invert_hooked_scroll(mut self, enabled: bool) -> Self {
+        self.scroll_inversion.hooked = enabled;
+        self
+    }
+
+    /// Choose where the hooked callbacks run (not available in the browsers), they
+    /// run on the thread that receives the events by default
+    ///
+    /// That thread is the low level hook on windows and the event tap on macOS, so
+    /// a slow callback there delays the input of the whole system, while the other
+    /// platforms receive the events on a thread of the listener
+    pub fn callback_execution(mut self, execution: CallbackExecution) -> Self {
+        self.callback_execution = execution;
+        self
+    }
+
+    /// Merge the bursts of moves before they reach the hooked callbacks (not available
+    /// in the browsers), every move reaches them by default
+    ///
+    /// The merging needs a queue, so with the inline execution the callbacks run on
+    /// the thread of the queue instead, e.g. at a polling rate of 1000 Hz
+    /// `MoveCoalescing::Sample(60)` reports a move per frame
+    pub fn move_coalescing(mut self, coalescing: MoveCoalescing) -> Self {
+        self.move_coalescing = coalescing;
+        self
+    }
+
+    /// Set the number of the events that the listener queues for the hooked callbacks and
+    /// what happens to the events while the queue is full (linux and the BSDs only), the
+    /// listener queues 1024 events and blocks by default
+    ///
+    /// The dropped events are counted by `MouseActions::dropped_events`
+    pub fn listener_queue(mut self, capacity: usize, backpressure: Backpressure) -> Self {
+        self.listener_capacity = capacity.max(1);
+        self.backpressure = backpressure;
+        self
+    }
+
+    /// Build the mouse manager of the current platform
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::builder()
+    ///     .device_name("my-bot")
+    ///     .vendor(0x1234)
+    ///     .tap_listen_only(false)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn build(self) -> Result<Box<dyn MouseActions>, Error> {
+        if self.device_name.len() > MAX_DEVICE_NAME_LENGTH || self.device_name.contains('\0') {
+            return Err(Error::CustomError("the device name is not valid"));
+        }
+        crate::Mouse::from_builder(&self)
+    }
+}
+
+impl Default for MouseBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builder::MouseBuilder;
+    use crate::common::{Backpressure, CallbackExecution, EventSourceState, MoveCoalescing};
+    use crate::error::Error;
+    use crate::pacing::Pacing;
+    use std::time::Duration;
+
+    #[test]
+    fn builder_options() {
+        let builder = MouseBuilder::new()
+            .device_name("my-bot")
+            .vendor(0x1234)
+            .product(0x5678)
+            .uinput_ready_timeout(Duration::from_millis(50))
+            .share_uinput_device(true)
+            .x11_display(":1")
+            .x11_screen(1)
+            .tap_listen_only(false)
+            .event_source_state(EventSourceState::HidSystem)
+            .post_move_events(false)
+            .hook_module_handle(true)
+            .pacing(Pacing::new().min_gap(Duration::from_millis(5)))
+            .invert_scroll(true, false)
+            .invert_hooked_scroll(true)
+            .callback_execution(CallbackExecution::Pool(2))
+            .move_coalescing(MoveCoalescing::Sample(60))
+            .listener_queue(0, Backpressure::DropOldest);
+
+        assert_eq!(builder.device_name, "my-bot");
+        assert_eq!(builder.vendor, 0x1234);
+        assert_eq!(builder.product, 0x5678);
+        assert_eq!(builder.uinput_ready_timeout, Duration::from_millis(50));
+        assert!(builder.share_uinput_device);
+        assert_eq!(builder.x11_display.as_deref(), Some(":1"));
+        assert_eq!(builder.x11_screen, Some(1));
+        assert!(!builder.tap_listen_only);
+        assert_eq!(builder.event_source_state, EventSourceState::HidSystem);
+        assert!(!builder.post_move_events);
+        assert!(builder.hook_module_handle);
+        assert_eq!(builder.pacing.interval(), Duration::from_millis(5));
+        assert!(builder.scroll_inversion.vertical);
+        assert!(!builder.scroll_inversion.horizontal);
+        assert!(builder.scroll_inversion.hooked);
+        assert_eq!(builder.callback_execution, CallbackExecution::Pool(2));
+        assert_eq!(builder.move_coalescing, MoveCoalescing::Sample(60));
+        // The queue holds at least one event
+        assert_eq!(builder.listener_capacity, 1);
+        assert_eq!(builder.backpressure, Backpressure::DropOldest);
+    }
+
+    #[test]
+    fn reject_invalid_device_name() {
+        let result = MouseBuilder::new().device_name(&"a".repeat(80)).build();
+        assert_eq!(
+            result.err(),
+            Some(Error::CustomError("the device name is not valid"))
+        );
+    }
+}
@@ -0,0 +1,210 @@
+///
+/// This module contains the double-click detection for the hooked events
+///
+/// The backends only report the raw presses and releases, wrap a hook
+/// callback with `detect_double_clicks` to also receive a `DoubleClick`
+/// event right after the press that completes a double-click
+///
+use crate::common::{EventCallback, EventInfo, MouseButton, MouseEvent};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Detects the double-clicks in a stream of hooked events
+#[derive(Debug, Clone)]
+pub struct DoubleClickDetector {
+    interval: Duration,
+    distance: u32,
+    /// The position of the cursor, it is only relative to an
+    /// arbitrary origin when the backend does not know the position
+    position: (i32, i32),
+    /// The button, time and position of the previous click
+    last_click: Option<(MouseButton, Instant, (i32, i32))>,
+}
+
+impl DoubleClickDetector {
+    /// Create a detector that uses the double-click interval and distance of the system
+    pub fn new() -> Self {
+        let (interval, distance) = crate::Mouse::double_click_settings();
+        Self::with_thresholds(interval, distance)
+    }
+
+    /// Create a detector where the second click must follow the first one within
+    /// `interval` and at most `distance` pixels away on both of the axes
+    pub fn with_thresholds(interval: Duration, distance: u32) -> Self {
+        DoubleClickDetector {
+            interval,
+            distance,
+            position: (0, 0),
+            last_click: None,
+        }
+    }
+
+    /// Process the given hooked event, returns a `DoubleClick` event
+    /// if the event is a press that completes a double-click
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use mouce::clicks::DoubleClickDetector;
+    /// use mouce::common::{EventInfo, MouseButton, MouseEvent};
+    /// use std::time::Duration;
+    ///
+    /// let mut detector = DoubleClickDetector::with_thresholds(Duration::from_secs(1), 4);
+    /// let info = EventInfo {
+    ///     position: Some((100, 100)),
+    ///     ..EventInfo::default()
+    /// };
+    ///
+    /// let press = MouseEvent::Press(MouseButton::Left);
+    /// let release = MouseEvent::Release(MouseButton::Left);
+    /// assert_eq!(detector.process(&press, &info), None);
+    /// assert_eq!(detector.process(&release, &info), None);
+    /// assert_eq!(
+    ///     detector.process(&press, &info),
+    ///     Some(MouseEvent::DoubleClick {
+    ///         button: MouseButton::Left,
+    ///         position: Some((100, 100)),
+    ///     })
+    /// );
+    /// ```
+    pub fn process(&mut self, event: &MouseEvent, info: &EventInfo) -> Option<MouseEvent> {
+        self.process_at(event, info, Instant::now())
+    }
+
+    fn process_at(
+        &mut self,
+        event: &MouseEvent,
+        info: &EventInfo,
+        time: Instant,
+    ) -> Option<MouseEvent> {
+        match event {
+            MouseEvent::Move {
+                position: None,
+                delta,
+            }
+            | MouseEvent::Drag {
+                position: None,
+                delta,
+                ..
+            } => {
+                self.position.0 += delta.0;
+                self.position.1 += delta.1;
+            }
+            MouseEvent::Move {
+                position: Some(position),
+                ..
+            }
+            | MouseEvent::Drag {
+                position: Some(position),
+                ..
+            } => self.position = *position,
+            _ => {}
+        }
+        if let Some(position) = info.position {
+            self.position = position;
+        }
+
+        let button = match event {
+            MouseEvent::Press(button) => *button,
+            _ => return None,
+        };
+
+        match self.last_click.take() {
+            // The click after a double-click starts a new one
+            Some((last_button, last_time, last_position))
+                if last_button == button
+                    && time.duration_since(last_time) <= self.interval
+                    && self.position.0.abs_diff(last_position.0) <= self.distance
+                    && self.position.1.abs_diff(last_position.1) <= self.distance =>
+            {
+                Some(MouseEvent::DoubleClick {
+                    button,
+                    position: info.position,
+                })
+            }
+            _ => {
+                self.last_click = Some((button, time, self.position));
+                None
+            }
+        }
+    }
+}
+
+impl Default for DoubleClickDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Wrap the given hook callback so that it also receives the `DoubleClick`
+/// events, the system double-click interval and distance are used
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use mouce::clicks::detect_double_clicks;
+/// use mouce::common::MouseEvent;
+/// use mouce::Mouse;
+///
+/// let mut manager = Mouse::new();
+/// let hook_result = manager.hook_with_info(detect_double_clicks(Box::new(|event, _| {
+///     if let MouseEvent::DoubleClick { button, .. } = event {
+///         println!("{:?} button is double-clicked", button);
+///     }
+/// })));
+/// assert!(hook_result.is_ok());
+/// ```
+pub fn detect_double_clicks(callback: EventCallback) -> EventCallback {
+    let detector = Mutex::new(DoubleClickDetector::new());
+    Box::new(move |event, info| {
+        callback(event, info);
+        if let Some(double_click) = detector.lock().unwrap().process(event, info) {
+            callback(&double_click, info);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::clicks::DoubleClickDetector;
+    use crate::common::{EventInfo, MouseButton, MouseEvent};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn double_click_thresholds() {
+        let mut detector = DoubleClickDetector::with_thresholds(Duration::from_millis(500), 4);
+        let info = EventInfo::default();
+        let press = MouseEvent::Press(MouseButton::Left);
+        let double_click = Some(MouseEvent::DoubleClick {
+            button: MouseButton::Left,
+            position: None,
+        });
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+
+        assert_eq!(detector.process_at(&press, &info, at(0)), None);
+        assert_eq!(detector.process_at(&press, &info, at(400)), double_click);
+        // The third click starts a new double-click
+        assert_eq!(detector.process_at(&press, &info, at(500)), None);
+        // Too late
+        assert_eq!(detector.process_at(&press, &info, at(1100)), None);
+        // Another button
+        let right = MouseEvent::Press(MouseButton::Right);
+        assert_eq!(detector.process_at(&right, &info, at(1200)), None);
+
+        // The relative moves are tracked when the position is not known
+        assert_eq!(detector.process_at(&press, &info, at(2000)), None);
+        let moved = MouseEvent::Move {
+            position: None,
+            delta: (5, 0),
+        };
+        assert_eq!(detector.process_at(&moved, &info, at(2100)), None);
+        assert_eq!(detector.process_at(&press, &info, at(2200)), None);
+        let back = MouseEvent::Move {
+            position: None,
+            delta: (-2, 0),
+        };
+        assert_eq!(detector.process_at(&back, &info, at(2300)), None);
+        assert_eq!(detector.process_at(&press, &info, at(2400)), double_click);
+    }
+}
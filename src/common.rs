@@ -1,33 +1,1659 @@
+use crate::display::Monitor;
 use crate::error::Error;
+use crate::path::Path;
+#[cfg(feature = "async")]
+use futures_core::Stream;
+#[cfg(feature = "async")]
+use std::collections::VecDeque;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::ops::BitOr;
+use std::panic::{self, AssertUnwindSafe};
+#[cfg(feature = "async")]
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "async")]
+use std::task::{Context, Poll, Waker};
+use std::thread;
+use std::time::{Duration, Instant};
 
-pub type CallbackId = u8;
+/// The identifier of a hooked callback, it is unique among all the
+/// callbacks of the process and is never reused
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CallbackId(u64);
 
-#[derive(Debug, Copy, Clone)]
+/// The identifier of the next hooked callback
+static NEXT_CALLBACK_ID: AtomicU64 = AtomicU64::new(0);
+
+impl CallbackId {
+    /// Get a new identifier for a callback that is about to be hooked
+    pub(crate) fn next() -> CallbackId {
+        CallbackId(NEXT_CALLBACK_ID.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Get the raw value of the identifier
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Deprecated, `CallbackId` used to be a `u8`. This conversion is only kept for
+/// the code that stored the identifiers as `u8`, it will be removed in 0.3
+impl From<u8> for CallbackId {
+    fn from(id: u8) -> Self {
+        CallbackId(id as u64)
+    }
+}
+
+/// Deprecated, `CallbackId` used to be a `u8`. This conversion is only kept for
+/// the code that stored the identifiers as `u8`, it will be removed in 0.3
+impl TryFrom<CallbackId> for u8 {
+    type Error = std::num::TryFromIntError;
+
+    fn try_from(id: CallbackId) -> Result<Self, Self::Error> {
+        u8::try_from(id.0)
+    }
+}
+
+/// The interval between the intermediate moves of a drag, roughly one move per frame
+const DRAG_STEP_INTERVAL: Duration = Duration::from_millis(16);
+/// The interval between the moves while tracing a path
+const TRACE_STEP_INTERVAL: Duration = Duration::from_millis(8);
+/// The pause after pressing and before releasing the button while dragging,
+/// some applications need the pointer to settle to recognize a drag
+const DRAG_SETTLE_DELAY: Duration = Duration::from_millis(50);
+/// The pause between the clicks of a multi-click, well below the
+/// default double-click time of every supported platform
+const MULTI_CLICK_INTERVAL: Duration = Duration::from_millis(30);
+/// The most steps that a line is split into, the longer drags take longer steps
+const MAX_LINE_STEPS: u32 = 100_000;
+
+/// A hook callback that also receives the additional information of the events
+pub type EventCallback = Box<dyn Fn(&MouseEvent, &EventInfo) + Send>;
+/// A hook callback that can mutate the state it captures
+pub type EventCallbackMut = Box<dyn FnMut(&MouseEvent, &EventInfo) + Send>;
+
+pub(crate) type Callbacks = Arc<Mutex<CallbackMap>>;
+/// The channels that the panics of the callbacks are reported to, see `MouseActions::callback_panics`
+type PanicReporters = Arc<Mutex<Vec<Sender<Error>>>>;
+/// An invocation of a callback that is queued for the threads of a `CallbackExecution`
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Where the hooked callbacks run, see `MouseBuilder::callback_execution`
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum CallbackExecution {
+    /// On the thread that receives the events, this has the lowest latency but on windows
+    /// and macOS it is the low level hook, so a slow callback delays the input of the system
+    #[default]
+    Inline,
+    /// On a dedicated thread, the events are queued for it and it receives them in order
+    Dispatcher,
+    /// On the given number of threads, the callbacks run in parallel so a slow
+    /// callback does not delay the others, but they may receive the events out of order
+    Pool(usize),
+}
+
+/// How the bursts of moves are merged before they reach the callbacks, see `MouseBuilder::move_coalescing`
+///
+/// A merged move has the position of the latest move and the sum of the deltas, so no motion is lost
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum MoveCoalescing {
+    /// Every move reaches the callbacks
+    #[default]
+    None,
+    /// The moves that queue up while the callbacks are busy are merged
+    Latest,
+    /// The moves are merged so that the callbacks receive at most the given number of them per second
+    Sample(u32),
+}
+
+/// What the listener does with a new event while its queue is full, see `MouseBuilder::listener_queue`
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum Backpressure {
+    /// Drop the oldest queued event to make room for the new one
+    DropOldest,
+    /// Drop the new event
+    DropNewest,
+    /// Wait until the callbacks take an event, the kernel drops the events of the devices meanwhile
+    #[default]
+    Block,
+}
+
+/// The state that the injected events come from on macOS, see `MouseBuilder::event_source_state`
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum EventSourceState {
+    /// The state of the current login session, which also has the posted events and their modifiers
+    #[default]
+    CombinedSession,
+    /// The state of the hardware, the listeners can not tell the injected events from the
+    /// hardware ones, so `EventInfo::injected` is `false` for them
+    HidSystem,
+}
+
+/// A hooked callback, it is shared with the threads that run the callbacks
+pub(crate) struct HookedCallback {
+    callback: Mutex<EventCallback>,
+    /// A callback that panicked is not invoked again, it is unhooked by the next event
+    panicked: AtomicBool,
+}
+
+impl HookedCallback {
+    /// Invoke the callback unless it has panicked before, reports its panic if it panics now
+    fn invoke(
+        &self,
+        id: CallbackId,
+        event: &MouseEvent,
+        info: &EventInfo,
+        reporters: &PanicReporters,
+    ) {
+        if self.panicked.load(Ordering::SeqCst) {
+            return;
+        }
+        let callback = self.callback.lock().unwrap();
+        if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| callback(event, info))) {
+            self.panicked.store(true, Ordering::SeqCst);
+            let message = panic_message(payload.as_ref());
+            reporters.lock().unwrap().retain(|reporter| {
+                reporter
+                    .send(Error::CallbackPanicked {
+                        id,
+                        message: message.clone(),
+                    })
+                    .is_ok()
+            });
+        }
+    }
+}
+
+/// The hooked callbacks of a mouse manager
+#[derive(Default)]
+pub(crate) struct CallbackMap {
+    callbacks: HashMap<CallbackId, Arc<HookedCallback>>,
+    /// The callbacks stay hooked while paused, but they are not invoked
+    pub(crate) paused: bool,
+    panic_reporters: PanicReporters,
+    execution: CallbackExecution,
+    /// The queue of the threads that run the callbacks, they are started by the
+    /// first event and they stop once the queue is dropped
+    jobs: Option<Sender<Job>>,
+    coalescing: MoveCoalescing,
+    /// The queue of the thread that merges the moves, it is started like the one of the jobs
+    events: Option<Sender<Dispatch>>,
+}
+
+impl CallbackMap {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Change the execution model, the threads of the previous one finish the queued invocations
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    pub(crate) fn set_execution(&mut self, execution: CallbackExecution) {
+        if self.execution != execution {
+            self.execution = execution;
+            self.jobs = None;
+            self.events = None;
+        }
+    }
+
+    /// Change how the moves are merged, the moves that are queued already are merged the previous way
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    pub(crate) fn set_move_coalescing(&mut self, coalescing: MoveCoalescing) {
+        if self.coalescing != coalescing {
+            self.coalescing = coalescing;
+            self.events = None;
+        }
+    }
+
+    pub(crate) fn insert(&mut self, id: CallbackId, callback: EventCallback) {
+        let callback = HookedCallback {
+            callback: Mutex::new(callback),
+            panicked: AtomicBool::new(false),
+        };
+        self.callbacks.insert(id, Arc::new(callback));
+    }
+
+    pub(crate) fn remove(&mut self, id: &CallbackId) -> Option<Arc<HookedCallback>> {
+        self.callbacks.remove(id)
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.callbacks.clear();
+    }
+
+    #[cfg_attr(
+        not(any(
+            target_os = "linux",
+            target_os = "dragonfly",
+            target_os = "freebsd",
+            target_os = "netbsd",
+            target_os = "openbsd"
+        )),
+        allow(dead_code)
+    )]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.callbacks.is_empty()
+    }
+
+    /// Get the callbacks to invoke for an event, there are none while paused
+    #[cfg(test)]
+    pub(crate) fn values(&self) -> impl Iterator<Item = &Arc<HookedCallback>> {
+        let callbacks = (!self.paused).then(|| self.callbacks.values());
+        callbacks.into_iter().flatten()
+    }
+
+    /// Invoke the callbacks with an event according to the execution model, a callback
+    /// that panics is unhooked and its panic is reported, so it does not stop the others
+    pub(crate) fn invoke(&mut self, event: &MouseEvent, info: &EventInfo) {
+        if self.paused {
+            return;
+        }
+        // The callbacks that panicked on the other threads are unhooked here
+        self.callbacks
+            .retain(|_, callback| !callback.panicked.load(Ordering::SeqCst));
+
+        if self.execution == CallbackExecution::Inline && self.coalescing == MoveCoalescing::None {
+            for (id, callback) in &self.callbacks {
+                callback.invoke(*id, event, info, &self.panic_reporters);
+            }
+            self.callbacks
+                .retain(|_, callback| !callback.panicked.load(Ordering::SeqCst));
+            return;
+        }
+
+        let dispatch = Dispatch {
+            event: *event,
+            info: info.clone(),
+            callbacks: self
+                .callbacks
+                .iter()
+                .map(|(id, callback)| (*id, callback.clone()))
+                .collect(),
+        };
+        let jobs = match self.execution {
+            CallbackExecution::Inline => None,
+            execution => Some(&*self.jobs.get_or_insert_with(|| start_workers(execution))),
+        };
+        if self.coalescing == MoveCoalescing::None {
+            dispatch.run(jobs, &self.panic_reporters);
+            return;
+        }
+        // The merging needs a queue, so the callbacks run on its thread with the inline execution
+        let (coalescing, reporters) = (self.coalescing, &self.panic_reporters);
+        let events = self
+            .events
+            .get_or_insert_with(|| start_coalescer(coalescing, jobs.cloned(), reporters.clone()));
+        let _ = events.send(dispatch);
+    }
+
+    /// Get a channel that receives the panics of the callbacks from now on
+    pub(crate) fn panic_receiver(&mut self) -> Receiver<Error> {
+        let (sender, receiver) = mpsc::channel();
+        self.panic_reporters.lock().unwrap().push(sender);
+        receiver
+    }
+}
+
+/// An event along with the callbacks that are hooked when it arrives
+struct Dispatch {
+    event: MouseEvent,
+    info: EventInfo,
+    callbacks: Vec<(CallbackId, Arc<HookedCallback>)>,
+}
+
+impl Dispatch {
+    /// Invoke the callbacks on the current thread, or queue them for the threads of the execution model
+    fn run(self, jobs: Option<&Sender<Job>>, reporters: &PanicReporters) {
+        for (id, callback) in self.callbacks {
+            match jobs {
+                Some(jobs) => {
+                    let (event, info, reporters) =
+                        (self.event, self.info.clone(), reporters.clone());
+                    let _ = jobs.send(Box::new(move || {
+                        callback.invoke(id, &event, &info, &reporters)
+                    }));
+                }
+                None => callback.invoke(id, &self.event, &self.info, reporters),
+            }
+        }
+    }
+}
+
+/// Merge two consecutive moves, or two drags of the same button, `None` if they can not be merged
+fn merge_moves(previous: &MouseEvent, next: &MouseEvent) -> Option<MouseEvent> {
+    let add =
+        |(x, y): (i32, i32), (dx, dy): (i32, i32)| (x.saturating_add(dx), y.saturating_add(dy));
+    match (previous, next) {
+        (
+            MouseEvent::Move { delta, .. },
+            MouseEvent::Move {
+                position,
+                delta: next,
+            },
+        ) => Some(MouseEvent::Move {
+            position: *position,
+            delta: add(*delta, *next),
+        }),
+        (
+            MouseEvent::Drag { button, delta, .. },
+            MouseEvent::Drag {
+                button: next_button,
+                position,
+                delta: next,
+            },
+        ) if button == next_button => Some(MouseEvent::Drag {
+            button: *button,
+            position: *position,
+            delta: add(*delta, *next),
+        }),
+        _ => None,
+    }
+}
+
+/// Start the thread that merges the moves of the returned queue, the callbacks
+/// run on the thread itself without the queue of the execution model
+fn start_coalescer(
+    coalescing: MoveCoalescing,
+    jobs: Option<Sender<Job>>,
+    reporters: PanicReporters,
+) -> Sender<Dispatch> {
+    let (sender, receiver) = mpsc::channel::<Dispatch>();
+    // The latest moves are merged only while the next ones are queued already
+    let interval = match coalescing {
+        MoveCoalescing::Sample(rate) if rate > 0 => Duration::from_secs_f64(1. / rate as f64),
+        _ => Duration::ZERO,
+    };
+    thread::spawn(move || {
+        let run = |dispatch: Dispatch| dispatch.run(jobs.as_ref(), &reporters);
+        // The merged move that waits for its time, and the earliest time for the next move
+        let mut pending: Option<Dispatch> = None;
+        let mut next_at = Instant::now();
+        loop {
+            let received = match pending {
+                Some(_) => receiver.recv_timeout(next_at.saturating_duration_since(Instant::now())),
+                None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            let dispatch = match received {
+                Ok(dispatch) => dispatch,
+                Err(err) => {
+                    if let Some(previous) = pending.take() {
+                        run(previous);
+                        next_at = Instant::now() + interval;
+                    }
+                    match err {
+                        RecvTimeoutError::Timeout => continue,
+                        RecvTimeoutError::Disconnected => break,
+                    }
+                }
+            };
+
+            let merged = pending
+                .as_ref()
+                .and_then(|previous| merge_moves(&previous.event, &dispatch.event));
+            match (merged, pending.take()) {
+                (Some(event), _) => pending = Some(Dispatch { event, ..dispatch }),
+                (None, previous) => {
+                    if let Some(previous) = previous {
+                        run(previous);
+                        next_at = Instant::now() + interval;
+                    }
+                    if matches!(
+                        dispatch.event,
+                        MouseEvent::Move { .. } | MouseEvent::Drag { .. }
+                    ) {
+                        pending = Some(dispatch);
+                    } else {
+                        run(dispatch);
+                    }
+                }
+            }
+        }
+    });
+    sender
+}
+
+/// Start the threads of the given execution model, they run the jobs of the returned queue
+fn start_workers(execution: CallbackExecution) -> Sender<Job> {
+    let (sender, receiver) = mpsc::channel::<Job>();
+    let receiver = Arc::new(Mutex::new(receiver));
+    let count = match execution {
+        CallbackExecution::Pool(count) => count.max(1),
+        _ => 1,
+    };
+    for _ in 0..count {
+        let receiver = receiver.clone();
+        thread::spawn(move || loop {
+            // The lock is released before the job runs, so the other threads can take the next one
+            let job = receiver.lock().unwrap().recv();
+            match job {
+                Ok(job) => job(),
+                Err(_) => break,
+            }
+        });
+    }
+    sender
+}
+
+/// Get the message of a panic, the payload is a string unless `panic_any` is used
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    match payload.downcast_ref::<&str>() {
+        Some(message) => message.to_string(),
+        None => payload
+            .downcast_ref::<String>()
+            .cloned()
+            .unwrap_or_else(|| "the callback panicked".to_string()),
+    }
+}
+
+/// A hook callback that decides what happens to an event, returning `None` swallows
+/// the event and returning another event injects it in place of the original
+pub type RewriteCallback = Box<dyn Fn(&MouseEvent, &EventInfo) -> Option<MouseEvent> + Send>;
+/// The rewriting callbacks, they are ordered so that they run in the order they are hooked
+#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+pub(crate) type Rewriters = BTreeMap<CallbackId, RewriteCallback>;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum MouseButton {
     Left,
     Middle,
     Right,
+    /// The side button that navigates back, usually the fourth button
+    Back,
+    /// The side button that navigates forward, usually the fifth button
+    Forward,
+    /// Any other button, `Other(0)` is the sixth button of the mouse
+    Other(u8),
+}
+
+impl MouseButton {
+    /// Get the bit of this button in a ButtonState, the buttons that do
+    /// not fit into the state do not have a bit
+    fn bit(&self) -> u32 {
+        match self {
+            MouseButton::Left => 1 << 0,
+            MouseButton::Middle => 1 << 1,
+            MouseButton::Right => 1 << 2,
+            MouseButton::Back => 1 << 3,
+            MouseButton::Forward => 1 << 4,
+            MouseButton::Other(index) => 1u32.checked_shl(5 + *index as u32).unwrap_or(0),
+        }
+    }
+
+    /// Get the button of the given bit index in a ButtonState
+    fn from_bit_index(index: u32) -> MouseButton {
+        match index {
+            0 => MouseButton::Left,
+            1 => MouseButton::Middle,
+            2 => MouseButton::Right,
+            3 => MouseButton::Back,
+            4 => MouseButton::Forward,
+            _ => MouseButton::Other((index - 5) as u8),
+        }
+    }
+}
+
+/// Parse a button from its name, or from its number starting from the sixth button
+///
+/// # Examples
+///
+/// ```rust
+/// use mouce::common::MouseButton;
+///
+/// assert_eq!("left".parse(), Ok(MouseButton::Left));
+/// assert_eq!("7".parse(), Ok(MouseButton::Other(1)));
+/// assert_eq!(MouseButton::Other(1).to_string(), "7");
+/// ```
+impl FromStr for MouseButton {
+    type Err = Error;
+
+    fn from_str(button: &str) -> Result<Self, Self::Err> {
+        match button.to_ascii_lowercase().as_str() {
+            "left" => Ok(MouseButton::Left),
+            "middle" => Ok(MouseButton::Middle),
+            "right" => Ok(MouseButton::Right),
+            "back" => Ok(MouseButton::Back),
+            "forward" => Ok(MouseButton::Forward),
+            number => match number.parse::<u8>() {
+                Ok(number) if number >= 6 => Ok(MouseButton::Other(number - 6)),
+                _ => Err(Error::CustomError(
+                    "the button is not left, right, middle, back, forward or a button number starting from 6",
+                )),
+            },
+        }
+    }
+}
+
+impl fmt::Display for MouseButton {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MouseButton::Left => write!(f, "left"),
+            MouseButton::Middle => write!(f, "middle"),
+            MouseButton::Right => write!(f, "right"),
+            MouseButton::Back => write!(f, "back"),
+            MouseButton::Forward => write!(f, "forward"),
+            MouseButton::Other(index) => write!(f, "{}", *index as u16 + 6),
+        }
+    }
+}
+
+/// The set of the mouse buttons that are held down at the same moment
+///
+/// # Examples
+///
+/// ```rust
+/// use mouce::common::{ButtonState, MouseButton};
+///
+/// let mut state = ButtonState::new();
+/// state.insert(&MouseButton::Left);
+/// state.insert(&MouseButton::Right);
+/// assert!(state.is_pressed(&MouseButton::Left));
+/// assert_eq!(state.iter().collect::<Vec<_>>(), vec![MouseButton::Left, MouseButton::Right]);
+/// ```
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ButtonState {
+    bits: u32,
+}
+
+impl ButtonState {
+    /// Create a state where none of the buttons are pressed
+    pub const fn new() -> Self {
+        ButtonState { bits: 0 }
+    }
+
+    /// Check whether the given button is pressed
+    pub fn is_pressed(&self, button: &MouseButton) -> bool {
+        self.bits & button.bit() != 0
+    }
+
+    /// Check whether none of the buttons are pressed
+    pub fn is_empty(&self) -> bool {
+        self.bits == 0
+    }
+
+    /// Mark the given button as pressed
+    pub fn insert(&mut self, button: &MouseButton) {
+        self.bits |= button.bit();
+    }
+
+    /// Mark the given button as released
+    pub fn remove(&mut self, button: &MouseButton) {
+        self.bits &= !button.bit();
+    }
+
+    /// Get the pressed buttons
+    ///
+    /// Only `Other(0)` to `Other(26)` can be tracked, the higher
+    /// buttons are never reported as pressed
+    pub fn iter(&self) -> impl Iterator<Item = MouseButton> + '_ {
+        (0..u32::BITS)
+            .filter(|index| self.bits & (1 << index) != 0)
+            .map(MouseButton::from_bit_index)
+    }
+
+    /// Get the raw bitmask, bit 0 is the left, bit 1 is the middle,
+    /// bit 2 is the right, bit 3 is the back and bit 4 is the forward
+    /// button, the following bits are the `Other` buttons in order
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+}
+
+impl FromIterator<MouseButton> for ButtonState {
+    fn from_iter<I: IntoIterator<Item = MouseButton>>(buttons: I) -> Self {
+        let mut state = ButtonState::new();
+        for button in buttons {
+            state.insert(&button);
+        }
+        state
+    }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum ScrollDirection {
     Up,
     Down,
     Right,
-    Left
+    Left,
+}
+
+impl FromStr for ScrollDirection {
+    type Err = Error;
+
+    fn from_str(direction: &str) -> Result<Self, Self::Err> {
+        match direction.to_ascii_lowercase().as_str() {
+            "up" => Ok(ScrollDirection::Up),
+            "down" => Ok(ScrollDirection::Down),
+            "right" => Ok(ScrollDirection::Right),
+            "left" => Ok(ScrollDirection::Left),
+            _ => Err(Error::CustomError(
+                "the direction is not up, down, right or left",
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ScrollDirection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScrollDirection::Up => write!(f, "up"),
+            ScrollDirection::Down => write!(f, "down"),
+            ScrollDirection::Right => write!(f, "right"),
+            ScrollDirection::Left => write!(f, "left"),
+        }
+    }
+}
+
+/// The unit of the scroll distance
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ScrollUnit {
+    /// One step of the scroll wheel, which usually scrolls a few lines
+    Line,
+    /// A pixel, as reported by the precise scrolling devices such as the touchpads
+    ///
+    /// X11 can only fake the wheel buttons, so the pixels are scrolled in steps of
+    /// 50 pixels there and the smaller distances add up until they make a step
+    Pixel,
+}
+
+impl FromStr for ScrollUnit {
+    type Err = Error;
+
+    fn from_str(unit: &str) -> Result<Self, Self::Err> {
+        match unit.to_ascii_lowercase().as_str() {
+            "line" => Ok(ScrollUnit::Line),
+            "pixel" => Ok(ScrollUnit::Pixel),
+            _ => Err(Error::CustomError("the unit is not line or pixel")),
+        }
+    }
 }
 
-#[derive(Debug, Copy, Clone)]
+impl fmt::Display for ScrollUnit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScrollUnit::Line => write!(f, "line"),
+            ScrollUnit::Pixel => write!(f, "pixel"),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum MouseEvent {
-    RelativeMove(i32, i32),
-    AbsoluteMove(i32, i32),
+    /// The mouse moved by `delta`, `position` is the new position of the cursor
+    /// or `None` if the backend only sees the relative motion (e.g. `/dev/input`)
+    Move {
+        position: Option<(i32, i32)>,
+        delta: (i32, i32),
+    },
     Press(MouseButton),
     Release(MouseButton),
-    Scroll(ScrollDirection),
+    /// The mouse moved by `delta` while `button` is held down, it is reported
+    /// instead of a `Move`, the first of the held buttons is reported if there
+    /// are many of them in the order of `ButtonState`
+    Drag {
+        button: MouseButton,
+        position: Option<(i32, i32)>,
+        delta: (i32, i32),
+    },
+    /// The second press of a double-click, reported after the raw press by
+    /// the callbacks that are wrapped with `mouce::clicks::detect_double_clicks`
+    DoubleClick {
+        button: MouseButton,
+        position: Option<(i32, i32)>,
+    },
+    /// The wheel scrolled towards `direction` by `distance`, the distance may be
+    /// fractional with the high-precision wheels and the touchpads
+    Scroll {
+        direction: ScrollDirection,
+        unit: ScrollUnit,
+        distance: f64,
+    },
+    /// The system disabled the listener for a while and the events in between are lost,
+    /// e.g. the event tap on macOS once the callbacks are too slow or the system sleeps
+    ListenerInterrupted,
 }
 
-pub trait MouseActions {
-    /// Move the mouse to the given `x`, `y` coordinates
+/// The axes that the scrolls of a manager are inverted on, see `MouseBuilder::invert_scroll`
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub(crate) struct ScrollInversion {
+    pub(crate) vertical: bool,
+    pub(crate) horizontal: bool,
+    /// Whether the scrolls that the rewriting hooks re-inject are inverted as well
+    pub(crate) hooked: bool,
+}
+
+impl ScrollInversion {
+    pub(crate) const NONE: ScrollInversion = ScrollInversion {
+        vertical: false,
+        horizontal: false,
+        hooked: false,
+    };
+
+    /// Get the direction that the given direction is inverted to
+    pub(crate) fn direction(&self, direction: &ScrollDirection) -> ScrollDirection {
+        match direction {
+            ScrollDirection::Up if self.vertical => ScrollDirection::Down,
+            ScrollDirection::Down if self.vertical => ScrollDirection::Up,
+            ScrollDirection::Left if self.horizontal => ScrollDirection::Right,
+            ScrollDirection::Right if self.horizontal => ScrollDirection::Left,
+            direction => *direction,
+        }
+    }
+
+    /// Get the inverted distances of the horizontal and the vertical axes
+    pub(crate) fn deltas(&self, dx: f64, dy: f64) -> (f64, f64) {
+        (
+            if self.horizontal { -dx } else { dx },
+            if self.vertical { -dy } else { dy },
+        )
+    }
+
+    /// Get the event that a rewriting hook re-injects in place of
+    /// the given one, the scrolls are inverted if the hooked ones are
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    pub(crate) fn hooked_event(&self, event: MouseEvent) -> MouseEvent {
+        match event {
+            MouseEvent::Scroll {
+                direction,
+                unit,
+                distance,
+            } if self.hooked => MouseEvent::Scroll {
+                direction: self.direction(&direction),
+                unit,
+                distance,
+            },
+            event => event,
+        }
+    }
+}
+
+/// An action of a batch, see `MouseActions::send_batch`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Action {
+    MoveTo(usize, usize),
+    MoveRelative(i32, i32),
+    Press(MouseButton),
+    Release(MouseButton),
+    Click(MouseButton),
+    Scroll(ScrollDirection, ScrollUnit, u32),
+}
+
+/// The set of the kinds of events that a filtered hook receives
+///
+/// # Examples
+///
+/// ```rust
+/// use mouce::common::{EventMask, MouseButton, MouseEvent};
+///
+/// let mask = EventMask::PRESS | EventMask::RELEASE;
+/// assert!(mask.matches(&MouseEvent::Press(MouseButton::Left)));
+/// assert!(!mask.matches(&MouseEvent::Move {
+///     position: None,
+///     delta: (1, 0),
+/// }));
+/// ```
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct EventMask {
+    bits: u8,
+}
+
+impl EventMask {
+    /// The moves and the drags
+    pub const MOVE: EventMask = EventMask { bits: 1 };
+    /// The presses and the double-clicks
+    pub const PRESS: EventMask = EventMask { bits: 1 << 1 };
+    /// The releases
+    pub const RELEASE: EventMask = EventMask { bits: 1 << 2 };
+    /// The scrolls
+    pub const SCROLL: EventMask = EventMask { bits: 1 << 3 };
+    /// Every kind of event
+    pub const ALL: EventMask = EventMask { bits: 0b1111 };
+
+    /// Check whether every kind of event in `other` is also in this mask
+    pub fn contains(&self, other: EventMask) -> bool {
+        self.bits & other.bits == other.bits
+    }
+
+    /// Check whether the mask selects none of the events
+    pub fn is_empty(&self) -> bool {
+        self.bits == 0
+    }
+
+    /// Get the kinds of events in this mask that are not in `other`
+    pub fn difference(&self, other: EventMask) -> EventMask {
+        EventMask {
+            bits: self.bits & !other.bits,
+        }
+    }
+
+    /// Check whether the given event is selected by the mask
+    pub fn matches(&self, event: &MouseEvent) -> bool {
+        let kind = match event {
+            MouseEvent::Move { .. } | MouseEvent::Drag { .. } => EventMask::MOVE,
+            MouseEvent::Press(_) | MouseEvent::DoubleClick { .. } => EventMask::PRESS,
+            MouseEvent::Release(_) => EventMask::RELEASE,
+            MouseEvent::Scroll { .. } => EventMask::SCROLL,
+            // Every hook is told that it missed events
+            MouseEvent::ListenerInterrupted => return true,
+        };
+        self.contains(kind)
+    }
+
+    /// Wrap the given callback so that it only receives the events selected by the mask
+    pub(crate) fn filter(self, callback: EventCallback) -> EventCallback {
+        if self == EventMask::ALL {
+            return callback;
+        }
+        Box::new(move |event, info| {
+            if self.matches(event) {
+                callback(event, info);
+            }
+        })
+    }
+}
+
+/// Parse the comma separated kinds of events, e.g. `press,release,scroll`
+///
+/// # Examples
+///
+/// ```rust
+/// use mouce::common::EventMask;
+///
+/// assert_eq!("press,release".parse(), Ok(EventMask::PRESS | EventMask::RELEASE));
+/// assert_eq!("all".parse(), Ok(EventMask::ALL));
+/// ```
+impl FromStr for EventMask {
+    type Err = Error;
+
+    fn from_str(mask: &str) -> Result<Self, Self::Err> {
+        mask.split(',')
+            .map(|kind| match kind.trim().to_ascii_lowercase().as_str() {
+                "move" => Ok(EventMask::MOVE),
+                "press" => Ok(EventMask::PRESS),
+                "release" => Ok(EventMask::RELEASE),
+                "scroll" => Ok(EventMask::SCROLL),
+                "all" => Ok(EventMask::ALL),
+                _ => Err(Error::CustomError(
+                    "the event is not move, press, release, scroll or all",
+                )),
+            })
+            .try_fold(EventMask::default(), |mask, kind| Ok(mask | kind?))
+    }
+}
+
+impl BitOr for EventMask {
+    type Output = EventMask;
+
+    fn bitor(self, other: EventMask) -> EventMask {
+        EventMask {
+            bits: self.bits | other.bits,
+        }
+    }
+}
+
+/// A rectangle on the screen, the right and the bottom edges are excluded
+///
+/// # Examples
+///
+/// ```rust
+/// use mouce::common::Region;
+///
+/// let region = Region::new(100, 100, 50, 20);
+/// assert!(region.contains((100, 119)));
+/// assert!(!region.contains((150, 110)));
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Region {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Region {
+    /// Create a region with its top left corner at `x` and `y`
+    pub fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Region {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Check whether the given position is inside the region
+    pub fn contains(&self, position: (i32, i32)) -> bool {
+        let (x, y) = (position.0 as i64, position.1 as i64);
+        x >= self.x as i64
+            && x < self.x as i64 + self.width as i64
+            && y >= self.y as i64
+            && y < self.y as i64 + self.height as i64
+    }
+
+    /// Check whether the region has no area, so it contains no positions
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// Get the position inside the region that is the closest to the given one,
+    /// an empty region clamps every position to its top left corner
+    pub fn clamp(&self, position: (i32, i32)) -> (i32, i32) {
+        let clamp = |value: i32, start: i32, length: u32| {
+            let last = (start as i64 + length as i64 - 1).clamp(start as i64, i32::MAX as i64);
+            (value as i64).clamp(start as i64, last) as i32
+        };
+        (
+            clamp(position.0, self.x, self.width),
+            clamp(position.1, self.y, self.height),
+        )
+    }
+
+    /// Wrap the given callback so that it only receives the events inside the region
+    pub(crate) fn filter(self, callback: EventCallback) -> EventCallback {
+        Box::new(move |event, info| {
+            let position = match event {
+                MouseEvent::Move { position, .. }
+                | MouseEvent::Drag { position, .. }
+                | MouseEvent::DoubleClick { position, .. } => position.or(info.position),
+                _ => info.position,
+            };
+            if position.is_some_and(|position| self.contains(position)) {
+                callback(event, info);
+            }
+        })
+    }
+}
+
+/// A blocking iterator over the hooked mouse events, see `MouseActions::events`
+#[derive(Debug)]
+pub struct MouseEvents {
+    callback_id: CallbackId,
+    receiver: Receiver<MouseEvent>,
+    timeout: Option<Duration>,
+}
+
+impl MouseEvents {
+    /// Make the iterator end when no event is received within the given timeout
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Get the id of the callback that receives the events, unhooking
+    /// it ends the iterator once the received events are consumed
+    pub fn callback_id(&self) -> CallbackId {
+        self.callback_id
+    }
+}
+
+impl Iterator for MouseEvents {
+    type Item = MouseEvent;
+
+    fn next(&mut self) -> Option<MouseEvent> {
+        match self.timeout {
+            Some(timeout) => self.receiver.recv_timeout(timeout).ok(),
+            None => self.receiver.recv().ok(),
+        }
+    }
+}
+
+/// An asynchronous stream of the hooked mouse events, see `MouseActions::event_stream`
+///
+/// It works with any async runtime, the task that polls it is woken up by the listener
+#[cfg(feature = "async")]
+#[derive(Debug)]
+pub struct EventStream {
+    callback_id: CallbackId,
+    state: Arc<Mutex<EventStreamState>>,
+}
+
+#[cfg(feature = "async")]
+#[derive(Debug, Default)]
+struct EventStreamState {
+    events: VecDeque<MouseEvent>,
+    waker: Option<Waker>,
+    /// Whether the callback behind the stream is removed
+    closed: bool,
+}
+
+#[cfg(feature = "async")]
+impl EventStream {
+    /// Get the id of the callback that receives the events, unhooking
+    /// it ends the stream once the received events are consumed
+    pub fn callback_id(&self) -> CallbackId {
+        self.callback_id
+    }
+}
+
+#[cfg(feature = "async")]
+impl Stream for EventStream {
+    type Item = MouseEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<MouseEvent>> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(event) = state.events.pop_front() {
+            return Poll::Ready(Some(event));
+        }
+        if state.closed {
+            return Poll::Ready(None);
+        }
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+/// The sending half of an `EventStream`, it is owned by the callback
+/// behind the stream so the stream is closed when the callback is dropped
+#[cfg(feature = "async")]
+struct EventStreamSender(Arc<Mutex<EventStreamState>>);
+
+#[cfg(feature = "async")]
+impl EventStreamSender {
+    fn send(&self, event: MouseEvent) {
+        let mut state = self.0.lock().unwrap();
+        state.events.push_back(event);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl Drop for EventStreamSender {
+    fn drop(&mut self) {
+        let mut state = self.0.lock().unwrap();
+        state.closed = true;
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// A platform specific identifier of an input device
+///
+/// The identifier is the device number of the event node on linux, the
+/// XInput2 device id on X11, the Raw Input handle on windows and the
+/// registry entry id of the HID device on macOS
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct DeviceId(pub u64);
+
+/// An input device that produced a mouse event
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Device {
+    pub id: DeviceId,
+    /// The name reported by the device, e.g. `Logitech USB Optical Mouse`
+    pub name: Option<String>,
+}
+
+/// The additional information about a hooked mouse event
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventInfo {
+    /// The device that produced the event, `None` if the backend can not
+    /// tell the devices apart (e.g. the CGEventTap on macOS or XRecord)
+    pub device: Option<Arc<Device>>,
+    /// The position of the cursor when the event happened, `None` if the
+    /// backend only sees the relative motion (e.g. `/dev/input` or libinput)
+    pub position: Option<(i32, i32)>,
+    /// Whether the event is injected by software rather than produced by the
+    /// hardware, `None` if the backend can not tell (e.g. XRecord)
+    pub injected: Option<bool>,
+    /// The time of the event since the start of the system, `None` if the backend
+    /// does not report it (only the low-level hook on Windows reports it)
+    pub timestamp: Option<Duration>,
+    /// The value that the injector attached to the event, which tells apart the
+    /// injectors that mark their events, e.g. `dwExtraInfo` on Windows
+    pub extra_info: Option<usize>,
+}
+
+/// Get the number of the steps of a line that takes the given duration,
+/// each step takes roughly the given interval
+pub(crate) fn line_steps(duration: Duration, interval: Duration) -> u32 {
+    let steps = duration.as_millis() / interval.as_millis().max(1);
+    steps.clamp(1, MAX_LINE_STEPS as u128) as u32
+}
+
+/// Get the point of the given step of the line between `from` and `to`
+pub(crate) fn line_point(from: (i32, i32), to: (i32, i32), step: u32, steps: u32) -> (i32, i32) {
+    // The distance between the ends does not fit in i32 on the edges of the screen
+    let axis = |from: i32, to: i32| {
+        let offset = (to as i64 - from as i64) * step as i64 / steps.max(1) as i64;
+        (from as i64 + offset) as i32
+    };
+    (axis(from.0, to.0), axis(from.1, to.1))
+}
+
+/// Invoke the given callbacks with a simulated event, see `MouseActions::simulate_event`
+pub(crate) fn simulate_event(callbacks: &Callbacks, event: &MouseEvent) {
+    let info = EventInfo {
+        injected: Some(true),
+        ..EventInfo::default()
+    };
+    callbacks.lock().unwrap().invoke(event, &info);
+}
+
+/// Pass the given event through the rewriting callbacks, each callback
+/// receives the event that is returned by the previous one
+#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+pub(crate) fn rewrite_event(
+    rewriters: &Rewriters,
+    event: MouseEvent,
+    info: &EventInfo,
+) -> Option<MouseEvent> {
+    rewriters
+        .values()
+        .try_fold(event, |event, rewriter| rewriter(&event, info))
+}
+
+/// Turns the moves into drags while a button is held down,
+/// for the listeners that do not report the drags themselves
+#[derive(Debug, Default)]
+#[cfg_attr(any(target_vendor = "apple", target_arch = "wasm32"), allow(dead_code))]
+pub(crate) struct DragTracker {
+    pressed: ButtonState,
+}
+
+#[cfg_attr(any(target_vendor = "apple", target_arch = "wasm32"), allow(dead_code))]
+impl DragTracker {
+    /// Track the pressed buttons with the given event and convert it to a drag if needed
+    pub(crate) fn track(&mut self, event: MouseEvent) -> MouseEvent {
+        match event {
+            MouseEvent::Press(button) => self.pressed.insert(&button),
+            MouseEvent::Release(button) => self.pressed.remove(&button),
+            MouseEvent::Move { position, delta } => {
+                if let Some(button) = self.pressed.iter().next() {
+                    return MouseEvent::Drag {
+                        button,
+                        position,
+                        delta,
+                    };
+                }
+            }
+            _ => {}
+        }
+        event
+    }
+}
+
+pub trait MouseActions {
+    /// Move the mouse to the given `x`, `y` coordinates
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// assert_eq!(manager.move_to(0, 0), Ok(()));
+    /// ```
+    fn move_to(&self, x: usize, y: usize) -> Result<(), Error>;
+    /// Move the mouse relative to the current position
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// assert_eq!(manager.move_relative(100, 100), Ok(()));
+    /// ```
+    fn move_relative(&self, x_offset: i32, y_offset: i32) -> Result<(), Error> {
+        let (x, y) = self.get_position()?;
+        self.move_to((x + x_offset) as usize, (y + y_offset) as usize)
+    }
+    /// Move the mouse to the given `x`, `y` coordinates on the monitor with the given id,
+    /// the coordinates are relative to the top left corner of the monitor
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// // Move to the center of the second monitor, wherever it is placed
+    /// let monitor = mouce::display::monitor(1).unwrap();
+    /// let (width, height) = (monitor.bounds.width, monitor.bounds.height);
+    /// assert_eq!(manager.move_to_monitor(1, width / 2, height / 2), Ok(()));
+    /// ```
+    fn move_to_monitor(&self, monitor_id: usize, x: u32, y: u32) -> Result<(), Error> {
+        let (x, y) = crate::display::monitor(monitor_id)?.to_global(x, y)?;
+        match (usize::try_from(x), usize::try_from(y)) {
+            (Ok(x), Ok(y)) => self.move_to(x, y),
+            _ => Err(Error::CustomError(
+                "the monitor is outside of the coordinates of move_to",
+            )),
+        }
+    }
+    /// Get the monitor that the mouse is on
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// let monitor = manager.current_monitor().unwrap();
+    /// println!("The mouse is on the monitor {} at {:?}", monitor.id, monitor.bounds);
+    /// ```
+    fn current_monitor(&self) -> Result<Monitor, Error> {
+        crate::display::monitor_at(self.get_position()?)
+    }
+    /// Get the current position of the mouse
+    ///
+    /// # Examples
+    ///
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::error::Error;
+    ///
+    /// let manager = Mouse::new();
+    /// manager.move_to(0, 0);
+    /// // This function may not be implemented on some platforms such as Linux Wayland
+    /// let valid_outs = vec![Ok((0, 0)), Err(Error::NotImplemented)];
+    /// assert!(valid_outs.contains(&manager.get_position()));
+    /// ```
+    fn get_position(&self) -> Result<(i32, i32), Error>;
+    /// Check whether the given mouse button is currently held down
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::MouseButton;
+    ///
+    /// let manager = Mouse::new();
+    /// // Recover from an interrupted drag
+    /// if manager.is_button_pressed(&MouseButton::Left) == Ok(true) {
+    ///     manager.release_button(&MouseButton::Left).unwrap();
+    /// }
+    /// ```
+    fn is_button_pressed(&self, button: &MouseButton) -> Result<bool, Error> {
+        Ok(self.get_pressed_buttons()?.is_pressed(button))
+    }
+    /// Get all of the mouse buttons that are currently held down in a single snapshot
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// if let Ok(state) = manager.get_pressed_buttons() {
+    ///     // Release everything that is still held down
+    ///     for button in state.iter() {
+    ///         manager.release_button(&button).unwrap();
+    ///     }
+    /// }
+    /// ```
+    fn get_pressed_buttons(&self) -> Result<ButtonState, Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Press down the given mouse button
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::MouseButton;
+    ///
+    /// let manager = Mouse::new();
+    /// assert_eq!(manager.press_button(&MouseButton::Left), Ok(()));
+    /// ```
+    fn press_button(&self, button: &MouseButton) -> Result<(), Error>;
+    /// Release the given mouse button
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::MouseButton;
+    ///
+    /// let manager = Mouse::new();
+    /// assert_eq!(manager.release_button(&MouseButton::Left), Ok(()));
+    /// ```
+    fn release_button(&self, button: &MouseButton) -> Result<(), Error>;
+    /// Click the given mouse button
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::MouseButton;
+    ///
+    /// let manager = Mouse::new();
+    /// assert_eq!(manager.click_button(&MouseButton::Left), Ok(()));
+    /// ```
+    fn click_button(&self, button: &MouseButton) -> Result<(), Error> {
+        self.press_button(button)?;
+        self.release_button(button)
+    }
+    /// Click the given mouse button once as the click number `click_count` of a multi-click,
+    /// e.g. 2 for the second click of a double-click (the click count is macOS only)
+    ///
+    /// macOS applications tell the multi-clicks apart by the click count of the events
+    /// rather than their timing, it is set as `kCGMouseEventClickState`. The other
+    /// platforms detect the multi-clicks from the timing, so this is a plain click there
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::MouseButton;
+    ///
+    /// let manager = Mouse::new();
+    /// assert_eq!(manager.click_with_count(&MouseButton::Left, 1), Ok(()));
+    /// assert_eq!(manager.click_with_count(&MouseButton::Left, 2), Ok(()));
+    /// ```
+    fn click_with_count(&self, button: &MouseButton, click_count: u32) -> Result<(), Error> {
+        let _ = click_count;
+        self.click_button(button)
+    }
+    /// Click the given mouse button `count` times in a row, fast enough to be
+    /// recognized as a double-click, triple-click etc.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::MouseButton;
+    ///
+    /// let manager = Mouse::new();
+    /// // Triple-click to select a paragraph
+    /// assert_eq!(manager.click_n(&MouseButton::Left, 3), Ok(()));
+    /// ```
+    fn click_n(&self, button: &MouseButton, count: u32) -> Result<(), Error> {
+        for click in 0..count {
+            if click > 0 {
+                thread::sleep(MULTI_CLICK_INTERVAL);
+            }
+            self.click_with_count(button, click + 1)?;
+        }
+        Ok(())
+    }
+    /// Double-click the given mouse button
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::MouseButton;
+    ///
+    /// let manager = Mouse::new();
+    /// assert_eq!(manager.double_click(&MouseButton::Left), Ok(()));
+    /// ```
+    fn double_click(&self, button: &MouseButton) -> Result<(), Error> {
+        self.click_n(button, 2)
+    }
+    /// Press the given mouse button, keep it down for the given duration and release it,
+    /// e.g. for the long-press interactions
+    ///
+    /// The duration is counted from the end of the press, so the delays that the
+    /// backends add while injecting the press do not shorten the hold
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::MouseButton;
+    /// use std::time::Duration;
+    ///
+    /// let manager = Mouse::new();
+    /// assert_eq!(manager.hold_button(&MouseButton::Left, Duration::from_secs(1)), Ok(()));
+    /// ```
+    fn hold_button(&self, button: &MouseButton, duration: Duration) -> Result<(), Error> {
+        self.press_button(button)?;
+        thread::sleep(duration);
+        self.release_button(button)
+    }
+    /// Move the mouse to the given `x`, `y` coordinates and press down the given button
+    ///
+    /// Backends that can embed the coordinates into the button event do so, so the
+    /// press can not land anywhere else if the mouse is moved in between
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::MouseButton;
+    ///
+    /// let manager = Mouse::new();
+    /// assert_eq!(manager.press_at(100, 100, &MouseButton::Left), Ok(()));
+    /// ```
+    fn press_at(&self, x: usize, y: usize, button: &MouseButton) -> Result<(), Error> {
+        self.move_to(x, y)?;
+        self.press_button(button)
+    }
+    /// Move the mouse to the given `x`, `y` coordinates and release the given button
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::MouseButton;
+    ///
+    /// let manager = Mouse::new();
+    /// assert_eq!(manager.release_at(100, 100, &MouseButton::Left), Ok(()));
+    /// ```
+    fn release_at(&self, x: usize, y: usize, button: &MouseButton) -> Result<(), Error> {
+        self.move_to(x, y)?;
+        self.release_button(button)
+    }
+    /// Move the mouse to the given `x`, `y` coordinates and click the given button
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::MouseButton;
+    ///
+    /// let manager = Mouse::new();
+    /// assert_eq!(manager.click_at(100, 100, &MouseButton::Left), Ok(()));
+    /// ```
+    fn click_at(&self, x: usize, y: usize, button: &MouseButton) -> Result<(), Error> {
+        self.move_to(x, y)?;
+        self.click_button(button)
+    }
+    /// Scroll the mouse wheel towards to the given direction by the given distance
+    ///
+    /// Backends that can not scroll in pixels return `Error::NotImplemented` for
+    /// `ScrollUnit::Pixel`, except for X11 which approximates the pixels with wheel steps,
+    /// one step for every 50 pixels, so a smaller distance scrolls once they add up to it
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::{ScrollDirection, ScrollUnit};
+    /// use std::{thread, time};
+    ///
+    /// let manager = Mouse::new();
+    /// let sleep_duration = time::Duration::from_millis(250);
+    ///
+    /// for _ in 0..5 {
+    ///     assert_eq!(manager.scroll_wheel(&ScrollDirection::Down, ScrollUnit::Line, 1), Ok(()));
+    ///     thread::sleep(sleep_duration);
+    /// }
+    ///
+    /// // Scroll back smoothly
+    /// for _ in 0..50 {
+    ///     manager.scroll_wheel(&ScrollDirection::Up, ScrollUnit::Pixel, 10).unwrap();
+    ///     thread::sleep(time::Duration::from_millis(10));
+    /// }
+    /// ```
+    fn scroll_wheel(
+        &self,
+        direction: &ScrollDirection,
+        unit: ScrollUnit,
+        distance: u32,
+    ) -> Result<(), Error>;
+    /// Drag from the `from` position to the `to` position while holding down the given button
+    ///
+    /// The mouse is moved to `from`, the button is pressed and the mouse is moved
+    /// towards `to` in small steps over the given `duration` before the button is
+    /// released, since many applications ignore a press that is followed by a single jump
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::MouseButton;
+    /// use std::time::Duration;
+    ///
+    /// let manager = Mouse::new();
+    /// let duration = Duration::from_millis(500);
+    /// assert_eq!(manager.drag((100, 100), (500, 300), &MouseButton::Left, duration), Ok(()));
+    /// ```
+    fn drag(
+        &self,
+        from: (i32, i32),
+        to: (i32, i32),
+        button: &MouseButton,
+        duration: Duration,
+    ) -> Result<(), Error> {
+        let move_to = |(x, y): (i32, i32)| self.move_to(x.max(0) as usize, y.max(0) as usize);
+
+        move_to(from)?;
+        self.press_button(button)?;
+        thread::sleep(DRAG_SETTLE_DELAY);
+
+        let steps = line_steps(duration, DRAG_STEP_INTERVAL);
+        let interval = duration / steps;
+        for step in 1..=steps {
+            if let Err(err) = move_to(line_point(from, to, step, steps)) {
+                // Do not leave the button pressed down
+                let _ = self.release_button(button);
+                return Err(err);
+            }
+            if step < steps {
+                thread::sleep(interval);
+            }
+        }
+
+        thread::sleep(DRAG_SETTLE_DELAY);
+        self.release_button(button)
+    }
+    /// Scroll both of the axes at once, positive `dx` scrolls to the right
+    /// and positive `dy` scrolls down
+    ///
+    /// Backends that support it emit both of the axes in a single event, so
+    /// the diagonal scrolls are not split into a horizontal and a vertical one
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::common::ScrollUnit;
+    ///
+    /// let manager = Mouse::new();
+    /// // Scroll diagonally towards the bottom right
+    /// assert_eq!(manager.scroll_by(20., 40., ScrollUnit::Pixel), Ok(()));
+    /// ```
+    fn scroll_by(&self, dx: f64, dy: f64, unit: ScrollUnit) -> Result<(), Error> {
+        let vertical = if dy < 0. {
+            ScrollDirection::Up
+        } else {
+            ScrollDirection::Down
+        };
+        let horizontal = if dx < 0. {
+            ScrollDirection::Left
+        } else {
+            ScrollDirection::Right
+        };
+
+        for (direction, distance) in [(vertical, dy), (horizontal, dx)] {
+            let distance = distance.abs().round() as u32;
+            if distance > 0 {
+                self.scroll_wheel(&direction, unit, distance)?;
+            }
+        }
+        Ok(())
+    }
+    /// Execute the given actions in order with as few calls to the system as possible
+    ///
+    /// The actions are injected with a single `SendInput` call on windows and with a
+    /// single write of all the reports on uinput, without the delays between them.
+    /// macOS posts the events in a row with a single query of the cursor position,
+    /// the other backends execute the actions one by one
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::common::{Action, MouseButton};
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// let actions = [Action::MoveTo(100, 100), Action::Click(MouseButton::Left)];
+    /// assert_eq!(manager.send_batch(&actions), Ok(()));
+    /// ```
+    fn send_batch(&self, actions: &[Action]) -> Result<(), Error> {
+        for action in actions {
+            match action {
+                Action::MoveTo(x, y) => self.move_to(*x, *y)?,
+                Action::MoveRelative(x, y) => self.move_relative(*x, *y)?,
+                Action::Press(button) => self.press_button(button)?,
+                Action::Release(button) => self.release_button(button)?,
+                Action::Click(button) => self.click_button(button)?,
+                Action::Scroll(direction, unit, distance) => {
+                    self.scroll_wheel(direction, *unit, *distance)?
+                }
+            }
+        }
+        Ok(())
+    }
+    /// Move the mouse along the given path with the given `speed` in pixels per second
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    /// use mouce::path::Path;
+    ///
+    /// let manager = Mouse::new();
+    /// let path = Path::new((100, 100)).quadratic_to((400, 0), (700, 100));
+    /// assert_eq!(manager.trace_path(&path, 1000.), Ok(()));
+    /// ```
+    fn trace_path(&self, path: &Path, speed: f64) -> Result<(), Error> {
+        if speed.is_nan() || speed <= 0. {
+            return Err(Error::CustomError("the speed must be a positive number"));
+        }
+
+        let spacing = speed * TRACE_STEP_INTERVAL.as_secs_f64();
+        let interval = Duration::from_secs_f64(spacing.max(1.) / speed);
+        for (index, (x, y)) in path.waypoints(spacing).into_iter().enumerate() {
+            if index > 0 {
+                thread::sleep(interval);
+            }
+            self.move_to(x.max(0) as usize, y.max(0) as usize)?;
+        }
+        Ok(())
+    }
+    /// Keep the cursor inside the given region of the screen, or let it
+    /// move freely again if the region is `None`
+    ///
+    /// The cursor stays confined until `clip_cursor(None)` is called, windows
+    /// may release it early when another window takes the focus
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::common::Region;
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// // Keep the cursor on the window of a game
+    /// manager.clip_cursor(Some(Region::new(0, 0, 800, 600))).unwrap();
+    /// manager.clip_cursor(None).unwrap();
+    /// ```
+    fn clip_cursor(&self, _region: Option<Region>) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Hide the cursor until `show_cursor` is called, the calls do not nest
+    /// so a single `show_cursor` shows the cursor again
+    ///
+    /// The cursor is only hidden over the windows of the current thread on windows,
+    /// and only while the current application is active on macOS
     ///
     /// # Examples
     ///
@@ -35,10 +1661,20 @@ pub trait MouseActions {
     /// use mouce::Mouse;
     ///
     /// let manager = Mouse::new();
-    /// assert_eq!(manager.move_to(0, 0), Ok(()));
+    /// // Keep the cursor out of the slides of a presentation
+    /// manager.hide_cursor().unwrap();
+    /// manager.show_cursor().unwrap();
     /// ```
-    fn move_to(&self, x: usize, y: usize) -> Result<(), Error>;
-    /// Move the mouse relative to the current position
+    fn hide_cursor(&self) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Show the cursor after `hide_cursor`, nothing changes if it is not hidden
+    fn show_cursor(&self) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Enable or disable the relative capture mode, the movements of the mouse do not
+    /// move the cursor while it is enabled but the hooks still receive their deltas,
+    /// e.g. for the games and the pointer lock of the browsers (macOS only)
     ///
     /// # Examples
     ///
@@ -46,110 +1682,258 @@ pub trait MouseActions {
     /// use mouce::Mouse;
     ///
     /// let manager = Mouse::new();
-    /// assert_eq!(manager.move_relative(100, 100), Ok(()));
+    /// // Keep the cursor in place while a game turns its camera
+    /// manager.set_relative_capture(true).unwrap();
+    /// manager.set_relative_capture(false).unwrap();
     /// ```
-    fn move_relative(&self, x_offset: i32, y_offset: i32) -> Result<(), Error> {
-        let (x, y) = self.get_position()?;
-        self.move_to((x + x_offset) as usize, (y + y_offset) as usize)
+    fn set_relative_capture(&self, _enabled: bool) -> Result<(), Error> {
+        Err(Error::NotImplemented)
     }
-    /// Get the current position of the mouse
+    /// Attach a callback function to mouse events
     ///
     /// # Examples
     ///
-    ///
     /// ```rust,no_run
     /// use mouce::Mouse;
     /// use mouce::error::Error;
     ///
-    /// let manager = Mouse::new();
-    /// manager.move_to(0, 0);
-    /// // This function may not be implemented on some platforms such as Linux Wayland
-    /// let valid_outs = vec![Ok((0, 0)), Err(Error::NotImplemented)];
-    /// assert!(valid_outs.contains(&manager.get_position()));
+    /// let mut manager = Mouse::new();
+    /// let hook_result = manager.hook(Box::new(|e| println!("New event: {:?}", e)));
+    /// match hook_result {
+    ///     Ok(id) => {
+    ///         assert_eq!(manager.unhook(id), Ok(()));
+    ///     }
+    ///     // Hooking may require user privileges on some systems
+    ///     // e.g. requires super user for Linux
+    ///     Err(err) => assert_eq!(Error::PermissionDenied, err),
+    /// }
     /// ```
-    fn get_position(&self) -> Result<(i32, i32), Error>;
-    /// Press down the given mouse button
+    fn hook(&mut self, callback: Box<dyn Fn(&MouseEvent) + Send>) -> Result<CallbackId, Error> {
+        self.hook_with_info(Box::new(move |event, _| callback(event)))
+    }
+    /// Attach a callback function to mouse events that also receives
+    /// the additional information of the events, such as the device
+    /// that produced them
     ///
     /// # Examples
     ///
     /// ```rust,no_run
     /// use mouce::Mouse;
-    /// use mouce::common::MouseButton;
     ///
-    /// let manager = Mouse::new();
-    /// assert_eq!(manager.press_button(&MouseButton::Left), Ok(()));
+    /// let mut manager = Mouse::new();
+    /// let hook_result = manager.hook_with_info(Box::new(|event, info| {
+    ///     if let Some(device) = &info.device {
+    ///         println!("{:?} from {:?}", event, device.name);
+    ///     }
+    /// }));
+    /// assert!(hook_result.is_ok());
     /// ```
-    fn press_button(&self, button: &MouseButton) -> Result<(), Error>;
-    /// Release the given mouse button
+    fn hook_with_info(&mut self, callback: EventCallback) -> Result<CallbackId, Error>;
+    /// Attach a callback function that can mutate the state it captures,
+    /// e.g. a counter, without wrapping the state in an `Arc<Mutex<...>>`
     ///
     /// # Examples
     ///
     /// ```rust,no_run
     /// use mouce::Mouse;
-    /// use mouce::common::MouseButton;
     ///
-    /// let manager = Mouse::new();
-    /// assert_eq!(manager.release_button(&MouseButton::Left), Ok(()));
+    /// let mut manager = Mouse::new();
+    /// let mut clicks = 0;
+    /// let hook_result = manager.hook_mut(Box::new(move |event, _| {
+    ///     if let mouce::common::MouseEvent::Press(_) = event {
+    ///         clicks += 1;
+    ///         println!("Clicked {} times", clicks);
+    ///     }
+    /// }));
+    /// assert!(hook_result.is_ok());
     /// ```
-    fn release_button(&self, button: &MouseButton) -> Result<(), Error>;
-    /// Click the given mouse button
+    fn hook_mut(&mut self, callback: EventCallbackMut) -> Result<CallbackId, Error> {
+        // The callbacks are never invoked at the same time, the lock is never contended
+        let callback = Mutex::new(callback);
+        self.hook_with_info(Box::new(move |event, info| {
+            (callback.lock().unwrap())(event, info)
+        }))
+    }
+    /// Attach a callback function that only receives the events selected by `mask`
+    ///
+    /// The backends that can filter the events at the source (e.g. the event
+    /// tap on macOS) do not even listen to the events that no hook selects,
+    /// which saves a lot of work for the hooks that do not need the moves
     ///
     /// # Examples
     ///
     /// ```rust,no_run
+    /// use mouce::common::EventMask;
     /// use mouce::Mouse;
-    /// use mouce::common::MouseButton;
     ///
-    /// let manager = Mouse::new();
-    /// assert_eq!(manager.click_button(&MouseButton::Left), Ok(()));
+    /// let mut manager = Mouse::new();
+    /// let hook_result = manager.hook_filtered(
+    ///     EventMask::PRESS | EventMask::RELEASE,
+    ///     Box::new(|event, _| println!("Clicked: {:?}", event)),
+    /// );
+    /// assert!(hook_result.is_ok());
     /// ```
-    fn click_button(&self, button: &MouseButton) -> Result<(), Error> {
-        self.press_button(button)?;
-        self.release_button(button)
+    fn hook_filtered(
+        &mut self,
+        mask: EventMask,
+        callback: EventCallback,
+    ) -> Result<CallbackId, Error> {
+        self.hook_with_info(mask.filter(callback))
     }
-    /// Scroll the mouse wheel towards to the given direction
+    /// Attach a callback function that only receives the events
+    /// whose position falls inside the given region of the screen
+    ///
+    /// The events without a position are never received, so the backends that
+    /// only see the relative motion (e.g. `/dev/input` or libinput) never call it
     ///
     /// # Examples
     ///
     /// ```rust,no_run
+    /// use mouce::common::Region;
     /// use mouce::Mouse;
-    /// use mouce::common::ScrollDirection;
-    /// use std::{thread, time};
     ///
-    /// let manager = Mouse::new();
-    /// let sleep_duration = time::Duration::from_millis(250);
+    /// let mut manager = Mouse::new();
+    /// let hook_result = manager.hook_in_region(
+    ///     Region::new(0, 0, 200, 100),
+    ///     Box::new(|event, _| println!("Inside the region: {:?}", event)),
+    /// );
+    /// assert!(hook_result.is_ok());
+    /// ```
+    fn hook_in_region(
+        &mut self,
+        region: Region,
+        callback: EventCallback,
+    ) -> Result<CallbackId, Error> {
+        self.hook_with_info(region.filter(callback))
+    }
+    /// Get a blocking iterator over the hooked mouse events, it ends once the
+    /// callback behind it is removed (e.g. by `unhook_all` or `stop_listening`)
     ///
-    /// for _ in 0..5 {
-    ///     assert_eq!(manager.scroll_wheel(&ScrollDirection::Down), Ok(()));
-    ///     thread::sleep(sleep_duration);
-    /// }
+    /// # Examples
     ///
-    /// for _ in 0..5 {
-    ///     assert_eq!(manager.scroll_wheel(&ScrollDirection::Up), Ok(()));
-    ///     thread::sleep(sleep_duration);
+    /// ```rust,no_run
+    /// use mouce::common::MouseEvent;
+    /// use mouce::Mouse;
+    /// use std::time::Duration;
+    ///
+    /// let mut manager = Mouse::new();
+    /// // Stop once the mouse is idle for 5 seconds
+    /// for event in manager.events().unwrap().timeout(Duration::from_secs(5)) {
+    ///     if let MouseEvent::Press(button) = event {
+    ///         println!("{:?} button is pressed", button);
+    ///     }
     /// }
     /// ```
-    fn scroll_wheel(&self, direction: &ScrollDirection) -> Result<(), Error>;
-    /// Attach a callback function to mouse events
+    fn events(&mut self) -> Result<MouseEvents, Error> {
+        let (sender, receiver) = mpsc::channel();
+        let callback_id = self.hook(Box::new(move |event| {
+            let _ = sender.send(*event);
+        }))?;
+        Ok(MouseEvents {
+            callback_id,
+            receiver,
+            timeout: None,
+        })
+    }
+    /// Get an asynchronous stream of the hooked mouse events, it ends once the
+    /// callback behind it is removed (e.g. by `unhook_all` or `stop_listening`)
     ///
     /// # Examples
     ///
     /// ```rust,no_run
+    /// use futures_core::Stream;
     /// use mouce::Mouse;
-    /// use mouce::error::Error;
+    /// use std::future::poll_fn;
+    /// use std::pin::Pin;
     ///
-    /// let mut manager = Mouse::new();
-    /// let hook_result = manager.hook(Box::new(|e| println!("New event: {:?}", e)));
-    /// match hook_result {
-    ///     Ok(id) => {
-    ///         assert_eq!(manager.unhook(id), Ok(()));
+    /// async fn print_events() {
+    ///     let mut manager = Mouse::new();
+    ///     let mut stream = manager.event_stream().unwrap();
+    ///     while let Some(event) = poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await {
+    ///         println!("{:?}", event);
     ///     }
-    ///     // Hooking may require user privileges on some systems
-    ///     // e.g. requires super user for Linux
-    ///     Err(err) => assert_eq!(Error::PermissionDenied, err),
     /// }
     /// ```
-    fn hook(&mut self, callback: Box<dyn Fn(&MouseEvent) + Send>) -> Result<CallbackId, Error>;
+    #[cfg(feature = "async")]
+    fn event_stream(&mut self) -> Result<EventStream, Error> {
+        let state = Arc::new(Mutex::new(EventStreamState::default()));
+        let sender = EventStreamSender(state.clone());
+        let callback_id = self.hook(Box::new(move |event| sender.send(*event)))?;
+        Ok(EventStream { callback_id, state })
+    }
+    /// Attach a callback function that can swallow or replace the events
+    /// before they reach the other applications, e.g. to remap the buttons
+    ///
+    /// The replaced events are injected as new events, so they are the ones
+    /// that the other hooks receive. It needs the exclusive access to the
+    /// mice on linux (uinput only), and an event tap that is not listen only
+    /// on macOS (see `MouseBuilder::tap_listen_only`)
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::common::{MouseButton, MouseEvent};
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::builder().tap_listen_only(false).build().unwrap();
+    /// // Swap the back and the forward buttons
+    /// let hook_result = manager.hook_rewrite(Box::new(|event, _| match event {
+    ///     MouseEvent::Press(MouseButton::Back) => Some(MouseEvent::Press(MouseButton::Forward)),
+    ///     MouseEvent::Press(MouseButton::Forward) => Some(MouseEvent::Press(MouseButton::Back)),
+    ///     MouseEvent::Release(MouseButton::Back) => Some(MouseEvent::Release(MouseButton::Forward)),
+    ///     MouseEvent::Release(MouseButton::Forward) => Some(MouseEvent::Release(MouseButton::Back)),
+    ///     event => Some(*event),
+    /// }));
+    /// assert!(hook_result.is_ok());
+    /// ```
+    fn hook_rewrite(&mut self, _callback: RewriteCallback) -> Result<CallbackId, Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Choose whether the hooks receive the events that are injected by the
+    /// mouse managers of this process, they are received by default
+    ///
+    /// The injected events are tagged so that they can be told apart, with
+    /// the extra info of the input on windows, the source user data of the
+    /// event on macOS and the virtual uinput device on linux (uinput only)
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::new();
+    /// assert_eq!(manager.set_ignore_own_events(true), Ok(()));
+    /// manager.hook(Box::new(|event| println!("Only the user moved: {:?}", event))).unwrap();
+    /// // The hook does not receive this move
+    /// manager.move_to(100, 100).unwrap();
+    /// ```
+    fn set_ignore_own_events(&mut self, _ignore: bool) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Invoke the hooked callbacks with the given event as if the listener received it,
+    /// the event does not reach the operating system, so the callbacks can be tested
+    ///
+    /// The event is reported as injected and without a position, and the callbacks
+    /// are not invoked while the listening is paused
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::common::{MouseButton, MouseEvent};
+    /// use mouce::Mouse;
+    /// use std::sync::mpsc;
+    ///
+    /// let mut manager = Mouse::new();
+    /// let (tx, rx) = mpsc::channel();
+    /// manager.hook(Box::new(move |event| tx.send(*event).unwrap())).unwrap();
+    ///
+    /// let event = MouseEvent::Press(MouseButton::Left);
+    /// assert_eq!(manager.simulate_event(&event), Ok(()));
+    /// assert_eq!(rx.recv(), Ok(event));
+    /// ```
+    fn simulate_event(&self, _event: &MouseEvent) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
     /// Remove the callback function with the given `CallbackId`
     fn unhook(&mut self, callback_id: CallbackId) -> Result<(), Error>;
     /// Remove all callback functions
@@ -163,14 +1947,548 @@ pub trait MouseActions {
     /// assert_eq!(manager.unhook_all(), Ok(()));
     /// ```
     fn unhook_all(&mut self) -> Result<(), Error>;
+    /// Stop invoking the hooked callbacks until `resume_listening` is called,
+    /// the callbacks stay hooked so they do not need to be hooked again
+    ///
+    /// The event tap is disabled on macOS and the hook is removed on windows,
+    /// the other backends keep receiving the events but drop them
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::new();
+    /// manager.hook(Box::new(|event| println!("{:?}", event))).unwrap();
+    /// assert_eq!(manager.pause_listening(), Ok(()));
+    /// // Nothing is printed until the listening is resumed
+    /// assert_eq!(manager.resume_listening(), Ok(()));
+    /// ```
+    fn pause_listening(&mut self) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Resume invoking the hooked callbacks after `pause_listening`
+    fn resume_listening(&mut self) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Remove all of the hooked callbacks and tear down the listener, the
+    /// listener threads are joined and the devices that it opened are closed
+    ///
+    /// Unlike `unhook_all` nothing keeps running in the background afterwards,
+    /// hooking a new callback starts a new listener
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::new();
+    /// manager.hook(Box::new(|event| println!("{:?}", event))).unwrap();
+    /// assert_eq!(manager.stop_listening(), Ok(()));
+    /// ```
+    fn stop_listening(&mut self) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Get a channel that receives an `Error::CallbackPanicked` for each hooked
+    /// callback that panics from now on
+    ///
+    /// A callback that panics is unhooked, the other callbacks keep receiving the events
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::new();
+    /// let panics = manager.callback_panics().unwrap();
+    /// manager.hook(Box::new(|_| panic!("oops"))).unwrap();
+    /// // The panic is reported once the first event arrives
+    /// println!("{}", panics.recv().unwrap());
+    /// ```
+    fn callback_panics(&mut self) -> Result<Receiver<Error>, Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Get the number of the events that the listener dropped so far because its
+    /// queue was full, see `MouseBuilder::listener_queue` (linux and the BSDs only)
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::new();
+    /// manager.hook(Box::new(|event| println!("{:?}", event))).unwrap();
+    /// println!("{} events were dropped", manager.dropped_events().unwrap());
+    /// ```
+    fn dropped_events(&self) -> Result<u64, Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Get the number of times that the system disabled the listener so far, e.g. the
+    /// event tap on macOS once the callbacks are too slow or the system sleeps (macOS only)
+    ///
+    /// The listener is enabled again right away, but the events in between are lost,
+    /// the hooks receive a `MouseEvent::ListenerInterrupted` each time
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::new();
+    /// manager.hook(Box::new(|event| println!("{:?}", event))).unwrap();
+    /// println!("The listener was interrupted {} times", manager.listener_interruptions().unwrap());
+    /// ```
+    fn listener_interruptions(&self) -> Result<u64, Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Get the event files of the mice that the listener could not open, along with
+    /// the reasons, e.g. the missing permissions of `/dev/input` (linux and the BSDs only)
+    ///
+    /// The mice that can be opened are hooked nonetheless, hooking only fails if none of them can be
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let mut manager = Mouse::new();
+    /// manager.hook(Box::new(|event| println!("{:?}", event))).unwrap();
+    /// for (path, err) in manager.unreadable_devices().unwrap() {
+    ///     println!("{} is not hooked: {}", path, err);
+    /// }
+    /// ```
+    fn unreadable_devices(&self) -> Result<Vec<(String, Error)>, Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Take the exclusive access to the mouse with the given id, its events do
+    /// not reach the rest of the system anymore, only the hooked callbacks
+    ///
+    /// The devices are grabbed with EVIOCGRAB, so it needs the read access to
+    /// `/dev/input` (linux and the BSDs only), the ids are the ones that the
+    /// `EventInfo` of the hooked events and `mouce::hotplug` report
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::common::{MouseButton, MouseEvent};
+    /// use mouce::Mouse;
+    /// use std::sync::mpsc;
+    ///
+    /// let mut manager = Mouse::new();
+    /// let (tx, rx) = mpsc::channel();
+    /// manager
+    ///     .hook_with_info(Box::new(move |event, info| {
+    ///         if *event == MouseEvent::Press(MouseButton::Left) {
+    ///             let _ = tx.send(info.device.clone());
+    ///         }
+    ///     }))
+    ///     .unwrap();
+    /// // Own the mouse that is clicked first
+    /// if let Some(device) = rx.recv().unwrap() {
+    ///     assert_eq!(manager.grab_device(device.id), Ok(()));
+    /// }
+    /// ```
+    fn grab_device(&mut self, _device_id: DeviceId) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
+    /// Give the mouse that is grabbed with `grab_device` back to the system
+    fn release_device(&mut self, _device_id: DeviceId) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::common::{
+        line_point, line_steps, rewrite_event, ButtonState, CallbackExecution, CallbackId,
+        CallbackMap, DragTracker, EventInfo, EventMask, MouseButton, MouseEvent, MoveCoalescing,
+        Region, Rewriters, ScrollDirection, ScrollInversion, ScrollUnit,
+    };
     use crate::error::Error;
-    use crate::{common::MouseButton, common::ScrollDirection, Mouse};
+    use crate::Mouse;
+    use std::sync::{mpsc, Arc, Mutex};
     use std::{thread, time};
 
+    #[test]
+    fn button_state() {
+        let mut state = ButtonState::new();
+        assert!(state.is_empty());
+
+        state.insert(&MouseButton::Right);
+        state.insert(&MouseButton::Left);
+        state.insert(&MouseButton::Left);
+        assert_eq!(state.bits(), 0b101);
+        assert!(!state.is_pressed(&MouseButton::Middle));
+
+        state.remove(&MouseButton::Left);
+        assert_eq!(state.iter().collect::<Vec<_>>(), vec![MouseButton::Right]);
+        assert_eq!(
+            [MouseButton::Right].into_iter().collect::<ButtonState>(),
+            state
+        );
+
+        state.insert(&MouseButton::Forward);
+        state.insert(&MouseButton::Other(26));
+        // The buttons that do not fit into the state are ignored
+        state.insert(&MouseButton::Other(27));
+        assert_eq!(
+            state.iter().collect::<Vec<_>>(),
+            vec![
+                MouseButton::Right,
+                MouseButton::Forward,
+                MouseButton::Other(26)
+            ]
+        );
+        assert!(!state.is_pressed(&MouseButton::Other(27)));
+    }
+
+    #[test]
+    fn line_interpolation() {
+        assert_eq!(line_point((0, 0), (100, -50), 1, 4), (25, -12));
+        assert_eq!(line_point((0, 0), (100, -50), 4, 4), (100, -50));
+        // The distance between the ends overflows i32
+        assert_eq!(
+            line_point((i32::MIN, i32::MAX), (i32::MAX, i32::MIN), 2, 2),
+            (i32::MAX, i32::MIN)
+        );
+        assert_eq!(line_point((i32::MIN, 0), (i32::MAX, 0), 1, 2), (-1, 0));
+
+        let interval = time::Duration::from_millis(10);
+        assert_eq!(line_steps(time::Duration::ZERO, interval), 1);
+        assert_eq!(line_steps(time::Duration::from_millis(105), interval), 10);
+        assert_eq!(line_steps(time::Duration::MAX, interval), 100_000);
+    }
+
+    #[test]
+    fn drag_tracker() {
+        let mut tracker = DragTracker::default();
+        let moved = MouseEvent::Move {
+            position: None,
+            delta: (3, -1),
+        };
+        assert_eq!(tracker.track(moved), moved);
+
+        tracker.track(MouseEvent::Press(MouseButton::Right));
+        tracker.track(MouseEvent::Press(MouseButton::Left));
+        assert_eq!(
+            tracker.track(moved),
+            MouseEvent::Drag {
+                button: MouseButton::Left,
+                position: None,
+                delta: (3, -1)
+            }
+        );
+
+        tracker.track(MouseEvent::Release(MouseButton::Left));
+        tracker.track(MouseEvent::Release(MouseButton::Right));
+        assert_eq!(tracker.track(moved), moved);
+    }
+
+    #[test]
+    fn scroll_inversion() {
+        let mut inversion = ScrollInversion {
+            vertical: true,
+            ..ScrollInversion::NONE
+        };
+        assert_eq!(
+            inversion.direction(&ScrollDirection::Up),
+            ScrollDirection::Down
+        );
+        assert_eq!(
+            inversion.direction(&ScrollDirection::Left),
+            ScrollDirection::Left
+        );
+        assert_eq!(inversion.deltas(2., -3.), (2., 3.));
+
+        // The hooked scrolls are only inverted if the option is enabled
+        let scroll = MouseEvent::Scroll {
+            direction: ScrollDirection::Down,
+            unit: ScrollUnit::Line,
+            distance: 1.,
+        };
+        assert_eq!(inversion.hooked_event(scroll), scroll);
+        inversion.hooked = true;
+        assert_eq!(
+            inversion.hooked_event(scroll),
+            MouseEvent::Scroll {
+                direction: ScrollDirection::Up,
+                unit: ScrollUnit::Line,
+                distance: 1.,
+            }
+        );
+        let press = MouseEvent::Press(MouseButton::Left);
+        assert_eq!(inversion.hooked_event(press), press);
+    }
+
+    #[test]
+    fn callback_ids() {
+        let first = CallbackId::next();
+        let second = CallbackId::next();
+        assert!(second > first);
+        assert_eq!(CallbackId::from(7u8).as_u64(), 7);
+        assert_eq!(u8::try_from(CallbackId(300)).ok(), None);
+    }
+
+    #[test]
+    fn paused_callbacks() {
+        let mut callbacks = CallbackMap::new();
+        callbacks.insert(CallbackId::next(), Box::new(|_, _| {}));
+        assert_eq!(callbacks.values().count(), 1);
+
+        callbacks.paused = true;
+        assert_eq!(callbacks.values().count(), 0);
+        callbacks.paused = false;
+        assert_eq!(callbacks.values().count(), 1);
+    }
+
+    #[test]
+    fn panicking_callbacks() {
+        let mut callbacks = CallbackMap::new();
+        let panics = callbacks.panic_receiver();
+        let id = CallbackId::next();
+        callbacks.insert(id, Box::new(|_, _| panic!("oops")));
+        let count = Arc::new(Mutex::new(0));
+        let counter = count.clone();
+        callbacks.insert(
+            CallbackId::next(),
+            Box::new(move |_, _| *counter.lock().unwrap() += 1),
+        );
+
+        // The panicking callback is unhooked and the other one keeps running
+        let press = MouseEvent::Press(MouseButton::Left);
+        callbacks.invoke(&press, &EventInfo::default());
+        callbacks.invoke(&press, &EventInfo::default());
+        assert_eq!(*count.lock().unwrap(), 2);
+        assert_eq!(callbacks.values().count(), 1);
+        assert_eq!(
+            panics.try_recv(),
+            Ok(Error::CallbackPanicked {
+                id,
+                message: "oops".to_string()
+            })
+        );
+        assert!(panics.try_recv().is_err());
+    }
+
+    #[test]
+    fn callback_execution() {
+        for execution in [CallbackExecution::Dispatcher, CallbackExecution::Pool(3)] {
+            let mut callbacks = CallbackMap::new();
+            callbacks.set_execution(execution);
+            let panics = callbacks.panic_receiver();
+            let (sender, receiver) = mpsc::channel();
+            let caller = thread::current().id();
+            callbacks.insert(
+                CallbackId::next(),
+                Box::new(move |event, _| {
+                    assert_ne!(thread::current().id(), caller);
+                    sender.send(*event).unwrap();
+                }),
+            );
+            callbacks.insert(CallbackId::next(), Box::new(|_, _| panic!("oops")));
+
+            let press = MouseEvent::Press(MouseButton::Left);
+            callbacks.invoke(&press, &EventInfo::default());
+            callbacks.invoke(&press, &EventInfo::default());
+            let timeout = time::Duration::from_secs(5);
+            assert_eq!(receiver.recv_timeout(timeout), Ok(press));
+            assert_eq!(receiver.recv_timeout(timeout), Ok(press));
+
+            // The panic is reported from the other thread, the next event unhooks the callback
+            assert!(panics.recv_timeout(timeout).is_ok());
+            callbacks.invoke(&press, &EventInfo::default());
+            assert_eq!(callbacks.values().count(), 1);
+        }
+    }
+
+    #[test]
+    fn move_coalescing() {
+        let moved = |dx| MouseEvent::Move {
+            position: None,
+            delta: (dx, 0),
+        };
+        let timeout = time::Duration::from_secs(5);
+
+        // The moves that queue up while the callback is busy are merged
+        let mut callbacks = CallbackMap::new();
+        callbacks.set_move_coalescing(MoveCoalescing::Latest);
+        let (sender, receiver) = mpsc::channel();
+        let (resume, resumed) = mpsc::channel::<()>();
+        let resumed = Mutex::new(resumed);
+        callbacks.insert(
+            CallbackId::next(),
+            Box::new(move |event, _| {
+                sender.send(*event).unwrap();
+                let _ = resumed.lock().unwrap().recv();
+            }),
+        );
+        callbacks.invoke(&moved(1), &EventInfo::default());
+        assert_eq!(receiver.recv_timeout(timeout), Ok(moved(1)));
+        for event in [
+            moved(1),
+            moved(2),
+            MouseEvent::Press(MouseButton::Left),
+            moved(4),
+        ] {
+            callbacks.invoke(&event, &EventInfo::default());
+        }
+        drop(resume);
+        let received: Vec<MouseEvent> = (0..3)
+            .map(|_| receiver.recv_timeout(timeout).unwrap())
+            .collect();
+        assert_eq!(
+            received,
+            [moved(3), MouseEvent::Press(MouseButton::Left), moved(4)]
+        );
+
+        // The sampled moves keep the sum of the deltas
+        let mut callbacks = CallbackMap::new();
+        callbacks.set_move_coalescing(MoveCoalescing::Sample(10));
+        let (sender, receiver) = mpsc::channel();
+        callbacks.insert(
+            CallbackId::next(),
+            Box::new(move |event, _| sender.send(*event).unwrap()),
+        );
+        for _ in 0..20 {
+            callbacks.invoke(&moved(1), &EventInfo::default());
+        }
+        let mut total = 0;
+        let mut count = 0;
+        while total < 20 {
+            match receiver.recv_timeout(timeout) {
+                Ok(MouseEvent::Move { delta, .. }) => total += delta.0,
+                event => panic!("unexpected event {:?}", event),
+            }
+            count += 1;
+        }
+        assert_eq!(total, 20);
+        assert!(count < 20);
+    }
+
+    #[test]
+    fn event_mask() {
+        let clicks = EventMask::PRESS | EventMask::RELEASE;
+        assert!(EventMask::ALL.contains(clicks));
+        assert!(!clicks.contains(EventMask::MOVE));
+        assert_eq!(
+            EventMask::ALL.difference(clicks),
+            EventMask::MOVE | EventMask::SCROLL
+        );
+        assert!(clicks.difference(EventMask::ALL).is_empty());
+
+        assert!(EventMask::MOVE.matches(&MouseEvent::Drag {
+            button: MouseButton::Left,
+            position: None,
+            delta: (1, 1),
+        }));
+        assert!(EventMask::SCROLL.matches(&MouseEvent::Scroll {
+            direction: ScrollDirection::Up,
+            unit: ScrollUnit::Line,
+            distance: 1.,
+        }));
+        assert!(!clicks.matches(&MouseEvent::Scroll {
+            direction: ScrollDirection::Up,
+            unit: ScrollUnit::Line,
+            distance: 1.,
+        }));
+    }
+
+    #[test]
+    fn parse_and_display() {
+        for button in [
+            MouseButton::Left,
+            MouseButton::Middle,
+            MouseButton::Right,
+            MouseButton::Back,
+            MouseButton::Forward,
+            MouseButton::Other(0),
+            MouseButton::Other(249),
+        ] {
+            assert_eq!(button.to_string().parse(), Ok(button));
+        }
+        assert_eq!("Left".parse(), Ok(MouseButton::Left));
+        assert!("5".parse::<MouseButton>().is_err());
+        assert!("256".parse::<MouseButton>().is_err());
+
+        for direction in [
+            ScrollDirection::Up,
+            ScrollDirection::Down,
+            ScrollDirection::Right,
+            ScrollDirection::Left,
+        ] {
+            assert_eq!(direction.to_string().parse(), Ok(direction));
+        }
+        assert!("sideways".parse::<ScrollDirection>().is_err());
+        assert_eq!("pixel".parse(), Ok(ScrollUnit::Pixel));
+        assert_eq!(ScrollUnit::Line.to_string(), "line");
+
+        assert_eq!(
+            "Scroll, move".parse(),
+            Ok(EventMask::SCROLL | EventMask::MOVE)
+        );
+        assert!("press,".parse::<EventMask>().is_err());
+    }
+
+    #[test]
+    fn region_filter() {
+        let region = Region::new(-10, -10, 20, 20);
+        assert!(region.contains((-10, 9)));
+        assert!(!region.contains((10, 0)));
+        assert!(!Region::new(i32::MAX, 0, u32::MAX, 1).contains((i32::MIN, 0)));
+        assert_eq!(region.clamp((-20, 15)), (-10, 9));
+        assert_eq!(region.clamp((0, 0)), (0, 0));
+        assert!(Region::new(0, 0, 0, 10).is_empty());
+
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sender = received.clone();
+        let callback = region.filter(Box::new(move |event, _| {
+            sender.lock().unwrap().push(*event);
+        }));
+        let inside = EventInfo {
+            position: Some((0, 0)),
+            ..EventInfo::default()
+        };
+        let press = MouseEvent::Press(MouseButton::Left);
+        callback(&press, &inside);
+        callback(&press, &EventInfo::default());
+        // The position of the move is preferred over the position of the event info
+        let moved = MouseEvent::Move {
+            position: Some((20, 0)),
+            delta: (20, 0),
+        };
+        callback(&moved, &inside);
+        assert_eq!(*received.lock().unwrap(), vec![press]);
+    }
+
+    #[test]
+    fn rewrite_chain() {
+        let info = EventInfo::default();
+        let press = MouseEvent::Press(MouseButton::Back);
+        let mut rewriters = Rewriters::new();
+        assert_eq!(rewrite_event(&rewriters, press, &info), Some(press));
+
+        rewriters.insert(
+            CallbackId(1),
+            Box::new(|event, _| match event {
+                MouseEvent::Press(MouseButton::Forward) => None,
+                event => Some(*event),
+            }),
+        );
+        rewriters.insert(
+            CallbackId(0),
+            Box::new(|event, _| match event {
+                MouseEvent::Press(MouseButton::Back) => {
+                    Some(MouseEvent::Press(MouseButton::Forward))
+                }
+                event => Some(*event),
+            }),
+        );
+        // The earlier callback remaps the button, so the later one swallows it
+        assert_eq!(rewrite_event(&rewriters, press, &info), None);
+        let release = MouseEvent::Release(MouseButton::Back);
+        assert_eq!(rewrite_event(&rewriters, release, &info), Some(release));
+    }
+
     #[test]
     #[ignore]
     fn move_to_right_bottom() {
@@ -245,7 +2563,7 @@ mod tests {
         let manager = Mouse::new();
         match manager.get_position() {
             Ok(_) => {
-                let positions = vec![
+                let positions = [
                     (0, 0),
                     (100, 100),
                     (250, 250),
@@ -279,7 +2597,10 @@ mod tests {
     fn scroll_down() {
         let manager = Mouse::new();
         for _ in 0..10 {
-            assert_eq!(manager.scroll_wheel(&ScrollDirection::Down), Ok(()));
+            assert_eq!(
+                manager.scroll_wheel(&ScrollDirection::Down, ScrollUnit::Line, 1),
+                Ok(())
+            );
             let sleep_duration = time::Duration::from_millis(250);
             thread::sleep(sleep_duration);
         }
@@ -290,7 +2611,10 @@ mod tests {
     fn scroll_up() {
         let manager = Mouse::new();
         for _ in 0..10 {
-            assert_eq!(manager.scroll_wheel(&ScrollDirection::Up), Ok(()));
+            assert_eq!(
+                manager.scroll_wheel(&ScrollDirection::Up, ScrollUnit::Line, 1),
+                Ok(())
+            );
             let sleep_duration = time::Duration::from_millis(250);
             thread::sleep(sleep_duration);
         }
@@ -301,7 +2625,10 @@ mod tests {
     fn scroll_right() {
         let manager = Mouse::new();
         for _ in 0..10 {
-            assert_eq!(manager.scroll_wheel(&ScrollDirection::Right), Ok(()));
+            assert_eq!(
+                manager.scroll_wheel(&ScrollDirection::Right, ScrollUnit::Line, 1),
+                Ok(())
+            );
             let sleep_duration = time::Duration::from_millis(250);
             thread::sleep(sleep_duration);
         }
@@ -312,7 +2639,10 @@ mod tests {
     fn scroll_left() {
         let manager = Mouse::new();
         for _ in 0..10 {
-            assert_eq!(manager.scroll_wheel(&ScrollDirection::Left), Ok(()));
+            assert_eq!(
+                manager.scroll_wheel(&ScrollDirection::Left, ScrollUnit::Line, 1),
+                Ok(())
+            );
             let sleep_duration = time::Duration::from_millis(250);
             thread::sleep(sleep_duration);
         }
@@ -322,7 +2652,10 @@ mod tests {
     #[ignore]
     fn hook_and_unhook() {
         let mut manager = Mouse::new();
-        assert_eq!(manager.unhook(5), Err(Error::UnhookFailed));
+        assert_eq!(
+            manager.unhook(CallbackId(u64::MAX)),
+            Err(Error::UnhookFailed)
+        );
         let hook_result = manager.hook(Box::new(|e| println!("{:?}", e)));
         match hook_result {
             Ok(id) => {
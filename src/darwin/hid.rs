@@ -0,0 +1,519 @@
+///
+/// This module contains a raw event listener for the darwin systems
+/// that is built on top of the IOKit HID manager
+///
+/// Unlike the CGEventTap based hook, the events reported here come
+/// straight from the HID devices, so every event is tagged with the
+/// device that produced it (e.g. to tell a physical mouse from a trackpad)
+///
+use crate::common::{Device, DeviceId};
+use crate::darwin::{
+    create_cf_string, kCFRunLoopDefaultMode, run_until_stopped, CFGetTypeID, CFNumberGetTypeID,
+    CFNumberGetValue, CFRelease, CFRetain, CFRunLoopGetCurrent, CFTypeRef, RunLoopThread,
+    CF_STRING_ENCODING_UTF8,
+};
+use crate::error::Error;
+use crate::hotplug::{DeviceTracker, DeviceWatcher};
+use std::collections::HashMap;
+use std::os::raw::{c_char, c_int, c_long, c_uint, c_ulong, c_void};
+use std::ptr::null_mut;
+use std::sync::atomic::AtomicBool;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+/// A HID device that produced a raw event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HidDevice {
+    /// The IORegistry entry id of the device, it stays the same until the device is removed
+    pub id: DeviceId,
+    pub vendor_id: i32,
+    pub product_id: i32,
+    pub primary_usage_page: u32,
+    pub primary_usage: u32,
+    /// The product name reported by the device, e.g. `Magic Trackpad`
+    pub product: Option<String>,
+    /// The transport used by the device, e.g. `USB` or `Bluetooth`
+    pub transport: Option<String>,
+}
+
+impl HidDevice {
+    /// Returns true if the device looks like a trackpad rather than a mouse
+    pub fn is_trackpad(&self) -> bool {
+        if self.primary_usage_page == USAGE_PAGE_DIGITIZER
+            && self.primary_usage == USAGE_DIGITIZER_TOUCH_PAD
+        {
+            return true;
+        }
+
+        match &self.product {
+            Some(product) => product.to_lowercase().contains("trackpad"),
+            None => false,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HidEventKind {
+    /// A button changed its state, `index` starts from 1 (primary button)
+    Button { index: u32, pressed: bool },
+    /// A generic desktop axis reported a value, e.g. X (0x30), Y (0x31) or Wheel (0x38)
+    Axis { usage: u32, value: i64 },
+    /// Any other element that is not a button or a generic desktop axis
+    Other {
+        usage_page: u32,
+        usage: u32,
+        value: i64,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct HidEvent {
+    pub device: Arc<HidDevice>,
+    pub kind: HidEventKind,
+}
+
+/// A running IOHIDManager listener, the listener is stopped when this is dropped
+pub struct HidListener {
+    thread: Option<RunLoopThread>,
+}
+
+struct ListenerState {
+    /// The callback of the raw events, the input values are not received without it
+    callback: Option<Box<dyn Fn(&HidEvent) + Send>>,
+    devices: Mutex<HashMap<usize, Arc<HidDevice>>>,
+    /// The tracker of the connected devices, see `watch_devices`
+    tracker: Option<Mutex<DeviceTracker>>,
+}
+
+impl HidListener {
+    /// Start listening to the raw events of all the connected mice and trackpads
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::darwin::hid::HidListener;
+    ///
+    /// let listener = HidListener::start(Box::new(|event| {
+    ///     if event.device.is_trackpad() {
+    ///         println!("Trackpad event: {:?}", event.kind);
+    ///     }
+    /// }));
+    /// assert!(listener.is_ok());
+    /// ```
+    pub fn start(callback: Box<dyn Fn(&HidEvent) + Send>) -> Result<Self, Error> {
+        Self::spawn(ListenerState {
+            callback: Some(callback),
+            devices: Mutex::new(HashMap::new()),
+            tracker: None,
+        })
+    }
+
+    fn spawn(state: ListenerState) -> Result<Self, Error> {
+        let (tx, rx) = mpsc::channel();
+        let stopped = Arc::new(AtomicBool::new(false));
+        let thread_stopped = stopped.clone();
+
+        let thread = thread::spawn(move || {
+            let state = Box::into_raw(Box::new(state));
+
+            unsafe {
+                let manager = IOHIDManagerCreate(null_mut(), 0);
+                if manager.is_null() {
+                    let _ = tx.send(Err(Error::CustomError(
+                        "IOKit: failed to create the HID manager",
+                    )));
+                    drop(Box::from_raw(state));
+                    return;
+                }
+
+                let matching = create_matching_array();
+                IOHIDManagerSetDeviceMatchingMultiple(manager, matching);
+                CFRelease(matching);
+
+                IOHIDManagerRegisterDeviceMatchingCallback(
+                    manager,
+                    Some(device_matching_callback),
+                    state as *mut c_void,
+                );
+                IOHIDManagerRegisterDeviceRemovalCallback(
+                    manager,
+                    Some(device_removal_callback),
+                    state as *mut c_void,
+                );
+                if (*state).callback.is_some() {
+                    IOHIDManagerRegisterInputValueCallback(
+                        manager,
+                        Some(input_value_callback),
+                        state as *mut c_void,
+                    );
+                }
+
+                let current_loop = CFRunLoopGetCurrent();
+                IOHIDManagerScheduleWithRunLoop(manager, current_loop, kCFRunLoopDefaultMode);
+
+                let result = IOHIDManagerOpen(manager, 0);
+                if result != IO_RETURN_SUCCESS {
+                    let err = if result == IO_RETURN_NOT_PERMITTED {
+                        Error::PermissionDenied
+                    } else {
+                        Error::CustomError("IOKit: failed to open the HID manager")
+                    };
+                    let _ = tx.send(Err(err));
+                    CFRelease(manager);
+                    drop(Box::from_raw(state));
+                    return;
+                }
+
+                // Keep the run loop alive until the listener is dropped, so that
+                // it is always safe to stop it from another thread
+                CFRetain(current_loop);
+                let _ = tx.send(Ok(current_loop as usize));
+                run_until_stopped(&thread_stopped);
+
+                IOHIDManagerClose(manager, 0);
+                CFRelease(manager);
+                drop(Box::from_raw(state));
+            }
+        });
+
+        match rx.recv() {
+            Ok(Ok(run_loop)) => Ok(HidListener {
+                thread: Some(RunLoopThread {
+                    run_loop,
+                    stopped,
+                    thread,
+                }),
+            }),
+            Ok(Err(err)) => {
+                let _ = thread.join();
+                Err(err)
+            }
+            Err(_) => Err(Error::CustomError("IOKit: the HID listener thread died")),
+        }
+    }
+}
+
+/// Watch the mice and the trackpads that are connected or removed, see `DeviceWatcher`
+pub(crate) fn watch_devices(tracker: DeviceTracker) -> Result<DeviceWatcher, Error> {
+    let listener = HidListener::spawn(ListenerState {
+        callback: None,
+        devices: Mutex::new(HashMap::new()),
+        tracker: Some(Mutex::new(tracker)),
+    })?;
+    Ok(DeviceWatcher::new(move || drop(listener)))
+}
+
+impl Drop for HidListener {
+    fn drop(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            thread.stop();
+        }
+    }
+}
+
+unsafe extern "C" fn device_matching_callback(
+    context: *mut c_void,
+    _result: c_int,
+    _sender: *mut c_void,
+    device: IOHIDDeviceRef,
+) {
+    let state = &*(context as *const ListenerState);
+    let info = Arc::new(read_device_info(device));
+    if let Some(tracker) = &state.tracker {
+        tracker.lock().unwrap().add(Device {
+            id: info.id,
+            name: info.product.clone(),
+        });
+    }
+    state.devices.lock().unwrap().insert(device as usize, info);
+}
+
+unsafe extern "C" fn device_removal_callback(
+    context: *mut c_void,
+    _result: c_int,
+    _sender: *mut c_void,
+    device: IOHIDDeviceRef,
+) {
+    let state = &*(context as *const ListenerState);
+    // The registry entry of the device may be gone already, so the id is taken from the known ones
+    let info = state.devices.lock().unwrap().remove(&(device as usize));
+    if let (Some(tracker), Some(info)) = (&state.tracker, info) {
+        tracker.lock().unwrap().remove(info.id);
+    }
+}
+
+unsafe extern "C" fn input_value_callback(
+    context: *mut c_void,
+    _result: c_int,
+    _sender: *mut c_void,
+    value: IOHIDValueRef,
+) {
+    let state = &*(context as *const ListenerState);
+
+    let element = IOHIDValueGetElement(value);
+    let device = IOHIDElementGetDevice(element);
+    let usage_page = IOHIDElementGetUsagePage(element);
+    let usage = IOHIDElementGetUsage(element);
+    let int_value = IOHIDValueGetIntegerValue(value) as i64;
+
+    let kind = match usage_page {
+        USAGE_PAGE_BUTTON => HidEventKind::Button {
+            index: usage,
+            pressed: int_value != 0,
+        },
+        USAGE_PAGE_GENERIC_DESKTOP => HidEventKind::Axis {
+            usage,
+            value: int_value,
+        },
+        _ => HidEventKind::Other {
+            usage_page,
+            usage,
+            value: int_value,
+        },
+    };
+
+    let device = state
+        .devices
+        .lock()
+        .unwrap()
+        .entry(device as usize)
+        .or_insert_with(|| Arc::new(read_device_info(device)))
+        .clone();
+
+    if let Some(callback) = &state.callback {
+        callback(&HidEvent { device, kind });
+    }
+}
+
+/// Create the matching dictionaries for mice, pointers and trackpads
+unsafe fn create_matching_array() -> CFTypeRef {
+    let usages = [
+        (USAGE_PAGE_GENERIC_DESKTOP, USAGE_GENERIC_DESKTOP_MOUSE),
+        (USAGE_PAGE_GENERIC_DESKTOP, USAGE_GENERIC_DESKTOP_POINTER),
+        (USAGE_PAGE_DIGITIZER, USAGE_DIGITIZER_TOUCH_PAD),
+    ];
+
+    let page_key = create_cf_string("DeviceUsagePage");
+    let usage_key = create_cf_string("DeviceUsage");
+
+    let mut dictionaries = vec![];
+    for (page, usage) in usages {
+        let page_number = CFNumberCreate(
+            null_mut(),
+            CF_NUMBER_SINT32_TYPE,
+            &(page as i32) as *const i32 as *const c_void,
+        );
+        let usage_number = CFNumberCreate(
+            null_mut(),
+            CF_NUMBER_SINT32_TYPE,
+            &(usage as i32) as *const i32 as *const c_void,
+        );
+
+        let keys = [page_key, usage_key];
+        let values = [page_number, usage_number];
+        dictionaries.push(CFDictionaryCreate(
+            null_mut(),
+            keys.as_ptr(),
+            values.as_ptr(),
+            2,
+            &kCFTypeDictionaryKeyCallBacks,
+            &kCFTypeDictionaryValueCallBacks,
+        ));
+
+        CFRelease(page_number);
+        CFRelease(usage_number);
+    }
+
+    let array = CFArrayCreate(
+        null_mut(),
+        dictionaries.as_ptr(),
+        dictionaries.len() as c_long,
+        &kCFTypeArrayCallBacks,
+    );
+
+    for dictionary in dictionaries {
+        CFRelease(dictionary);
+    }
+    CFRelease(page_key);
+    CFRelease(usage_key);
+
+    array
+}
+
+unsafe fn read_device_info(device: IOHIDDeviceRef) -> HidDevice {
+    HidDevice {
+        id: DeviceId(get_registry_entry_id(device).unwrap_or(0)),
+        vendor_id: get_int_property(device, "VendorID").unwrap_or(0),
+        product_id: get_int_property(device, "ProductID").unwrap_or(0),
+        primary_usage_page: get_int_property(device, "PrimaryUsagePage").unwrap_or(0) as u32,
+        primary_usage: get_int_property(device, "PrimaryUsage").unwrap_or(0) as u32,
+        product: get_string_property(device, "Product"),
+        transport: get_string_property(device, "Transport"),
+    }
+}
+
+unsafe fn get_registry_entry_id(device: IOHIDDeviceRef) -> Option<u64> {
+    let mut entry_id = 0;
+    if IORegistryEntryGetRegistryEntryID(IOHIDDeviceGetService(device), &mut entry_id) == 0 {
+        Some(entry_id)
+    } else {
+        None
+    }
+}
+
+unsafe fn get_int_property(device: IOHIDDeviceRef, key: &str) -> Option<i32> {
+    let cf_key = create_cf_string(key);
+    let property = IOHIDDeviceGetProperty(device, cf_key);
+    CFRelease(cf_key);
+
+    if property.is_null() || CFGetTypeID(property) != CFNumberGetTypeID() {
+        return None;
+    }
+
+    let mut value: i32 = 0;
+    if CFNumberGetValue(
+        property,
+        CF_NUMBER_SINT32_TYPE,
+        &mut value as *mut i32 as *mut c_void,
+    ) {
+        Some(value)
+    } else {
+        None
+    }
+}
+
+unsafe fn get_string_property(device: IOHIDDeviceRef, key: &str) -> Option<String> {
+    let cf_key = create_cf_string(key);
+    let property = IOHIDDeviceGetProperty(device, cf_key);
+    CFRelease(cf_key);
+
+    if property.is_null() || CFGetTypeID(property) != CFStringGetTypeID() {
+        return None;
+    }
+
+    let mut buffer = [0 as c_char; 256];
+    if !CFStringGetCString(
+        property,
+        buffer.as_mut_ptr(),
+        buffer.len() as c_long,
+        CF_STRING_ENCODING_UTF8,
+    ) {
+        return None;
+    }
+
+    let bytes: Vec<u8> = buffer
+        .iter()
+        .take_while(|ch| **ch != 0)
+        .map(|ch| *ch as u8)
+        .collect();
+    String::from_utf8(bytes).ok()
+}
+
+/// IOKit and CoreFoundation definitions
+const IO_RETURN_SUCCESS: c_int = 0;
+const IO_RETURN_NOT_PERMITTED: c_int = 0xe00002e2_u32 as c_int;
+const USAGE_PAGE_GENERIC_DESKTOP: u32 = 0x01;
+const USAGE_PAGE_BUTTON: u32 = 0x09;
+const USAGE_PAGE_DIGITIZER: u32 = 0x0D;
+const USAGE_GENERIC_DESKTOP_POINTER: u32 = 0x01;
+const USAGE_GENERIC_DESKTOP_MOUSE: u32 = 0x02;
+const USAGE_DIGITIZER_TOUCH_PAD: u32 = 0x05;
+const CF_NUMBER_SINT32_TYPE: c_int = 3;
+
+type IOHIDManagerRef = CFTypeRef;
+type IOHIDDeviceRef = CFTypeRef;
+type IOHIDElementRef = CFTypeRef;
+type IOHIDValueRef = CFTypeRef;
+type IOHIDValueCallback = Option<
+    unsafe extern "C" fn(
+        context: *mut c_void,
+        result: c_int,
+        sender: *mut c_void,
+        value: IOHIDValueRef,
+    ),
+>;
+type IOHIDDeviceCallback = Option<
+    unsafe extern "C" fn(
+        context: *mut c_void,
+        result: c_int,
+        sender: *mut c_void,
+        device: IOHIDDeviceRef,
+    ),
+>;
+
+// The callback structs are only passed around by reference, so their
+// layout does not matter for us
+#[repr(C)]
+struct CFCallBacks {
+    _private: [u8; 0],
+}
+
+#[link(name = "IOKit", kind = "framework")]
+extern "C" {
+    fn IOHIDManagerCreate(allocator: *mut c_void, options: c_uint) -> IOHIDManagerRef;
+    fn IOHIDManagerSetDeviceMatchingMultiple(manager: IOHIDManagerRef, multiple: CFTypeRef);
+    fn IOHIDManagerRegisterDeviceMatchingCallback(
+        manager: IOHIDManagerRef,
+        callback: IOHIDDeviceCallback,
+        context: *mut c_void,
+    );
+    fn IOHIDManagerRegisterDeviceRemovalCallback(
+        manager: IOHIDManagerRef,
+        callback: IOHIDDeviceCallback,
+        context: *mut c_void,
+    );
+    fn IOHIDManagerRegisterInputValueCallback(
+        manager: IOHIDManagerRef,
+        callback: IOHIDValueCallback,
+        context: *mut c_void,
+    );
+    fn IOHIDManagerScheduleWithRunLoop(
+        manager: IOHIDManagerRef,
+        run_loop: *mut c_void,
+        run_loop_mode: *const c_void,
+    );
+    fn IOHIDManagerOpen(manager: IOHIDManagerRef, options: c_uint) -> c_int;
+    fn IOHIDManagerClose(manager: IOHIDManagerRef, options: c_uint) -> c_int;
+    fn IOHIDDeviceGetProperty(device: IOHIDDeviceRef, key: CFTypeRef) -> CFTypeRef;
+    fn IOHIDDeviceGetService(device: IOHIDDeviceRef) -> c_uint;
+    fn IORegistryEntryGetRegistryEntryID(entry: c_uint, entry_id: *mut u64) -> c_int;
+    fn IOHIDValueGetElement(value: IOHIDValueRef) -> IOHIDElementRef;
+    fn IOHIDValueGetIntegerValue(value: IOHIDValueRef) -> c_long;
+    fn IOHIDElementGetDevice(element: IOHIDElementRef) -> IOHIDDeviceRef;
+    fn IOHIDElementGetUsagePage(element: IOHIDElementRef) -> u32;
+    fn IOHIDElementGetUsage(element: IOHIDElementRef) -> u32;
+}
+
+#[link(name = "CoreFoundation", kind = "framework")]
+extern "C" {
+    static kCFTypeDictionaryKeyCallBacks: CFCallBacks;
+    static kCFTypeDictionaryValueCallBacks: CFCallBacks;
+    static kCFTypeArrayCallBacks: CFCallBacks;
+
+    fn CFStringGetTypeID() -> c_ulong;
+    fn CFNumberCreate(
+        allocator: *mut c_void,
+        the_type: c_int,
+        value_ptr: *const c_void,
+    ) -> CFTypeRef;
+    fn CFStringGetCString(
+        the_string: CFTypeRef,
+        buffer: *mut c_char,
+        buffer_size: c_long,
+        encoding: c_uint,
+    ) -> bool;
+    fn CFDictionaryCreate(
+        allocator: *mut c_void,
+        keys: *const CFTypeRef,
+        values: *const CFTypeRef,
+        num_values: c_long,
+        key_call_backs: *const CFCallBacks,
+        value_call_backs: *const CFCallBacks,
+    ) -> CFTypeRef;
+    fn CFArrayCreate(
+        allocator: *mut c_void,
+        values: *const CFTypeRef,
+        num_values: c_long,
+        call_backs: *const CFCallBacks,
+    ) -> CFTypeRef;
+}
@@ -0,0 +1,1657 @@
+///
+/// This module contains the mouse action functions
+/// for the darwin systems (MacOS)
+/// Uses the CoreGraphics (a.k.a Quartz) framework
+///
+use crate::builder::MouseBuilder;
+use crate::common::{
+    rewrite_event, simulate_event, Action, ButtonState, CallbackId, CallbackMap, Callbacks,
+    EventCallback, EventInfo, EventMask, EventSourceState, MouseActions, MouseButton, MouseEvent,
+    Region, RewriteCallback, Rewriters, ScrollDirection, ScrollInversion, ScrollUnit,
+};
+use crate::display::Monitor;
+use crate::error::Error;
+use crate::pacing::Pacer;
+use std::ffi::CString;
+use std::os::raw::{c_char, c_double, c_int, c_long, c_uint, c_ulong, c_void};
+use std::ptr::{addr_of, null_mut};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "iohid")]
+pub mod hid;
+
+static CURSOR_HIDDEN: AtomicBool = AtomicBool::new(false);
+/// Whether the movements of the mouse are disconnected from the cursor
+static RELATIVE_CAPTURE: AtomicBool = AtomicBool::new(false);
+static CLIP_REGION: Mutex<Option<Region>> = Mutex::new(None);
+/// The run loop of the event tap that keeps the cursor in the clip region
+static CLIP_TAP: Mutex<Option<RunLoopThread>> = Mutex::new(None);
+/// The event tap that keeps the cursor in the clip region, it is enabled again once the system disables it
+static CLIP_TAP_REF: AtomicUsize = AtomicUsize::new(0);
+
+pub struct DarwinMouseManager {
+    tap_listen_only: bool,
+    /// The events that the started event taps listen to, a tap can
+    /// not be changed once it is created so a new one is started for
+    /// the events that a new hook needs in addition
+    tap_mask: EventMask,
+    /// The run loops of the event taps
+    tap_threads: Vec<RunLoopThread>,
+    tap_state: Arc<TapState>,
+    post_move_events: bool,
+    /// The last posted press, the presses that follow it quickly at the
+    /// same place continue its multi-click sequence
+    last_press: Mutex<Option<LastPress>>,
+    pacer: Pacer,
+    scroll_inversion: ScrollInversion,
+}
+
+#[derive(Copy, Clone)]
+struct LastPress {
+    button: MouseButton,
+    position: (f64, f64),
+    time: Instant,
+    click_state: i64,
+}
+
+/// The source of the posted events and the replacements of the rewritten events, a manager
+/// creates it once and reuses it, the address is kept so the taps can share it
+struct EventSource(usize);
+
+impl EventSource {
+    fn new(state: EventSourceState) -> Result<Self, Error> {
+        let state_id = match state {
+            EventSourceState::CombinedSession => CGEventSourceStateID::CombinedSessionState,
+            EventSourceState::HidSystem => CGEventSourceStateID::HIDSystemState,
+        };
+        let source = unsafe { CGEventSourceCreate(state_id) };
+        if source.is_null() {
+            return Err(Error::CustomError("failed to create the event source"));
+        }
+        Ok(EventSource(source as usize))
+    }
+
+    fn as_ptr(&self) -> CGEventSourceRef {
+        self.0 as CGEventSourceRef
+    }
+}
+
+impl Drop for EventSource {
+    fn drop(&mut self) {
+        unsafe { CFRelease(self.0 as CFTypeRef) };
+    }
+}
+
+/// The state that the event taps of a manager share with it, the taps get
+/// a pointer to it as their user info so each manager has its own hooks
+struct TapState {
+    callbacks: Callbacks,
+    rewriters: Mutex<Rewriters>,
+    scroll_inversion: ScrollInversion,
+    ignore_own_events: AtomicBool,
+    paused: AtomicBool,
+    /// The number of times that the system disabled the event taps
+    interruptions: AtomicU64,
+    /// The started event taps, the raw pointers can not be shared between the threads as is
+    event_refs: Mutex<Vec<usize>>,
+    event_source: EventSource,
+}
+
+impl TapState {
+    /// Enable or disable all of the started event taps
+    fn set_taps_enabled(&self, enabled: bool) {
+        for event_ref in self.event_refs.lock().unwrap().iter() {
+            unsafe { CGEventTapEnable(*event_ref as CFTypeRef, enabled) };
+        }
+    }
+}
+
+impl DarwinMouseManager {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new() -> Box<dyn MouseActions> {
+        Self::from_builder(&MouseBuilder::new()).expect("failed to create the mouse manager")
+    }
+
+    /// Get a builder to customize the mouse manager
+    pub fn builder() -> MouseBuilder {
+        MouseBuilder::new()
+    }
+
+    /// Get the double-click interval and distance of the system
+    pub(crate) fn double_click_settings() -> (Duration, u32) {
+        // This is the preference behind NSEvent.doubleClickInterval, it is
+        // only set once the user changes the default of half a second
+        let interval = unsafe { get_double_preference("com.apple.mouse.doubleClickThreshold") };
+        (Duration::from_secs_f64(interval.unwrap_or(0.5)), 4)
+    }
+
+    /// Get the distance the cursor moves with a held button before it is a drag,
+    /// macOS does not expose it so the distance of the double-clicks is used
+    pub(crate) fn drag_threshold() -> u32 {
+        Self::double_click_settings().1
+    }
+
+    /// Get the active displays, the main display is the one with the menu bar
+    pub(crate) fn monitors() -> Result<Vec<Monitor>, Error> {
+        let mut display_ids = [0; MAX_DISPLAYS];
+        let mut count = 0;
+        unsafe {
+            let result =
+                CGGetActiveDisplayList(MAX_DISPLAYS as u32, display_ids.as_mut_ptr(), &mut count);
+            if result != CGError::Success {
+                return Err(Error::CGError {
+                    context: "Failed to list the displays",
+                    code: result as i32,
+                });
+            }
+
+            let main_display_id = CGMainDisplayID();
+            Ok(display_ids[..count as usize]
+                .iter()
+                .enumerate()
+                .map(|(index, display_id)| {
+                    let bounds = CGDisplayBounds(*display_id);
+                    Monitor {
+                        id: index,
+                        bounds: Region::new(
+                            bounds.origin.x as i32,
+                            bounds.origin.y as i32,
+                            bounds.size.width as u32,
+                            bounds.size.height as u32,
+                        ),
+                        scale_factor: display_scale_factor(*display_id),
+                        primary: *display_id == main_display_id,
+                    }
+                })
+                .collect())
+        }
+    }
+
+    /// Get the pointer speed, the mouse acceleration of the HID system goes from 0 to 3
+    pub(crate) fn pointer_speed() -> Result<f64, Error> {
+        let acceleration = unsafe { hid_mouse_acceleration()? };
+        if acceleration < 0. {
+            return Err(Error::CustomError(
+                "the pointer speed is not available while the acceleration is disabled",
+            ));
+        }
+        Ok(acceleration / 3.)
+    }
+
+    pub(crate) fn set_pointer_speed(speed: f64) -> Result<(), Error> {
+        unsafe { set_hid_mouse_acceleration(speed * 3.) }
+    }
+
+    /// Check if the pointer acceleration is enabled, a negative acceleration disables it
+    pub(crate) fn pointer_acceleration() -> Result<bool, Error> {
+        Ok(unsafe { hid_mouse_acceleration()? } >= 0.)
+    }
+
+    pub(crate) fn set_pointer_acceleration(enabled: bool) -> Result<(), Error> {
+        unsafe {
+            if !enabled {
+                return set_hid_mouse_acceleration(-1.);
+            }
+            // Keep the speed if the acceleration is already enabled
+            if hid_mouse_acceleration()? < 0. {
+                set_hid_mouse_acceleration(DEFAULT_MOUSE_ACCELERATION)?;
+            }
+            Ok(())
+        }
+    }
+
+    pub(crate) fn from_builder(builder: &MouseBuilder) -> Result<Box<dyn MouseActions>, Error> {
+        let mut callbacks = CallbackMap::new();
+        callbacks.set_execution(builder.callback_execution);
+        callbacks.set_move_coalescing(builder.move_coalescing);
+        let tap_state = TapState {
+            callbacks: Arc::new(Mutex::new(callbacks)),
+            rewriters: Mutex::new(Rewriters::new()),
+            scroll_inversion: builder.scroll_inversion,
+            ignore_own_events: AtomicBool::new(false),
+            paused: AtomicBool::new(false),
+            interruptions: AtomicU64::new(0),
+            event_refs: Mutex::new(vec![]),
+            event_source: EventSource::new(builder.event_source_state)?,
+        };
+        Ok(Box::new(DarwinMouseManager {
+            tap_listen_only: builder.tap_listen_only,
+            tap_mask: EventMask::default(),
+            tap_threads: vec![],
+            tap_state: Arc::new(tap_state),
+            post_move_events: builder.post_move_events,
+            last_press: Mutex::new(None),
+            pacer: Pacer::new(&builder.pacing),
+            scroll_inversion: builder.scroll_inversion,
+        }))
+    }
+
+    /// Post a press or a release of the given button at the given position, the press is the
+    /// next click of a multi-click if it follows the previous press like a double-click does
+    fn create_button_event_at(
+        &self,
+        button: &MouseButton,
+        is_press: bool,
+        x: c_double,
+        y: c_double,
+    ) -> Result<(), Error> {
+        let mut last_press = self.last_press.lock().unwrap();
+        let click_state = if is_press {
+            let (interval, distance) = Self::double_click_settings();
+            let click_state = match *last_press {
+                Some(last)
+                    if last.button == *button
+                        && last.time.elapsed() <= interval
+                        && (last.position.0 - x).abs() <= distance as f64
+                        && (last.position.1 - y).abs() <= distance as f64 =>
+                {
+                    last.click_state + 1
+                }
+                _ => 1,
+            };
+            *last_press = Some(LastPress {
+                button: *button,
+                position: (x, y),
+                time: Instant::now(),
+                click_state,
+            });
+            click_state
+        } else {
+            // The release belongs to the click of the last press
+            match *last_press {
+                Some(last) if last.button == *button => last.click_state,
+                _ => 1,
+            }
+        };
+        drop(last_press);
+
+        let (event_type, mouse_button) = if is_press {
+            press_event(button)
+        } else {
+            release_event(button)
+        };
+        self.create_mouse_event_at(event_type, mouse_button, x, y, click_state)
+    }
+
+    /// Post a mouse event located at the given position, the cursor is
+    /// moved along with the event so no separate warp is needed
+    ///
+    /// `click_state` is the number of the click in a multi-click sequence,
+    /// applications rely on it rather than the timing to detect double-clicks
+    fn create_mouse_event_at(
+        &self,
+        event_type: CGEventType,
+        mouse_button: CGMouseButton,
+        x: c_double,
+        y: c_double,
+        click_state: i64,
+    ) -> Result<(), Error> {
+        let position = CGPoint { x, y };
+
+        unsafe {
+            let event = CGEventCreateMouseEvent(
+                self.tap_state.event_source.as_ptr(),
+                event_type,
+                position,
+                mouse_button,
+            );
+            if event == null_mut() {
+                return Err(Error::CGCouldNotCreateEvent);
+            }
+            CGEventSetIntegerValueField(event, MOUSE_EVENT_CLICK_STATE, click_state);
+            CGEventSetIntegerValueField(event, EVENT_SOURCE_USER_DATA, OWN_EVENT_USER_DATA);
+            self.pacer.wait();
+            CGEventPost(CGEventTapLocation::CGHIDEventTap, event);
+            CFRelease(event as CFTypeRef);
+        }
+
+        Ok(())
+    }
+
+    /// Post a scroll event, positive `vertical` scrolls up and positive `horizontal` scrolls left
+    fn create_scroll_wheel_event(
+        &self,
+        vertical: c_int,
+        horizontal: c_int,
+        unit: ScrollUnit,
+    ) -> Result<(), Error> {
+        let unit = match unit {
+            ScrollUnit::Line => CGScrollEventUnit::Line,
+            ScrollUnit::Pixel => CGScrollEventUnit::Pixel,
+        };
+
+        unsafe {
+            let event = CGEventCreateScrollWheelEvent(
+                self.tap_state.event_source.as_ptr(),
+                unit,
+                2,
+                vertical,
+                horizontal,
+            );
+
+            if event == null_mut() {
+                return Err(Error::CGCouldNotCreateEvent);
+            }
+            CGEventSetIntegerValueField(event, EVENT_SOURCE_USER_DATA, OWN_EVENT_USER_DATA);
+            self.pacer.wait();
+            CGEventPost(CGEventTapLocation::CGHIDEventTap, event);
+            CFRelease(event as CFTypeRef);
+        }
+        Ok(())
+    }
+
+    /// Move the cursor to the given position, the posted event moves it as well
+    fn warp(&self, x: c_double, y: c_double) -> Result<(), Error> {
+        if self.post_move_events {
+            let (event_type, mouse_button) = move_event(&self.get_pressed_buttons()?);
+            return self.create_mouse_event_at(event_type, mouse_button, x, y, 0);
+        }
+        self.pacer.wait();
+        unsafe {
+            let result = CGWarpMouseCursorPosition(CGPoint { x, y });
+            if result != CGError::Success {
+                return Err(Error::CGError {
+                    context: "Failed to move the mouse",
+                    code: result as i32,
+                });
+            }
+        };
+
+        Ok(())
+    }
+
+    /// Start an event tap that listens to the events selected by `mask`
+    fn start_listener(&mut self, mask: EventMask) -> Result<(), Error> {
+        let tap_option = if self.tap_listen_only {
+            CGEventTapOption::ListenOnly
+        } else {
+            CGEventTapOption::Default
+        };
+
+        let mut event_types = Vec::new();
+        if mask.contains(EventMask::MOVE) {
+            event_types.extend([
+                CGEventType::MouseMoved,
+                CGEventType::LeftMouseDragged,
+                CGEventType::RightMouseDragged,
+                CGEventType::OtherMouseDragged,
+            ]);
+        }
+        if mask.contains(EventMask::PRESS) {
+            event_types.extend([
+                CGEventType::LeftMouseDown,
+                CGEventType::RightMouseDown,
+                CGEventType::OtherMouseDown,
+            ]);
+        }
+        if mask.contains(EventMask::RELEASE) {
+            event_types.extend([
+                CGEventType::LeftMouseUp,
+                CGEventType::RightMouseUp,
+                CGEventType::OtherMouseUp,
+            ]);
+        }
+        if mask.contains(EventMask::SCROLL) {
+            event_types.push(CGEventType::ScrollWheel);
+        }
+        let events_of_interest = event_types
+            .into_iter()
+            .fold(0, |bits, event_type| bits | 1 << event_type as u64);
+
+        // The tap is created on the listener thread, which reports it back
+        let (sender, receiver) = mpsc::channel();
+        let tap_state = self.tap_state.clone();
+        let stopped = Arc::new(AtomicBool::new(false));
+        let thread_stopped = stopped.clone();
+        let thread = thread::spawn(move || {
+            unsafe extern "C" fn mouse_on_event_callback(
+                _proxy: *const c_void,
+                event_type: CGEventType,
+                cg_event: CGEventRef,
+                user_info: *mut c_void,
+            ) -> CGEventRef {
+                // The thread of the tap keeps the state alive until the tap is released
+                let state = &*(user_info as *const TapState);
+                // Construct the library's MouseEvent
+                let mouse_event = match event_type {
+                    // The system disables the taps whose callbacks take too long, and
+                    // the taps of all the hooks are enabled again as they are paused together
+                    CGEventType::TapDisabledByTimeout | CGEventType::TapDisabledByUserInput => {
+                        state.interruptions.fetch_add(1, Ordering::SeqCst);
+                        if !state.paused.load(Ordering::SeqCst) {
+                            state.set_taps_enabled(true);
+                        }
+                        state
+                            .callbacks
+                            .lock()
+                            .unwrap()
+                            .invoke(&MouseEvent::ListenerInterrupted, &EventInfo::default());
+                        return cg_event;
+                    }
+                    CGEventType::LeftMouseDown => Some(MouseEvent::Press(MouseButton::Left)),
+                    CGEventType::LeftMouseUp => Some(MouseEvent::Release(MouseButton::Left)),
+                    CGEventType::RightMouseDown => Some(MouseEvent::Press(MouseButton::Right)),
+                    CGEventType::RightMouseUp => Some(MouseEvent::Release(MouseButton::Right)),
+                    CGEventType::OtherMouseDown => {
+                        let number =
+                            CGEventGetIntegerValueField(cg_event, MOUSE_EVENT_BUTTON_NUMBER);
+                        button_from_number(number).map(MouseEvent::Press)
+                    }
+                    CGEventType::OtherMouseUp => {
+                        let number =
+                            CGEventGetIntegerValueField(cg_event, MOUSE_EVENT_BUTTON_NUMBER);
+                        button_from_number(number).map(MouseEvent::Release)
+                    }
+                    CGEventType::MouseMoved
+                    | CGEventType::LeftMouseDragged
+                    | CGEventType::RightMouseDragged
+                    | CGEventType::OtherMouseDragged => {
+                        let point = CGEventGetLocation(cg_event);
+                        let position = Some((point.x as i32, point.y as i32));
+                        let delta_x = CGEventGetIntegerValueField(cg_event, MOUSE_EVENT_DELTA_X);
+                        let delta_y = CGEventGetIntegerValueField(cg_event, MOUSE_EVENT_DELTA_Y);
+                        let delta = (delta_x as i32, delta_y as i32);
+
+                        let button = match event_type {
+                            CGEventType::LeftMouseDragged => Some(MouseButton::Left),
+                            CGEventType::RightMouseDragged => Some(MouseButton::Right),
+                            CGEventType::OtherMouseDragged => button_from_number(
+                                CGEventGetIntegerValueField(cg_event, MOUSE_EVENT_BUTTON_NUMBER),
+                            ),
+                            _ => None,
+                        };
+                        match button {
+                            Some(button) => Some(MouseEvent::Drag {
+                                button,
+                                position,
+                                delta,
+                            }),
+                            None => Some(MouseEvent::Move { position, delta }),
+                        }
+                    }
+                    CGEventType::ScrollWheel => {
+                        // The trackpads and the magic mice scroll continuously in pixels, the
+                        // line deltas of the wheels are fractional when they are accelerated
+                        let (unit, delta_y, delta_x) = if CGEventGetIntegerValueField(
+                            cg_event,
+                            SCROLL_WHEEL_EVENT_IS_CONTINUOUS,
+                        ) != 0
+                        {
+                            (
+                                ScrollUnit::Pixel,
+                                CGEventGetDoubleValueField(
+                                    cg_event,
+                                    SCROLL_WHEEL_EVENT_POINT_DELTA_AXIS_1,
+                                ),
+                                CGEventGetDoubleValueField(
+                                    cg_event,
+                                    SCROLL_WHEEL_EVENT_POINT_DELTA_AXIS_2,
+                                ),
+                            )
+                        } else {
+                            (
+                                ScrollUnit::Line,
+                                CGEventGetDoubleValueField(
+                                    cg_event,
+                                    SCROLL_WHEEL_EVENT_FIXED_PT_DELTA_AXIS_1,
+                                ),
+                                CGEventGetDoubleValueField(
+                                    cg_event,
+                                    SCROLL_WHEEL_EVENT_FIXED_PT_DELTA_AXIS_2,
+                                ),
+                            )
+                        };
+
+                        let scroll = if delta_y > 0. {
+                            Some((ScrollDirection::Up, delta_y))
+                        } else if delta_y < 0. {
+                            Some((ScrollDirection::Down, -delta_y))
+                        } else if delta_x < 0. {
+                            Some((ScrollDirection::Right, -delta_x))
+                        } else if delta_x > 0. {
+                            Some((ScrollDirection::Left, delta_x))
+                        } else {
+                            // Probably axis3 wheel scrolled
+                            None
+                        };
+                        scroll.map(|(direction, distance)| MouseEvent::Scroll {
+                            direction,
+                            unit,
+                            distance,
+                        })
+                    }
+                };
+
+                let event = match mouse_event {
+                    Some(event) => event,
+                    None => return cg_event,
+                };
+                if state.ignore_own_events.load(Ordering::Relaxed)
+                    && CGEventGetIntegerValueField(cg_event, EVENT_SOURCE_USER_DATA)
+                        == OWN_EVENT_USER_DATA
+                {
+                    return cg_event;
+                }
+                let point = CGEventGetLocation(cg_event);
+                // Only the hardware events come from the state of the HID system
+                let state_id = CGEventGetIntegerValueField(cg_event, EVENT_SOURCE_STATE_ID);
+                let info = EventInfo {
+                    position: Some((point.x as i32, point.y as i32)),
+                    injected: Some(state_id != CGEventSourceStateID::HIDSystemState as i64),
+                    ..EventInfo::default()
+                };
+
+                let mut cg_event = cg_event;
+                let rewritten = rewrite_event(&state.rewriters.lock().unwrap(), event, &info)
+                    .map(|event| state.scroll_inversion.hooked_event(event));
+                let event = match rewritten {
+                    // Returning null from the tap swallows the event
+                    None => return null_mut(),
+                    Some(rewritten) if rewritten != event => {
+                        // The returned event is released along with the original one
+                        let replacement = replacement_event(&rewritten, point, &state.event_source);
+                        if !replacement.is_null() {
+                            cg_event = replacement;
+                        }
+                        rewritten
+                    }
+                    Some(event) => event,
+                };
+
+                state.callbacks.lock().unwrap().invoke(&event, &info);
+
+                cg_event
+            }
+
+            unsafe {
+                // Create the mouse listener hook
+                let tap_event_ref = CGEventTapCreate(
+                    CGEventTapLocation::CGHIDEventTap,
+                    CGEventTapPlacement::HeadInsertEventTap,
+                    tap_option as u32,
+                    events_of_interest,
+                    Some(mouse_on_event_callback),
+                    Arc::as_ptr(&tap_state) as *mut c_void,
+                );
+                if tap_event_ref.is_null() {
+                    let _ = sender.send((0, 0));
+                    return;
+                }
+
+                let loop_source = CFMachPortCreateRunLoopSource(null_mut(), tap_event_ref, 0);
+                let current_loop = CFRunLoopGetCurrent();
+                CFRunLoopAddSource(current_loop, loop_source, kCFRunLoopDefaultMode);
+                CGEventTapEnable(tap_event_ref, !tap_state.paused.load(Ordering::SeqCst));
+                // The raw pointers can not be sent between the threads as is, the run
+                // loop is retained until the thread is joined, see `RunLoopThread`
+                CFRetain(current_loop);
+                let _ = sender.send((tap_event_ref as usize, current_loop as usize));
+                run_until_stopped(&thread_stopped);
+                // The tap is torn down on the thread of its run loop, once the loop is stopped
+                CGEventTapEnable(tap_event_ref, false);
+                CFMachPortInvalidate(tap_event_ref);
+                CFRelease(loop_source);
+                CFRelease(tap_event_ref);
+            }
+        });
+
+        match receiver.recv() {
+            Ok((tap_event_ref, run_loop)) if tap_event_ref != 0 => {
+                self.tap_state
+                    .event_refs
+                    .lock()
+                    .unwrap()
+                    .push(tap_event_ref);
+                self.tap_threads.push(RunLoopThread {
+                    run_loop,
+                    stopped,
+                    thread,
+                });
+                self.tap_mask = self.tap_mask | mask;
+                Ok(())
+            }
+            _ => Err(tap_error()),
+        }
+    }
+
+    /// Stop the run loops of the event taps and wait for their threads to release the taps
+    fn stop_taps(&mut self) {
+        self.tap_state.set_taps_enabled(false);
+        for tap_thread in self.tap_threads.drain(..) {
+            tap_thread.stop();
+        }
+        self.tap_state.event_refs.lock().unwrap().clear();
+        self.tap_mask = EventMask::default();
+    }
+
+    /// Enable or disable all of the started event taps
+    fn set_taps_enabled(&self, enabled: bool) {
+        self.tap_state.paused.store(!enabled, Ordering::SeqCst);
+        self.tap_state.set_taps_enabled(enabled);
+    }
+}
+
+impl Drop for DarwinMouseManager {
+    fn drop(&mut self) {
+        self.stop_taps();
+    }
+}
+
+impl MouseActions for DarwinMouseManager {
+    fn move_to(&self, x: usize, y: usize) -> Result<(), Error> {
+        self.warp(x as f64, y as f64)
+    }
+
+    fn move_to_monitor(&self, monitor_id: usize, x: u32, y: u32) -> Result<(), Error> {
+        // The displays left of or above the main display have negative coordinates
+        let (x, y) = crate::display::monitor(monitor_id)?.to_global(x, y)?;
+        self.warp(x as f64, y as f64)
+    }
+
+    fn get_position(&self) -> Result<(i32, i32), Error> {
+        unsafe {
+            let event = CGEventCreate(null_mut());
+            if event == null_mut() {
+                return Err(Error::CGCouldNotCreateEvent);
+            }
+            let cursor = CGEventGetLocation(event);
+            CFRelease(event as CFTypeRef);
+            return Ok((cursor.x as i32, cursor.y as i32));
+        }
+    }
+
+    fn get_pressed_buttons(&self) -> Result<ButtonState, Error> {
+        // Check every button that fits into the state, the combined
+        // state includes both the hardware and the posted events
+        Ok((0..u32::BITS)
+            .filter(|number| unsafe {
+                CGEventSourceButtonState(CGEventSourceStateID::CombinedSessionState, *number)
+            })
+            .filter_map(|number| button_from_number(number as c_long))
+            .collect())
+    }
+
+    fn press_button(&self, button: &MouseButton) -> Result<(), Error> {
+        let (x, y) = self.get_position()?;
+        self.create_button_event_at(button, true, x as f64, y as f64)
+    }
+
+    fn release_button(&self, button: &MouseButton) -> Result<(), Error> {
+        let (x, y) = self.get_position()?;
+        self.create_button_event_at(button, false, x as f64, y as f64)
+    }
+
+    fn click_button(&self, button: &MouseButton) -> Result<(), Error> {
+        self.press_button(button)?;
+        self.release_button(button)
+    }
+
+    fn click_with_count(&self, button: &MouseButton, click_count: u32) -> Result<(), Error> {
+        let (x, y) = self.get_position()?;
+        let (x, y) = (x as f64, y as f64);
+        let click_state = click_count as i64;
+        let (event_type, mouse_button) = press_event(button);
+        self.create_mouse_event_at(event_type, mouse_button, x, y, click_state)?;
+        // A press right after the click continues its sequence
+        *self.last_press.lock().unwrap() = Some(LastPress {
+            button: *button,
+            position: (x, y),
+            time: Instant::now(),
+            click_state,
+        });
+        let (event_type, mouse_button) = release_event(button);
+        self.create_mouse_event_at(event_type, mouse_button, x, y, click_state)
+    }
+
+    fn click_n(&self, button: &MouseButton, count: u32) -> Result<(), Error> {
+        // The click count tells the clicks apart, so they do not need to be paced
+        for click_count in 1..=count {
+            self.click_with_count(button, click_count)?;
+        }
+        Ok(())
+    }
+
+    fn double_click(&self, button: &MouseButton) -> Result<(), Error> {
+        self.click_with_count(button, 1)?;
+        self.click_with_count(button, 2)
+    }
+
+    fn press_at(&self, x: usize, y: usize, button: &MouseButton) -> Result<(), Error> {
+        self.create_button_event_at(button, true, x as f64, y as f64)
+    }
+
+    fn release_at(&self, x: usize, y: usize, button: &MouseButton) -> Result<(), Error> {
+        self.create_button_event_at(button, false, x as f64, y as f64)
+    }
+
+    fn click_at(&self, x: usize, y: usize, button: &MouseButton) -> Result<(), Error> {
+        self.press_at(x, y, button)?;
+        self.release_at(x, y, button)
+    }
+
+    fn scroll_wheel(
+        &self,
+        direction: &ScrollDirection,
+        unit: ScrollUnit,
+        distance: u32,
+    ) -> Result<(), Error> {
+        let distance = distance.min(c_int::MAX as u32) as c_int;
+        match self.scroll_inversion.direction(direction) {
+            ScrollDirection::Up => self.create_scroll_wheel_event(distance, 0, unit),
+            ScrollDirection::Down => self.create_scroll_wheel_event(-distance, 0, unit),
+            ScrollDirection::Left => self.create_scroll_wheel_event(0, distance, unit),
+            ScrollDirection::Right => self.create_scroll_wheel_event(0, -distance, unit),
+        }
+    }
+
+    fn scroll_by(&self, dx: f64, dy: f64, unit: ScrollUnit) -> Result<(), Error> {
+        let (dx, dy) = self.scroll_inversion.deltas(dx, dy);
+        self.create_scroll_wheel_event(-dy.round() as c_int, -dx.round() as c_int, unit)
+    }
+
+    fn send_batch(&self, actions: &[Action]) -> Result<(), Error> {
+        // The position is tracked through the batch, so it is queried at most once
+        let mut position = None;
+        for action in actions {
+            let (x, y) = match (action, position) {
+                (Action::MoveTo(x, y), _) => (*x as c_double, *y as c_double),
+                (_, Some(position)) => position,
+                (_, None) => {
+                    let (x, y) = self.get_position()?;
+                    (x as c_double, y as c_double)
+                }
+            };
+            position = Some(match action {
+                Action::MoveTo(..) => {
+                    self.warp(x, y)?;
+                    (x, y)
+                }
+                Action::MoveRelative(dx, dy) => {
+                    let (x, y) = (x + *dx as c_double, y + *dy as c_double);
+                    self.warp(x, y)?;
+                    (x, y)
+                }
+                Action::Press(button) => {
+                    let (event_type, mouse_button) = press_event(button);
+                    self.create_mouse_event_at(event_type, mouse_button, x, y, 1)?;
+                    (x, y)
+                }
+                Action::Release(button) => {
+                    let (event_type, mouse_button) = release_event(button);
+                    self.create_mouse_event_at(event_type, mouse_button, x, y, 1)?;
+                    (x, y)
+                }
+                Action::Click(button) => {
+                    let (event_type, mouse_button) = press_event(button);
+                    self.create_mouse_event_at(event_type, mouse_button, x, y, 1)?;
+                    let (event_type, mouse_button) = release_event(button);
+                    self.create_mouse_event_at(event_type, mouse_button, x, y, 1)?;
+                    (x, y)
+                }
+                Action::Scroll(direction, unit, distance) => {
+                    self.scroll_wheel(direction, *unit, *distance)?;
+                    (x, y)
+                }
+            });
+        }
+        Ok(())
+    }
+
+    fn hide_cursor(&self) -> Result<(), Error> {
+        // CoreGraphics counts the hide requests, so only the first one is sent
+        if !CURSOR_HIDDEN.swap(true, Ordering::SeqCst) {
+            let result = unsafe { CGDisplayHideCursor(MAIN_DISPLAY) };
+            if result != CGError::Success {
+                CURSOR_HIDDEN.store(false, Ordering::SeqCst);
+                return Err(Error::CGError {
+                    context: "Failed to hide the cursor",
+                    code: result as i32,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn show_cursor(&self) -> Result<(), Error> {
+        if CURSOR_HIDDEN.swap(false, Ordering::SeqCst) {
+            let result = unsafe { CGDisplayShowCursor(MAIN_DISPLAY) };
+            if result != CGError::Success {
+                CURSOR_HIDDEN.store(true, Ordering::SeqCst);
+                return Err(Error::CGError {
+                    context: "Failed to show the cursor",
+                    code: result as i32,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn set_relative_capture(&self, enabled: bool) -> Result<(), Error> {
+        associate_mouse_and_cursor(!enabled)
+    }
+
+    fn clip_cursor(&self, region: Option<Region>) -> Result<(), Error> {
+        if region.is_some_and(|region| region.is_empty()) {
+            return Err(Error::CustomError("the region is empty"));
+        }
+
+        let mut clip_tap = CLIP_TAP.lock().unwrap();
+        *CLIP_REGION.lock().unwrap() = region;
+        match region {
+            Some(region) => {
+                if clip_tap.is_none() {
+                    match start_clip_tap() {
+                        Ok(tap) => *clip_tap = Some(tap),
+                        Err(err) => {
+                            *CLIP_REGION.lock().unwrap() = None;
+                            return Err(err);
+                        }
+                    }
+                }
+                // The tap only catches the cursor once it moves
+                let (x, y) = self.get_position()?;
+                let (clamped_x, clamped_y) = region.clamp((x, y));
+                if (clamped_x, clamped_y) != (x, y) {
+                    self.move_to(clamped_x as usize, clamped_y as usize)?;
+                }
+            }
+            None => {
+                if let Some(tap_thread) = clip_tap.take() {
+                    tap_thread.stop();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn hook_with_info(&mut self, callback: EventCallback) -> Result<CallbackId, Error> {
+        self.hook_filtered(EventMask::ALL, callback)
+    }
+
+    fn hook_filtered(
+        &mut self,
+        mask: EventMask,
+        callback: EventCallback,
+    ) -> Result<CallbackId, Error> {
+        // Only listen to the events that the hooks need
+        let missing = mask.difference(self.tap_mask);
+        if !missing.is_empty() {
+            self.start_listener(missing)?;
+        }
+
+        let id = CallbackId::next();
+        let mut callbacks = self.tap_state.callbacks.lock().unwrap();
+        callbacks.insert(id, mask.filter(callback));
+        Ok(id)
+    }
+
+    fn hook_rewrite(&mut self, callback: RewriteCallback) -> Result<CallbackId, Error> {
+        // A listen only tap can not change the events
+        if self.tap_listen_only {
+            return Err(Error::CustomError(
+                "rewriting the events needs an event tap that is not listen only",
+            ));
+        }
+        let missing = EventMask::ALL.difference(self.tap_mask);
+        if !missing.is_empty() {
+            self.start_listener(missing)?;
+        }
+
+        let id = CallbackId::next();
+        let mut rewriters = self.tap_state.rewriters.lock().unwrap();
+        rewriters.insert(id, callback);
+        Ok(id)
+    }
+
+    fn set_ignore_own_events(&mut self, ignore: bool) -> Result<(), Error> {
+        self.tap_state
+            .ignore_own_events
+            .store(ignore, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn pause_listening(&mut self) -> Result<(), Error> {
+        self.set_taps_enabled(false);
+        Ok(())
+    }
+
+    fn resume_listening(&mut self) -> Result<(), Error> {
+        self.set_taps_enabled(true);
+        Ok(())
+    }
+
+    fn stop_listening(&mut self) -> Result<(), Error> {
+        self.unhook_all()?;
+        self.stop_taps();
+        Ok(())
+    }
+
+    fn simulate_event(&self, event: &MouseEvent) -> Result<(), Error> {
+        // The listener does not invoke the callbacks while it is paused
+        if self.tap_state.paused.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        simulate_event(&self.tap_state.callbacks, event);
+        Ok(())
+    }
+
+    fn callback_panics(&mut self) -> Result<Receiver<Error>, Error> {
+        Ok(self.tap_state.callbacks.lock().unwrap().panic_receiver())
+    }
+
+    fn listener_interruptions(&self) -> Result<u64, Error> {
+        Ok(self.tap_state.interruptions.load(Ordering::SeqCst))
+    }
+
+    fn unhook(&mut self, callback_id: CallbackId) -> Result<(), Error> {
+        if self
+            .tap_state
+            .rewriters
+            .lock()
+            .unwrap()
+            .remove(&callback_id)
+            .is_some()
+        {
+            return Ok(());
+        }
+        match self
+            .tap_state
+            .callbacks
+            .lock()
+            .unwrap()
+            .remove(&callback_id)
+        {
+            Some(_) => Ok(()),
+            None => Err(Error::UnhookFailed),
+        }
+    }
+
+    fn unhook_all(&mut self) -> Result<(), Error> {
+        self.tap_state.rewriters.lock().unwrap().clear();
+        self.tap_state.callbacks.lock().unwrap().clear();
+        Ok(())
+    }
+}
+
+/// Create the event that replaces a rewritten event in the event tap, the
+/// events without a position are located at the position of the original one
+unsafe fn replacement_event(
+    event: &MouseEvent,
+    location: CGPoint,
+    source: &EventSource,
+) -> CGEventRef {
+    let mouse_event = |(event_type, mouse_button), position: Option<(i32, i32)>| {
+        let position = match position {
+            Some((x, y)) => CGPoint {
+                x: x as c_double,
+                y: y as c_double,
+            },
+            None => location,
+        };
+        CGEventCreateMouseEvent(source.as_ptr(), event_type, position, mouse_button)
+    };
+
+    match event {
+        MouseEvent::Move { position, delta }
+        | MouseEvent::Drag {
+            position, delta, ..
+        } => {
+            let event_type = match event {
+                MouseEvent::Drag { button, .. } => drag_event(button),
+                _ => (CGEventType::MouseMoved, MOUSE_BUTTON_LEFT),
+            };
+            let cg_event = mouse_event(event_type, *position);
+            if !cg_event.is_null() {
+                CGEventSetIntegerValueField(cg_event, MOUSE_EVENT_DELTA_X, delta.0 as i64);
+                CGEventSetIntegerValueField(cg_event, MOUSE_EVENT_DELTA_Y, delta.1 as i64);
+            }
+            cg_event
+        }
+        MouseEvent::Press(button) => mouse_event(press_event(button), None),
+        MouseEvent::DoubleClick { button, position } => {
+            let cg_event = mouse_event(press_event(button), *position);
+            if !cg_event.is_null() {
+                CGEventSetIntegerValueField(cg_event, MOUSE_EVENT_CLICK_STATE, 2);
+            }
+            cg_event
+        }
+        MouseEvent::Release(button) => mouse_event(release_event(button), None),
+        MouseEvent::Scroll {
+            direction,
+            unit,
+            distance,
+        } => {
+            let units = match unit {
+                ScrollUnit::Line => CGScrollEventUnit::Line,
+                ScrollUnit::Pixel => CGScrollEventUnit::Pixel,
+            };
+            let distance = distance.round() as c_int;
+            let (vertical, horizontal) = match direction {
+                ScrollDirection::Up => (distance, 0),
+                ScrollDirection::Down => (-distance, 0),
+                ScrollDirection::Left => (0, distance),
+                ScrollDirection::Right => (0, -distance),
+            };
+            CGEventCreateScrollWheelEvent(source.as_ptr(), units, 2, vertical, horizontal)
+        }
+        MouseEvent::ListenerInterrupted => null_mut(),
+    }
+}
+
+/// Get the event type of a drag with the given button held down
+fn drag_event(button: &MouseButton) -> (CGEventType, CGMouseButton) {
+    let event_type = match button {
+        MouseButton::Left => CGEventType::LeftMouseDragged,
+        MouseButton::Right => CGEventType::RightMouseDragged,
+        _ => CGEventType::OtherMouseDragged,
+    };
+    (event_type, button_number(button))
+}
+
+fn press_event(button: &MouseButton) -> (CGEventType, CGMouseButton) {
+    let event_type = match button {
+        MouseButton::Left => CGEventType::LeftMouseDown,
+        MouseButton::Right => CGEventType::RightMouseDown,
+        _ => CGEventType::OtherMouseDown,
+    };
+    (event_type, button_number(button))
+}
+
+fn release_event(button: &MouseButton) -> (CGEventType, CGMouseButton) {
+    let event_type = match button {
+        MouseButton::Left => CGEventType::LeftMouseUp,
+        MouseButton::Right => CGEventType::RightMouseUp,
+        _ => CGEventType::OtherMouseUp,
+    };
+    (event_type, button_number(button))
+}
+
+/// Get the event that moves the cursor while the given buttons are held,
+/// the applications track the drags by the dragged events
+fn move_event(pressed: &ButtonState) -> (CGEventType, CGMouseButton) {
+    match pressed.iter().next() {
+        Some(MouseButton::Left) => (CGEventType::LeftMouseDragged, MOUSE_BUTTON_LEFT),
+        Some(MouseButton::Right) => (CGEventType::RightMouseDragged, MOUSE_BUTTON_RIGHT),
+        Some(button) => (CGEventType::OtherMouseDragged, button_number(&button)),
+        None => (CGEventType::MouseMoved, MOUSE_BUTTON_LEFT),
+    }
+}
+
+/// Get the CoreGraphics button number of the given button
+fn button_number(button: &MouseButton) -> CGMouseButton {
+    match button {
+        MouseButton::Left => MOUSE_BUTTON_LEFT,
+        MouseButton::Right => MOUSE_BUTTON_RIGHT,
+        MouseButton::Middle => MOUSE_BUTTON_CENTER,
+        MouseButton::Back => MOUSE_BUTTON_CENTER + 1,
+        MouseButton::Forward => MOUSE_BUTTON_CENTER + 2,
+        MouseButton::Other(index) => MOUSE_BUTTON_CENTER + 3 + *index as CGMouseButton,
+    }
+}
+
+/// Get the button of the given CoreGraphics button number
+fn button_from_number(number: c_long) -> Option<MouseButton> {
+    match number {
+        0 => Some(MouseButton::Left),
+        1 => Some(MouseButton::Right),
+        2 => Some(MouseButton::Middle),
+        3 => Some(MouseButton::Back),
+        4 => Some(MouseButton::Forward),
+        _ => u8::try_from(number - 5).ok().map(MouseButton::Other),
+    }
+}
+
+unsafe fn create_cf_string(value: &str) -> CFTypeRef {
+    let c_value = CString::new(value).unwrap();
+    CFStringCreateWithCString(null_mut(), c_value.as_ptr(), CF_STRING_ENCODING_UTF8)
+}
+
+/// Read the given numeric preference of the global domain
+unsafe fn get_double_preference(key: &str) -> Option<f64> {
+    let cf_key = create_cf_string(key);
+    let value = CFPreferencesCopyAppValue(cf_key, kCFPreferencesAnyApplication);
+    CFRelease(cf_key);
+    if value.is_null() {
+        return None;
+    }
+
+    let mut number: f64 = 0.;
+    let is_number = CFGetTypeID(value) == CFNumberGetTypeID()
+        && CFNumberGetValue(
+            value,
+            CF_NUMBER_DOUBLE_TYPE,
+            &mut number as *mut f64 as *mut c_void,
+        );
+    CFRelease(value);
+    is_number.then_some(number)
+}
+
+/// Get the number of pixels per point of the given display, which is 2 on the retina displays
+unsafe fn display_scale_factor(display_id: u32) -> f64 {
+    let mode = CGDisplayCopyDisplayMode(display_id);
+    if mode.is_null() {
+        return 1.;
+    }
+    let points = CGDisplayModeGetWidth(mode);
+    let pixels = CGDisplayModeGetPixelWidth(mode);
+    CFRelease(mode);
+    if points == 0 {
+        return 1.;
+    }
+    pixels as f64 / points as f64
+}
+
+/// Check whether the process is trusted for accessibility, which the event taps of the hooks
+/// and `clip_cursor` need, and ask the user to allow it in the system settings if `prompt` is set
+///
+/// The settings only apply to the processes that are started after the permission is allowed
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// if !mouce::darwin::is_trusted(true) {
+///     println!("Allow the accessibility access in the system settings and restart the app");
+/// }
+/// ```
+pub fn is_trusted(prompt: bool) -> bool {
+    unsafe {
+        let keys = [kAXTrustedCheckOptionPrompt];
+        let values = [if prompt {
+            kCFBooleanTrue
+        } else {
+            kCFBooleanFalse
+        }];
+        let options = CFDictionaryCreate(
+            null_mut(),
+            keys.as_ptr(),
+            values.as_ptr(),
+            1,
+            addr_of!(kCFTypeDictionaryKeyCallBacks),
+            addr_of!(kCFTypeDictionaryValueCallBacks),
+        );
+        let trusted = AXIsProcessTrustedWithOptions(options);
+        if !options.is_null() {
+            CFRelease(options);
+        }
+        trusted
+    }
+}
+
+/// Connect or disconnect the movements of the mouse and the cursor, the cursor
+/// stays in place while they are disconnected but the events still have their deltas
+///
+/// This is the relative capture mode of `MouseActions::set_relative_capture`
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// // Read the movements of the mouse without moving the cursor
+/// mouce::darwin::associate_mouse_and_cursor(false).unwrap();
+/// mouce::darwin::associate_mouse_and_cursor(true).unwrap();
+/// ```
+pub fn associate_mouse_and_cursor(connected: bool) -> Result<(), Error> {
+    let result = unsafe { CGAssociateMouseAndMouseCursorPosition(connected as c_int) };
+    if result != CGError::Success {
+        return Err(Error::CGError {
+            context: "Failed to associate the mouse and the cursor",
+            code: result as i32,
+        });
+    }
+    RELATIVE_CAPTURE.store(!connected, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Get the error of an event tap that could not be created, the taps are
+/// not created for the processes that are not trusted for accessibility
+fn tap_error() -> Error {
+    if is_trusted(false) {
+        Error::CustomError("failed to create the event tap")
+    } else {
+        Error::AccessibilityNotTrusted
+    }
+}
+
+/// A retained run loop along with the thread that runs it until it is stopped
+struct RunLoopThread {
+    run_loop: usize,
+    stopped: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+impl RunLoopThread {
+    /// Stop the run loop, wait for its thread and release the loop
+    fn stop(self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        unsafe { CFRunLoopStop(self.run_loop as *mut c_void) };
+        let _ = self.thread.join();
+        unsafe { CFRelease(self.run_loop as CFTypeRef) };
+    }
+}
+
+/// Run the run loop of the current thread until the given flag is set
+///
+/// CFRunLoopStop does nothing to a loop that is not running yet, so the loop runs
+/// for a limited time at once and the flag is checked in between the runs
+unsafe fn run_until_stopped(stopped: &AtomicBool) {
+    while !stopped.load(Ordering::SeqCst) {
+        CFRunLoopRunInMode(kCFRunLoopDefaultMode, RUN_LOOP_INTERVAL, 0);
+    }
+}
+
+/// Start an event tap that warps the cursor back into the clip region
+/// whenever the mouse moves it out
+fn start_clip_tap() -> Result<RunLoopThread, Error> {
+    unsafe extern "C" fn clip_callback(
+        _proxy: *const c_void,
+        event_type: CGEventType,
+        cg_event: CGEventRef,
+        _user_info: *mut c_void,
+    ) -> CGEventRef {
+        if let CGEventType::TapDisabledByTimeout | CGEventType::TapDisabledByUserInput = event_type
+        {
+            CGEventTapEnable(CLIP_TAP_REF.load(Ordering::SeqCst) as CFTypeRef, true);
+            return cg_event;
+        }
+        if let Some(region) = *CLIP_REGION.lock().unwrap() {
+            let point = CGEventGetLocation(cg_event);
+            let position = (point.x as i32, point.y as i32);
+            let (x, y) = region.clamp(position);
+            if (x, y) != position {
+                let clamped = || CGPoint {
+                    x: x as c_double,
+                    y: y as c_double,
+                };
+                CGWarpMouseCursorPosition(clamped());
+                // A warp freezes the cursor for a moment unless it is associated
+                // again, which would end the relative capture mode
+                if !RELATIVE_CAPTURE.load(Ordering::SeqCst) {
+                    CGAssociateMouseAndMouseCursorPosition(1);
+                }
+                CGEventSetLocation(cg_event, clamped());
+            }
+        }
+        cg_event
+    }
+
+    let events_of_interest = [
+        CGEventType::MouseMoved,
+        CGEventType::LeftMouseDragged,
+        CGEventType::RightMouseDragged,
+        CGEventType::OtherMouseDragged,
+    ]
+    .into_iter()
+    .fold(0, |bits, event_type| bits | 1 << event_type as u64);
+
+    let (sender, receiver) = mpsc::channel();
+    let stopped = Arc::new(AtomicBool::new(false));
+    let thread_stopped = stopped.clone();
+    let thread = thread::spawn(move || unsafe {
+        let tap_event_ref = CGEventTapCreate(
+            CGEventTapLocation::CGHIDEventTap,
+            CGEventTapPlacement::HeadInsertEventTap,
+            CGEventTapOption::Default as u32,
+            events_of_interest,
+            Some(clip_callback),
+            null_mut(),
+        );
+        if tap_event_ref.is_null() {
+            let _ = sender.send(0);
+            return;
+        }
+
+        let loop_source = CFMachPortCreateRunLoopSource(null_mut(), tap_event_ref, 0);
+        let current_loop = CFRunLoopGetCurrent();
+        CFRunLoopAddSource(current_loop, loop_source, kCFRunLoopDefaultMode);
+        CGEventTapEnable(tap_event_ref, true);
+        CLIP_TAP_REF.store(tap_event_ref as usize, Ordering::SeqCst);
+        // The run loop is retained until the thread is joined, see `RunLoopThread`
+        CFRetain(current_loop);
+        let _ = sender.send(current_loop as usize);
+        run_until_stopped(&thread_stopped);
+        CLIP_TAP_REF.store(0, Ordering::SeqCst);
+        CGEventTapEnable(tap_event_ref, false);
+        CFMachPortInvalidate(tap_event_ref);
+        CFRelease(loop_source);
+        CFRelease(tap_event_ref);
+    });
+
+    match receiver.recv() {
+        Ok(run_loop) if run_loop != 0 => Ok(RunLoopThread {
+            run_loop,
+            stopped,
+            thread,
+        }),
+        _ => Err(tap_error()),
+    }
+}
+
+/// Run the given function with a connection to the HID system,
+/// which keeps the acceleration settings of the pointer
+unsafe fn with_hid_system<T>(f: impl FnOnce(c_uint) -> Result<T, Error>) -> Result<T, Error> {
+    let name = CString::new("IOHIDSystem").unwrap();
+    // The matching dictionary is consumed by IOServiceGetMatchingService
+    let service = IOServiceGetMatchingService(0, IOServiceMatching(name.as_ptr()));
+    if service == 0 {
+        return Err(Error::CustomError("failed to find the HID system"));
+    }
+
+    let mut connect = 0;
+    let result = IOServiceOpen(
+        service,
+        mach_task_self_,
+        HID_PARAM_CONNECT_TYPE,
+        &mut connect,
+    );
+    IOObjectRelease(service);
+    if result != 0 {
+        return Err(Error::CustomError("failed to connect to the HID system"));
+    }
+
+    let result = f(connect);
+    IOServiceClose(connect);
+    result
+}
+
+unsafe fn hid_mouse_acceleration() -> Result<f64, Error> {
+    with_hid_system(|connect| {
+        let key = create_cf_string(HID_MOUSE_ACCELERATION);
+        let mut acceleration = 0.;
+        let result = IOHIDGetAccelerationWithKey(connect, key, &mut acceleration);
+        CFRelease(key);
+        if result != 0 {
+            return Err(Error::CustomError(
+                "failed to read the pointer acceleration",
+            ));
+        }
+        Ok(acceleration)
+    })
+}
+
+unsafe fn set_hid_mouse_acceleration(acceleration: f64) -> Result<(), Error> {
+    with_hid_system(|connect| {
+        let key = create_cf_string(HID_MOUSE_ACCELERATION);
+        let result = IOHIDSetAccelerationWithKey(connect, key, acceleration);
+        CFRelease(key);
+        if result != 0 {
+            return Err(Error::CustomError("failed to set the pointer acceleration"));
+        }
+        Ok(())
+    })
+}
+
+/// CoreGraphics type definitions
+#[allow(dead_code)]
+#[derive(PartialEq, Eq)]
+#[repr(C)]
+enum CGError {
+    CannotComplete = 1004,
+    Failure = 1000,
+    IllegalArgument = 1001,
+    InvalidConnection = 1002,
+    InvalidContext = 1003,
+    InvalidOperation = 1010,
+    NoneAvailable = 1011,
+    NotImplemented = 1006,
+    RangeCheck = 1007,
+    Success = 0,
+    TypeCheck = 1008,
+}
+#[repr(C)]
+pub struct CGPoint {
+    x: c_double,
+    y: c_double,
+}
+#[repr(C)]
+struct CGSize {
+    width: c_double,
+    height: c_double,
+}
+#[repr(C)]
+struct CGRect {
+    origin: CGPoint,
+    size: CGSize,
+}
+enum CGEventSource {}
+enum CGEvent {}
+type CGEventSourceRef = *mut CGEventSource;
+type CGEventRef = *mut CGEvent;
+type CFTypeRef = *const c_void;
+type CGEventMask = c_ulong;
+
+#[repr(u32)]
+enum CGEventType {
+    LeftMouseDown = 1,
+    LeftMouseUp = 2,
+    RightMouseDown = 3,
+    RightMouseUp = 4,
+    MouseMoved = 5,
+    LeftMouseDragged = 6,
+    RightMouseDragged = 7,
+    ScrollWheel = 22,
+    OtherMouseDown = 25,
+    OtherMouseUp = 26,
+    OtherMouseDragged = 27,
+    // The system disabled the tap, these are only passed to the callbacks of the taps
+    #[allow(dead_code)]
+    TapDisabledByTimeout = 0xFFFFFFFE,
+    #[allow(dead_code)]
+    TapDisabledByUserInput = 0xFFFFFFFF,
+}
+
+// The most displays that are listed, far more than a mac can drive
+const MAX_DISPLAYS: usize = 32;
+// kCGDirectMainDisplay, the cursor functions ignore the display anyway
+const MAIN_DISPLAY: c_uint = 0;
+
+// CGMouseButton, the numbers of the other buttons follow the center button
+type CGMouseButton = c_uint;
+const MOUSE_BUTTON_LEFT: CGMouseButton = 0;
+const MOUSE_BUTTON_RIGHT: CGMouseButton = 1;
+const MOUSE_BUTTON_CENTER: CGMouseButton = 2;
+
+// kIOHIDParamConnectType, the connection of IOHIDSystem that changes its parameters
+const HID_PARAM_CONNECT_TYPE: c_uint = 1;
+// kIOHIDMouseAccelerationType
+const HID_MOUSE_ACCELERATION: &str = "HIDMouseAcceleration";
+// The acceleration of the "Tracking speed" that a new user starts with
+const DEFAULT_MOUSE_ACCELERATION: f64 = 0.6875;
+
+// The longest time that a run loop runs before it checks whether it is stopped, in seconds
+const RUN_LOOP_INTERVAL: c_double = 0.1;
+
+// CFNumberType::kCFNumberDoubleType
+const CF_NUMBER_DOUBLE_TYPE: c_int = 13;
+// CFStringBuiltInEncodings::kCFStringEncodingUTF8
+const CF_STRING_ENCODING_UTF8: c_uint = 0x08000100;
+
+#[repr(C)]
+enum CGEventSourceStateID {
+    _Private = -1,
+    CombinedSessionState = 0,
+    HIDSystemState = 1,
+}
+
+#[repr(C)]
+enum CGEventTapLocation {
+    CGHIDEventTap = 0,
+    _CGSessionEventTap = 1,
+    _CGAnnotatedSessionEventTap = 2,
+}
+
+#[repr(C)]
+enum CGScrollEventUnit {
+    Pixel = 0,
+    Line = 1,
+}
+
+#[repr(C)]
+enum CGEventTapPlacement {
+    HeadInsertEventTap = 0,
+    _TailAppendEventTap = 1,
+}
+
+#[repr(C)]
+enum CGEventTapOption {
+    Default = 0,
+    ListenOnly = 1,
+}
+
+// CGEventField::kCGEventSourceStateID
+const EVENT_SOURCE_STATE_ID: c_uint = 45;
+// CGEventField::kCGEventSourceUserData
+const EVENT_SOURCE_USER_DATA: c_uint = 42;
+// The source user data of the events that are posted by the mouse managers
+const OWN_EVENT_USER_DATA: i64 = 0x6d6f7563;
+// CGEventField::kCGMouseEventClickState
+const MOUSE_EVENT_CLICK_STATE: c_uint = 1;
+// CGEventField::kCGMouseEventButtonNumber
+const MOUSE_EVENT_BUTTON_NUMBER: c_uint = 3;
+// CGEventField::kCGMouseEventDeltaX and kCGMouseEventDeltaY
+const MOUSE_EVENT_DELTA_X: c_uint = 4;
+const MOUSE_EVENT_DELTA_Y: c_uint = 5;
+// CGEventField::kCGScrollWheelEventFixedPtDeltaAxis1 and Axis2
+const SCROLL_WHEEL_EVENT_FIXED_PT_DELTA_AXIS_1: c_uint = 93;
+const SCROLL_WHEEL_EVENT_FIXED_PT_DELTA_AXIS_2: c_uint = 94;
+// CGEventField::kCGScrollWheelEventPointDeltaAxis1 and Axis2
+const SCROLL_WHEEL_EVENT_POINT_DELTA_AXIS_1: c_uint = 96;
+const SCROLL_WHEEL_EVENT_POINT_DELTA_AXIS_2: c_uint = 97;
+// CGEventField::kCGScrollWheelEventIsContinuous
+const SCROLL_WHEEL_EVENT_IS_CONTINUOUS: c_uint = 88;
+
+type CGEventTapCallback = Option<
+    unsafe extern "C" fn(
+        proxy: *const c_void,
+        event_type: CGEventType,
+        cg_event: CGEventRef,
+        user_info: *mut c_void,
+    ) -> CGEventRef,
+>;
+
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    fn CGWarpMouseCursorPosition(new_cursor_position: CGPoint) -> CGError;
+    fn CGGetActiveDisplayList(
+        max_displays: u32,
+        active_displays: *mut u32,
+        display_count: *mut u32,
+    ) -> CGError;
+    fn CGMainDisplayID() -> u32;
+    fn CGDisplayBounds(display: u32) -> CGRect;
+    fn CGDisplayCopyDisplayMode(display: u32) -> CFTypeRef;
+    fn CGDisplayModeGetWidth(mode: CFTypeRef) -> usize;
+    fn CGDisplayModeGetPixelWidth(mode: CFTypeRef) -> usize;
+    fn CGDisplayHideCursor(display: c_uint) -> CGError;
+    fn CGDisplayShowCursor(display: c_uint) -> CGError;
+    fn CGEventCreate(source: CGEventSourceRef) -> CGEventRef;
+    fn CGEventGetLocation(event: CGEventRef) -> CGPoint;
+    fn CGEventSetLocation(event: CGEventRef, location: CGPoint);
+    fn CGAssociateMouseAndMouseCursorPosition(connected: c_int) -> CGError;
+    fn CGEventCreateMouseEvent(
+        source: CGEventSourceRef,
+        mouse_type: CGEventType,
+        mouse_cursor_position: CGPoint,
+        mouse_button: CGMouseButton,
+    ) -> CGEventRef;
+    fn CGEventCreateScrollWheelEvent(
+        source: CGEventSourceRef,
+        units: CGScrollEventUnit,
+        // Number of scroll directions/wheels, maximum is 3
+        wheel_count: c_int,
+        // Vertical wheel movement distance
+        wheel1: c_int,
+        // Horizontal wheel movement distance
+        wheel2: c_int,
+    ) -> CGEventRef;
+    fn CGEventPost(tap: CGEventTapLocation, event: CGEventRef);
+    fn CGEventTapCreate(
+        tap: CGEventTapLocation,
+        place: CGEventTapPlacement,
+        options: c_uint,
+        eventsOfInterest: CGEventMask,
+        callback: CGEventTapCallback,
+        refcon: *mut c_void,
+    ) -> CFTypeRef;
+    fn CGEventTapEnable(tap: *const c_void, enable: bool);
+    fn CGEventGetIntegerValueField(event: CGEventRef, field: c_uint) -> c_long;
+    fn CGEventGetDoubleValueField(event: CGEventRef, field: c_uint) -> f64;
+    fn CGEventSetIntegerValueField(event: CGEventRef, field: c_uint, value: i64);
+    fn CGEventSourceButtonState(state_id: CGEventSourceStateID, button: CGMouseButton) -> bool;
+    fn CGEventSourceCreate(state_id: CGEventSourceStateID) -> CGEventSourceRef;
+}
+#[link(name = "IOKit", kind = "framework")]
+extern "C" {
+    fn IOServiceMatching(name: *const c_char) -> CFTypeRef;
+    fn IOServiceGetMatchingService(main_port: c_uint, matching: CFTypeRef) -> c_uint;
+    fn IOServiceOpen(
+        service: c_uint,
+        owning_task: c_uint,
+        kind: c_uint,
+        connect: *mut c_uint,
+    ) -> c_int;
+    fn IOServiceClose(connect: c_uint) -> c_int;
+    fn IOObjectRelease(object: c_uint) -> c_int;
+    fn IOHIDGetAccelerationWithKey(handle: c_uint, key: CFTypeRef, acceleration: *mut f64)
+        -> c_int;
+    fn IOHIDSetAccelerationWithKey(handle: c_uint, key: CFTypeRef, acceleration: f64) -> c_int;
+}
+extern "C" {
+    static mach_task_self_: c_uint;
+}
+#[link(name = "ApplicationServices", kind = "framework")]
+extern "C" {
+    static kAXTrustedCheckOptionPrompt: CFTypeRef;
+
+    fn AXIsProcessTrustedWithOptions(options: CFTypeRef) -> bool;
+}
+/// The callbacks of the dictionary values, only their addresses are passed on
+#[repr(C)]
+struct CFDictionaryCallBacks {
+    _private: [u8; 0],
+}
+#[link(name = "CoreFoundation", kind = "framework")]
+extern "C" {
+    static kCFRunLoopDefaultMode: *const c_void;
+    static kCFPreferencesAnyApplication: CFTypeRef;
+    static kCFBooleanTrue: CFTypeRef;
+    static kCFBooleanFalse: CFTypeRef;
+    static kCFTypeDictionaryKeyCallBacks: CFDictionaryCallBacks;
+    static kCFTypeDictionaryValueCallBacks: CFDictionaryCallBacks;
+
+    fn CFRelease(cf: CFTypeRef);
+    fn CFRetain(cf: *const c_void) -> CFTypeRef;
+    fn CFMachPortCreateRunLoopSource(
+        allocator: *mut c_void,
+        tap: *const c_void,
+        order: c_ulong,
+    ) -> *mut c_void;
+    fn CFMachPortInvalidate(port: *const c_void);
+    fn CFRunLoopGetCurrent() -> *mut c_void;
+    fn CFRunLoopAddSource(rl: *mut c_void, source: *mut c_void, mode: *const c_void);
+    fn CFRunLoopRunInMode(
+        mode: *const c_void,
+        seconds: c_double,
+        return_after_source_handled: u8,
+    ) -> c_int;
+    fn CFRunLoopStop(rl: *mut c_void);
+    fn CFGetTypeID(cf: CFTypeRef) -> c_ulong;
+    fn CFNumberGetTypeID() -> c_ulong;
+    fn CFNumberGetValue(number: CFTypeRef, the_type: c_int, value_ptr: *mut c_void) -> bool;
+    fn CFStringCreateWithCString(
+        allocator: *mut c_void,
+        c_str: *const c_char,
+        encoding: c_uint,
+    ) -> CFTypeRef;
+    fn CFPreferencesCopyAppValue(key: CFTypeRef, application_id: CFTypeRef) -> CFTypeRef;
+    fn CFDictionaryCreate(
+        allocator: *mut c_void,
+        keys: *const CFTypeRef,
+        values: *const CFTypeRef,
+        count: c_long,
+        key_callbacks: *const CFDictionaryCallBacks,
+        value_callbacks: *const CFDictionaryCallBacks,
+    ) -> CFTypeRef;
+}
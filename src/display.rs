@@ -0,0 +1,228 @@
+///
+/// This module contains the enumeration of the monitors of the system
+///
+/// The bounds of the monitors are in the coordinates of the virtual desktop
+/// that spans all of them, so the monitors that are placed left of or above
+/// the primary monitor have negative origins
+///
+/// These are the coordinates of `move_to` and `get_position`, which are not
+/// always the physical pixels of the monitors (e.g. in screenshots):
+/// - macOS uses points, which span two pixels on the retina displays
+/// - Windows scales the coordinates of the processes that are not DPI aware
+/// - The browsers use CSS pixels, which depend on the zoom level as well
+/// - X11 and uinput use the physical pixels, Xft.dpi only scales the toolkits,
+///   but the transform of an output in XRandR (e.g. `xrandr --scale`) scales them
+///
+use crate::common::Region;
+use crate::error::Error;
+
+/// A monitor that is connected to the system
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Monitor {
+    /// The index of the monitor in the list of `monitors()`
+    pub id: usize,
+    /// The area of the virtual desktop that the monitor shows
+    pub bounds: Region,
+    /// The number of physical pixels that a unit of the coordinates spans on the monitor
+    pub scale_factor: f64,
+    pub primary: bool,
+}
+
+impl Monitor {
+    /// Translate the given position on the monitor, relative to its top left
+    /// corner, to the coordinates of the virtual desktop
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use mouce::common::Region;
+    /// use mouce::display::Monitor;
+    ///
+    /// // A monitor that is placed left of the primary monitor
+    /// let monitor = Monitor {
+    ///     id: 1,
+    ///     bounds: Region::new(-1920, 0, 1920, 1080),
+    ///     scale_factor: 1.,
+    ///     primary: false,
+    /// };
+    /// assert_eq!(monitor.to_global(100, 100), Ok((-1820, 100)));
+    /// assert!(monitor.to_global(1920, 0).is_err());
+    /// ```
+    pub fn to_global(&self, x: u32, y: u32) -> Result<(i32, i32), Error> {
+        if x >= self.bounds.width || y >= self.bounds.height {
+            return Err(Error::CustomError("the position is outside of the monitor"));
+        }
+        // The position is inside of the bounds, so it fits into the virtual desktop
+        Ok((
+            (self.bounds.x as i64 + x as i64) as i32,
+            (self.bounds.y as i64 + y as i64) as i32,
+        ))
+    }
+
+    /// Convert the given position of the virtual desktop to the physical pixel of
+    /// the monitor, relative to its top left corner like in a screenshot of the monitor
+    pub fn to_physical(&self, position: (i32, i32)) -> (i32, i32) {
+        let scale = |value: i32, start: i32| {
+            ((value as i64 - start as i64) as f64 * self.scale_factor).floor() as i32
+        };
+        (
+            scale(position.0, self.bounds.x),
+            scale(position.1, self.bounds.y),
+        )
+    }
+
+    /// Convert the given physical pixel of the monitor, relative to its top left
+    /// corner, to the position of the virtual desktop that covers it
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// // Click a button that was found at the given pixel of a screenshot of the primary monitor
+    /// let monitors = mouce::display::monitors().unwrap();
+    /// let monitor = monitors.iter().find(|monitor| monitor.primary).unwrap();
+    /// let (x, y) = monitor.from_physical((640, 360));
+    /// manager.move_to(x as usize, y as usize).unwrap();
+    /// ```
+    pub fn from_physical(&self, pixel: (u32, u32)) -> (i32, i32) {
+        let scale = |value: u32, start: i32| {
+            (start as f64 + (value as f64 / self.scale_factor).floor()) as i32
+        };
+        (scale(pixel.0, self.bounds.x), scale(pixel.1, self.bounds.y))
+    }
+}
+
+/// Get the monitors that are connected to the system
+pub fn monitors() -> Result<Vec<Monitor>, Error> {
+    crate::Mouse::monitors()
+}
+
+/// Get the monitor with the given id
+pub fn monitor(id: usize) -> Result<Monitor, Error> {
+    monitors()?
+        .into_iter()
+        .find(|monitor| monitor.id == id)
+        .ok_or(Error::CustomError("there is no monitor with the given id"))
+}
+
+/// Get the monitor that shows the given position of the virtual desktop,
+/// the closest monitor is chosen if the position falls between them
+pub fn monitor_at(position: (i32, i32)) -> Result<Monitor, Error> {
+    closest_monitor(&monitors()?, position).ok_or(Error::CustomError("there are no monitors"))
+}
+
+/// Get the bounds of the virtual desktop, the smallest region that covers all the monitors
+///
+/// The monitors that are placed left of or above the primary monitor have negative
+/// coordinates, they can be reached with `move_to_monitor`
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let bounds = mouce::display::virtual_screen_bounds().unwrap();
+/// // The left edge is negative with a monitor left of the primary one
+/// println!("The desktop starts at {}, {}", bounds.x, bounds.y);
+/// ```
+pub fn virtual_screen_bounds() -> Result<Region, Error> {
+    screen_bounds()
+}
+
+#[cfg(target_os = "windows")]
+use crate::windows::virtual_screen_bounds as screen_bounds;
+
+#[cfg(not(target_os = "windows"))]
+fn screen_bounds() -> Result<Region, Error> {
+    bounding_region(&monitors()?).ok_or(Error::CustomError("there are no monitors"))
+}
+
+/// Get the smallest region that covers the given monitors
+#[cfg_attr(target_os = "windows", allow(dead_code))]
+fn bounding_region(monitors: &[Monitor]) -> Option<Region> {
+    let edges = |monitor: &Monitor| {
+        let bounds = monitor.bounds;
+        (
+            bounds.x as i64,
+            bounds.y as i64,
+            bounds.x as i64 + bounds.width as i64,
+            bounds.y as i64 + bounds.height as i64,
+        )
+    };
+    let (left, top, right, bottom) = monitors
+        .iter()
+        .map(edges)
+        .reduce(|a, b| (a.0.min(b.0), a.1.min(b.1), a.2.max(b.2), a.3.max(b.3)))?;
+    Some(Region::new(
+        left as i32,
+        top as i32,
+        (right - left).min(u32::MAX as i64) as u32,
+        (bottom - top).min(u32::MAX as i64) as u32,
+    ))
+}
+
+pub(crate) fn closest_monitor(monitors: &[Monitor], position: (i32, i32)) -> Option<Monitor> {
+    monitors.iter().copied().min_by_key(|monitor| {
+        let (x, y) = monitor.bounds.clamp(position);
+        let (dx, dy) = (x as i64 - position.0 as i64, y as i64 - position.1 as i64);
+        dx * dx + dy * dy
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bounding_region, closest_monitor, Monitor};
+    use crate::common::Region;
+
+    #[test]
+    fn closest_monitors() {
+        let monitors = [
+            Monitor {
+                id: 0,
+                bounds: Region::new(0, 0, 1920, 1080),
+                scale_factor: 1.,
+                primary: true,
+            },
+            Monitor {
+                id: 1,
+                bounds: Region::new(-1280, 200, 1280, 1024),
+                scale_factor: 2.,
+                primary: false,
+            },
+        ];
+        let closest = |position| closest_monitor(&monitors, position).map(|monitor| monitor.id);
+        assert_eq!(closest((100, 100)), Some(0));
+        assert_eq!(closest((-1, 500)), Some(1));
+        // Below the primary monitor, but still closer to it
+        assert_eq!(closest((500, 1300)), Some(0));
+        assert_eq!(closest_monitor(&[], (0, 0)), None);
+
+        assert_eq!(
+            bounding_region(&monitors),
+            Some(Region::new(-1280, 0, 3200, 1224))
+        );
+        assert_eq!(bounding_region(&[]), None);
+    }
+
+    #[test]
+    fn physical_pixels() {
+        let monitor = Monitor {
+            id: 1,
+            bounds: Region::new(-1280, 200, 1280, 1024),
+            scale_factor: 2.,
+            primary: false,
+        };
+        assert_eq!(monitor.to_physical((-1280, 200)), (0, 0));
+        assert_eq!(monitor.to_physical((-1000, 300)), (560, 200));
+        assert_eq!(monitor.from_physical((560, 200)), (-1000, 300));
+        // Both of the pixels of a point are covered by it
+        assert_eq!(monitor.from_physical((561, 201)), (-1000, 300));
+
+        let monitor = Monitor {
+            scale_factor: 1.5,
+            ..monitor
+        };
+        assert_eq!(monitor.from_physical((3, 3)), (-1278, 202));
+        assert_eq!(monitor.to_physical((-1278, 202)), (3, 3));
+    }
+}
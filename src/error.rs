@@ -1,6 +1,13 @@
+use crate::common::CallbackId;
 use std::fmt;
+use std::io;
 
-#[derive(Debug, PartialEq, Eq)]
+/// The errors of the mouse actions
+///
+/// New variants may be added in the future, so the matches on
+/// the errors need a wildcard arm
+#[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
     NotImplemented,
     WriteFailed,
@@ -9,10 +16,154 @@ pub enum Error {
     InputIsBlocked,
     CGCouldNotCreateEvent,
     PermissionDenied,
+    /// The process is not allowed to control the computer on macOS, which the event
+    /// taps need, see `mouce::darwin::is_trusted`
+    AccessibilityNotTrusted,
     CustomError(&'static str),
+    /// A call to the operating system failed, `code` is the `errno` on the
+    /// unix-like systems and the `GetLastError` code on windows
+    OsError {
+        context: &'static str,
+        code: i32,
+    },
+    /// A CoreGraphics call failed with the given CGError, e.g. 1001 for kCGErrorIllegalArgument
+    CGError {
+        context: &'static str,
+        code: i32,
+    },
+    /// The X server rejected a request with the given error code, e.g. 3 for BadWindow
+    X11Error {
+        context: &'static str,
+        code: u8,
+    },
+    /// Reading or writing a device or a socket failed with the given IO error
+    Io {
+        context: &'static str,
+        source: io::Error,
+    },
+    /// A hooked callback panicked with the given message, it is unhooked afterwards
+    CallbackPanicked {
+        id: CallbackId,
+        message: String,
+    },
 }
 
-impl std::error::Error for Error {}
+impl Error {
+    /// Create an error from the last error of the operating system on the current thread
+    // macOS and the browsers report their errors without errno
+    #[cfg_attr(any(target_vendor = "apple", target_arch = "wasm32"), allow(dead_code))]
+    pub(crate) fn last_os_error(context: &'static str) -> Self {
+        Error::OsError {
+            context,
+            code: io::Error::last_os_error().raw_os_error().unwrap_or(0),
+        }
+    }
+
+    /// Create an error that keeps the given IO error as its source
+    // Only the device files of the unix-like systems are read and written directly
+    #[cfg_attr(
+        not(any(
+            target_os = "linux",
+            target_os = "dragonfly",
+            target_os = "freebsd",
+            target_os = "netbsd",
+            target_os = "openbsd"
+        )),
+        allow(dead_code)
+    )]
+    pub(crate) fn from_io(context: &'static str, source: io::Error) -> Self {
+        Error::Io { context, source }
+    }
+
+    /// Get the error code of the operating system, if the error carries one
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// if let Err(err) = manager.move_to(0, 0) {
+    ///     // e.g. compare the code with EACCES or ERROR_ACCESS_DENIED
+    ///     println!("{} (code {:?})", err, err.raw_os_error());
+    /// }
+    /// ```
+    pub fn raw_os_error(&self) -> Option<i32> {
+        match self {
+            Error::OsError { code, .. } => Some(*code),
+            Error::Io { source, .. } => source.raw_os_error(),
+            _ => None,
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+// io::Error can not be compared, so the IO errors are equal when their kinds and codes are
+impl PartialEq for Error {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                Error::Io { context, source },
+                Error::Io {
+                    context: other_context,
+                    source: other_source,
+                },
+            ) => {
+                context == other_context
+                    && source.kind() == other_source.kind()
+                    && source.raw_os_error() == other_source.raw_os_error()
+            }
+            (Error::CustomError(a), Error::CustomError(b)) => a == b,
+            (
+                Error::OsError { context, code },
+                Error::OsError {
+                    context: other_context,
+                    code: other_code,
+                },
+            )
+            | (
+                Error::CGError { context, code },
+                Error::CGError {
+                    context: other_context,
+                    code: other_code,
+                },
+            ) => context == other_context && code == other_code,
+            (
+                Error::X11Error { context, code },
+                Error::X11Error {
+                    context: other_context,
+                    code: other_code,
+                },
+            ) => context == other_context && code == other_code,
+            (
+                Error::CallbackPanicked { id, message },
+                Error::CallbackPanicked {
+                    id: other_id,
+                    message: other_message,
+                },
+            ) => id == other_id && message == other_message,
+            (Error::NotImplemented, Error::NotImplemented)
+            | (Error::WriteFailed, Error::WriteFailed)
+            | (Error::UnhookFailed, Error::UnhookFailed)
+            | (Error::X11PointerWindowMismatch, Error::X11PointerWindowMismatch)
+            | (Error::InputIsBlocked, Error::InputIsBlocked)
+            | (Error::CGCouldNotCreateEvent, Error::CGCouldNotCreateEvent)
+            | (Error::PermissionDenied, Error::PermissionDenied)
+            | (Error::AccessibilityNotTrusted, Error::AccessibilityNotTrusted) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Error {}
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -32,9 +183,62 @@ impl fmt::Display for Error {
             Error::PermissionDenied => {
                 "permission denied for this operation, plese try as super user"
             }
+            Error::AccessibilityNotTrusted => {
+                "the process is not trusted for accessibility, allow it in the Privacy & Security settings"
+            }
             Error::CustomError(err_description) => err_description,
+            Error::OsError { context, code } => {
+                return write!(f, "{}: {}", context, io::Error::from_raw_os_error(*code));
+            }
+            Error::CGError { context, code } => {
+                return write!(f, "{}, CGError {}", context, code);
+            }
+            Error::X11Error { context, code } => {
+                return write!(f, "{}, X11 error code {}", context, code);
+            }
+            Error::CallbackPanicked { id, message } => {
+                return write!(f, "the callback {} panicked: {}", id.as_u64(), message);
+            }
+            // The source is not repeated, the error chains show it after the context
+            Error::Io { context, .. } => context,
         };
 
         write!(f, "{}", err_message)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Error;
+    use std::error::Error as _;
+    use std::io;
+
+    #[test]
+    fn io_errors() {
+        let err = Error::from_io(
+            "failed to open the device",
+            io::Error::from_raw_os_error(13),
+        );
+        assert_eq!(err.raw_os_error(), Some(13));
+        assert_eq!(err.to_string(), "failed to open the device");
+        assert_eq!(
+            err.source().unwrap().to_string(),
+            io::Error::from_raw_os_error(13).to_string()
+        );
+        assert_eq!(
+            err,
+            Error::from_io(
+                "failed to open the device",
+                io::Error::from_raw_os_error(13)
+            )
+        );
+        assert_ne!(
+            err,
+            Error::from_io("failed to open the device", io::ErrorKind::NotFound.into())
+        );
+
+        assert_eq!(Error::PermissionDenied.raw_os_error(), None);
+        assert!(Error::PermissionDenied.source().is_none());
+        assert_ne!(Error::PermissionDenied, Error::NotImplemented);
+    }
+}
@@ -0,0 +1,211 @@
+///
+/// This module contains the watcher of the mice that are connected to or
+/// removed from the system
+///
+/// The devices are watched with inotify on linux, the device interface
+/// notifications (WM_DEVICECHANGE) on windows and the matching callbacks
+/// of the IOKit HID manager on macOS, which needs the `iohid` feature.
+/// The mice that are already connected when the watcher starts are
+/// reported as added first
+///
+/// The devices have the same ids as the devices in the `EventInfo` of the
+/// hooked events, so the events can be told apart by the reported devices
+///
+use crate::common::{Device, DeviceId};
+use crate::error::Error;
+use std::collections::HashSet;
+
+/// A change of the connected mice
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceEvent {
+    Added(Device),
+    Removed(DeviceId),
+}
+
+/// The callback that is invoked on the thread of the watcher for each change
+pub type DeviceCallback = Box<dyn Fn(&DeviceEvent) + Send>;
+
+/// Reports the mice that are connected or removed, the watcher stops once it is dropped
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use mouce::hotplug::{DeviceEvent, DeviceWatcher};
+///
+/// let watcher = DeviceWatcher::start(Box::new(|event| match event {
+///     DeviceEvent::Added(device) => println!("Connected: {:?}", device.name),
+///     DeviceEvent::Removed(id) => println!("Removed: {:?}", id),
+/// }));
+/// assert!(watcher.is_ok());
+/// ```
+pub struct DeviceWatcher {
+    /// Stops the platform watcher and waits for its thread to finish
+    stop: Option<Box<dyn FnOnce() + Send>>,
+}
+
+impl DeviceWatcher {
+    /// Start watching the mice, fails with `Error::NotImplemented` on the
+    /// platforms that can not watch them (e.g. the BSDs and the browsers)
+    pub fn start(callback: DeviceCallback) -> Result<Self, Error> {
+        watch_devices(DeviceTracker::new(callback))
+    }
+
+    #[cfg_attr(
+        not(any(
+            target_os = "linux",
+            target_os = "windows",
+            all(target_vendor = "apple", feature = "iohid")
+        )),
+        allow(dead_code)
+    )]
+    pub(crate) fn new<F>(stop: F) -> Self
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        DeviceWatcher {
+            stop: Some(Box::new(stop)),
+        }
+    }
+
+    /// Stop watching and wait for the thread of the watcher to finish
+    pub fn stop(mut self) {
+        self.join();
+    }
+
+    fn join(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            stop();
+        }
+    }
+}
+
+impl Drop for DeviceWatcher {
+    fn drop(&mut self) {
+        self.join();
+    }
+}
+
+#[cfg(target_os = "linux")]
+use crate::nix::watch_devices;
+
+#[cfg(target_os = "windows")]
+use crate::windows::watch_devices;
+
+#[cfg(all(target_vendor = "apple", feature = "iohid"))]
+use crate::darwin::hid::watch_devices;
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "windows",
+    all(target_vendor = "apple", feature = "iohid")
+)))]
+fn watch_devices(_tracker: DeviceTracker) -> Result<DeviceWatcher, Error> {
+    Err(Error::NotImplemented)
+}
+
+/// Keeps the ids of the connected mice, so that only the changes are reported
+#[cfg_attr(
+    not(any(
+        target_os = "linux",
+        target_os = "windows",
+        all(target_vendor = "apple", feature = "iohid")
+    )),
+    allow(dead_code)
+)]
+pub(crate) struct DeviceTracker {
+    callback: DeviceCallback,
+    devices: HashSet<DeviceId>,
+}
+
+#[cfg_attr(
+    not(any(
+        target_os = "linux",
+        target_os = "windows",
+        all(target_vendor = "apple", feature = "iohid")
+    )),
+    allow(dead_code)
+)]
+impl DeviceTracker {
+    pub(crate) fn new(callback: DeviceCallback) -> Self {
+        DeviceTracker {
+            callback,
+            devices: HashSet::new(),
+        }
+    }
+
+    /// Report the given device as added, unless it is already known
+    pub(crate) fn add(&mut self, device: Device) {
+        if self.devices.insert(device.id) {
+            (self.callback)(&DeviceEvent::Added(device));
+        }
+    }
+
+    /// Report the device with the given id as removed, unless it is not known
+    pub(crate) fn remove(&mut self, id: DeviceId) {
+        if self.devices.remove(&id) {
+            (self.callback)(&DeviceEvent::Removed(id));
+        }
+    }
+
+    /// Report the changes between the known devices and the currently connected
+    /// ones, `describe` is only called for the devices that are not known yet
+    #[cfg_attr(target_vendor = "apple", allow(dead_code))]
+    pub(crate) fn update<T, F>(&mut self, connected: Vec<(DeviceId, T)>, describe: F)
+    where
+        F: Fn(DeviceId, T) -> Device,
+    {
+        let ids: HashSet<DeviceId> = connected.iter().map(|(id, _)| *id).collect();
+        let mut removed: Vec<DeviceId> = self.devices.difference(&ids).copied().collect();
+        removed.sort_by_key(|id| id.0);
+        for id in removed {
+            self.remove(id);
+        }
+        for (id, source) in connected {
+            if !self.devices.contains(&id) {
+                self.add(describe(id, source));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DeviceEvent, DeviceTracker};
+    use crate::common::{Device, DeviceId};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn tracked_changes() {
+        let events = Arc::new(Mutex::new(vec![]));
+        let log = events.clone();
+        let mut tracker = DeviceTracker::new(Box::new(move |event| {
+            log.lock().unwrap().push(event.clone());
+        }));
+        let describe = |id: DeviceId, name: &str| Device {
+            id,
+            name: Some(name.to_string()),
+        };
+
+        tracker.update(vec![(DeviceId(1), "mouse"), (DeviceId(2), "pad")], describe);
+        // The known devices are not described again
+        tracker.update(
+            vec![(DeviceId(2), "renamed"), (DeviceId(3), "trackball")],
+            describe,
+        );
+        tracker.remove(DeviceId(1));
+        tracker.add(describe(DeviceId(3), "trackball"));
+        tracker.update(vec![], describe);
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                DeviceEvent::Added(describe(DeviceId(1), "mouse")),
+                DeviceEvent::Added(describe(DeviceId(2), "pad")),
+                DeviceEvent::Removed(DeviceId(1)),
+                DeviceEvent::Added(describe(DeviceId(3), "trackball")),
+                DeviceEvent::Removed(DeviceId(2)),
+                DeviceEvent::Removed(DeviceId(3)),
+            ]
+        );
+    }
+}
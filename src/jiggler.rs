@@ -0,0 +1,239 @@
+///
+/// This module contains the jiggler that nudges the cursor periodically,
+/// e.g. to keep the system awake and the presence of the user active
+///
+/// The cursor is moved back and forth by `amplitude` pixels, so it does not
+/// drift away. With the zero net movement mode the cursor is moved back right
+/// after each nudge instead, so it stays where it is between the nudges
+///
+use crate::builder::MouseBuilder;
+use crate::common::MouseActions;
+use crate::error::Error;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// The time between a nudge and the move back with the zero net movement mode
+const RETURN_DELAY: Duration = Duration::from_millis(20);
+
+/// Nudges the cursor periodically
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use mouce::jiggler::Jiggler;
+/// use std::time::Duration;
+///
+/// // Nudge the cursor by a pixel every minute until the guard is dropped
+/// let guard = Jiggler::new(Duration::from_secs(60), 1)
+///     .zero_net_movement(true)
+///     .start()
+///     .unwrap();
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Jiggler {
+    interval: Duration,
+    amplitude: u16,
+    zero_net_movement: bool,
+}
+
+impl Jiggler {
+    /// Create a jiggler that nudges the cursor by `amplitude` pixels every `interval`
+    pub fn new(interval: Duration, amplitude: u16) -> Self {
+        Jiggler {
+            interval,
+            amplitude,
+            zero_net_movement: false,
+        }
+    }
+
+    /// Move the cursor back right after each nudge (disabled by default)
+    pub fn zero_net_movement(mut self, enabled: bool) -> Self {
+        self.zero_net_movement = enabled;
+        self
+    }
+
+    /// Start nudging with a mouse manager of the current platform,
+    /// the manager is created on the thread of the jiggler
+    pub fn start(self) -> Result<JigglerGuard, Error> {
+        self.spawn(|| MouseBuilder::new().build())
+    }
+
+    /// Start nudging with the given mouse manager
+    pub fn start_with(self, manager: Box<dyn MouseActions + Send>) -> Result<JigglerGuard, Error> {
+        self.spawn(move || Ok(manager))
+    }
+
+    fn spawn<F>(self, manager: F) -> Result<JigglerGuard, Error>
+    where
+        F: FnOnce() -> Result<Box<dyn MouseActions>, Error> + Send + 'static,
+    {
+        if self.interval.is_zero() || self.amplitude == 0 {
+            return Err(Error::CustomError(
+                "the interval and the amplitude of the jiggler must not be zero",
+            ));
+        }
+
+        let (stop, stopped) = mpsc::channel();
+        let thread = thread::spawn(move || {
+            let manager = manager()?;
+            self.run(manager.as_ref(), &stopped)
+        });
+        Ok(JigglerGuard {
+            stop: Some(stop),
+            thread: Some(thread),
+        })
+    }
+
+    fn run(&self, manager: &dyn MouseActions, stopped: &Receiver<()>) -> Result<(), Error> {
+        let amplitude = self.amplitude as i32;
+        let mut direction = 1;
+        // Wait for the interval, unless the jiggler is stopped first
+        while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(self.interval) {
+            manager.move_relative(direction * amplitude, 0)?;
+            if self.zero_net_movement {
+                thread::sleep(RETURN_DELAY);
+                manager.move_relative(-direction * amplitude, 0)?;
+            }
+            direction = -direction;
+        }
+        Ok(())
+    }
+}
+
+/// A running jiggler, it is stopped once the guard is dropped
+#[derive(Debug)]
+pub struct JigglerGuard {
+    /// The jiggler stops once this end of the channel is dropped
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<Result<(), Error>>>,
+}
+
+impl JigglerGuard {
+    /// Stop nudging and wait for the thread to finish, returns the
+    /// error that stopped the jiggler before, if there is any
+    pub fn stop(mut self) -> Result<(), Error> {
+        self.join()
+    }
+
+    fn join(&mut self) -> Result<(), Error> {
+        self.stop.take();
+        match self.thread.take() {
+            Some(thread) => thread
+                .join()
+                .map_err(|_| Error::CustomError("the jiggler thread panicked"))?,
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for JigglerGuard {
+    fn drop(&mut self) {
+        let _ = self.join();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Jiggler;
+    use crate::common::{
+        CallbackId, EventCallback, MouseActions, MouseButton, ScrollDirection, ScrollUnit,
+    };
+    use crate::error::Error;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    /// A manager that logs the relative moves
+    struct TestMouseManager {
+        moves: Arc<Mutex<Vec<i32>>>,
+    }
+
+    impl MouseActions for TestMouseManager {
+        fn move_to(&self, _x: usize, _y: usize) -> Result<(), Error> {
+            Err(Error::NotImplemented)
+        }
+
+        fn move_relative(&self, x_offset: i32, y_offset: i32) -> Result<(), Error> {
+            assert_eq!(y_offset, 0);
+            self.moves.lock().unwrap().push(x_offset);
+            Ok(())
+        }
+
+        fn get_position(&self) -> Result<(i32, i32), Error> {
+            Err(Error::NotImplemented)
+        }
+
+        fn press_button(&self, _button: &MouseButton) -> Result<(), Error> {
+            Err(Error::NotImplemented)
+        }
+
+        fn release_button(&self, _button: &MouseButton) -> Result<(), Error> {
+            Err(Error::NotImplemented)
+        }
+
+        fn scroll_wheel(
+            &self,
+            _direction: &ScrollDirection,
+            _unit: ScrollUnit,
+            _distance: u32,
+        ) -> Result<(), Error> {
+            Err(Error::NotImplemented)
+        }
+
+        fn hook_with_info(&mut self, _callback: EventCallback) -> Result<CallbackId, Error> {
+            Err(Error::NotImplemented)
+        }
+
+        fn unhook(&mut self, _callback_id: CallbackId) -> Result<(), Error> {
+            Err(Error::NotImplemented)
+        }
+
+        fn unhook_all(&mut self) -> Result<(), Error> {
+            Err(Error::NotImplemented)
+        }
+    }
+
+    /// Run the given jiggler until it nudges the cursor a few times
+    fn jiggle(jiggler: Jiggler) -> Vec<i32> {
+        let moves = Arc::new(Mutex::new(vec![]));
+        let manager = TestMouseManager {
+            moves: moves.clone(),
+        };
+        let guard = jiggler.start_with(Box::new(manager)).unwrap();
+        while moves.lock().unwrap().len() < 6 {
+            thread::sleep(Duration::from_millis(1));
+        }
+        drop(guard);
+        let moves = moves.lock().unwrap().clone();
+        moves
+    }
+
+    #[test]
+    fn back_and_forth() {
+        let moves = jiggle(Jiggler::new(Duration::from_millis(1), 2));
+        for (index, offset) in moves.iter().enumerate() {
+            assert_eq!(*offset, if index % 2 == 0 { 2 } else { -2 });
+        }
+
+        // The cursor is back where it started once the jiggler is stopped
+        let moves = jiggle(Jiggler::new(Duration::from_millis(1), 1).zero_net_movement(true));
+        assert_eq!(moves.len() % 2, 0);
+        assert_eq!(moves.iter().sum::<i32>(), 0);
+        assert_eq!(&moves[..4], [1, -1, -1, 1]);
+    }
+
+    #[test]
+    fn invalid_options() {
+        let moves = Arc::new(Mutex::new(vec![]));
+        for jiggler in [
+            Jiggler::new(Duration::ZERO, 1),
+            Jiggler::new(Duration::from_secs(1), 0),
+        ] {
+            let manager = TestMouseManager {
+                moves: moves.clone(),
+            };
+            assert!(jiggler.start_with(Box::new(manager)).is_err());
+        }
+    }
+}
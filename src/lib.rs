@@ -25,8 +25,30 @@ pub mod windows;
 #[cfg(target_os = "windows")]
 pub use crate::windows::WindowsMouseManager as Mouse;
 
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
+#[cfg(target_arch = "wasm32")]
+pub use crate::wasm::WasmMouseManager as Mouse;
+
+pub mod autoclick;
+pub mod builder;
+pub mod clicks;
 pub mod common;
+pub mod display;
 pub mod error;
+pub mod hotplug;
+pub mod jiggler;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod pacing;
+pub mod path;
+pub mod recorder;
+pub mod remap;
+#[cfg(feature = "remote")]
+pub mod remote;
+pub mod script;
+pub mod settings;
+pub mod system;
 
 pub use common::MouseActions;
 
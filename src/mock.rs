@@ -0,0 +1,572 @@
+///
+/// This module contains a mock mouse manager that does not
+/// touch the OS at all, it is intended for testing applications
+/// that use mouce on headless environments (e.g. CI pipelines)
+///
+/// Every call is recorded into a log that can be inspected later,
+/// and synthetic events can be pushed to the hooked callbacks
+///
+use crate::common::{
+    simulate_event, ButtonState, CallbackId, CallbackMap, Callbacks, EventCallback, EventInfo,
+    MouseActions, MouseButton, MouseEvent, Region, ScrollDirection, ScrollUnit,
+};
+use crate::error::Error;
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+
+/// A recorded call to the MockMouseManager
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MockCall {
+    MoveTo(usize, usize),
+    MoveRelative(i32, i32),
+    GetPosition,
+    IsButtonPressed(MouseButton),
+    GetPressedButtons,
+    PressButton(MouseButton),
+    ReleaseButton(MouseButton),
+    ClickButton(MouseButton),
+    ScrollWheel(ScrollDirection, ScrollUnit, u32),
+    HideCursor,
+    ShowCursor,
+    ClipCursor(Option<Region>),
+    RelativeCapture(bool),
+    Hook(CallbackId),
+    Unhook(CallbackId),
+    UnhookAll,
+    StopListening,
+}
+
+pub struct MockMouseManager {
+    calls: Mutex<Vec<MockCall>>,
+    position: Mutex<(i32, i32)>,
+    pressed_buttons: Mutex<ButtonState>,
+    callbacks: Callbacks,
+}
+
+impl MockMouseManager {
+    pub fn new() -> Self {
+        MockMouseManager {
+            calls: Mutex::new(vec![]),
+            position: Mutex::new((0, 0)),
+            pressed_buttons: Mutex::new(ButtonState::new()),
+            callbacks: Arc::new(Mutex::new(CallbackMap::new())),
+        }
+    }
+
+    /// Get all the calls that were made so far, in order
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use mouce::common::MouseButton;
+    /// use mouce::mock::{MockCall, MockMouseManager};
+    /// use mouce::MouseActions;
+    ///
+    /// let manager = MockMouseManager::new();
+    /// manager.move_to(10, 20).unwrap();
+    /// manager.click_button(&MouseButton::Left).unwrap();
+    /// assert_eq!(
+    ///     manager.calls(),
+    ///     vec![MockCall::MoveTo(10, 20), MockCall::ClickButton(MouseButton::Left)]
+    /// );
+    /// ```
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// Clear the recorded calls
+    pub fn clear_calls(&self) {
+        self.calls.lock().unwrap().clear();
+    }
+
+    /// Set the position that is reported by `get_position`
+    pub fn set_position(&self, x: i32, y: i32) {
+        *self.position.lock().unwrap() = (x, y);
+    }
+
+    /// Push the given event to all the hooked callbacks, the event
+    /// is reported at the current position of the mock
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use mouce::common::{MouseButton, MouseEvent};
+    /// use mouce::mock::MockMouseManager;
+    /// use mouce::MouseActions;
+    /// use std::sync::mpsc;
+    ///
+    /// let mut manager = MockMouseManager::new();
+    /// let (tx, rx) = mpsc::channel();
+    /// manager.hook(Box::new(move |e| tx.send(*e).unwrap())).unwrap();
+    ///
+    /// manager.emit(&MouseEvent::Press(MouseButton::Left));
+    /// assert_eq!(rx.recv(), Ok(MouseEvent::Press(MouseButton::Left)));
+    /// ```
+    pub fn emit(&self, event: &MouseEvent) {
+        let info = EventInfo {
+            position: Some(*self.position.lock().unwrap()),
+            ..EventInfo::default()
+        };
+        self.emit_with_info(event, &info);
+    }
+
+    /// Invoke the hooked callbacks with the given event and its information
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use mouce::common::{Device, DeviceId, EventInfo, MouseEvent};
+    /// use mouce::mock::MockMouseManager;
+    /// use mouce::MouseActions;
+    /// use std::sync::{mpsc, Arc};
+    ///
+    /// let mut manager = MockMouseManager::new();
+    /// let (tx, rx) = mpsc::channel();
+    /// manager
+    ///     .hook_with_info(Box::new(move |_, info| tx.send(info.clone()).unwrap()))
+    ///     .unwrap();
+    ///
+    /// let info = EventInfo {
+    ///     device: Some(Arc::new(Device {
+    ///         id: DeviceId(3),
+    ///         name: Some("Test Mouse".to_string()),
+    ///     })),
+    ///     position: Some((10, 20)),
+    ///     injected: Some(false),
+    ///     ..EventInfo::default()
+    /// };
+    /// manager.emit_with_info(&MouseEvent::Move {
+    ///     position: Some((10, 20)),
+    ///     delta: (1, 0),
+    /// }, &info);
+    /// assert_eq!(rx.recv(), Ok(info));
+    /// ```
+    pub fn emit_with_info(&self, event: &MouseEvent, info: &EventInfo) {
+        self.callbacks.lock().unwrap().invoke(event, info);
+    }
+
+    fn record(&self, call: MockCall) {
+        self.calls.lock().unwrap().push(call);
+    }
+}
+
+impl Default for MockMouseManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MouseActions for MockMouseManager {
+    fn move_to(&self, x: usize, y: usize) -> Result<(), Error> {
+        self.record(MockCall::MoveTo(x, y));
+        *self.position.lock().unwrap() = (x as i32, y as i32);
+        Ok(())
+    }
+
+    fn move_relative(&self, x_offset: i32, y_offset: i32) -> Result<(), Error> {
+        self.record(MockCall::MoveRelative(x_offset, y_offset));
+        let mut position = self.position.lock().unwrap();
+        *position = (position.0 + x_offset, position.1 + y_offset);
+        Ok(())
+    }
+
+    fn get_position(&self) -> Result<(i32, i32), Error> {
+        self.record(MockCall::GetPosition);
+        Ok(*self.position.lock().unwrap())
+    }
+
+    fn is_button_pressed(&self, button: &MouseButton) -> Result<bool, Error> {
+        self.record(MockCall::IsButtonPressed(*button));
+        Ok(self.pressed_buttons.lock().unwrap().is_pressed(button))
+    }
+
+    fn get_pressed_buttons(&self) -> Result<ButtonState, Error> {
+        self.record(MockCall::GetPressedButtons);
+        Ok(*self.pressed_buttons.lock().unwrap())
+    }
+
+    fn press_button(&self, button: &MouseButton) -> Result<(), Error> {
+        self.record(MockCall::PressButton(*button));
+        self.pressed_buttons.lock().unwrap().insert(button);
+        Ok(())
+    }
+
+    fn release_button(&self, button: &MouseButton) -> Result<(), Error> {
+        self.record(MockCall::ReleaseButton(*button));
+        self.pressed_buttons.lock().unwrap().remove(button);
+        Ok(())
+    }
+
+    fn click_button(&self, button: &MouseButton) -> Result<(), Error> {
+        self.record(MockCall::ClickButton(*button));
+        Ok(())
+    }
+
+    fn scroll_wheel(
+        &self,
+        direction: &ScrollDirection,
+        unit: ScrollUnit,
+        distance: u32,
+    ) -> Result<(), Error> {
+        self.record(MockCall::ScrollWheel(*direction, unit, distance));
+        Ok(())
+    }
+
+    fn hide_cursor(&self) -> Result<(), Error> {
+        self.record(MockCall::HideCursor);
+        Ok(())
+    }
+
+    fn show_cursor(&self) -> Result<(), Error> {
+        self.record(MockCall::ShowCursor);
+        Ok(())
+    }
+
+    fn clip_cursor(&self, region: Option<Region>) -> Result<(), Error> {
+        self.record(MockCall::ClipCursor(region));
+        Ok(())
+    }
+
+    fn set_relative_capture(&self, enabled: bool) -> Result<(), Error> {
+        self.record(MockCall::RelativeCapture(enabled));
+        Ok(())
+    }
+
+    fn hook_with_info(&mut self, callback: EventCallback) -> Result<CallbackId, Error> {
+        let id = CallbackId::next();
+        self.callbacks.lock().unwrap().insert(id, callback);
+        self.record(MockCall::Hook(id));
+        Ok(id)
+    }
+
+    fn simulate_event(&self, event: &MouseEvent) -> Result<(), Error> {
+        simulate_event(&self.callbacks, event);
+        Ok(())
+    }
+
+    fn callback_panics(&mut self) -> Result<Receiver<Error>, Error> {
+        Ok(self.callbacks.lock().unwrap().panic_receiver())
+    }
+
+    fn unhook(&mut self, callback_id: CallbackId) -> Result<(), Error> {
+        self.record(MockCall::Unhook(callback_id));
+        match self.callbacks.lock().unwrap().remove(&callback_id) {
+            Some(_) => Ok(()),
+            None => Err(Error::UnhookFailed),
+        }
+    }
+
+    fn unhook_all(&mut self) -> Result<(), Error> {
+        self.record(MockCall::UnhookAll);
+        self.callbacks.lock().unwrap().clear();
+        Ok(())
+    }
+
+    fn pause_listening(&mut self) -> Result<(), Error> {
+        self.callbacks.lock().unwrap().paused = true;
+        Ok(())
+    }
+
+    fn resume_listening(&mut self) -> Result<(), Error> {
+        self.callbacks.lock().unwrap().paused = false;
+        Ok(())
+    }
+
+    fn stop_listening(&mut self) -> Result<(), Error> {
+        self.record(MockCall::StopListening);
+        self.callbacks.lock().unwrap().clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Action, MouseButton, MouseEvent, ScrollDirection, ScrollUnit};
+    use crate::error::Error;
+    use crate::mock::{MockCall, MockMouseManager};
+    use crate::path::Path;
+    use crate::MouseActions;
+    use std::sync::{mpsc, Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn records_calls() {
+        let manager = MockMouseManager::new();
+        manager.move_to(100, 200).unwrap();
+        manager.move_relative(-50, 25).unwrap();
+        manager.press_button(&MouseButton::Right).unwrap();
+        manager.release_button(&MouseButton::Right).unwrap();
+        manager
+            .scroll_wheel(&ScrollDirection::Down, ScrollUnit::Line, 2)
+            .unwrap();
+
+        assert_eq!(
+            manager.calls(),
+            vec![
+                MockCall::MoveTo(100, 200),
+                MockCall::MoveRelative(-50, 25),
+                MockCall::PressButton(MouseButton::Right),
+                MockCall::ReleaseButton(MouseButton::Right),
+                MockCall::ScrollWheel(ScrollDirection::Down, ScrollUnit::Line, 2),
+            ]
+        );
+
+        manager.clear_calls();
+        assert!(manager.calls().is_empty());
+    }
+
+    #[test]
+    fn tracks_position() {
+        let manager = MockMouseManager::new();
+        assert_eq!(manager.get_position(), Ok((0, 0)));
+        manager.move_to(100, 200).unwrap();
+        manager.move_relative(-50, 25).unwrap();
+        assert_eq!(manager.get_position(), Ok((50, 225)));
+        manager.set_position(7, 8);
+        assert_eq!(manager.get_position(), Ok((7, 8)));
+    }
+
+    #[test]
+    fn drag_interpolates_moves() {
+        let manager = MockMouseManager::new();
+        let duration = Duration::from_millis(64);
+        manager
+            .drag((0, 0), (100, -20), &MouseButton::Left, duration)
+            .unwrap();
+
+        assert_eq!(
+            manager.calls(),
+            vec![
+                MockCall::MoveTo(0, 0),
+                MockCall::PressButton(MouseButton::Left),
+                MockCall::MoveTo(25, 0),
+                MockCall::MoveTo(50, 0),
+                MockCall::MoveTo(75, 0),
+                MockCall::MoveTo(100, 0),
+                MockCall::ReleaseButton(MouseButton::Left),
+            ]
+        );
+        assert_eq!(manager.get_position(), Ok((100, 0)));
+    }
+
+    #[test]
+    fn scroll_by_splits_axes() {
+        let manager = MockMouseManager::new();
+        manager.scroll_by(-2.4, 3., ScrollUnit::Line).unwrap();
+        manager.scroll_by(0.2, 0., ScrollUnit::Pixel).unwrap();
+
+        assert_eq!(
+            manager.calls(),
+            vec![
+                MockCall::ScrollWheel(ScrollDirection::Down, ScrollUnit::Line, 3),
+                MockCall::ScrollWheel(ScrollDirection::Left, ScrollUnit::Line, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn batch_runs_in_order() {
+        let manager = MockMouseManager::new();
+        let actions = [
+            Action::MoveTo(10, 20),
+            Action::Click(MouseButton::Left),
+            Action::MoveRelative(5, -5),
+            Action::Scroll(ScrollDirection::Up, ScrollUnit::Pixel, 30),
+        ];
+        manager.send_batch(&actions).unwrap();
+
+        assert_eq!(
+            manager.calls(),
+            vec![
+                MockCall::MoveTo(10, 20),
+                MockCall::ClickButton(MouseButton::Left),
+                MockCall::MoveRelative(5, -5),
+                MockCall::ScrollWheel(ScrollDirection::Up, ScrollUnit::Pixel, 30),
+            ]
+        );
+        assert_eq!(manager.get_position(), Ok((15, 15)));
+    }
+
+    #[test]
+    fn tracks_pressed_buttons() {
+        let manager = MockMouseManager::new();
+        manager.press_button(&MouseButton::Left).unwrap();
+        manager.press_button(&MouseButton::Middle).unwrap();
+        manager.release_button(&MouseButton::Left).unwrap();
+        assert_eq!(manager.is_button_pressed(&MouseButton::Left), Ok(false));
+        assert_eq!(manager.is_button_pressed(&MouseButton::Middle), Ok(true));
+        assert_eq!(manager.is_button_pressed(&MouseButton::Right), Ok(false));
+        assert_eq!(
+            manager.get_pressed_buttons().map(|state| state.bits()),
+            Ok(0b10)
+        );
+    }
+
+    #[test]
+    fn multi_click() {
+        let manager = MockMouseManager::new();
+        manager.double_click(&MouseButton::Left).unwrap();
+        manager.click_n(&MouseButton::Right, 3).unwrap();
+        manager.click_n(&MouseButton::Right, 0).unwrap();
+
+        assert_eq!(
+            manager.calls(),
+            vec![
+                MockCall::ClickButton(MouseButton::Left),
+                MockCall::ClickButton(MouseButton::Left),
+                MockCall::ClickButton(MouseButton::Right),
+                MockCall::ClickButton(MouseButton::Right),
+                MockCall::ClickButton(MouseButton::Right),
+            ]
+        );
+    }
+
+    #[test]
+    fn hold_button() {
+        let manager = MockMouseManager::new();
+        let start = Instant::now();
+        manager
+            .hold_button(&MouseButton::Right, Duration::from_millis(20))
+            .unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        assert_eq!(
+            manager.calls(),
+            vec![
+                MockCall::PressButton(MouseButton::Right),
+                MockCall::ReleaseButton(MouseButton::Right),
+            ]
+        );
+        assert_eq!(manager.is_button_pressed(&MouseButton::Right), Ok(false));
+    }
+
+    #[test]
+    fn trace_path() {
+        let manager = MockMouseManager::new();
+        let path = Path::new((0, 0)).line_to((100, 0));
+        manager.trace_path(&path, 10000.).unwrap();
+        assert_eq!(
+            manager.calls(),
+            vec![
+                MockCall::MoveTo(0, 0),
+                MockCall::MoveTo(50, 0),
+                MockCall::MoveTo(100, 0),
+            ]
+        );
+        assert!(manager.trace_path(&path, 0.).is_err());
+    }
+
+    #[test]
+    fn emit_to_hooks() {
+        let mut manager = MockMouseManager::new();
+        let received = Arc::new(Mutex::new(vec![]));
+
+        let received_clone = received.clone();
+        let id = manager
+            .hook(Box::new(move |e| received_clone.lock().unwrap().push(*e)))
+            .unwrap();
+
+        let scroll = MouseEvent::Scroll {
+            direction: ScrollDirection::Up,
+            unit: ScrollUnit::Pixel,
+            distance: 2.5,
+        };
+        manager.emit(&MouseEvent::Press(MouseButton::Left));
+        manager.emit(&scroll);
+        assert_eq!(
+            *received.lock().unwrap(),
+            vec![MouseEvent::Press(MouseButton::Left), scroll]
+        );
+
+        assert_eq!(manager.unhook(id), Ok(()));
+        assert_eq!(manager.unhook(id), Err(Error::UnhookFailed));
+        manager.emit(&MouseEvent::Release(MouseButton::Left));
+        assert_eq!(received.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn hook_mut_keeps_state() {
+        let mut manager = MockMouseManager::new();
+        let (tx, rx) = mpsc::channel();
+        let mut count = 0;
+        manager
+            .hook_mut(Box::new(move |_, _| {
+                count += 1;
+                tx.send(count).unwrap();
+            }))
+            .unwrap();
+
+        manager.emit(&MouseEvent::Press(MouseButton::Left));
+        manager.emit(&MouseEvent::Release(MouseButton::Left));
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![1, 2]);
+    }
+
+    #[test]
+    fn events_iterator() {
+        let mut manager = MockMouseManager::new();
+        let mut events = manager.events().unwrap().timeout(Duration::from_millis(10));
+        manager.emit(&MouseEvent::Press(MouseButton::Left));
+        manager.emit(&MouseEvent::Release(MouseButton::Left));
+        assert_eq!(events.next(), Some(MouseEvent::Press(MouseButton::Left)));
+        assert_eq!(events.next(), Some(MouseEvent::Release(MouseButton::Left)));
+        // Timed out
+        assert_eq!(events.next(), None);
+
+        // The iterator ends once the listening is stopped
+        let events = manager.events().unwrap();
+        manager.emit(&MouseEvent::Press(MouseButton::Right));
+        manager.stop_listening().unwrap();
+        assert_eq!(
+            events.collect::<Vec<_>>(),
+            vec![MouseEvent::Press(MouseButton::Right)]
+        );
+    }
+
+    #[test]
+    fn simulated_events() {
+        let mut manager = MockMouseManager::new();
+        let (tx, rx) = mpsc::channel();
+        manager
+            .hook_with_info(Box::new(move |event, info| {
+                tx.send((*event, info.injected)).unwrap();
+            }))
+            .unwrap();
+
+        let event = MouseEvent::Release(MouseButton::Back);
+        assert_eq!(manager.simulate_event(&event), Ok(()));
+        assert_eq!(rx.try_recv(), Ok((event, Some(true))));
+        // The simulated events do not reach the system
+        assert!(manager
+            .calls()
+            .iter()
+            .all(|call| matches!(call, MockCall::Hook(_))));
+
+        manager.pause_listening().unwrap();
+        manager.simulate_event(&event).unwrap();
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn event_stream() {
+        use futures_core::Stream;
+        use std::pin::Pin;
+        use std::task::{Context, Poll, Waker};
+
+        let mut manager = MockMouseManager::new();
+        let mut stream = manager.event_stream().unwrap();
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut poll_next = || Pin::new(&mut stream).poll_next(&mut cx);
+        assert_eq!(poll_next(), Poll::Pending);
+
+        manager.emit(&MouseEvent::Press(MouseButton::Left));
+        assert_eq!(
+            poll_next(),
+            Poll::Ready(Some(MouseEvent::Press(MouseButton::Left)))
+        );
+        assert_eq!(poll_next(), Poll::Pending);
+
+        // The stream ends once the listening is stopped
+        manager.stop_listening().unwrap();
+        assert_eq!(poll_next(), Poll::Ready(None));
+    }
+}
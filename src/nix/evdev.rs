@@ -0,0 +1,229 @@
+///
+/// This module contains the mouse action functions
+/// for the linux systems that write directly to an existing
+/// evdev device node (e.g. `/dev/input/event3`)
+///
+/// This is useful on setups where creating a new uinput device
+/// is not possible, but an existing device node is writable
+///
+/// - Unsupported mouse actions
+///     - get_position is not available on evdev
+///
+use crate::builder::MouseBuilder;
+use crate::common::{
+    simulate_event, ButtonState, CallbackId, CallbackMap, DeviceId, EventCallback, MouseActions,
+    MouseButton, MouseEvent, ScrollDirection, ScrollUnit,
+};
+use crate::error::Error;
+use crate::nix::listener::{Listener, ListenerQueue};
+use crate::nix::uinput::{EV_KEY, EV_REL, REL_HWHEEL, REL_WHEEL, REL_X, REL_Y};
+use crate::nix::{Callbacks, GrabbedDevices, UnreadableDevices};
+use std::fs::File;
+use std::io::ErrorKind;
+use std::os::raw::c_int;
+use std::path::Path;
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+
+pub struct EvdevMouseManager {
+    device_file: File,
+    callbacks: Callbacks,
+    listener: Option<Listener>,
+    listener_queue: ListenerQueue,
+    grabbed_devices: GrabbedDevices,
+    unreadable_devices: UnreadableDevices,
+}
+
+impl EvdevMouseManager {
+    /// Open the given evdev device node to inject mouse events into it
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::nix::EvdevMouseManager;
+    /// use mouce::MouseActions;
+    ///
+    /// let manager = EvdevMouseManager::open("/dev/input/event3").unwrap();
+    /// assert_eq!(manager.move_relative(100, 100), Ok(()));
+    /// ```
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let device_file = match File::options().write(true).open(path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::PermissionDenied => {
+                return Err(Error::PermissionDenied)
+            }
+            Err(err) => return Err(Error::from_io("failed to open the evdev device node", err)),
+        };
+
+        Ok(EvdevMouseManager {
+            device_file,
+            callbacks: Arc::new(Mutex::new(CallbackMap::new())),
+            listener: None,
+            listener_queue: ListenerQueue::new(&MouseBuilder::new()),
+            grabbed_devices: GrabbedDevices::default(),
+            unreadable_devices: UnreadableDevices::default(),
+        })
+    }
+
+    /// Write the given event to the device node
+    fn emit(&self, r#type: c_int, code: c_int, value: c_int) -> Result<(), Error> {
+        super::emit(&self.device_file, r#type, code, value)
+    }
+
+    /// Syncronize the device
+    fn syncronize(&self) -> Result<(), Error> {
+        super::syncronize(&self.device_file)
+    }
+}
+
+impl MouseActions for EvdevMouseManager {
+    fn move_to(&self, x: usize, y: usize) -> Result<(), Error> {
+        // An existing relative device can not be positioned absolutely, so
+        // first move the mouse to the top left, then move it to the position
+        self.move_relative(i32::MIN / 2, i32::MIN / 2)?;
+        self.move_relative(x as i32, y as i32)
+    }
+
+    /// Move the mouse relative to the current position
+    ///
+    /// The offsets are written to the device as they are, so the actual
+    /// distance in pixels depends on the pointer acceleration of the device
+    fn move_relative(&self, x_offset: i32, y_offset: i32) -> Result<(), Error> {
+        self.emit(EV_REL, REL_X as c_int, x_offset)?;
+        self.emit(EV_REL, REL_Y as c_int, y_offset)?;
+        self.syncronize()
+    }
+
+    fn get_position(&self) -> Result<(i32, i32), Error> {
+        // evdev does not let us get the current position of the mouse
+        Err(Error::NotImplemented)
+    }
+
+    fn get_pressed_buttons(&self) -> Result<ButtonState, Error> {
+        super::get_evdev_pressed_buttons()
+    }
+
+    fn press_button(&self, button: &MouseButton) -> Result<(), Error> {
+        self.emit(EV_KEY, super::code_from_button(button)?, 1)?;
+        self.syncronize()
+    }
+
+    fn release_button(&self, button: &MouseButton) -> Result<(), Error> {
+        self.emit(EV_KEY, super::code_from_button(button)?, 0)?;
+        self.syncronize()
+    }
+
+    fn scroll_wheel(
+        &self,
+        direction: &ScrollDirection,
+        unit: ScrollUnit,
+        distance: u32,
+    ) -> Result<(), Error> {
+        if unit == ScrollUnit::Pixel {
+            // The wheel axes of evdev only report the wheel steps
+            return Err(Error::NotImplemented);
+        }
+
+        let distance = distance.min(i32::MAX as u32) as i32;
+        let (scroll_dir, scroll_value) = match direction {
+            ScrollDirection::Up => (REL_WHEEL, distance),
+            ScrollDirection::Down => (REL_WHEEL, -distance),
+            ScrollDirection::Left => (REL_HWHEEL, -distance),
+            ScrollDirection::Right => (REL_HWHEEL, distance),
+        };
+        self.emit(EV_REL, scroll_dir as c_int, scroll_value)?;
+        self.syncronize()
+    }
+
+    fn scroll_by(&self, dx: f64, dy: f64, unit: ScrollUnit) -> Result<(), Error> {
+        if unit == ScrollUnit::Pixel {
+            return Err(Error::NotImplemented);
+        }
+
+        // Report both of the axes in the same frame, a positive wheel value scrolls up
+        let (dx, dy) = (dx.round() as i32, -dy.round() as i32);
+        if dy != 0 {
+            self.emit(EV_REL, REL_WHEEL as c_int, dy)?;
+        }
+        if dx != 0 {
+            self.emit(EV_REL, REL_HWHEEL as c_int, dx)?;
+        }
+        self.syncronize()
+    }
+
+    fn hook_with_info(&mut self, callback: EventCallback) -> Result<CallbackId, Error> {
+        if self.listener.is_none() {
+            self.listener = Some(super::start_nix_listener(
+                &self.callbacks,
+                &self.listener_queue,
+                &self.unreadable_devices,
+            )?);
+        }
+
+        let id = CallbackId::next();
+        self.callbacks.lock().unwrap().insert(id, callback);
+        Ok(id)
+    }
+
+    fn simulate_event(&self, event: &MouseEvent) -> Result<(), Error> {
+        simulate_event(&self.callbacks, event);
+        Ok(())
+    }
+
+    fn callback_panics(&mut self) -> Result<Receiver<Error>, Error> {
+        Ok(self.callbacks.lock().unwrap().panic_receiver())
+    }
+
+    fn dropped_events(&self) -> Result<u64, Error> {
+        Ok(self.listener_queue.dropped())
+    }
+
+    fn unreadable_devices(&self) -> Result<Vec<(String, Error)>, Error> {
+        Ok(self.unreadable_devices.list())
+    }
+
+    fn grab_device(&mut self, device_id: DeviceId) -> Result<(), Error> {
+        self.grabbed_devices
+            .grab(device_id, &self.callbacks, &self.listener_queue)
+    }
+
+    fn release_device(&mut self, device_id: DeviceId) -> Result<(), Error> {
+        self.grabbed_devices.release(device_id)
+    }
+
+    fn unhook(&mut self, callback_id: CallbackId) -> Result<(), Error> {
+        let removed = self.callbacks.lock().unwrap().remove(&callback_id);
+        match removed {
+            Some(_) => {
+                super::stop_idle_listener(&mut self.listener, &self.callbacks);
+                Ok(())
+            }
+            None => Err(Error::UnhookFailed),
+        }
+    }
+
+    fn unhook_all(&mut self) -> Result<(), Error> {
+        self.callbacks.lock().unwrap().clear();
+        super::stop_idle_listener(&mut self.listener, &self.callbacks);
+        Ok(())
+    }
+
+    fn pause_listening(&mut self) -> Result<(), Error> {
+        self.callbacks.lock().unwrap().paused = true;
+        Ok(())
+    }
+
+    fn resume_listening(&mut self) -> Result<(), Error> {
+        self.callbacks.lock().unwrap().paused = false;
+        Ok(())
+    }
+
+    fn stop_listening(&mut self) -> Result<(), Error> {
+        self.callbacks.lock().unwrap().clear();
+        if let Some(listener) = self.listener.take() {
+            listener.stop();
+        }
+        self.grabbed_devices.release_all();
+        Ok(())
+    }
+}
@@ -0,0 +1,398 @@
+///
+/// This module contains the event listener for the unix-like systems
+/// that consumes the mouse events through libinput
+///
+/// libinput takes care of the device discovery (through udev), pointer
+/// acceleration and touchpads, so the reported movement matches what
+/// the user actually sees on the screen
+///
+use crate::common::{
+    Device, DeviceId, DragTracker, EventInfo, MouseEvent, Region, ScrollDirection, ScrollUnit,
+};
+use crate::error::Error;
+use crate::nix::listener::Listener;
+use crate::nix::{button_from_code, is_virtual_device, Callbacks};
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int, c_uint, c_void};
+use std::ptr::null_mut;
+use std::sync::Arc;
+
+/// Start the libinput based event listener
+pub(super) fn start_libinput_listener(callbacks: &Callbacks) -> Result<Listener, Error> {
+    let mut listener = Listener::new()?;
+    let context = unsafe {
+        let udev = udev_new();
+        if udev.is_null() {
+            return Err(Error::CustomError(
+                "libinput: failed to create the udev context",
+            ));
+        }
+
+        let context = libinput_udev_create_context(&INTERFACE, null_mut(), udev);
+        // libinput keeps its own reference to the udev context
+        udev_unref(udev);
+        if context.is_null() {
+            return Err(Error::CustomError("libinput: failed to create the context"));
+        }
+
+        let seat = CString::new("seat0").unwrap();
+        if libinput_udev_assign_seat(context, seat.as_ptr()) != 0 {
+            libinput_unref(context);
+            return Err(Error::PermissionDenied);
+        }
+
+        // Assigning the seat succeeds even if none of the devices could be opened,
+        // so fall back to the other listeners unless a pointer device was added
+        if !has_pointer_device(context) {
+            libinput_unref(context);
+            return Err(Error::PermissionDenied);
+        }
+
+        context as usize
+    };
+    // The absolute positions are scaled to the whole desktop
+    let screen = crate::display::virtual_screen_bounds().ok();
+
+    let callbacks = callbacks.clone();
+    listener.spawn(move |stop| {
+        let context = context as *mut LibInput;
+        let fd = unsafe { libinput_get_fd(context) };
+
+        // libinput reports sub-pixel deltas, keep the remainders so that
+        // slow movements are not lost while rounding to whole pixels
+        let mut remainder = (0., 0.);
+        // The last position reported by an absolute device, to find the deltas
+        let mut last_position = None;
+        // The devices that produced the events, keyed by their libinput handles
+        // The devices are kept along with whether they are virtual
+        let mut devices: HashMap<usize, (Arc<Device>, bool)> = HashMap::new();
+        let mut drags = DragTracker::default();
+
+        loop {
+            unsafe {
+                if !stop.wait(fd) {
+                    // Closes the devices through `close_restricted`
+                    libinput_unref(context);
+                    return;
+                }
+                libinput_dispatch(context);
+
+                loop {
+                    let event = libinput_get_event(context);
+                    if event.is_null() {
+                        break;
+                    }
+
+                    let device = libinput_event_get_device(event);
+                    if libinput_event_get_type(event) == LIBINPUT_EVENT_DEVICE_REMOVED {
+                        // The handle may be reused by a new device
+                        devices.remove(&(device as usize));
+                    }
+
+                    for mouse_event in
+                        convert_event(event, &mut remainder, screen, &mut last_position)
+                    {
+                        let mouse_event = drags.track(mouse_event);
+                        let (device, injected) = devices
+                            .entry(device as usize)
+                            .or_insert_with(|| {
+                                let device = read_device(device);
+                                let injected = is_virtual_device(device.id);
+                                (Arc::new(device), injected)
+                            })
+                            .clone();
+                        let info = EventInfo {
+                            device: Some(device),
+                            injected: Some(injected),
+                            ..EventInfo::default()
+                        };
+
+                        // Invoke all given callbacks with the constructed mouse event
+                        callbacks.lock().unwrap().invoke(&mouse_event, &info);
+                    }
+
+                    libinput_event_destroy(event);
+                }
+            }
+        }
+    })?;
+
+    Ok(listener)
+}
+
+/// Process the initial events of the context and check whether
+/// any of the added devices is a pointer device
+unsafe fn has_pointer_device(context: *mut LibInput) -> bool {
+    libinput_dispatch(context);
+    let mut found = false;
+    loop {
+        let event = libinput_get_event(context);
+        if event.is_null() {
+            return found;
+        }
+        if libinput_event_get_type(event) == LIBINPUT_EVENT_DEVICE_ADDED
+            && libinput_device_has_capability(
+                libinput_event_get_device(event),
+                LIBINPUT_DEVICE_CAP_POINTER,
+            ) != 0
+        {
+            found = true;
+        }
+        libinput_event_destroy(event);
+    }
+}
+
+/// Get the identity of the given libinput device, the id is the
+/// device number of its event node like the evdev listener uses
+unsafe fn read_device(device: *mut LibInputDevice) -> Device {
+    let udev_device = libinput_device_get_udev_device(device);
+    let id = if udev_device.is_null() {
+        0
+    } else {
+        let devnum = udev_device_get_devnum(udev_device);
+        udev_device_unref(udev_device);
+        devnum
+    };
+
+    let name = libinput_device_get_name(device);
+    let name = if name.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(name).to_string_lossy().into_owned())
+    };
+
+    Device {
+        id: DeviceId(id),
+        name,
+    }
+}
+
+/// Construct a scroll event from the value of the given axis
+unsafe fn scroll_event(
+    pointer_event: *mut LibInputEventPointer,
+    axis: c_uint,
+    negative: ScrollDirection,
+    positive: ScrollDirection,
+) -> Option<MouseEvent> {
+    if libinput_event_pointer_has_axis(pointer_event, axis) == 0 {
+        return None;
+    }
+
+    // The axis value is 0 when the user stops scrolling on a touchpad
+    let value = libinput_event_pointer_get_axis_value(pointer_event, axis);
+    let direction = if value < 0. {
+        negative
+    } else if value > 0. {
+        positive
+    } else {
+        return None;
+    };
+
+    // The wheels report the rotation in degrees, the touchpads and
+    // the other continuous sources report the distance in pixels
+    let (unit, distance) = match libinput_event_pointer_get_axis_source(pointer_event) {
+        LIBINPUT_POINTER_AXIS_SOURCE_WHEEL | LIBINPUT_POINTER_AXIS_SOURCE_WHEEL_TILT => {
+            (ScrollUnit::Line, value.abs() / WHEEL_DEGREES_PER_LINE)
+        }
+        _ => (ScrollUnit::Pixel, value.abs()),
+    };
+    Some(MouseEvent::Scroll {
+        direction,
+        unit,
+        distance,
+    })
+}
+
+/// Construct the library's MouseEvents from the given libinput event
+unsafe fn convert_event(
+    event: *mut LibInputEvent,
+    remainder: &mut (f64, f64),
+    screen: Option<Region>,
+    last_position: &mut Option<(i32, i32)>,
+) -> Vec<MouseEvent> {
+    let event_type = libinput_event_get_type(event);
+    if !matches!(
+        event_type,
+        LIBINPUT_EVENT_POINTER_MOTION
+            | LIBINPUT_EVENT_POINTER_MOTION_ABSOLUTE
+            | LIBINPUT_EVENT_POINTER_BUTTON
+            | LIBINPUT_EVENT_POINTER_AXIS
+    ) {
+        return vec![];
+    }
+
+    let pointer_event = libinput_event_get_pointer_event(event);
+    if pointer_event.is_null() {
+        return vec![];
+    }
+
+    match event_type {
+        LIBINPUT_EVENT_POINTER_MOTION => {
+            let dx = libinput_event_pointer_get_dx(pointer_event) + remainder.0;
+            let dy = libinput_event_pointer_get_dy(pointer_event) + remainder.1;
+            let (x, y) = (dx.trunc(), dy.trunc());
+            *remainder = (dx - x, dy - y);
+
+            if x == 0. && y == 0. {
+                return vec![];
+            }
+            vec![MouseEvent::Move {
+                position: None,
+                delta: (x as i32, y as i32),
+            }]
+        }
+        LIBINPUT_EVENT_POINTER_MOTION_ABSOLUTE => {
+            // Tablets and virtual machine pointers report positions, which
+            // can only be placed on the screen when its size is known
+            let screen = match screen {
+                Some(screen) => screen,
+                None => return vec![],
+            };
+            let x = libinput_event_pointer_get_absolute_x_transformed(pointer_event, screen.width);
+            let y = libinput_event_pointer_get_absolute_y_transformed(pointer_event, screen.height);
+            let position = (screen.x + x as i32, screen.y + y as i32);
+            let delta = match last_position.replace(position) {
+                Some(last) => (position.0 - last.0, position.1 - last.1),
+                None => (0, 0),
+            };
+            vec![MouseEvent::Move {
+                position: Some(position),
+                delta,
+            }]
+        }
+        LIBINPUT_EVENT_POINTER_BUTTON => {
+            let code = libinput_event_pointer_get_button(pointer_event) as i32;
+            let button = match button_from_code(code) {
+                Some(button) => button,
+                None => return vec![],
+            };
+            if libinput_event_pointer_get_button_state(pointer_event)
+                == LIBINPUT_BUTTON_STATE_PRESSED
+            {
+                vec![MouseEvent::Press(button)]
+            } else {
+                vec![MouseEvent::Release(button)]
+            }
+        }
+        _ => {
+            // libinput uses positive values for scrolling down and right,
+            // a diagonal scroll has both of the axes in the same event
+            let vertical = scroll_event(
+                pointer_event,
+                LIBINPUT_POINTER_AXIS_SCROLL_VERTICAL,
+                ScrollDirection::Up,
+                ScrollDirection::Down,
+            );
+            let horizontal = scroll_event(
+                pointer_event,
+                LIBINPUT_POINTER_AXIS_SCROLL_HORIZONTAL,
+                ScrollDirection::Left,
+                ScrollDirection::Right,
+            );
+            vertical.into_iter().chain(horizontal).collect()
+        }
+    }
+}
+
+unsafe extern "C" fn open_restricted(
+    path: *const c_char,
+    flags: c_int,
+    _data: *mut c_void,
+) -> c_int {
+    let fd = open(path, flags);
+    if fd < 0 {
+        -std::io::Error::last_os_error().raw_os_error().unwrap_or(1)
+    } else {
+        fd
+    }
+}
+
+unsafe extern "C" fn close_restricted(fd: c_int, _data: *mut c_void) {
+    close(fd);
+}
+
+static INTERFACE: LibInputInterface = LibInputInterface {
+    open_restricted: Some(open_restricted),
+    close_restricted: Some(close_restricted),
+};
+
+/// libinput definitions
+const LIBINPUT_EVENT_DEVICE_ADDED: c_uint = 1;
+const LIBINPUT_EVENT_DEVICE_REMOVED: c_uint = 2;
+const LIBINPUT_EVENT_POINTER_MOTION: c_uint = 400;
+const LIBINPUT_EVENT_POINTER_MOTION_ABSOLUTE: c_uint = 401;
+const LIBINPUT_EVENT_POINTER_BUTTON: c_uint = 402;
+const LIBINPUT_EVENT_POINTER_AXIS: c_uint = 403;
+const LIBINPUT_DEVICE_CAP_POINTER: c_uint = 1;
+const LIBINPUT_BUTTON_STATE_PRESSED: c_uint = 1;
+const LIBINPUT_POINTER_AXIS_SCROLL_VERTICAL: c_uint = 0;
+const LIBINPUT_POINTER_AXIS_SCROLL_HORIZONTAL: c_uint = 1;
+const LIBINPUT_POINTER_AXIS_SOURCE_WHEEL: c_uint = 1;
+const LIBINPUT_POINTER_AXIS_SOURCE_WHEEL_TILT: c_uint = 4;
+// A click of most of the wheels rotates them by 15 degrees
+const WHEEL_DEGREES_PER_LINE: f64 = 15.;
+
+enum Udev {}
+enum UdevDevice {}
+enum LibInput {}
+enum LibInputEvent {}
+enum LibInputDevice {}
+enum LibInputEventPointer {}
+
+#[repr(C)]
+struct LibInputInterface {
+    open_restricted:
+        Option<unsafe extern "C" fn(path: *const c_char, flags: c_int, data: *mut c_void) -> c_int>,
+    close_restricted: Option<unsafe extern "C" fn(fd: c_int, data: *mut c_void)>,
+}
+
+#[link(name = "udev")]
+extern "C" {
+    fn udev_new() -> *mut Udev;
+    fn udev_unref(udev: *mut Udev) -> *mut Udev;
+    fn udev_device_get_devnum(udev_device: *mut UdevDevice) -> u64;
+    fn udev_device_unref(udev_device: *mut UdevDevice) -> *mut UdevDevice;
+}
+
+#[link(name = "input")]
+extern "C" {
+    fn libinput_udev_create_context(
+        interface: *const LibInputInterface,
+        user_data: *mut c_void,
+        udev: *mut Udev,
+    ) -> *mut LibInput;
+    fn libinput_udev_assign_seat(libinput: *mut LibInput, seat_id: *const c_char) -> c_int;
+    fn libinput_unref(libinput: *mut LibInput) -> *mut LibInput;
+    fn libinput_get_fd(libinput: *mut LibInput) -> c_int;
+    fn libinput_dispatch(libinput: *mut LibInput) -> c_int;
+    fn libinput_get_event(libinput: *mut LibInput) -> *mut LibInputEvent;
+    fn libinput_event_destroy(event: *mut LibInputEvent);
+    fn libinput_event_get_type(event: *mut LibInputEvent) -> c_uint;
+    fn libinput_event_get_device(event: *mut LibInputEvent) -> *mut LibInputDevice;
+    fn libinput_device_get_name(device: *mut LibInputDevice) -> *const c_char;
+    fn libinput_device_has_capability(device: *mut LibInputDevice, capability: c_uint) -> c_int;
+    fn libinput_device_get_udev_device(device: *mut LibInputDevice) -> *mut UdevDevice;
+    fn libinput_event_get_pointer_event(event: *mut LibInputEvent) -> *mut LibInputEventPointer;
+    fn libinput_event_pointer_get_dx(event: *mut LibInputEventPointer) -> f64;
+    fn libinput_event_pointer_get_dy(event: *mut LibInputEventPointer) -> f64;
+    fn libinput_event_pointer_get_absolute_x_transformed(
+        event: *mut LibInputEventPointer,
+        width: u32,
+    ) -> f64;
+    fn libinput_event_pointer_get_absolute_y_transformed(
+        event: *mut LibInputEventPointer,
+        height: u32,
+    ) -> f64;
+    fn libinput_event_pointer_get_button(event: *mut LibInputEventPointer) -> u32;
+    fn libinput_event_pointer_get_button_state(event: *mut LibInputEventPointer) -> c_uint;
+    fn libinput_event_pointer_has_axis(event: *mut LibInputEventPointer, axis: c_uint) -> c_int;
+    fn libinput_event_pointer_get_axis_value(event: *mut LibInputEventPointer, axis: c_uint)
+        -> f64;
+    fn libinput_event_pointer_get_axis_source(event: *mut LibInputEventPointer) -> c_uint;
+}
+
+extern "C" {
+    fn open(path: *const c_char, flags: c_int, ...) -> c_int;
+    fn close(fd: c_int) -> c_int;
+}
@@ -0,0 +1,335 @@
+///
+/// This module contains the threads of the event listeners of the unix-like systems
+///
+/// The listener threads wait for their files along with a socket that is
+/// hung up when the listener is stopped, so the blocking reads can be
+/// interrupted and the threads can be joined
+///
+use crate::builder::MouseBuilder;
+use crate::common::Backpressure;
+use crate::error::Error;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, Read};
+use std::os::raw::{c_int, c_short, c_ulong};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+
+/// The threads of a started listener
+pub(crate) struct Listener {
+    /// The other end of the socket is readable once this end is closed
+    hang_up: UnixStream,
+    stop: UnixStream,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl Listener {
+    pub(crate) fn new() -> Result<Self, Error> {
+        let (hang_up, stop) = UnixStream::pair()
+            .map_err(|err| Error::from_io("failed to create the listener socket", err))?;
+        Ok(Listener {
+            hang_up,
+            stop,
+            threads: vec![],
+        })
+    }
+
+    /// Spawn a thread of the listener, the thread is given the signal
+    /// that tells when the listener is stopped
+    pub(crate) fn spawn<F>(&mut self, f: F) -> Result<(), Error>
+    where
+        F: FnOnce(Stop) + Send + 'static,
+    {
+        let stop = self
+            .stop
+            .try_clone()
+            .map_err(|err| Error::from_io("failed to clone the listener socket", err))?;
+        self.threads.push(thread::spawn(move || f(Stop(stop))));
+        Ok(())
+    }
+
+    /// Check whether all the threads of the listener have finished by themselves
+    pub(crate) fn is_finished(&self) -> bool {
+        self.threads.iter().all(|thread| thread.is_finished())
+    }
+
+    /// Stop the threads of the listener and wait for them to finish
+    ///
+    /// Dropping the listener stops the threads as well, but does not wait for them
+    pub(crate) fn stop(self) {
+        let Listener {
+            hang_up, threads, ..
+        } = self;
+        drop(hang_up);
+        for thread in threads {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// The signal of a listener thread that tells when the listener is stopped
+pub(crate) struct Stop(UnixStream);
+
+impl Stop {
+    /// Wait until the given file descriptor is readable,
+    /// returns `false` if the listener is stopped first
+    pub(crate) fn wait(&self, fd: RawFd) -> bool {
+        let mut poll_fds = [
+            PollFd {
+                fd,
+                events: POLLIN,
+                revents: 0,
+            },
+            PollFd {
+                fd: self.0.as_raw_fd(),
+                events: POLLIN,
+                revents: 0,
+            },
+        ];
+        loop {
+            if unsafe { poll(poll_fds.as_mut_ptr(), poll_fds.len() as c_ulong, -1) } >= 0 {
+                return poll_fds[1].revents == 0;
+            }
+            if io::Error::last_os_error().kind() != io::ErrorKind::Interrupted {
+                return false;
+            }
+        }
+    }
+
+    /// Wrap the given file so that its reads end once the listener is stopped
+    pub(crate) fn reader(self, file: File) -> StoppableReader {
+        StoppableReader { file, stop: self }
+    }
+}
+
+/// A file whose reads report the end of the file once the listener is stopped
+pub(crate) struct StoppableReader {
+    file: File,
+    stop: Stop,
+}
+
+impl Read for StoppableReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.stop.wait(self.file.as_raw_fd()) {
+            return Ok(0);
+        }
+        self.file.read(buf)
+    }
+}
+
+/// The options of the queue between the reader threads and the thread
+/// that invokes the callbacks, see `MouseBuilder::listener_queue`
+#[derive(Debug, Clone)]
+pub(crate) struct ListenerQueue {
+    capacity: usize,
+    backpressure: Backpressure,
+    /// The number of the dropped events, it is shared by the queues of the manager
+    dropped: Arc<AtomicU64>,
+}
+
+impl ListenerQueue {
+    pub(crate) fn new(builder: &MouseBuilder) -> Self {
+        ListenerQueue {
+            capacity: builder.listener_capacity,
+            backpressure: builder.backpressure,
+            dropped: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Get the number of the events that the queues with these options dropped so far
+    pub(crate) fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Get the same options, except that the senders wait instead of dropping the events
+    pub(crate) fn blocking(&self) -> Self {
+        ListenerQueue {
+            backpressure: Backpressure::Block,
+            ..self.clone()
+        }
+    }
+
+    /// Create a queue with these options
+    pub(crate) fn channel<T>(&self) -> (QueueSender<T>, QueueReceiver<T>) {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(QueueState {
+                items: VecDeque::new(),
+                senders: 1,
+                closed: false,
+            }),
+            changed: Condvar::new(),
+            options: self.clone(),
+        });
+        (
+            QueueSender {
+                shared: shared.clone(),
+            },
+            QueueReceiver { shared },
+        )
+    }
+}
+
+struct Shared<T> {
+    state: Mutex<QueueState<T>>,
+    /// Notified whenever an item is queued or taken and whenever an end is dropped
+    changed: Condvar,
+    options: ListenerQueue,
+}
+
+struct QueueState<T> {
+    items: VecDeque<T>,
+    senders: usize,
+    /// Whether the receiver is dropped
+    closed: bool,
+}
+
+/// The sending end of a listener queue, the receiver ends once all of them are dropped
+pub(crate) struct QueueSender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> QueueSender<T> {
+    /// Queue the given item according to the backpressure, returns `false` once the receiver is dropped
+    pub(crate) fn send(&self, item: T) -> bool {
+        let options = &self.shared.options;
+        let mut state = self.shared.state.lock().unwrap();
+        while !state.closed && state.items.len() >= options.capacity {
+            match options.backpressure {
+                Backpressure::Block => state = self.shared.changed.wait(state).unwrap(),
+                Backpressure::DropOldest => {
+                    state.items.pop_front();
+                    options.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+                Backpressure::DropNewest => {
+                    options.dropped.fetch_add(1, Ordering::Relaxed);
+                    return true;
+                }
+            }
+        }
+        if state.closed {
+            return false;
+        }
+        state.items.push_back(item);
+        self.shared.changed.notify_all();
+        true
+    }
+}
+
+impl<T> Clone for QueueSender<T> {
+    fn clone(&self) -> Self {
+        self.shared.state.lock().unwrap().senders += 1;
+        QueueSender {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for QueueSender<T> {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().senders -= 1;
+        self.shared.changed.notify_all();
+    }
+}
+
+/// The receiving end of a listener queue, it iterates over the items until all the senders are dropped
+pub(crate) struct QueueReceiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Iterator for QueueReceiver<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let mut state = self.shared.state.lock().unwrap();
+        loop {
+            if let Some(item) = state.items.pop_front() {
+                self.shared.changed.notify_all();
+                return Some(item);
+            }
+            if state.senders == 0 {
+                return None;
+            }
+            state = self.shared.changed.wait(state).unwrap();
+        }
+    }
+}
+
+impl<T> Drop for QueueReceiver<T> {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().closed = true;
+        self.shared.changed.notify_all();
+    }
+}
+
+const POLLIN: c_short = 0x001;
+
+#[repr(C)]
+struct PollFd {
+    fd: c_int,
+    events: c_short,
+    revents: c_short,
+}
+
+extern "C" {
+    fn poll(fds: *mut PollFd, nfds: c_ulong, timeout: c_int) -> c_int;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Listener, ListenerQueue};
+    use crate::builder::MouseBuilder;
+    use crate::common::Backpressure;
+    use std::os::unix::io::AsRawFd;
+    use std::os::unix::net::UnixStream;
+    use std::sync::mpsc;
+    use std::thread;
+
+    #[test]
+    fn stop_wakes_the_threads() {
+        let mut listener = Listener::new().unwrap();
+        // Nothing is ever written, so the thread waits until the listener is stopped
+        let (_writer, reader) = UnixStream::pair().unwrap();
+        let (tx, rx) = mpsc::channel();
+        listener
+            .spawn(move |stop| {
+                let _ = tx.send(stop.wait(reader.as_raw_fd()));
+            })
+            .unwrap();
+
+        listener.stop();
+        assert_eq!(rx.recv(), Ok(false));
+    }
+
+    #[test]
+    fn queue_backpressure() {
+        for (backpressure, expected) in [
+            (Backpressure::DropOldest, [3, 4]),
+            (Backpressure::DropNewest, [1, 2]),
+        ] {
+            let queue = ListenerQueue::new(&MouseBuilder::new().listener_queue(2, backpressure));
+            let (tx, rx) = queue.channel();
+            for item in 1..=4 {
+                assert!(tx.send(item));
+            }
+            drop(tx);
+            assert_eq!(rx.collect::<Vec<_>>(), expected);
+            assert_eq!(queue.dropped(), 2);
+        }
+
+        // The sender waits until the receiver takes an item
+        let queue = ListenerQueue::new(&MouseBuilder::new().listener_queue(1, Backpressure::Block));
+        let (tx, rx) = queue.channel();
+        let sender = thread::spawn(move || (1..=3).all(|item| tx.send(item)));
+        assert_eq!(rx.collect::<Vec<_>>(), [1, 2, 3]);
+        assert!(sender.join().unwrap());
+        assert_eq!(queue.dropped(), 0);
+
+        // The senders stop once the receiver is dropped
+        let (tx, rx) = queue.channel();
+        drop(rx);
+        assert!(!tx.send(1));
+    }
+}
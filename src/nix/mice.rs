@@ -0,0 +1,153 @@
+///
+/// This module contains the fallback event listener for the linux
+/// systems that reads the PS/2 packets of `/dev/input/mice`
+///
+/// `/dev/input/mice` combines all the mice, and is available even on
+/// minimal systems that lack the `/dev/input/by-id` and `by-path` symlinks
+///
+/// - Unsupported mouse events
+///     - Scroll events are not available on the PS/2 protocol
+///
+use crate::common::{DragTracker, EventInfo, MouseButton, MouseEvent};
+use crate::error::Error;
+use crate::nix::listener::Listener;
+use crate::nix::Callbacks;
+use std::fs::File;
+use std::io::{ErrorKind, Read};
+
+/// Start the event listener that reads `/dev/input/mice`
+pub(super) fn start_mice_listener(callbacks: &Callbacks) -> Result<Listener, Error> {
+    let device = match File::options().read(true).open("/dev/input/mice") {
+        Ok(file) => file,
+        Err(err) if err.kind() == ErrorKind::PermissionDenied => {
+            return Err(Error::PermissionDenied)
+        }
+        Err(err) => return Err(Error::from_io("failed to open /dev/input/mice", err)),
+    };
+
+    let mut listener = Listener::new()?;
+    let callbacks = callbacks.clone();
+    listener.spawn(move |stop| {
+        // The reads end once the listener is stopped
+        let mut device = stop.reader(device);
+        let mut packet = [0; 3];
+        let mut buttons = 0;
+        let mut drags = DragTracker::default();
+
+        loop {
+            // The fourth bit of the first byte is always set, skip until
+            // it is found in case the reading starts in the middle of a packet
+            if device.read_exact(&mut packet[..1]).is_err() {
+                return;
+            }
+            if packet[0] & ALWAYS_ONE == 0 {
+                continue;
+            }
+            if device.read_exact(&mut packet[1..]).is_err() {
+                return;
+            }
+
+            for mouse_event in decode_packet(&packet, &mut buttons) {
+                let mouse_event = drags.track(mouse_event);
+                // Invoke all given callbacks with the constructed mouse event
+                callbacks
+                    .lock()
+                    .unwrap()
+                    .invoke(&mouse_event, &EventInfo::default());
+            }
+        }
+    })?;
+
+    Ok(listener)
+}
+
+/// Decode a PS/2 packet into the library's MouseEvents
+///
+/// `buttons` holds the pressed buttons of the previous packet, since
+/// the packets report the button state rather than the changes
+fn decode_packet(packet: &[u8; 3], buttons: &mut u8) -> Vec<MouseEvent> {
+    let mut events = vec![];
+
+    let pressed = packet[0] & 0x07;
+    for (mask, button) in [
+        (0x01, MouseButton::Left),
+        (0x02, MouseButton::Right),
+        (0x04, MouseButton::Middle),
+    ] {
+        if pressed & mask != *buttons & mask {
+            events.push(if pressed & mask != 0 {
+                MouseEvent::Press(button)
+            } else {
+                MouseEvent::Release(button)
+            });
+        }
+    }
+    *buttons = pressed;
+
+    // The deltas are 9-bit signed values, the sign bits are in the first byte
+    let mut dx = packet[1] as i32;
+    if packet[0] & X_SIGN != 0 {
+        dx -= 0x100;
+    }
+    let mut dy = packet[2] as i32;
+    if packet[0] & Y_SIGN != 0 {
+        dy -= 0x100;
+    }
+
+    // The y axis of PS/2 points up
+    if dx != 0 || dy != 0 {
+        events.push(MouseEvent::Move {
+            position: None,
+            delta: (dx, -dy),
+        });
+    }
+
+    events
+}
+
+/// PS/2 definitions
+const ALWAYS_ONE: u8 = 0x08;
+const X_SIGN: u8 = 0x10;
+const Y_SIGN: u8 = 0x20;
+
+#[cfg(test)]
+mod tests {
+    use super::decode_packet;
+    use crate::common::{MouseButton, MouseEvent};
+
+    #[test]
+    fn decode_buttons() {
+        let mut buttons = 0;
+        assert_eq!(
+            decode_packet(&[0x09, 0, 0], &mut buttons),
+            vec![MouseEvent::Press(MouseButton::Left)]
+        );
+        assert_eq!(
+            decode_packet(&[0x0c, 0, 0], &mut buttons),
+            vec![
+                MouseEvent::Release(MouseButton::Left),
+                MouseEvent::Press(MouseButton::Middle)
+            ]
+        );
+        assert_eq!(decode_packet(&[0x0c, 0, 0], &mut buttons), vec![]);
+    }
+
+    #[test]
+    fn decode_movement() {
+        let mut buttons = 0;
+        assert_eq!(
+            decode_packet(&[0x08, 5, 3], &mut buttons),
+            vec![MouseEvent::Move {
+                position: None,
+                delta: (5, -3)
+            }]
+        );
+        assert_eq!(
+            decode_packet(&[0x38, 0xfb, 0xfd], &mut buttons),
+            vec![MouseEvent::Move {
+                position: None,
+                delta: (-5, 3)
+            }]
+        );
+    }
+}
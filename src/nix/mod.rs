@@ -2,55 +2,891 @@
 /// This module contains the mouse action functions
 /// for the unix-like systems
 ///
-use crate::common::{CallbackId, MouseActions, MouseButton, MouseEvent, ScrollDirection};
+use crate::builder::MouseBuilder;
+use crate::common::{
+    rewrite_event, ButtonState, Callbacks, Device, DeviceId, DragTracker, EventInfo, MouseActions,
+    MouseButton, MouseEvent, Region, Rewriters, ScrollDirection, ScrollInversion, ScrollUnit,
+};
+use crate::display::Monitor;
 use crate::error::Error;
+#[cfg(target_os = "linux")]
+use crate::hotplug::{DeviceTracker, DeviceWatcher};
+use crate::nix::listener::{Listener, ListenerQueue, QueueReceiver, QueueSender};
 use crate::nix::uinput::{
-    InputEvent, TimeVal, BTN_LEFT, BTN_MIDDLE, BTN_RIGHT, EV_KEY, EV_REL, REL_HWHEEL, REL_WHEEL,
-    REL_X, REL_Y,
+    ioctl, InputEvent, TimeVal, BTN_EXTRA, BTN_FORWARD, BTN_JOYSTICK, BTN_LEFT, BTN_MIDDLE,
+    BTN_RIGHT, BTN_SIDE, EV_KEY, EV_REL, EV_SYN, HI_RES_UNITS_PER_NOTCH, REL_HWHEEL,
+    REL_HWHEEL_HI_RES, REL_WHEEL, REL_WHEEL_HI_RES, REL_X, REL_Y, SYN_DROPPED, SYN_REPORT,
 };
 use glob::glob;
 use std::collections::HashMap;
+use std::ffi::CStr;
+#[cfg(target_os = "linux")]
+use std::ffi::CString;
 use std::fs::File;
+use std::io;
+#[cfg(target_os = "linux")]
+use std::io::Read;
 use std::mem::size_of;
+#[cfg(target_os = "linux")]
+use std::os::raw::c_char;
+use std::os::raw::{c_int, c_long, c_uint, c_ulong};
+use std::os::unix::fs::MetadataExt;
 use std::os::unix::io::AsRawFd;
-use std::sync::mpsc;
+#[cfg(target_os = "linux")]
+use std::os::unix::io::FromRawFd;
+use std::process::Command;
+use std::str::{from_utf8, FromStr};
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::Duration;
+
+/// The highest key code of evdev, the key state buffers hold a bit for each code
+const KEY_MAX: usize = 0x2ff;
+
+/// The schema of the mouse settings of GNOME
+const MOUSE_SETTINGS: &str = "org.gnome.desktop.peripherals.mouse";
 
-#[cfg(feature = "x11")]
-use std::{process::Command, str::from_utf8};
 #[cfg(feature = "x11")]
 mod x11;
+#[cfg(feature = "x11")]
+mod xinput2;
+#[cfg(feature = "x11")]
+mod xrecord;
 
+mod evdev;
+#[cfg(feature = "libinput")]
+mod libinput;
+mod listener;
+mod mice;
+#[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
+mod sysmouse;
 mod uinput;
+#[cfg(feature = "ydotool")]
+pub mod ydotool;
 
-type Callbacks = Arc<Mutex<HashMap<CallbackId, Box<dyn Fn(&MouseEvent) + Send>>>>;
+pub use evdev::EvdevMouseManager;
+#[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
+pub use sysmouse::SysmouseMouseManager;
+
+/// The kind of the graphical session the process is running in
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SessionKind {
+    /// A native X11 session
+    X11,
+    /// A Wayland session with an Xwayland server, X11 calls only reach the X11 clients
+    Xwayland,
+    /// A Wayland session without any X11 server
+    Wayland,
+    /// A virtual console without a display server
+    Console,
+    Unknown,
+}
 
 pub struct NixMouseManager {}
 
 impl NixMouseManager {
     #[allow(clippy::new_ret_no_self)]
     pub fn new() -> Box<dyn MouseActions> {
+        Self::from_builder(&MouseBuilder::new()).expect("failed to create the mouse manager")
+    }
+
+    /// Get a builder to customize the mouse manager
+    pub fn builder() -> MouseBuilder {
+        MouseBuilder::new()
+    }
+
+    /// Get the double-click interval and distance, there is no system wide setting
+    /// on these systems so the GNOME settings are read, and the defaults of GTK
+    /// are used if they are not available
+    pub(crate) fn double_click_settings() -> (Duration, u32) {
+        let interval = gsettings_int(MOUSE_SETTINGS, "double-click")
+            .and_then(|interval| u64::try_from(interval).ok())
+            .unwrap_or(400);
+        (Duration::from_millis(interval), 5)
+    }
+
+    /// Get the distance the cursor moves with a held button before it is a drag,
+    /// like the double-click settings it is read from the GNOME settings
+    pub(crate) fn drag_threshold() -> u32 {
+        gsettings_int(MOUSE_SETTINGS, "drag-threshold")
+            .and_then(|threshold| u32::try_from(threshold).ok())
+            .unwrap_or(8)
+    }
+
+    /// Get the monitors from the X server, or from the connected outputs without one,
+    /// which are assumed to be placed side by side like the uinput device does
+    pub(crate) fn monitors() -> Result<Vec<Monitor>, Error> {
+        #[cfg(feature = "x11")]
+        if session_kind() == SessionKind::X11 {
+            return x11::monitors();
+        }
+        let mut x = 0;
+        let monitors: Vec<Monitor> = uinput::output_sizes()
+            .into_iter()
+            .enumerate()
+            .map(|(index, (width, height))| {
+                let bounds = Region::new(x, 0, width as u32, height as u32);
+                x += width;
+                Monitor {
+                    id: index,
+                    bounds,
+                    // The modes of the outputs are in physical pixels
+                    scale_factor: 1.,
+                    primary: index == 0,
+                }
+            })
+            .collect();
+        if monitors.is_empty() {
+            return Err(Error::CustomError("failed to detect the monitors"));
+        }
+        Ok(monitors)
+    }
+
+    /// Get the pointer speed, there is no system wide setting either so the
+    /// speed of GNOME is read, which goes from -1 to 1
+    pub(crate) fn pointer_speed() -> Result<f64, Error> {
+        let speed: f64 = gsettings_get(MOUSE_SETTINGS, "speed")
+            .and_then(|speed| parse_gsettings_number(&speed))
+            .ok_or(Error::CustomError("the GNOME settings are not available"))?;
+        Ok((speed + 1.) / 2.)
+    }
+
+    pub(crate) fn set_pointer_speed(speed: f64) -> Result<(), Error> {
+        gsettings_set(MOUSE_SETTINGS, "speed", &(speed * 2. - 1.).to_string())
+    }
+
+    /// Check if the pointer acceleration is enabled, the acceleration of the
+    /// X server is used on X11 and the profile of GNOME otherwise
+    pub(crate) fn pointer_acceleration() -> Result<bool, Error> {
+        #[cfg(feature = "x11")]
+        if session_kind() == SessionKind::X11 {
+            return x11::pointer_acceleration();
+        }
+        gsettings_get(MOUSE_SETTINGS, "accel-profile")
+            .map(|profile| profile != "'flat'")
+            .ok_or(Error::CustomError("the GNOME settings are not available"))
+    }
+
+    pub(crate) fn set_pointer_acceleration(enabled: bool) -> Result<(), Error> {
+        #[cfg(feature = "x11")]
+        if session_kind() == SessionKind::X11 {
+            return x11::set_pointer_acceleration(enabled);
+        }
+        let profile = if enabled { "'default'" } else { "'flat'" };
+        gsettings_set(MOUSE_SETTINGS, "accel-profile", profile)
+    }
+
+    pub(crate) fn from_builder(builder: &MouseBuilder) -> Result<Box<dyn MouseActions>, Error> {
         #[cfg(feature = "x11")]
         {
-            if is_x11() {
-                Box::new(x11::X11MouseManager::new())
+            // A display that is given explicitly is used even in the other sessions, e.g. a nested Xephyr
+            if builder.x11_display.is_some() || session_kind() == SessionKind::X11 {
+                let manager =
+                    x11::X11MouseManager::open(builder.x11_display.as_deref(), builder.x11_screen)?;
+                Ok(Box::new(manager.with_options(builder)))
             } else {
-                Box::new(uinput::UInputMouseManager::new())
+                new_fallback_manager(builder)
             }
         }
         #[cfg(not(feature = "x11"))]
         {
-            // If x11 feature is disabled, just return uinput mouse manager
-            return Box::new(uinput::UInputMouseManager::new());
+            // If x11 feature is disabled, just return the fallback mouse manager
+            return new_fallback_manager(builder);
         }
     }
 }
 
+/// Read a value from the GNOME settings in the text format of GVariant,
+/// e.g. `int32 400`, `0.5` or `'flat'`
+fn gsettings_get(schema: &str, key: &str) -> Option<String> {
+    let output = Command::new("gsettings")
+        .args(["get", schema, key])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(from_utf8(&output.stdout).ok()?.trim().to_string())
+}
+
+/// Change a value of the GNOME settings, the value is in the text format of GVariant
+fn gsettings_set(schema: &str, key: &str, value: &str) -> Result<(), Error> {
+    let status = Command::new("gsettings")
+        .args(["set", schema, key, value])
+        .status()
+        .map_err(|_| Error::CustomError("the GNOME settings are not available"))?;
+    if !status.success() {
+        return Err(Error::CustomError("failed to change the GNOME settings"));
+    }
+    Ok(())
+}
+
+/// Read an integer from the GNOME settings
+fn gsettings_int(schema: &str, key: &str) -> Option<i64> {
+    parse_gsettings_number(&gsettings_get(schema, key)?)
+}
+
+/// Parse a number with an optional type annotation, e.g. `int32 400` or `400`
+fn parse_gsettings_number<T: FromStr>(value: &str) -> Option<T> {
+    value.split_whitespace().last()?.parse().ok()
+}
+
+/// Create the mouse manager that works without a display server
+fn new_fallback_manager(builder: &MouseBuilder) -> Result<Box<dyn MouseActions>, Error> {
+    // The BSDs route the mouse through sysmouse, unless the console is not available
+    #[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
+    if std::path::Path::new("/dev/consolectl").exists() {
+        return Ok(Box::new(
+            sysmouse::SysmouseMouseManager::new()?.with_options(builder),
+        ));
+    }
+
+    Ok(Box::new(uinput::UInputMouseManager::new(builder)?))
+}
+
 /// Start the event listener for nix systems
-fn start_nix_listener(callbacks: &Callbacks) -> Result<(), Error> {
-    let (tx, rx) = mpsc::channel();
+fn start_nix_listener(
+    callbacks: &Callbacks,
+    queue: &ListenerQueue,
+    unreadable: &UnreadableDevices,
+) -> Result<Listener, Error> {
+    // Prefer libinput if it is available, and fall back to reading
+    // the event files directly if libinput can not be initialized
+    #[cfg(feature = "libinput")]
+    if let Ok(listener) = libinput::start_libinput_listener(callbacks) {
+        return Ok(listener);
+    }
+
+    // The BSDs usually do not have the linux style `/dev/input` symlinks,
+    // all the mice are combined into `/dev/sysmouse` instead
+    #[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
+    if let Ok(listener) = sysmouse::start_sysmouse_listener(callbacks) {
+        return Ok(listener);
+    }
+
+    let paths = mouse_event_paths();
+    // Minimal systems may not have the symlinks at all, read the
+    // combined PS/2 stream of all the mice instead of hooking nothing
+    if paths.is_empty() {
+        return mice::start_mice_listener(callbacks);
+    }
+
+    let mut listener = Listener::new()?;
+    let (tx, rx) = queue.channel();
+
+    // Each mouse has a listener of its own, so that its reader
+    // can be stopped and started again when it is reconnected
+    unreadable.retain(&paths);
+    let mut readers = HashMap::new();
+    for path in paths {
+        // The mice that can not be opened are skipped and reported by `unreadable_devices`
+        let event = match unreadable.open(&path) {
+            Some(file) => file,
+            None => continue,
+        };
+        let mut reader = Listener::new()?;
+        spawn_event_reader(&mut reader, event, tx.clone())?;
+        readers.insert(path, reader);
+    }
+    if readers.is_empty() {
+        return Err(Error::PermissionDenied);
+    }
+
+    // The dispatcher finishes once the watcher stops all the readers
+    spawn_reader_watcher(&mut listener, readers, tx, unreadable)?;
+    spawn_dispatcher(&mut listener, rx, callbacks)?;
+
+    Ok(listener)
+}
+
+/// The readers of the mice, by the paths of their event files
+type Readers = HashMap<String, Listener>;
+
+/// Keep the given readers on a thread of the listener, and start the readers of the
+/// mice that are connected later, the readers of the removed mice end by themselves
+///
+/// The readers are stopped along with the listener
+#[cfg(target_os = "linux")]
+fn spawn_reader_watcher(
+    listener: &mut Listener,
+    mut readers: Readers,
+    tx: QueueSender<(Arc<Device>, bool, InputEvent)>,
+    unreadable: &UnreadableDevices,
+) -> Result<(), Error> {
+    let inotify = device_inotify()?;
+    let unreadable = unreadable.clone();
+    listener.spawn(move |stop| {
+        let fd = inotify.as_raw_fd();
+        let mut inotify = stop.reader(inotify);
+        let mut buffer = [0u8; 4096];
+        // Catch up with the mice that are connected before the watches are added
+        update_readers(&mut readers, &tx, &unreadable);
+        // The events are not parsed, any change in the directories causes a rescan
+        while matches!(inotify.read(&mut buffer), Ok(length) if length > 0) {
+            add_device_watches(fd);
+            update_readers(&mut readers, &tx, &unreadable);
+        }
+        for reader in readers.into_values() {
+            reader.stop();
+        }
+    })
+}
+
+/// Keep the given readers on a thread of the listener, the mice that
+/// are connected later are not read, as there is no inotify to watch them
+#[cfg(not(target_os = "linux"))]
+fn spawn_reader_watcher(
+    listener: &mut Listener,
+    readers: Readers,
+    tx: QueueSender<(Arc<Device>, bool, InputEvent)>,
+    _unreadable: &UnreadableDevices,
+) -> Result<(), Error> {
+    drop(tx);
+    listener.spawn(move |stop| {
+        // A negative file descriptor is ignored, so this waits for the listener to stop
+        stop.wait(-1);
+        for reader in readers.into_values() {
+            reader.stop();
+        }
+    })
+}
+
+/// Start the readers of the connected mice that are not read yet, and forget the finished ones
+#[cfg(target_os = "linux")]
+fn update_readers(
+    readers: &mut Readers,
+    tx: &QueueSender<(Arc<Device>, bool, InputEvent)>,
+    unreadable: &UnreadableDevices,
+) {
+    let paths = mouse_event_paths();
+    unreadable.retain(&paths);
+    // The event nodes are reused, so the node of a finished reader may belong to a new mouse
+    readers.retain(|path, reader| paths.contains(path) && !reader.is_finished());
+    for path in paths {
+        if readers.contains_key(&path) {
+            continue;
+        }
+        // The node can not be opened until udev sets its permissions, which is watched as well
+        let (event, mut reader) = match (unreadable.open(&path), Listener::new()) {
+            (Some(event), Ok(reader)) => (event, reader),
+            _ => continue,
+        };
+        if spawn_event_reader(&mut reader, event, tx.clone()).is_ok() {
+            readers.insert(path, reader);
+        }
+    }
+}
+
+/// Stop the given listener once there are no callbacks left, so its threads do not
+/// keep reading the events for nobody, the next hook starts a new listener
+fn stop_idle_listener(listener: &mut Option<Listener>, callbacks: &Callbacks) {
+    if callbacks.lock().unwrap().is_empty() {
+        if let Some(listener) = listener.take() {
+            listener.stop();
+        }
+    }
+}
 
+/// Create a thread of the listener that invokes the given callbacks with the
+/// events of its readers, the thread finishes once all the readers are stopped
+fn spawn_dispatcher(
+    listener: &mut Listener,
+    rx: QueueReceiver<(Arc<Device>, bool, InputEvent)>,
+    callbacks: &Callbacks,
+) -> Result<(), Error> {
+    let callbacks = callbacks.clone();
+    listener.spawn(move |_| {
+        let mut drags = DragTracker::default();
+        let mut frames: HashMap<DeviceId, EventFrame> = HashMap::new();
+        for (device, injected, received) in rx {
+            let frame = frames.entry(device.id).or_default();
+            let mouse_events = match frame.push(&received) {
+                Some(mouse_events) => mouse_events,
+                None => continue,
+            };
+
+            // Invoke all given callbacks with the constructed mouse events
+            let info = EventInfo {
+                device: Some(device),
+                injected: Some(injected),
+                ..EventInfo::default()
+            };
+            for mouse_event in mouse_events {
+                let mouse_event = drags.track(mouse_event);
+                callbacks.lock().unwrap().invoke(&mouse_event, &info);
+            }
+        }
+    })
+}
+
+/// Collects the mouse events of a hardware report of a device, they are delivered
+/// at once at its SYN_REPORT, so e.g. a diagonal motion is a single move
+#[derive(Debug, Default)]
+struct EventFrame {
+    delta: (i32, i32),
+    /// The scrolled lines, a positive `dy` scrolls up and a positive `dx` scrolls right
+    wheel: (f64, f64),
+    buttons: Vec<MouseEvent>,
+    /// The kernel dropped some events, the frame is incomplete until the next SYN_REPORT
+    dropped: bool,
+}
+
+impl EventFrame {
+    /// Add the given evdev event to the frame, returns the mouse events
+    /// of the frame once its SYN_REPORT is received
+    fn push(&mut self, received: &InputEvent) -> Option<Vec<MouseEvent>> {
+        if received.r#type as c_int == EV_SYN {
+            match received.code as c_int {
+                SYN_REPORT => return Some(self.take()),
+                SYN_DROPPED => {
+                    *self = EventFrame {
+                        dropped: true,
+                        ..EventFrame::default()
+                    };
+                }
+                _ => {}
+            }
+            return None;
+        }
+        if self.dropped {
+            return None;
+        }
+
+        match mouse_event_from(received)? {
+            MouseEvent::Move { delta, .. } => {
+                self.delta.0 = self.delta.0.saturating_add(delta.0);
+                self.delta.1 = self.delta.1.saturating_add(delta.1);
+            }
+            MouseEvent::Scroll {
+                direction,
+                distance,
+                ..
+            } => {
+                let (dx, dy) = wheel_lines(&direction, distance);
+                self.wheel = (self.wheel.0 + dx, self.wheel.1 + dy);
+            }
+            button => self.buttons.push(button),
+        }
+        None
+    }
+
+    /// Get the mouse events of the frame and start the next one, the motion
+    /// is reported first and then the scrolls and the buttons
+    fn take(&mut self) -> Vec<MouseEvent> {
+        let frame = std::mem::take(self);
+        if frame.dropped {
+            return vec![];
+        }
+
+        let mut events = vec![];
+        if frame.delta != (0, 0) {
+            events.push(MouseEvent::Move {
+                position: None,
+                delta: frame.delta,
+            });
+        }
+        let (dx, dy) = frame.wheel;
+        for (lines, positive, negative) in [
+            (dy, ScrollDirection::Up, ScrollDirection::Down),
+            (dx, ScrollDirection::Right, ScrollDirection::Left),
+        ] {
+            if lines != 0. {
+                events.push(MouseEvent::Scroll {
+                    direction: if lines > 0. { positive } else { negative },
+                    unit: ScrollUnit::Line,
+                    distance: lines.abs(),
+                });
+            }
+        }
+        events.extend(frame.buttons);
+        events
+    }
+}
+
+/// The event files of the mice that the listener could not open, along with the
+/// `errno` of the failed opens, they are shared by the listeners of the manager
+#[derive(Debug, Clone, Default)]
+pub(crate) struct UnreadableDevices(Arc<Mutex<HashMap<String, i32>>>);
+
+impl UnreadableDevices {
+    /// Open the given event file for reading, the failure is kept until the next open succeeds
+    fn open(&self, path: &str) -> Option<File> {
+        let mut unreadable = self.0.lock().unwrap();
+        match File::options().read(true).open(path) {
+            Ok(file) => {
+                unreadable.remove(path);
+                Some(file)
+            }
+            Err(err) => {
+                unreadable.insert(path.to_string(), err.raw_os_error().unwrap_or(0));
+                None
+            }
+        }
+    }
+
+    /// Forget the event files that are not in the given paths, their mice are removed
+    fn retain(&self, paths: &[String]) {
+        self.0
+            .lock()
+            .unwrap()
+            .retain(|path, _| paths.contains(path));
+    }
+
+    /// Get the event files that could not be opened along with the reasons, sorted by the paths
+    pub(crate) fn list(&self) -> Vec<(String, Error)> {
+        let mut unreadable: Vec<(String, Error)> = self
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(path, code)| {
+                let error = Error::OsError {
+                    context: "failed to open the event file of the mouse",
+                    code: *code,
+                };
+                (path.clone(), error)
+            })
+            .collect();
+        unreadable.sort_by(|a, b| a.0.cmp(&b.0));
+        unreadable
+    }
+}
+
+/// The mice that are grabbed with `MouseActions::grab_device`, each of them
+/// has a listener of its own that holds the exclusive access to the device
+#[derive(Default)]
+pub(crate) struct GrabbedDevices(HashMap<DeviceId, Listener>);
+
+impl GrabbedDevices {
+    /// Grab the mouse with the given id, its events are only passed to the given callbacks
+    pub(crate) fn grab(
+        &mut self,
+        device_id: DeviceId,
+        callbacks: &Callbacks,
+        queue: &ListenerQueue,
+    ) -> Result<(), Error> {
+        if self.0.contains_key(&device_id) {
+            return Ok(());
+        }
+
+        let path = mouse_event_paths()
+            .into_iter()
+            .find(|path| {
+                std::fs::metadata(path).is_ok_and(|metadata| metadata.rdev() == device_id.0)
+            })
+            .ok_or(Error::CustomError("the device is not connected"))?;
+        let event = match File::options().read(true).open(path) {
+            Ok(file) => file,
+            Err(_) => return Err(Error::PermissionDenied),
+        };
+        // Only this file receives the events of the device until it is closed,
+        // the other listeners of the manager do not see them anymore
+        if unsafe { ioctl(event.as_raw_fd(), EVIOCGRAB, 1 as c_int) } < 0 {
+            return Err(Error::last_os_error("failed to grab the mouse"));
+        }
+
+        let mut listener = Listener::new()?;
+        // The grabbed events do not reach the system otherwise, so none of them are dropped
+        let (tx, rx) = queue.blocking().channel();
+        spawn_event_reader(&mut listener, event, tx)?;
+        spawn_dispatcher(&mut listener, rx, callbacks)?;
+        self.0.insert(device_id, listener);
+        Ok(())
+    }
+
+    /// Give the mouse with the given id back to the system
+    pub(crate) fn release(&mut self, device_id: DeviceId) -> Result<(), Error> {
+        match self.0.remove(&device_id) {
+            // The grab is released when the reader closes the file
+            Some(listener) => {
+                listener.stop();
+                Ok(())
+            }
+            None => Err(Error::CustomError("the device is not grabbed")),
+        }
+    }
+
+    /// Give all of the grabbed mice back to the system
+    pub(crate) fn release_all(&mut self) {
+        for (_, listener) in self.0.drain() {
+            listener.stop();
+        }
+    }
+}
+
+/// Start grabbing all the mice, their events are passed through the rewriting
+/// callbacks and written to the given uinput device in place of the originals
+///
+/// The grabbed mice do not reach the other listeners anymore, so the
+/// callbacks are invoked with the rewritten events from here instead
+fn start_rewrite_listener(
+    uinput_file: File,
+    rewriters: &Arc<Mutex<Rewriters>>,
+    callbacks: &Callbacks,
+    scroll_inversion: ScrollInversion,
+    queue: &ListenerQueue,
+) -> Result<Listener, Error> {
+    let paths = mouse_event_paths();
+    if paths.is_empty() {
+        return Err(Error::NotImplemented);
+    }
+
+    let mut listener = Listener::new()?;
+    // The grabbed events do not reach the system otherwise, so none of them are dropped
+    let (tx, rx) = queue.blocking().channel();
+    for path in &paths {
+        let event = match File::options().read(true).open(path) {
+            Ok(file) => file,
+            Err(_) => return Err(Error::PermissionDenied),
+        };
+        // Take the exclusive access, so that the events only
+        // reach the system through the uinput device
+        if unsafe { ioctl(event.as_raw_fd(), EVIOCGRAB, 1 as c_int) } < 0 {
+            return Err(Error::last_os_error("failed to grab the mouse"));
+        }
+        // The grab is released when the reader closes the file
+        spawn_event_reader(&mut listener, event, tx.clone())?;
+    }
+
+    drop(tx);
+    let rewriters = rewriters.clone();
+    let callbacks = callbacks.clone();
+    listener.spawn(move |_| {
+        let mut drags = DragTracker::default();
+        let mut wheel = HiResWheel::default();
+        let mut frames: HashMap<DeviceId, EventFrame> = HashMap::new();
+        for (device, injected, received) in rx {
+            // The mouse events are written at the end of their frame, right before its SYN_REPORT
+            let frame = frames.entry(device.id).or_default();
+            let is_mouse_event = mouse_event_from(&received).is_some();
+            let mouse_events = frame.push(&received).unwrap_or_default();
+
+            let info = EventInfo {
+                device: Some(device),
+                injected: Some(injected),
+                ..EventInfo::default()
+            };
+            for mouse_event in mouse_events {
+                let mouse_event = drags.track(mouse_event);
+                // The grabbed events are passed through as they are while paused
+                let rewritten = if callbacks.lock().unwrap().paused {
+                    Some(mouse_event)
+                } else {
+                    rewrite_event(&rewriters.lock().unwrap(), mouse_event, &info)
+                        .map(|event| scroll_inversion.hooked_event(event))
+                };
+                if let Some(event) = rewritten {
+                    let _ = emit_mouse_event(&uinput_file, &event, &mut wheel);
+                    callbacks.lock().unwrap().invoke(&event, &info);
+                }
+            }
+
+            // Pass the rest of the events (e.g. SYN_REPORT) through as they are
+            if !is_mouse_event {
+                let (r#type, code) = (received.r#type as c_int, received.code as c_int);
+                let _ = emit(&uinput_file, r#type, code, received.value);
+            }
+        }
+    })?;
+
+    Ok(listener)
+}
+
+/// Read the events of the given event file on a new thread of the listener, and send them
+/// to the given channel along with the device that produced them and whether it is virtual
+fn spawn_event_reader(
+    listener: &mut Listener,
+    event: File,
+    tx: QueueSender<(Arc<Device>, bool, InputEvent)>,
+) -> Result<(), Error> {
+    let device = Arc::new(evdev_device(&event));
+    let injected = is_virtual_device(device.id);
+    // The legacy wheel events of a high resolution wheel would scroll twice
+    let hi_res = has_hi_res_wheel(&event);
+    listener.spawn(move |stop| loop {
+        let mut buffer = InputEvent {
+            time: TimeVal {
+                tv_sec: 0,
+                tv_usec: 0,
+            },
+            r#type: 0,
+            code: 0,
+            value: 0,
+        };
+        // The file is closed once the listener is stopped
+        if !stop.wait(event.as_raw_fd()) {
+            return;
+        }
+        let length = unsafe { read(event.as_raw_fd(), &mut buffer, size_of::<InputEvent>()) };
+        // The reads fail once the device is removed
+        if length != size_of::<InputEvent>() as i32 {
+            if length < 0 && io::Error::last_os_error().kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            return;
+        }
+        if hi_res && is_legacy_wheel_event(&buffer) {
+            continue;
+        }
+        if !tx.send((device.clone(), injected, buffer)) {
+            return;
+        }
+    })
+}
+
+/// Construct the library's MouseEvent from the given evdev event,
+/// `None` if the event is not a known mouse event
+fn mouse_event_from(received: &InputEvent) -> Option<MouseEvent> {
+    let r#type = received.r#type as i32;
+    let val = received.value;
+
+    if r#type == EV_KEY {
+        // Ignore the unknown mouse buttons
+        let button = button_from_code(received.code as i32)?;
+
+        if received.value == 1 {
+            Some(MouseEvent::Press(button))
+        } else {
+            Some(MouseEvent::Release(button))
+        }
+    } else if r#type == EV_REL {
+        let code = received.code as u32;
+        if code == REL_WHEEL {
+            Some(MouseEvent::Scroll {
+                direction: if received.value > 0 {
+                    ScrollDirection::Up
+                } else {
+                    ScrollDirection::Down
+                },
+                unit: ScrollUnit::Line,
+                distance: received.value.unsigned_abs() as f64,
+            })
+        } else if code == REL_HWHEEL {
+            Some(MouseEvent::Scroll {
+                direction: if received.value > 0 {
+                    ScrollDirection::Right
+                } else {
+                    ScrollDirection::Left
+                },
+                unit: ScrollUnit::Line,
+                distance: received.value.unsigned_abs() as f64,
+            })
+        } else if code == REL_WHEEL_HI_RES || code == REL_HWHEEL_HI_RES {
+            // The readers drop the legacy wheel events of the devices that report these,
+            // a notch is 120 units so the fractions of a line are kept
+            let direction = match (code == REL_WHEEL_HI_RES, received.value > 0) {
+                (true, true) => ScrollDirection::Up,
+                (true, false) => ScrollDirection::Down,
+                (false, true) => ScrollDirection::Right,
+                (false, false) => ScrollDirection::Left,
+            };
+            Some(MouseEvent::Scroll {
+                direction,
+                unit: ScrollUnit::Line,
+                distance: received.value.unsigned_abs() as f64 / HI_RES_UNITS_PER_NOTCH as f64,
+            })
+        } else if code == REL_X {
+            Some(MouseEvent::Move {
+                position: None,
+                delta: (val, 0),
+            })
+        } else if code == REL_Y {
+            Some(MouseEvent::Move {
+                position: None,
+                delta: (0, val),
+            })
+        } else {
+            None
+        }
+    } else {
+        // Ignore other unknown events
+        None
+    }
+}
+
+/// Write the evdev events of the given MouseEvent to the given device file,
+/// the caller is responsible for the syncronization
+fn emit_mouse_event(file: &File, event: &MouseEvent, wheel: &mut HiResWheel) -> Result<(), Error> {
+    match event {
+        MouseEvent::Move { delta, .. } | MouseEvent::Drag { delta, .. } => {
+            if delta.0 != 0 {
+                emit(file, EV_REL, REL_X as c_int, delta.0)?;
+            }
+            if delta.1 != 0 {
+                emit(file, EV_REL, REL_Y as c_int, delta.1)?;
+            }
+            Ok(())
+        }
+        MouseEvent::Press(button) | MouseEvent::DoubleClick { button, .. } => {
+            emit(file, EV_KEY, code_from_button(button)?, 1)
+        }
+        MouseEvent::Release(button) => emit(file, EV_KEY, code_from_button(button)?, 0),
+        MouseEvent::Scroll {
+            direction,
+            distance,
+            ..
+        } => {
+            let (dx, dy) = wheel_lines(direction, *distance);
+            for (r#type, code, value) in wheel.events(dx, dy) {
+                emit(file, r#type, code, value)?;
+            }
+            Ok(())
+        }
+        MouseEvent::ListenerInterrupted => Ok(()),
+    }
+}
+
+/// Get the lines that a scroll towards the given direction turns the wheels
+/// by, a positive `dy` scrolls up and a positive `dx` scrolls right
+fn wheel_lines(direction: &ScrollDirection, distance: f64) -> (f64, f64) {
+    match direction {
+        ScrollDirection::Up => (0., distance),
+        ScrollDirection::Down => (0., -distance),
+        ScrollDirection::Right => (distance, 0.),
+        ScrollDirection::Left => (-distance, 0.),
+    }
+}
+
+/// Splits the scrolls into the high resolution wheel events and the notches of the
+/// legacy wheel events, the desktops scroll smoothly with the former while the
+/// older applications only see the latter
+///
+/// The high resolution units that do not add up to a whole notch yet are kept,
+/// so the legacy events catch up with them like the ones of a real wheel do
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct HiResWheel {
+    remainder: (i32, i32),
+}
+
+impl HiResWheel {
+    /// Get the events of scrolling by the given lines, a positive `dy` scrolls up
+    /// and a positive `dx` scrolls right, the caller syncronizes them
+    pub(crate) fn events(&mut self, dx: f64, dy: f64) -> Vec<(c_int, c_int, c_int)> {
+        let mut events = vec![];
+        let axes = [
+            (dy, &mut self.remainder.1, REL_WHEEL_HI_RES, REL_WHEEL),
+            (dx, &mut self.remainder.0, REL_HWHEEL_HI_RES, REL_HWHEEL),
+        ];
+        for (lines, remainder, hi_res_code, code) in axes {
+            let units = (lines * HI_RES_UNITS_PER_NOTCH as f64).round() as c_int;
+            if units == 0 {
+                continue;
+            }
+            // A real wheel reports the high resolution event first as well
+            events.push((EV_REL, hi_res_code as c_int, units));
+            *remainder = remainder.saturating_add(units);
+            let notches = *remainder / HI_RES_UNITS_PER_NOTCH;
+            if notches != 0 {
+                *remainder -= notches * HI_RES_UNITS_PER_NOTCH;
+                events.push((EV_REL, code as c_int, notches));
+            }
+        }
+        events
+    }
+}
+
+/// Get the paths of the event files of all the mice
+fn mouse_event_paths() -> Vec<String> {
     let mut previous_paths = vec![];
     // Read all the mouse events listed under /dev/input/by-id and
     // /dev/input/by-path. These directories are collections of symlinks
@@ -75,8 +911,11 @@ fn start_nix_listener(callbacks: &Callbacks) -> Result<(), Error> {
                 // Push the relative path of the link (e.g. `../event8`)
                 file.push(rel_path);
                 // Get the absolute path to final path
-                file = std::fs::canonicalize(file)
-                    .expect("Can't get absolute path to linked device file");
+                // The link is left dangling for a moment when its device is removed
+                file = match std::fs::canonicalize(file) {
+                    Ok(file) => file,
+                    Err(_) => continue,
+                };
             }
         }
 
@@ -86,118 +925,538 @@ fn start_nix_listener(callbacks: &Callbacks) -> Result<(), Error> {
             continue;
         }
 
-        previous_paths.push(path.clone());
+        previous_paths.push(path);
+    }
 
-        let event = match File::options().read(true).open(path) {
+    previous_paths
+}
+
+/// Get the buttons that are held down on any of the mice,
+/// the key states are read from the event files with EVIOCGKEY
+///
+/// The mice that can not be read are skipped, it only fails if none of them can be read
+fn get_evdev_pressed_buttons() -> Result<ButtonState, Error> {
+    let mut state = ButtonState::new();
+    let mut readable = false;
+    let mut denied = false;
+    for path in mouse_event_paths() {
+        let file = match File::options().read(true).open(path) {
             Ok(file) => file,
-            Err(_) => return Err(Error::PermissionDenied),
+            Err(err) => {
+                denied |= err.kind() == io::ErrorKind::PermissionDenied;
+                continue;
+            }
         };
+        readable = true;
 
-        // Create a thread for this mouse-event file
-        let tx = tx.clone();
-        thread::spawn(move || loop {
-            let mut buffer = InputEvent {
-                time: TimeVal {
-                    tv_sec: 0,
-                    tv_usec: 0,
-                },
-                r#type: 0,
-                code: 0,
-                value: 0,
-            };
-            unsafe {
-                read(event.as_raw_fd(), &mut buffer, size_of::<InputEvent>());
+        let mut keys = [0u8; KEY_MAX / 8 + 1];
+        unsafe {
+            if ioctl(file.as_raw_fd(), eviocgkey(keys.len()), keys.as_mut_ptr()) < 0 {
+                continue;
             }
-            tx.send(buffer).unwrap();
-        });
+        }
+        for code in BTN_LEFT..BTN_JOYSTICK {
+            let index = code as usize;
+            if keys[index / 8] & (1 << (index % 8)) != 0 {
+                if let Some(button) = button_from_code(code) {
+                    state.insert(&button);
+                }
+            }
+        }
     }
 
-    let callbacks = callbacks.clone();
-    // Create a thread for handling the callbacks
-    thread::spawn(move || {
-        for received in rx {
-            // Construct the library's MouseEvent
-            let r#type = received.r#type as i32;
-            let code = received.code as i32;
-            let val = received.value as i32;
-
-            let mouse_event = if r#type == EV_KEY {
-                let button = if code == BTN_LEFT {
-                    MouseButton::Left
-                } else if code == BTN_RIGHT {
-                    MouseButton::Right
-                } else if code == BTN_MIDDLE {
-                    MouseButton::Middle
-                } else {
-                    // Ignore the unknown mouse buttons
-                    continue;
-                };
+    match (readable, denied) {
+        (true, _) => Ok(state),
+        (false, true) => Err(Error::PermissionDenied),
+        (false, false) => Err(Error::NotImplemented),
+    }
+}
 
-                if received.value == 1 {
-                    MouseEvent::Press(button)
-                } else {
-                    MouseEvent::Release(button)
-                }
-            } else if r#type == EV_REL {
-                let code = received.code as u32;
-                if code == REL_WHEEL {
-                    MouseEvent::Scroll(if received.value > 0 {
-                        ScrollDirection::Up
-                    } else {
-                        ScrollDirection::Down
-                    })
-                } else if code == REL_HWHEEL {
-                    MouseEvent::Scroll(if received.value > 0 {
-                        ScrollDirection::Right
-                    } else {
-                        ScrollDirection::Left
-                    })
-                } else if code == REL_X {
-                    MouseEvent::RelativeMove(val, 0)
-                } else if code == REL_Y {
-                    MouseEvent::RelativeMove(0, val)
-                } else {
-                    continue;
-                }
-            } else {
-                // Ignore other unknown events
-                continue;
-            };
+/// The EVIOCGRAB ioctl request, _IOW('E', 0x90, int)
+const EVIOCGRAB: c_ulong = 0x40044590;
 
-            // Invoke all given callbacks with the constructed mouse event
-            for callback in callbacks.lock().unwrap().values() {
-                callback(&mouse_event);
-            }
+/// Get the EVIOCGKEY ioctl request for a buffer of the given length
+fn eviocgkey(length: usize) -> c_ulong {
+    // _IOC(_IOC_READ, 'E', 0x18, length)
+    (2 << 30) | ((length as c_ulong) << 16) | ((b'E' as c_ulong) << 8) | 0x18
+}
+
+/// Get the EVIOCGBIT ioctl request of the given event type for a buffer of the given length
+fn eviocgbit(r#type: c_int, length: usize) -> c_ulong {
+    // _IOC(_IOC_READ, 'E', 0x20 + type, length)
+    (2 << 30) | ((length as c_ulong) << 16) | ((b'E' as c_ulong) << 8) | (0x20 + r#type as c_ulong)
+}
+
+/// Check whether the device behind the given event file has a high resolution wheel
+fn has_hi_res_wheel(file: &File) -> bool {
+    let mut bits = [0u8; 4];
+    let length = unsafe {
+        ioctl(
+            file.as_raw_fd(),
+            eviocgbit(EV_REL, bits.len()),
+            bits.as_mut_ptr(),
+        )
+    };
+    length > 0
+        && [REL_WHEEL_HI_RES, REL_HWHEEL_HI_RES]
+            .iter()
+            .any(|code| bits[*code as usize / 8] & (1 << (code % 8)) != 0)
+}
+
+/// Check whether the given event is a notch of the legacy wheel events
+fn is_legacy_wheel_event(event: &InputEvent) -> bool {
+    event.r#type as c_int == EV_REL
+        && (event.code as c_uint == REL_WHEEL || event.code as c_uint == REL_HWHEEL)
+}
+
+/// Get the EVIOCGNAME ioctl request for a buffer of the given length
+fn eviocgname(length: usize) -> c_ulong {
+    // _IOC(_IOC_READ, 'E', 0x06, length)
+    (2 << 30) | ((length as c_ulong) << 16) | ((b'E' as c_ulong) << 8) | 0x06
+}
+
+/// Get the identity of the device behind the given event file, the
+/// device number of the event node is unique while the device is connected
+fn evdev_device(file: &File) -> Device {
+    let id = file.metadata().map(|metadata| metadata.rdev()).unwrap_or(0);
+
+    let mut name = [0u8; 256];
+    let length = unsafe { ioctl(file.as_raw_fd(), eviocgname(name.len()), name.as_mut_ptr()) };
+    let name = if length > 0 {
+        CStr::from_bytes_until_nul(&name)
+            .ok()
+            .map(|name| name.to_string_lossy().into_owned())
+    } else {
+        None
+    };
+
+    Device {
+        id: DeviceId(id),
+        name,
+    }
+}
+
+/// Get the major and the minor numbers of the event node with the given device number
+fn device_numbers(id: DeviceId) -> (u64, u64) {
+    // The device number is encoded the way glibc's makedev does
+    let major = ((id.0 >> 32) & 0xfffff000) | ((id.0 >> 8) & 0xfff);
+    let minor = ((id.0 >> 12) & 0xffffff00) | (id.0 & 0xff);
+    (major, minor)
+}
+
+/// Check whether the event node with the given device number belongs to a
+/// virtual device (e.g. a uinput device), its events are injected by software
+fn is_virtual_device(id: DeviceId) -> bool {
+    let (major, minor) = device_numbers(id);
+    match std::fs::read_link(format!("/sys/dev/char/{}:{}", major, minor)) {
+        Ok(path) => path
+            .components()
+            .any(|component| component.as_os_str() == "virtual"),
+        Err(_) => false,
+    }
+}
+
+/// The directories that are watched for the connected and the removed mice,
+/// the links under `by-id` and `by-path` are created after the event nodes
+#[cfg(target_os = "linux")]
+const WATCHED_DIRECTORIES: [&str; 3] = ["/dev/input", "/dev/input/by-id", "/dev/input/by-path"];
+
+/// Watch the mice that are connected or removed, see `DeviceWatcher`
+#[cfg(target_os = "linux")]
+pub(crate) fn watch_devices(mut tracker: DeviceTracker) -> Result<DeviceWatcher, Error> {
+    let inotify = device_inotify()?;
+    let mut listener = Listener::new()?;
+    listener.spawn(move |stop| {
+        let fd = inotify.as_raw_fd();
+        let mut inotify = stop.reader(inotify);
+        let mut buffer = [0u8; 4096];
+        scan_devices(&mut tracker);
+        // The events are not parsed, any change in the directories causes a rescan
+        while matches!(inotify.read(&mut buffer), Ok(length) if length > 0) {
+            // The `by-id` and `by-path` directories come and go with the devices
+            add_device_watches(fd);
+            scan_devices(&mut tracker);
         }
+    })?;
+    Ok(DeviceWatcher::new(move || listener.stop()))
+}
+
+/// Create an inotify instance that watches the changes of the event files
+#[cfg(target_os = "linux")]
+fn device_inotify() -> Result<File, Error> {
+    let fd = unsafe { inotify_init1(IN_CLOEXEC) };
+    if fd < 0 {
+        return Err(Error::from_io(
+            "failed to initialize inotify",
+            io::Error::last_os_error(),
+        ));
+    }
+    add_device_watches(fd);
+    Ok(unsafe { File::from_raw_fd(fd) })
+}
+
+#[cfg(target_os = "linux")]
+fn add_device_watches(fd: c_int) {
+    for directory in WATCHED_DIRECTORIES {
+        let path = CString::new(directory).unwrap();
+        // Watching a directory again only updates its watch
+        unsafe {
+            inotify_add_watch(
+                fd,
+                path.as_ptr(),
+                IN_CREATE | IN_DELETE | IN_ATTRIB | IN_MOVED_TO,
+            )
+        };
+    }
+}
+
+/// Report the changes of the connected mice to the given tracker
+#[cfg(target_os = "linux")]
+fn scan_devices(tracker: &mut DeviceTracker) {
+    let connected = mouse_event_paths()
+        .into_iter()
+        .filter_map(|path| {
+            let id = std::fs::metadata(&path).ok()?.rdev();
+            Some((DeviceId(id), path))
+        })
+        .collect();
+    // The name can not be read until the permissions of the node are set
+    tracker.update(connected, |id, path| match File::open(path) {
+        Ok(file) => evdev_device(&file),
+        Err(_) => Device { id, name: None },
     });
+}
+
+/// Write the given event to the given device file
+fn emit(file: &File, r#type: c_int, code: c_int, value: c_int) -> Result<(), Error> {
+    emit_all(file, &[(r#type, code, value)])
+}
+
+/// Write the given events of `(type, code, value)` to the given device file at once
+fn emit_all(file: &File, events: &[(c_int, c_int, c_int)]) -> Result<(), Error> {
+    let mut events: Vec<InputEvent> = events
+        .iter()
+        .map(|(r#type, code, value)| InputEvent {
+            time: TimeVal {
+                tv_sec: 0,
+                tv_usec: 0,
+            },
+            r#type: *r#type as u16,
+            code: *code as u16,
+            value: *value,
+        })
+        .collect();
+    let fd = file.as_raw_fd();
+
+    unsafe {
+        let count = size_of::<InputEvent>() * events.len();
+        let written_bytes = write(fd, events.as_mut_ptr(), count);
+        if written_bytes == -1 {
+            return Err(Error::from_io(
+                "failed to write the event",
+                io::Error::last_os_error(),
+            ));
+        }
+        if written_bytes != count as c_long {
+            return Err(Error::WriteFailed);
+        }
+    }
 
     Ok(())
 }
 
-#[cfg(feature = "x11")]
-fn is_x11() -> bool {
-    // Try to verify x11 using loginctl
+/// Syncronize the given device file
+fn syncronize(file: &File) -> Result<(), Error> {
+    emit(file, EV_SYN, SYN_REPORT, 0)?;
+    // Give uinput some time to update the mouse location,
+    // otherwise it fails to move the mouse on release mode
+    // A delay of 1 milliseconds seems to be enough for it
+    thread::sleep(Duration::from_millis(1));
+    Ok(())
+}
+
+/// Convert the given evdev button code to a MouseButton
+fn button_from_code(code: i32) -> Option<MouseButton> {
+    match code {
+        BTN_LEFT => Some(MouseButton::Left),
+        BTN_RIGHT => Some(MouseButton::Right),
+        BTN_MIDDLE => Some(MouseButton::Middle),
+        BTN_SIDE => Some(MouseButton::Back),
+        BTN_EXTRA => Some(MouseButton::Forward),
+        // The rest of the mouse buttons follow the first five in order
+        BTN_FORWARD..BTN_JOYSTICK => Some(MouseButton::Other((code - BTN_FORWARD) as u8)),
+        _ => None,
+    }
+}
+
+/// Convert the given MouseButton to an evdev button code
+fn code_from_button(button: &MouseButton) -> Result<c_int, Error> {
+    match button {
+        MouseButton::Left => Ok(BTN_LEFT),
+        MouseButton::Right => Ok(BTN_RIGHT),
+        MouseButton::Middle => Ok(BTN_MIDDLE),
+        MouseButton::Back => Ok(BTN_SIDE),
+        MouseButton::Forward => Ok(BTN_EXTRA),
+        MouseButton::Other(index) => match BTN_FORWARD + *index as c_int {
+            code if code < BTN_JOYSTICK => Ok(code),
+            _ => Err(Error::CustomError("the button has no evdev button code")),
+        },
+    }
+}
+
+/// Detect the kind of the current graphical session
+///
+/// Note that the `x11` backend is only used on `SessionKind::X11`, on
+/// Xwayland it would only be able to control and observe the X11 clients
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use mouce::nix::{session_kind, SessionKind};
+///
+/// if session_kind() == SessionKind::Xwayland {
+///     println!("get_position is not available under Wayland");
+/// }
+/// ```
+pub fn session_kind() -> SessionKind {
+    // Try to get the session type using loginctl first
     let loginctl_output = Command::new("sh")
         .arg("-c")
         .arg("loginctl show-session $(loginctl | awk '/tty/ {print $1}') -p Type --value")
         .output();
 
-    if let Ok(out) = loginctl_output {
-        if from_utf8(&out.stdout).unwrap().trim().to_lowercase() == "x11" {
-            return true;
-        }
-    }
+    let mut session_type = match loginctl_output {
+        Ok(out) => from_utf8(&out.stdout).unwrap_or("").trim().to_lowercase(),
+        Err(_) => String::new(),
+    };
 
     // If loginctl fails try to read the environment variable $XDG_SESSION_TYPE
-    if let Ok(session_type) = std::env::var("XDG_SESSION_TYPE") {
-        if session_type.trim().to_lowercase() == "x11" {
-            return true;
+    if session_type.is_empty() {
+        if let Ok(env_session_type) = std::env::var("XDG_SESSION_TYPE") {
+            session_type = env_session_type.trim().to_lowercase();
         }
     }
 
-    false
+    let has_display = std::env::var_os("DISPLAY").is_some();
+    let has_wayland_display = std::env::var_os("WAYLAND_DISPLAY").is_some();
+
+    // Ask the X server itself, it is the only reliable way to detect
+    // Xwayland when the session type is reported wrong (e.g. nested sessions)
+    #[cfg(feature = "x11")]
+    let is_xwayland = has_display && x11::is_xwayland();
+    #[cfg(not(feature = "x11"))]
+    let is_xwayland = false;
+
+    classify_session(&session_type, has_display, has_wayland_display, is_xwayland)
+}
+
+fn classify_session(
+    session_type: &str,
+    has_display: bool,
+    has_wayland_display: bool,
+    is_xwayland: bool,
+) -> SessionKind {
+    if is_xwayland {
+        return SessionKind::Xwayland;
+    }
+
+    match session_type {
+        "x11" => SessionKind::X11,
+        "wayland" if has_display => SessionKind::Xwayland,
+        "wayland" => SessionKind::Wayland,
+        "tty" if !has_display && !has_wayland_display => SessionKind::Console,
+        _ if has_wayland_display && has_display => SessionKind::Xwayland,
+        _ if has_wayland_display => SessionKind::Wayland,
+        _ => SessionKind::Unknown,
+    }
 }
 
 extern "C" {
     fn read(fd: i32, buf: *mut InputEvent, count: usize) -> i32;
+    fn write(fd: c_int, buf: *mut InputEvent, count: usize) -> c_long;
+}
+
+#[cfg(target_os = "linux")]
+const IN_CLOEXEC: c_int = 0o2000000;
+#[cfg(target_os = "linux")]
+const IN_ATTRIB: u32 = 0x00000004;
+#[cfg(target_os = "linux")]
+const IN_MOVED_TO: u32 = 0x00000080;
+#[cfg(target_os = "linux")]
+const IN_CREATE: u32 = 0x00000100;
+#[cfg(target_os = "linux")]
+const IN_DELETE: u32 = 0x00000200;
+
+#[cfg(target_os = "linux")]
+extern "C" {
+    fn inotify_init1(flags: c_int) -> c_int;
+    fn inotify_add_watch(fd: c_int, pathname: *const c_char, mask: u32) -> c_int;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        classify_session, eviocgbit, eviocgkey, eviocgname, parse_gsettings_number, EventFrame,
+        GrabbedDevices, HiResWheel, InputEvent, ListenerQueue, SessionKind, TimeVal,
+        UnreadableDevices, BTN_LEFT, EV_KEY, EV_REL, EV_SYN, REL_HWHEEL, REL_HWHEEL_HI_RES,
+        REL_WHEEL, REL_WHEEL_HI_RES, REL_X, REL_Y, SYN_DROPPED, SYN_REPORT,
+    };
+    use crate::builder::MouseBuilder;
+    use crate::common::{
+        CallbackMap, DeviceId, MouseButton, MouseEvent, ScrollDirection, ScrollUnit,
+    };
+    use crate::error::Error;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn classify_sessions() {
+        assert_eq!(
+            classify_session("x11", true, false, false),
+            SessionKind::X11
+        );
+        assert_eq!(
+            classify_session("x11", true, true, true),
+            SessionKind::Xwayland
+        );
+        assert_eq!(
+            classify_session("wayland", true, true, false),
+            SessionKind::Xwayland
+        );
+        assert_eq!(
+            classify_session("wayland", false, true, false),
+            SessionKind::Wayland
+        );
+        assert_eq!(
+            classify_session("tty", false, false, false),
+            SessionKind::Console
+        );
+        assert_eq!(
+            classify_session("", true, true, false),
+            SessionKind::Xwayland
+        );
+        assert_eq!(
+            classify_session("", false, false, false),
+            SessionKind::Unknown
+        );
+    }
+
+    #[test]
+    fn eviocgkey_request() {
+        // EVIOCGKEY(96) as defined by linux/input.h
+        assert_eq!(eviocgkey(96), 0x80604518);
+        // EVIOCGNAME(256)
+        assert_eq!(eviocgname(256), 0x81004506);
+        // EVIOCGBIT(EV_REL, 4)
+        assert_eq!(eviocgbit(EV_REL, 4), 0x80044522);
+    }
+
+    #[test]
+    fn gsettings_values() {
+        assert_eq!(parse_gsettings_number("int32 400\n"), Some(400));
+        assert_eq!(parse_gsettings_number("8"), Some(8));
+        assert_eq!(parse_gsettings_number("-0.25"), Some(-0.25));
+        assert_eq!(parse_gsettings_number::<i64>(""), None);
+    }
+
+    #[test]
+    fn hi_res_wheel() {
+        let mut wheel = HiResWheel::default();
+        let (hi_res, notch) = (REL_WHEEL_HI_RES as i32, REL_WHEEL as i32);
+        assert_eq!(
+            wheel.events(0., 2.),
+            [(EV_REL, hi_res, 240), (EV_REL, notch, 2)]
+        );
+        // The fractions of a notch add up
+        assert_eq!(wheel.events(0., 0.5), [(EV_REL, hi_res, 60)]);
+        assert_eq!(
+            wheel.events(0., 0.75),
+            [(EV_REL, hi_res, 90), (EV_REL, notch, 1)]
+        );
+        assert_eq!(wheel.events(0., -0.25), [(EV_REL, hi_res, -30)]);
+        assert_eq!(
+            wheel.events(-1., 0.),
+            [
+                (EV_REL, REL_HWHEEL_HI_RES as i32, -120),
+                (EV_REL, REL_HWHEEL as i32, -1)
+            ]
+        );
+        assert!(wheel.events(0., 0.).is_empty());
+    }
+
+    #[test]
+    fn event_frames() {
+        let event = |r#type: i32, code: u32, value: i32| InputEvent {
+            time: TimeVal {
+                tv_sec: 0,
+                tv_usec: 0,
+            },
+            r#type: r#type as u16,
+            code: code as u16,
+            value,
+        };
+        let report = event(EV_SYN, SYN_REPORT as u32, 0);
+        let mut frame = EventFrame::default();
+        for received in [
+            event(EV_REL, REL_X, 3),
+            event(EV_KEY, BTN_LEFT as u32, 1),
+            event(EV_REL, REL_Y, -2),
+            event(EV_REL, REL_WHEEL_HI_RES, 60),
+            event(EV_REL, REL_WHEEL_HI_RES, -180),
+        ] {
+            assert_eq!(frame.push(&received), None);
+        }
+        assert_eq!(
+            frame.push(&report),
+            Some(vec![
+                MouseEvent::Move {
+                    position: None,
+                    delta: (3, -2),
+                },
+                MouseEvent::Scroll {
+                    direction: ScrollDirection::Down,
+                    unit: ScrollUnit::Line,
+                    distance: 1.,
+                },
+                MouseEvent::Press(MouseButton::Left),
+            ])
+        );
+        assert_eq!(frame.push(&report), Some(vec![]));
+
+        // The incomplete frames are discarded
+        frame.push(&event(EV_REL, REL_X, 1));
+        frame.push(&event(EV_SYN, SYN_DROPPED as u32, 0));
+        frame.push(&event(EV_REL, REL_X, 1));
+        assert_eq!(frame.push(&report), Some(vec![]));
+    }
+
+    #[test]
+    fn unreadable_devices() {
+        let unreadable = UnreadableDevices::default();
+        let path = "/dev/input/mouce-missing-event".to_string();
+        assert!(unreadable.open(&path).is_none());
+        let list = unreadable.list();
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].0, path);
+        // ENOENT
+        assert_eq!(list[0].1.raw_os_error(), Some(2));
+
+        // The removed mice are forgotten
+        unreadable.retain(&[path]);
+        assert_eq!(unreadable.list().len(), 1);
+        unreadable.retain(&[]);
+        assert!(unreadable.list().is_empty());
+    }
+
+    #[test]
+    fn unknown_grabbed_devices() {
+        let mut grabbed = GrabbedDevices::default();
+        let callbacks = Arc::new(Mutex::new(CallbackMap::new()));
+        let queue = ListenerQueue::new(&MouseBuilder::new());
+        // No event node has the device number zero
+        assert_eq!(
+            grabbed.grab(DeviceId(0), &callbacks, &queue),
+            Err(Error::CustomError("the device is not connected"))
+        );
+        assert!(grabbed.release(DeviceId(0)).is_err());
+    }
 }
@@ -0,0 +1,431 @@
+///
+/// This module contains the mouse action functions and the event listener
+/// for FreeBSD and DragonFly that use the sysmouse(4) and consolectl interfaces
+///
+/// Mouse actions are injected through `/dev/consolectl`, which moused(8) and
+/// X11 pick up just like the events of a physical mouse, and the events are
+/// read from `/dev/sysmouse`, which combines all mice attached with moused(8)
+///
+/// - Unsupported mouse actions
+///     - get_position is not available on sysmouse
+///     - Horizontal scrolling is not available on consolectl
+///
+use crate::builder::MouseBuilder;
+use crate::common::{
+    simulate_event, CallbackId, CallbackMap, DragTracker, EventCallback, EventInfo, MouseActions,
+    MouseButton, MouseEvent, ScrollDirection, ScrollInversion, ScrollUnit,
+};
+use crate::error::Error;
+use crate::nix::listener::Listener;
+use crate::nix::Callbacks;
+use crate::pacing::Pacer;
+use std::cell::Cell;
+use std::fs::File;
+use std::io::{ErrorKind, Read};
+use std::os::raw::{c_int, c_ulong};
+use std::os::unix::io::AsRawFd;
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+
+pub struct SysmouseMouseManager {
+    consolectl: File,
+    buttons: Cell<c_int>,
+    callbacks: Callbacks,
+    listener: Option<Listener>,
+    pacer: Pacer,
+    scroll_inversion: ScrollInversion,
+}
+
+impl SysmouseMouseManager {
+    pub fn new() -> Result<Self, Error> {
+        let consolectl = match File::options().write(true).open("/dev/consolectl") {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::PermissionDenied => {
+                return Err(Error::PermissionDenied)
+            }
+            Err(err) => return Err(Error::from_io("failed to open /dev/consolectl", err)),
+        };
+
+        Ok(SysmouseMouseManager {
+            consolectl,
+            buttons: Cell::new(0),
+            callbacks: Arc::new(Mutex::new(CallbackMap::new())),
+            listener: None,
+            pacer: Pacer::default(),
+            scroll_inversion: ScrollInversion::NONE,
+        })
+    }
+
+    /// Apply the pacing, the scroll inversion and the dispatch options of the given builder
+    pub(super) fn with_options(mut self, builder: &MouseBuilder) -> Self {
+        self.pacer = Pacer::new(&builder.pacing);
+        self.scroll_inversion = builder.scroll_inversion;
+        let mut callbacks = self.callbacks.lock().unwrap();
+        callbacks.set_execution(builder.callback_execution);
+        callbacks.set_move_coalescing(builder.move_coalescing);
+        drop(callbacks);
+        self
+    }
+
+    /// Send a mouse action with the given deltas and the current button state
+    fn action(&self, x: c_int, y: c_int, z: c_int) -> Result<(), Error> {
+        let mut info = MouseInfo {
+            operation: MOUSE_ACTION,
+            data: MouseData {
+                x,
+                y,
+                z,
+                buttons: self.buttons.get(),
+            },
+        };
+
+        self.pacer.wait();
+        unsafe {
+            if ioctl(self.consolectl.as_raw_fd(), CONS_MOUSECTL, &mut info) < 0 {
+                return Err(Error::WriteFailed);
+            }
+        }
+        Ok(())
+    }
+
+    fn button_event(&self, button: &MouseButton, is_press: bool) -> Result<(), Error> {
+        let mask = match button {
+            MouseButton::Left => MOUSE_BUTTON1DOWN,
+            MouseButton::Middle => MOUSE_BUTTON2DOWN,
+            MouseButton::Right => MOUSE_BUTTON3DOWN,
+            MouseButton::Back => MOUSE_BUTTON4DOWN,
+            MouseButton::Forward => MOUSE_BUTTON5DOWN,
+            // consolectl has the bits for up to eight buttons
+            MouseButton::Other(index) if *index < 3 => MOUSE_BUTTON6DOWN << index,
+            MouseButton::Other(_) => {
+                return Err(Error::CustomError(
+                    "the button is not supported by consolectl",
+                ))
+            }
+        };
+
+        // consolectl expects the state of all buttons with every action
+        let buttons = self.buttons.get();
+        self.buttons.set(if is_press {
+            buttons | mask
+        } else {
+            buttons & !mask
+        });
+        self.action(0, 0, 0)
+    }
+}
+
+impl MouseActions for SysmouseMouseManager {
+    fn move_to(&self, x: usize, y: usize) -> Result<(), Error> {
+        // consolectl only accepts relative movements, so first move
+        // the mouse to the top left, then move it to the position
+        self.move_relative(i32::MIN / 2, i32::MIN / 2)?;
+        self.move_relative(x as i32, y as i32)
+    }
+
+    fn move_relative(&self, x_offset: i32, y_offset: i32) -> Result<(), Error> {
+        self.action(x_offset, y_offset, 0)
+    }
+
+    fn get_position(&self) -> Result<(i32, i32), Error> {
+        // sysmouse does not let us get the current position of the mouse
+        Err(Error::NotImplemented)
+    }
+
+    fn press_button(&self, button: &MouseButton) -> Result<(), Error> {
+        self.button_event(button, true)
+    }
+
+    fn release_button(&self, button: &MouseButton) -> Result<(), Error> {
+        self.button_event(button, false)
+    }
+
+    fn scroll_wheel(
+        &self,
+        direction: &ScrollDirection,
+        unit: ScrollUnit,
+        distance: u32,
+    ) -> Result<(), Error> {
+        if unit == ScrollUnit::Pixel {
+            return Err(Error::NotImplemented);
+        }
+
+        let distance = distance.min(c_int::MAX as u32) as c_int;
+        match self.scroll_inversion.direction(direction) {
+            ScrollDirection::Up => self.action(0, 0, -distance),
+            ScrollDirection::Down => self.action(0, 0, distance),
+            ScrollDirection::Left | ScrollDirection::Right => Err(Error::NotImplemented),
+        }
+    }
+
+    fn hook_with_info(&mut self, callback: EventCallback) -> Result<CallbackId, Error> {
+        if self.listener.is_none() {
+            self.listener = Some(start_sysmouse_listener(&self.callbacks)?);
+        }
+
+        let id = CallbackId::next();
+        self.callbacks.lock().unwrap().insert(id, callback);
+        Ok(id)
+    }
+
+    fn simulate_event(&self, event: &MouseEvent) -> Result<(), Error> {
+        simulate_event(&self.callbacks, event);
+        Ok(())
+    }
+
+    fn callback_panics(&mut self) -> Result<Receiver<Error>, Error> {
+        Ok(self.callbacks.lock().unwrap().panic_receiver())
+    }
+
+    fn unhook(&mut self, callback_id: CallbackId) -> Result<(), Error> {
+        let removed = self.callbacks.lock().unwrap().remove(&callback_id);
+        match removed {
+            Some(_) => {
+                super::stop_idle_listener(&mut self.listener, &self.callbacks);
+                Ok(())
+            }
+            None => Err(Error::UnhookFailed),
+        }
+    }
+
+    fn unhook_all(&mut self) -> Result<(), Error> {
+        self.callbacks.lock().unwrap().clear();
+        super::stop_idle_listener(&mut self.listener, &self.callbacks);
+        Ok(())
+    }
+
+    fn pause_listening(&mut self) -> Result<(), Error> {
+        self.callbacks.lock().unwrap().paused = true;
+        Ok(())
+    }
+
+    fn resume_listening(&mut self) -> Result<(), Error> {
+        self.callbacks.lock().unwrap().paused = false;
+        Ok(())
+    }
+
+    fn stop_listening(&mut self) -> Result<(), Error> {
+        self.callbacks.lock().unwrap().clear();
+        if let Some(listener) = self.listener.take() {
+            listener.stop();
+        }
+        Ok(())
+    }
+}
+
+/// Start the event listener that reads `/dev/sysmouse`
+pub(super) fn start_sysmouse_listener(callbacks: &Callbacks) -> Result<Listener, Error> {
+    let device = match File::options().read(true).open("/dev/sysmouse") {
+        Ok(file) => file,
+        Err(err) if err.kind() == ErrorKind::PermissionDenied => {
+            return Err(Error::PermissionDenied)
+        }
+        Err(err) => return Err(Error::from_io("failed to open /dev/sysmouse", err)),
+    };
+
+    // Switch to the level 1 protocol, it also reports the wheel
+    let mut level: c_int = 1;
+    unsafe {
+        if ioctl(device.as_raw_fd(), MOUSE_SETLEVEL, &mut level) < 0 {
+            return Err(Error::last_os_error(
+                "failed to set the sysmouse protocol level",
+            ));
+        }
+    }
+
+    let mut listener = Listener::new()?;
+    let callbacks = callbacks.clone();
+    listener.spawn(move |stop| {
+        // The reads end once the listener is stopped
+        let mut device = stop.reader(device);
+        let mut packet = [0; PACKET_SIZE];
+        let mut buttons = 0;
+        let mut drags = DragTracker::default();
+
+        loop {
+            // Every packet starts with a sync byte, skip until the next one
+            // in case the reading starts in the middle of a packet
+            if device.read_exact(&mut packet[..1]).is_err() {
+                return;
+            }
+            if packet[0] & SYNC_MASK != SYNC_BYTE {
+                continue;
+            }
+            if device.read_exact(&mut packet[1..]).is_err() {
+                return;
+            }
+
+            for mouse_event in decode_packet(&packet, &mut buttons) {
+                let mouse_event = drags.track(mouse_event);
+                // Invoke all given callbacks with the constructed mouse event
+                callbacks
+                    .lock()
+                    .unwrap()
+                    .invoke(&mouse_event, &EventInfo::default());
+            }
+        }
+    })?;
+
+    Ok(listener)
+}
+
+/// Decode a level 1 sysmouse packet into the library's MouseEvents
+///
+/// `buttons` holds the pressed buttons of the previous packet, since
+/// the packets report the button state rather than the changes
+fn decode_packet(packet: &[u8; PACKET_SIZE], buttons: &mut u16) -> Vec<MouseEvent> {
+    let mut events = vec![];
+
+    // The button bits are active low, flip them to get the pressed buttons,
+    // the last byte holds the state of the buttons 4 to 10
+    let pressed = (!packet[0] & 0x07) as u16 | ((!packet[7] & 0x7f) as u16) << 3;
+    let extra_buttons = (0..7).map(|index| {
+        let button = match index {
+            0 => MouseButton::Back,
+            1 => MouseButton::Forward,
+            _ => MouseButton::Other(index - 2),
+        };
+        (0x08 << index, button)
+    });
+    for (mask, button) in [
+        (0x04, MouseButton::Left),
+        (0x02, MouseButton::Middle),
+        (0x01, MouseButton::Right),
+    ]
+    .into_iter()
+    .chain(extra_buttons)
+    {
+        if pressed & mask != *buttons & mask {
+            events.push(if pressed & mask != 0 {
+                MouseEvent::Press(button)
+            } else {
+                MouseEvent::Release(button)
+            });
+        }
+    }
+    *buttons = pressed;
+
+    // Each packet carries two movement samples, and the y axis points up
+    let dx = packet[1] as i8 as i32 + packet[3] as i8 as i32;
+    let dy = packet[2] as i8 as i32 + packet[4] as i8 as i32;
+    if dx != 0 || dy != 0 {
+        events.push(MouseEvent::Move {
+            position: None,
+            delta: (dx, -dy),
+        });
+    }
+
+    // The wheel is reported as two 7-bit signed values
+    let dz = sign_extend_7(packet[5]) + sign_extend_7(packet[6]);
+    if dz != 0 {
+        events.push(MouseEvent::Scroll {
+            direction: if dz > 0 {
+                ScrollDirection::Down
+            } else {
+                ScrollDirection::Up
+            },
+            unit: ScrollUnit::Line,
+            distance: dz.unsigned_abs() as f64,
+        });
+    }
+
+    events
+}
+
+fn sign_extend_7(value: u8) -> i32 {
+    (((value & 0x7f) << 1) as i8 >> 1) as i32
+}
+
+/// sysmouse and consolectl definitions
+const PACKET_SIZE: usize = 8;
+const SYNC_MASK: u8 = 0xf8;
+const SYNC_BYTE: u8 = 0x80;
+const MOUSE_ACTION: c_int = 0x07;
+const MOUSE_BUTTON1DOWN: c_int = 0x0001;
+const MOUSE_BUTTON2DOWN: c_int = 0x0002;
+const MOUSE_BUTTON3DOWN: c_int = 0x0004;
+const MOUSE_BUTTON4DOWN: c_int = 0x0008;
+const MOUSE_BUTTON5DOWN: c_int = 0x0010;
+const MOUSE_BUTTON6DOWN: c_int = 0x0020;
+const MOUSE_SETLEVEL: c_ulong = 0x80044d05;
+const CONS_MOUSECTL: c_ulong = 0xc014630a;
+
+#[repr(C)]
+struct MouseData {
+    x: c_int,
+    y: c_int,
+    z: c_int,
+    buttons: c_int,
+}
+
+#[repr(C)]
+struct MouseInfo {
+    operation: c_int,
+    data: MouseData,
+}
+
+extern "C" {
+    fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decode_packet;
+    use crate::common::{MouseButton, MouseEvent, ScrollDirection, ScrollUnit};
+
+    #[test]
+    fn decode_buttons() {
+        let mut buttons = 0;
+        assert_eq!(
+            decode_packet(&[0x83, 0, 0, 0, 0, 0, 0, 0x7f], &mut buttons),
+            vec![MouseEvent::Press(MouseButton::Left)]
+        );
+        assert_eq!(
+            decode_packet(&[0x86, 0, 0, 0, 0, 0, 0, 0x7f], &mut buttons),
+            vec![
+                MouseEvent::Release(MouseButton::Left),
+                MouseEvent::Press(MouseButton::Right)
+            ]
+        );
+        assert_eq!(
+            decode_packet(&[0x86, 0, 0, 0, 0, 0, 0, 0x7f], &mut buttons),
+            vec![]
+        );
+        assert_eq!(
+            decode_packet(&[0x86, 0, 0, 0, 0, 0, 0, 0x7a], &mut buttons),
+            vec![
+                MouseEvent::Press(MouseButton::Back),
+                MouseEvent::Press(MouseButton::Other(0))
+            ]
+        );
+    }
+
+    #[test]
+    fn decode_movement() {
+        let mut buttons = 0;
+        assert_eq!(
+            decode_packet(&[0x87, 3, 0xfe, 2, 0xff, 0, 0, 0x7f], &mut buttons),
+            vec![MouseEvent::Move {
+                position: None,
+                delta: (5, 3)
+            }]
+        );
+        assert_eq!(
+            decode_packet(&[0x87, 0, 0, 0, 0, 0x7f, 0, 0x7f], &mut buttons),
+            vec![MouseEvent::Scroll {
+                direction: ScrollDirection::Up,
+                unit: ScrollUnit::Line,
+                distance: 1.
+            }]
+        );
+        // Both of the wheel samples are added up
+        assert_eq!(
+            decode_packet(&[0x87, 0, 0, 0, 0, 0x01, 0x01, 0x7f], &mut buttons),
+            vec![MouseEvent::Scroll {
+                direction: ScrollDirection::Down,
+                unit: ScrollUnit::Line,
+                distance: 2.
+            }]
+        );
+    }
+}
@@ -2,48 +2,123 @@
 /// This module contains the mouse action functions
 /// for the linux systems that uses uinput
 ///
-/// - Unsupported mouse actions
-///     - get_position is not available on uinput
+/// - get_position is emulated from the moves of this device, uinput can not
+///   read the real position so it drifts once the other devices move the mouse
 ///
-use crate::common::{CallbackId, MouseActions, MouseButton, MouseEvent, ScrollDirection};
+use crate::builder::MouseBuilder;
+use crate::common::{
+    simulate_event, Action, ButtonState, CallbackId, CallbackMap, DeviceId, EventCallback,
+    MouseActions, MouseButton, MouseEvent, RewriteCallback, Rewriters, ScrollDirection,
+    ScrollInversion, ScrollUnit,
+};
 use crate::error::Error;
-use crate::nix::Callbacks;
-use std::collections::HashMap;
-use std::fs::File;
-use std::mem::size_of;
-use std::os::raw::{c_char, c_int, c_long, c_uint, c_ulong, c_ushort};
+use crate::nix::listener::{Listener, ListenerQueue};
+use crate::nix::{Callbacks, GrabbedDevices, HiResWheel, UnreadableDevices};
+use crate::pacing::Pacer;
+use glob::glob;
+use std::ffi::CStr;
+use std::fs::{self, File};
+use std::io::ErrorKind;
+use std::os::raw::{c_char, c_int, c_uint, c_ulong, c_ushort};
+use std::os::unix::fs::MetadataExt;
 use std::os::unix::prelude::AsRawFd;
-use std::sync::{Arc, Mutex};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex, Weak};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 const UINPUT_MAX_NAME_SIZE: usize = 80;
+/// The database of udev, it has an entry for each device that udev has processed
+const UDEV_DATA_DIRECTORY: &str = "/run/udev/data";
+/// How often the device is checked while waiting for it to be ready
+const READY_POLL_INTERVAL: Duration = Duration::from_millis(5);
+/// The time that is left for the listeners of udev after the device is processed
+const READY_SETTLE_TIME: Duration = Duration::from_millis(20);
+
+/// The virtual devices that are shared by the managers of this process, see
+/// `MouseBuilder::share_uinput_device`, the last manager of a device destroys it
+static SHARED_DEVICES: Mutex<Vec<Weak<UInputDevice>>> = Mutex::new(Vec::new());
+
+/// A virtual uinput device, it is destroyed once it is dropped
+struct UInputDevice {
+    file: File,
+    // The options of the builder that identify a shared device
+    name: String,
+    vendor: u16,
+    product: u16,
+    // The screen size that the absolute axes are mapped to, this is
+    // None if the screen size could not be detected
+    screen_size: Option<(i32, i32)>,
+    // The identity of the event node of this device, the hooks
+    // tell the injected events apart from it
+    own_device: Option<DeviceId>,
+    // The state of the device, which all of the managers sharing it update
+    state: Mutex<DeviceState>,
+}
+
+/// The state that follows the events written to a virtual device
+#[derive(Copy, Clone)]
+struct DeviceState {
+    last_absolute_position: Option<(i32, i32)>,
+    // The emulated position of the cursor, uinput can not read the real
+    // position so it is tracked from the moves of this device
+    cursor: (i32, i32),
+    // The high resolution units that do not add up to a notch of the wheels yet
+    wheel: HiResWheel,
+}
 
 pub struct UInputMouseManager {
-    uinput_file: File,
+    device: Arc<UInputDevice>,
+    // The buttons that are held down by this device, the virtual device
+    // has no symlinks under /dev/input so it is not found with the mice
+    pressed_buttons: Mutex<ButtonState>,
     callbacks: Callbacks,
-    callback_counter: CallbackId,
-    is_listening: bool,
+    rewriters: Arc<Mutex<Rewriters>>,
+    ignore_own_events: Arc<AtomicBool>,
+    listener: Option<Listener>,
+    rewrite_listener: Option<Listener>,
+    listener_queue: ListenerQueue,
+    grabbed_devices: GrabbedDevices,
+    unreadable_devices: UnreadableDevices,
+    pacer: Pacer,
+    scroll_inversion: ScrollInversion,
 }
 
-impl UInputMouseManager {
-    pub fn new() -> Self {
-        let manager = UInputMouseManager {
-            uinput_file: File::options()
-                .write(true)
-                .open("/dev/uinput")
-                .expect("uinput file can not be opened"),
-            callbacks: Arc::new(Mutex::new(HashMap::new())),
-            callback_counter: 0,
-            is_listening: false,
+impl UInputDevice {
+    /// Create a virtual device with the options of the given builder
+    fn create(builder: &MouseBuilder) -> Result<Self, Error> {
+        let file = match File::options().write(true).open("/dev/uinput") {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::PermissionDenied => {
+                return Err(Error::PermissionDenied)
+            }
+            Err(err) => return Err(Error::from_io("uinput file can not be opened", err)),
         };
-        let fd = manager.uinput_file.as_raw_fd();
+
+        let screen_size = screen_size();
+        let mut device = UInputDevice {
+            file,
+            name: builder.device_name.clone(),
+            vendor: builder.vendor,
+            product: builder.product,
+            screen_size,
+            own_device: None,
+            state: Mutex::new(DeviceState {
+                last_absolute_position: None,
+                // The cursor starts at the center of the screen on most of the desktops
+                cursor: screen_size.map_or((0, 0), |(width, height)| (width / 2, height / 2)),
+                wheel: HiResWheel::default(),
+            }),
+        };
+        let fd = device.file.as_raw_fd();
         unsafe {
             // For press events (also needed for mouse movement)
             ioctl(fd, UI_SET_EVBIT, EV_KEY);
-            ioctl(fd, UI_SET_KEYBIT, BTN_LEFT);
-            ioctl(fd, UI_SET_KEYBIT, BTN_RIGHT);
-            ioctl(fd, UI_SET_KEYBIT, BTN_MIDDLE);
+            for code in BTN_LEFT..BTN_JOYSTICK {
+                ioctl(fd, UI_SET_KEYBIT, code);
+            }
 
             // For mouse movement
             ioctl(fd, UI_SET_EVBIT, EV_REL);
@@ -51,23 +126,33 @@ impl UInputMouseManager {
             ioctl(fd, UI_SET_RELBIT, REL_Y);
             ioctl(fd, UI_SET_RELBIT, REL_WHEEL);
             ioctl(fd, UI_SET_RELBIT, REL_HWHEEL);
+            ioctl(fd, UI_SET_RELBIT, REL_WHEEL_HI_RES);
+            ioctl(fd, UI_SET_RELBIT, REL_HWHEEL_HI_RES);
+
+            // For absolute mouse movement
+            if device.screen_size.is_some() {
+                ioctl(fd, UI_SET_EVBIT, EV_ABS);
+                ioctl(fd, UI_SET_ABSBIT, ABS_X);
+                ioctl(fd, UI_SET_ABSBIT, ABS_Y);
+            }
         }
 
         let mut usetup = UInputSetup {
             id: InputId {
                 bustype: BUS_USB,
-                // Random vendor and product
-                vendor: 0x2222,
-                product: 0x3333,
+                vendor: builder.vendor,
+                product: builder.product,
                 version: 0,
             },
             name: [0; UINPUT_MAX_NAME_SIZE],
             ff_effects_max: 0,
         };
 
-        let mut device_bytes: Vec<c_char> = "mouce-library-fake-mouse"
-            .chars()
-            .map(|ch| ch as c_char)
+        let mut device_bytes: Vec<c_char> = builder
+            .device_name
+            .bytes()
+            .take(UINPUT_MAX_NAME_SIZE - 1)
+            .map(|byte| byte as c_char)
             .collect();
 
         // Fill the rest of the name buffer with empty chars
@@ -79,89 +164,262 @@ impl UInputMouseManager {
 
         unsafe {
             ioctl(fd, UI_DEV_SETUP, &usetup);
+
+            // Size the absolute axes to the screen, so that the absolute
+            // values map 1:1 to the pixels on the screen
+            if let Some((width, height)) = device.screen_size {
+                for (code, maximum) in [(ABS_X, width - 1), (ABS_Y, height - 1)] {
+                    let abs_setup = UInputAbsSetup {
+                        code: code as u16,
+                        absinfo: InputAbsInfo {
+                            value: 0,
+                            minimum: 0,
+                            maximum,
+                            fuzz: 0,
+                            flat: 0,
+                            resolution: 0,
+                        },
+                    };
+                    ioctl(fd, UI_ABS_SETUP, &abs_setup);
+                }
+            }
+
             ioctl(fd, UI_DEV_CREATE);
         }
-
         // On UI_DEV_CREATE the kernel will create the device node for this
-        // device. We are inserting a pause here so that userspace has time
-        // to detect, initialize the new device, and can start listening to
-        // the event, otherwise it will not notice the event we are about to send.
-        thread::sleep(Duration::from_millis(300));
+        // device. Userspace needs time to detect, initialize the new device,
+        // and start listening to it, otherwise it will not notice the events
+        // we are about to send
+        device.own_device = wait_until_ready(fd, builder.uinput_ready_timeout);
 
-        manager
+        Ok(device)
     }
 
-    /// Write the given event to the uinput file
-    fn emit(&self, r#type: c_int, code: c_int, value: c_int) -> Result<(), Error> {
-        let mut event = InputEvent {
-            time: TimeVal {
-                tv_sec: 0,
-                tv_usec: 0,
-            },
-            r#type: r#type as u16,
-            code: code as u16,
-            value,
-        };
-        let fd = self.uinput_file.as_raw_fd();
+    /// Get the shared device with the options of the given builder, it is created
+    /// if the process does not have one yet
+    fn shared(builder: &MouseBuilder) -> Result<Arc<Self>, Error> {
+        let mut devices = SHARED_DEVICES.lock().unwrap();
+        // The devices that all of their managers dropped are already destroyed
+        devices.retain(|device| device.strong_count() > 0);
+        let shared = devices.iter().filter_map(Weak::upgrade).find(|device| {
+            device.name == builder.device_name
+                && device.vendor == builder.vendor
+                && device.product == builder.product
+        });
+        if let Some(device) = shared {
+            return Ok(device);
+        }
+
+        let device = Arc::new(UInputDevice::create(builder)?);
+        devices.push(Arc::downgrade(&device));
+        Ok(device)
+    }
+}
 
+impl Drop for UInputDevice {
+    fn drop(&mut self) {
+        let fd = self.file.as_raw_fd();
         unsafe {
-            let count = size_of::<InputEvent>();
-            let written_bytes = write(fd, &mut event, count);
-            if written_bytes == -1 || written_bytes != count as c_long {
-                return Err(Error::WriteFailed);
-            }
+            // Destroy the device, the file is closed automatically by the File module
+            ioctl(fd, UI_DEV_DESTROY as c_ulong);
         }
+    }
+}
 
-        Ok(())
+impl UInputMouseManager {
+    pub fn new(builder: &MouseBuilder) -> Result<Self, Error> {
+        let device = if builder.share_uinput_device {
+            UInputDevice::shared(builder)?
+        } else {
+            Arc::new(UInputDevice::create(builder)?)
+        };
+
+        let manager = UInputMouseManager {
+            device,
+            pressed_buttons: Mutex::new(ButtonState::new()),
+            callbacks: Arc::new(Mutex::new(CallbackMap::new())),
+            rewriters: Arc::new(Mutex::new(Rewriters::new())),
+            ignore_own_events: Arc::new(AtomicBool::new(false)),
+            listener: None,
+            rewrite_listener: None,
+            listener_queue: ListenerQueue::new(builder),
+            grabbed_devices: GrabbedDevices::default(),
+            unreadable_devices: UnreadableDevices::default(),
+            pacer: Pacer::new(&builder.pacing),
+            scroll_inversion: builder.scroll_inversion,
+        };
+        {
+            let mut callbacks = manager.callbacks.lock().unwrap();
+            callbacks.set_execution(builder.callback_execution);
+            callbacks.set_move_coalescing(builder.move_coalescing);
+        }
+
+        Ok(manager)
+    }
+
+    /// Write the given event to the uinput file
+    pub(super) fn emit(&self, r#type: c_int, code: c_int, value: c_int) -> Result<(), Error> {
+        super::emit(&self.device.file, r#type, code, value)
     }
 
-    /// Syncronize the device
+    /// Syncronize the device, the readers get the events of a report at
+    /// once, so the reports are what the pacing spaces out
     fn syncronize(&self) -> Result<(), Error> {
-        self.emit(EV_SYN, SYN_REPORT, 0)?;
-        // Give uinput some time to update the mouse location,
-        // otherwise it fails to move the mouse on release mode
-        // A delay of 1 milliseconds seems to be enough for it
-        thread::sleep(Duration::from_millis(1));
-        Ok(())
+        self.pacer.wait();
+        super::syncronize(&self.device.file)
     }
 
     /// Move the mouse relative to the current position
     fn move_relative(&self, x: i32, y: i32) -> Result<(), Error> {
-        // uinput does not move the mouse in pixels but uses `units`. I couldn't
-        // find information regarding to this uinput `unit`, but according to
-        // my findings 1 unit corresponds to exactly 2 pixels.
-        //
-        // To achieve the expected behavior; divide the parameters by 2
-        //
-        // This seems like there is a bug in this crate, but the
-        // behavior is the same on other projects that make use of
-        // uinput. e.g. `ydotool`. When you try to move your mouse,
-        // it will move 2x further pixels
-        self.emit(EV_REL, REL_X as i32, (x as f32 / 2.).ceil() as i32)?;
-        self.emit(EV_REL, REL_Y as i32, (y as f32 / 2.).ceil() as i32)?;
+        for (r#type, code, value) in relative_move_events(x, y) {
+            self.emit(r#type, code, value)?;
+        }
+        self.syncronize()?;
+        let mut state = self.device.state.lock().unwrap();
+        let (cursor_x, cursor_y) = state.cursor;
+        self.set_cursor(
+            &mut state,
+            cursor_x.saturating_add(x),
+            cursor_y.saturating_add(y),
+        );
+        Ok(())
+    }
+
+    /// Write the wheel events of scrolling by the given lines, see `HiResWheel::events`
+    fn scroll_lines(&self, dx: f64, dy: f64) -> Result<(), Error> {
+        // The remainders of the wheel are updated along with writing their events
+        let mut state = self.device.state.lock().unwrap();
+        for (r#type, code, value) in state.wheel.events(dx, dy) {
+            self.emit(r#type, code, value)?;
+        }
+        drop(state);
         self.syncronize()
     }
-}
 
-impl Drop for UInputMouseManager {
-    fn drop(&mut self) {
-        let fd = self.uinput_file.as_raw_fd();
-        unsafe {
-            // Destroy the device, the file is closed automatically by the File module
-            ioctl(fd, UI_DEV_DESTROY as c_ulong);
+    /// Update the emulated position of the cursor, the position is kept on the screen
+    fn set_cursor(&self, state: &mut DeviceState, x: i32, y: i32) {
+        let (x, y) = match self.device.screen_size {
+            Some((width, height)) => (x.clamp(0, width - 1), y.clamp(0, height - 1)),
+            None => (x.max(0), y.max(0)),
+        };
+        state.cursor = (x, y);
+    }
+
+    /// Append the reports of the given action to `events`, the `last_absolute_position`
+    /// of `state` is the absolute position that the previous actions of the batch moved to
+    fn batch_events(
+        &self,
+        action: &Action,
+        state: &mut DeviceState,
+        events: &mut Vec<(c_int, c_int, c_int)>,
+    ) -> Result<(), Error> {
+        let report = (EV_SYN, SYN_REPORT, 0);
+        match action {
+            Action::MoveTo(x, y) => {
+                let (x, y) = (*x as i32, *y as i32);
+                if self.device.screen_size.is_none() {
+                    // The same work around as `move_to`
+                    events.extend(relative_move_events(i32::MIN, i32::MIN));
+                    events.push(report);
+                    events.extend(relative_move_events(x, y));
+                } else {
+                    if let Some((nudge_x, nudge_y)) =
+                        absolute_nudge(state.last_absolute_position, x, y)
+                    {
+                        events.push((EV_ABS, ABS_X as c_int, nudge_x));
+                        events.push((EV_ABS, ABS_Y as c_int, nudge_y));
+                        events.push(report);
+                    }
+                    events.push((EV_ABS, ABS_X as c_int, x));
+                    events.push((EV_ABS, ABS_Y as c_int, y));
+                    state.last_absolute_position = Some((x, y));
+                }
+            }
+            Action::MoveRelative(x, y) => events.extend(relative_move_events(*x, *y)),
+            Action::Press(button) => events.push((EV_KEY, super::code_from_button(button)?, 1)),
+            Action::Release(button) => events.push((EV_KEY, super::code_from_button(button)?, 0)),
+            Action::Click(button) => {
+                let code = super::code_from_button(button)?;
+                events.push((EV_KEY, code, 1));
+                events.push(report);
+                events.push((EV_KEY, code, 0));
+            }
+            Action::Scroll(direction, unit, distance) => {
+                if *unit == ScrollUnit::Pixel {
+                    return Err(Error::NotImplemented);
+                }
+                let direction = self.scroll_inversion.direction(direction);
+                let (dx, dy) = super::wheel_lines(&direction, *distance as f64);
+                events.extend(state.wheel.events(dx, dy));
+            }
         }
+        events.push(report);
+        Ok(())
+    }
+}
+
+/// Get the events that move the mouse relative to the current position
+fn relative_move_events(x: i32, y: i32) -> [(c_int, c_int, c_int); 2] {
+    // uinput does not move the mouse in pixels but uses `units`. I couldn't
+    // find information regarding to this uinput `unit`, but according to
+    // my findings 1 unit corresponds to exactly 2 pixels.
+    //
+    // To achieve the expected behavior; divide the parameters by 2
+    //
+    // This seems like there is a bug in this crate, but the
+    // behavior is the same on other projects that make use of
+    // uinput. e.g. `ydotool`. When you try to move your mouse,
+    // it will move 2x further pixels
+    [
+        (EV_REL, REL_X as c_int, (x as f32 / 2.).ceil() as i32),
+        (EV_REL, REL_Y as c_int, (y as f32 / 2.).ceil() as i32),
+    ]
+}
+
+/// Get the position that the axes are nudged to before moving to `x`, `y`
+///
+/// The kernel drops the absolute events that have the same value as
+/// the previous one, but the mouse might have been moved since then by
+/// another device, so the axes are nudged to make sure the move is applied
+fn absolute_nudge(last_position: Option<(i32, i32)>, x: i32, y: i32) -> Option<(i32, i32)> {
+    let (last_x, last_y) = last_position?;
+    if last_x != x && last_y != y {
+        return None;
     }
+    Some((
+        if x > 0 { x - 1 } else { x + 1 },
+        if y > 0 { y - 1 } else { y + 1 },
+    ))
 }
 
 impl MouseActions for UInputMouseManager {
     fn move_to(&self, x: usize, y: usize) -> Result<(), Error> {
-        // For some reason, absolute mouse move events are not working on uinput
-        // (as I understand those events are intended for touch events)
-        //
-        // As a work around solution; first set the mouse to top left, then
-        // call relative move function to simulate an absolute move event
-        self.move_relative(i32::MIN, i32::MIN)?;
-        self.move_relative(x as i32, y as i32)
+        let (x, y) = (x as i32, y as i32);
+
+        if self.device.screen_size.is_none() {
+            // Without knowing the screen size the absolute axes can not be mapped
+            // to the pixels, so as a work around solution; first set the mouse to
+            // top left, then call relative move function to simulate an absolute
+            // move event
+            self.move_relative(i32::MIN, i32::MIN)?;
+            return self.move_relative(x, y);
+        }
+
+        // The position is kept locked until the move is written, so that the other
+        // managers of a shared device do not nudge from an outdated position
+        let mut state = self.device.state.lock().unwrap();
+        if let Some((nudge_x, nudge_y)) = absolute_nudge(state.last_absolute_position, x, y) {
+            self.emit(EV_ABS, ABS_X as c_int, nudge_x)?;
+            self.emit(EV_ABS, ABS_Y as c_int, nudge_y)?;
+            self.syncronize()?;
+        }
+
+        self.emit(EV_ABS, ABS_X as c_int, x)?;
+        self.emit(EV_ABS, ABS_Y as c_int, y)?;
+        self.syncronize()?;
+        state.last_absolute_position = Some((x, y));
+        self.set_cursor(&mut state, x, y);
+        Ok(())
     }
 
     fn move_relative(&self, x_offset: i32, y_offset: i32) -> Result<(), Error> {
@@ -169,28 +427,35 @@ impl MouseActions for UInputMouseManager {
     }
 
     fn get_position(&self) -> Result<(i32, i32), Error> {
-        // uinput does not let us get the current position of the mouse
-        Err(Error::NotImplemented)
+        // uinput does not let us get the current position of the mouse, so this
+        // is where the moves of this device put it, it drifts once the mouse
+        // is moved by the other devices or the pointer acceleration
+        Ok(self.device.state.lock().unwrap().cursor)
     }
 
     fn press_button(&self, button: &MouseButton) -> Result<(), Error> {
-        let btn = match button {
-            MouseButton::Left => BTN_LEFT,
-            MouseButton::Right => BTN_RIGHT,
-            MouseButton::Middle => BTN_MIDDLE,
-        };
+        let btn = super::code_from_button(button)?;
         self.emit(EV_KEY, btn, 1)?;
-        self.syncronize()
+        self.syncronize()?;
+
+        self.pressed_buttons.lock().unwrap().insert(button);
+        Ok(())
     }
 
     fn release_button(&self, button: &MouseButton) -> Result<(), Error> {
-        let btn = match button {
-            MouseButton::Left => BTN_LEFT,
-            MouseButton::Right => BTN_RIGHT,
-            MouseButton::Middle => BTN_MIDDLE,
-        };
+        let btn = super::code_from_button(button)?;
         self.emit(EV_KEY, btn, 0)?;
-        self.syncronize()
+        self.syncronize()?;
+
+        self.pressed_buttons.lock().unwrap().remove(button);
+        Ok(())
+    }
+
+    fn get_pressed_buttons(&self) -> Result<ButtonState, Error> {
+        let pressed_buttons = *self.pressed_buttons.lock().unwrap();
+        // Without access to the mice only the buttons of this device are known
+        let mice_buttons = super::get_evdev_pressed_buttons().unwrap_or_default();
+        Ok(mice_buttons.iter().chain(pressed_buttons.iter()).collect())
     }
 
     fn click_button(&self, button: &MouseButton) -> Result<(), Error> {
@@ -198,61 +463,345 @@ impl MouseActions for UInputMouseManager {
         self.release_button(button)
     }
 
-    fn scroll_wheel(&self, direction: &ScrollDirection) -> Result<(), Error> {
-        let (scroll_dir, scroll_value) = match direction {
-            ScrollDirection::Up => (REL_WHEEL, 1),
-            ScrollDirection::Down => (REL_WHEEL, -1),
-            ScrollDirection::Left => (REL_HWHEEL, -1),
-            ScrollDirection::Right => (REL_HWHEEL, 1),
-        };
-        self.emit(EV_REL, scroll_dir as c_int, scroll_value)?;
-        self.syncronize()
+    fn scroll_wheel(
+        &self,
+        direction: &ScrollDirection,
+        unit: ScrollUnit,
+        distance: u32,
+    ) -> Result<(), Error> {
+        if unit == ScrollUnit::Pixel {
+            // The wheel axes of uinput only report the wheel steps
+            return Err(Error::NotImplemented);
+        }
+
+        let direction = self.scroll_inversion.direction(direction);
+        let (dx, dy) = super::wheel_lines(&direction, distance as f64);
+        self.scroll_lines(dx, dy)
     }
 
-    fn hook(&mut self, callback: Box<dyn Fn(&MouseEvent) + Send>) -> Result<CallbackId, Error> {
-        if !self.is_listening {
-            super::start_nix_listener(&self.callbacks)?;
-            self.is_listening = true;
+    fn scroll_by(&self, dx: f64, dy: f64, unit: ScrollUnit) -> Result<(), Error> {
+        if unit == ScrollUnit::Pixel {
+            return Err(Error::NotImplemented);
         }
 
-        let id = self.callback_counter;
+        // Report both of the axes in the same frame, a positive wheel value scrolls up,
+        // the fractions of a line are reported by the high resolution wheels
+        let (dx, dy) = self.scroll_inversion.deltas(dx, dy);
+        self.scroll_lines(dx, -dy)
+    }
+
+    fn send_batch(&self, actions: &[Action]) -> Result<(), Error> {
+        // The state is only updated once the whole batch is written
+        let mut state = self.device.state.lock().unwrap();
+        let mut batch_state = *state;
+        let mut events = vec![];
+        for action in actions {
+            self.batch_events(action, &mut batch_state, &mut events)?;
+        }
+
+        if events.is_empty() {
+            return Ok(());
+        }
+
+        // All of the reports are written at once, so the batch is paced as a single report
+        self.pacer.wait();
+        super::emit_all(&self.device.file, &events)?;
+        state.last_absolute_position = batch_state.last_absolute_position;
+        state.wheel = batch_state.wheel;
+        let mut pressed_buttons = self.pressed_buttons.lock().unwrap();
+        for action in actions {
+            match action {
+                Action::MoveTo(x, y) => self.set_cursor(&mut state, *x as i32, *y as i32),
+                Action::MoveRelative(x, y) => {
+                    let (cursor_x, cursor_y) = state.cursor;
+                    self.set_cursor(
+                        &mut state,
+                        cursor_x.saturating_add(*x),
+                        cursor_y.saturating_add(*y),
+                    );
+                }
+                Action::Press(button) => pressed_buttons.insert(button),
+                Action::Release(button) | Action::Click(button) => pressed_buttons.remove(button),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn hook_with_info(&mut self, callback: EventCallback) -> Result<CallbackId, Error> {
+        if self.listener.is_none() {
+            self.listener = Some(super::start_nix_listener(
+                &self.callbacks,
+                &self.listener_queue,
+                &self.unreadable_devices,
+            )?);
+        }
+
+        // The flag can be changed after hooking, so it is checked on every event
+        let ignore_own_events = self.ignore_own_events.clone();
+        let own_device = self.device.own_device;
+        let callback: EventCallback = Box::new(move |event, info| {
+            let is_own = info.device.as_ref().map(|device| device.id) == own_device;
+            if !(is_own && ignore_own_events.load(Ordering::Relaxed)) {
+                callback(event, info);
+            }
+        });
+
+        let id = CallbackId::next();
         self.callbacks.lock().unwrap().insert(id, callback);
-        self.callback_counter += 1;
         Ok(id)
     }
 
+    fn set_ignore_own_events(&mut self, ignore: bool) -> Result<(), Error> {
+        // Without the event node the injected events can not be told apart
+        if self.device.own_device.is_none() {
+            return Err(Error::NotImplemented);
+        }
+        self.ignore_own_events.store(ignore, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn hook_rewrite(&mut self, callback: RewriteCallback) -> Result<CallbackId, Error> {
+        if self.rewrite_listener.is_none() {
+            // The rewritten events are written to this device
+            let uinput_file = match self.device.file.try_clone() {
+                Ok(file) => file,
+                Err(err) => return Err(Error::from_io("uinput file can not be cloned", err)),
+            };
+            self.rewrite_listener = Some(super::start_rewrite_listener(
+                uinput_file,
+                &self.rewriters,
+                &self.callbacks,
+                self.scroll_inversion,
+                &self.listener_queue,
+            )?);
+        }
+
+        let id = CallbackId::next();
+        self.rewriters.lock().unwrap().insert(id, callback);
+        Ok(id)
+    }
+
+    fn simulate_event(&self, event: &MouseEvent) -> Result<(), Error> {
+        simulate_event(&self.callbacks, event);
+        Ok(())
+    }
+
+    fn callback_panics(&mut self) -> Result<Receiver<Error>, Error> {
+        Ok(self.callbacks.lock().unwrap().panic_receiver())
+    }
+
+    fn dropped_events(&self) -> Result<u64, Error> {
+        Ok(self.listener_queue.dropped())
+    }
+
+    fn unreadable_devices(&self) -> Result<Vec<(String, Error)>, Error> {
+        Ok(self.unreadable_devices.list())
+    }
+
+    fn grab_device(&mut self, device_id: DeviceId) -> Result<(), Error> {
+        self.grabbed_devices
+            .grab(device_id, &self.callbacks, &self.listener_queue)
+    }
+
+    fn release_device(&mut self, device_id: DeviceId) -> Result<(), Error> {
+        self.grabbed_devices.release(device_id)
+    }
+
     fn unhook(&mut self, callback_id: CallbackId) -> Result<(), Error> {
-        match self.callbacks.lock().unwrap().remove(&callback_id) {
-            Some(_) => Ok(()),
+        let mut rewriters = self.rewriters.lock().unwrap();
+        if rewriters.remove(&callback_id).is_some() {
+            // Stopping the rewriting gives the mice back to the system
+            if rewriters.is_empty() {
+                drop(rewriters);
+                if let Some(listener) = self.rewrite_listener.take() {
+                    listener.stop();
+                }
+            }
+            return Ok(());
+        }
+        drop(rewriters);
+        let removed = self.callbacks.lock().unwrap().remove(&callback_id);
+        match removed {
+            Some(_) => {
+                super::stop_idle_listener(&mut self.listener, &self.callbacks);
+                Ok(())
+            }
             None => Err(Error::UnhookFailed),
         }
     }
 
     fn unhook_all(&mut self) -> Result<(), Error> {
         self.callbacks.lock().unwrap().clear();
+        self.rewriters.lock().unwrap().clear();
+        // Stopping the rewriting gives the mice back to the system
+        if let Some(listener) = self.rewrite_listener.take() {
+            listener.stop();
+        }
+        super::stop_idle_listener(&mut self.listener, &self.callbacks);
+        Ok(())
+    }
+
+    fn pause_listening(&mut self) -> Result<(), Error> {
+        self.callbacks.lock().unwrap().paused = true;
+        Ok(())
+    }
+
+    fn resume_listening(&mut self) -> Result<(), Error> {
+        self.callbacks.lock().unwrap().paused = false;
+        Ok(())
+    }
+
+    fn stop_listening(&mut self) -> Result<(), Error> {
+        self.callbacks.lock().unwrap().clear();
+        self.rewriters.lock().unwrap().clear();
+        // Stopping the rewriting releases the grabbed mice as well
+        let listeners = [self.listener.take(), self.rewrite_listener.take()];
+        for listener in listeners.into_iter().flatten() {
+            listener.stop();
+        }
+        self.grabbed_devices.release_all();
         Ok(())
     }
 }
 
+/// Get the identity of the event node of the uinput device with the given
+/// file descriptor, it is the device number like the listeners report
+fn event_node_device(fd: c_int) -> Option<DeviceId> {
+    let mut sysname = [0u8; 64];
+    if unsafe { ioctl(fd, ui_get_sysname(sysname.len()), sysname.as_mut_ptr()) } < 0 {
+        return None;
+    }
+    let sysname = CStr::from_bytes_until_nul(&sysname).ok()?.to_str().ok()?;
+
+    // The input device has a single event node, e.g. `input42/event17`
+    let directory = format!("/sys/devices/virtual/input/{}", sysname);
+    let event_node = fs::read_dir(directory)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .find(|name| name.starts_with("event"))?;
+    let metadata = fs::metadata(format!("/dev/input/{}", event_node)).ok()?;
+    Some(DeviceId(metadata.rdev()))
+}
+
+/// Wait until the device with the given file descriptor is picked up by userspace or
+/// the timeout passes, returns the identity of its event node if it has one by then
+///
+/// The device is ready once udev has processed its event node, libinput and the
+/// compositors are told about the device after that. Without udev the event node
+/// is all there is to wait for
+fn wait_until_ready(fd: c_int, timeout: Duration) -> Option<DeviceId> {
+    let deadline = Instant::now() + timeout;
+    let has_udev = Path::new(UDEV_DATA_DIRECTORY).exists();
+    loop {
+        let device = event_node_device(fd);
+        if let Some(id) = device {
+            let (major, minor) = super::device_numbers(id);
+            let udev_data = format!("{}/c{}:{}", UDEV_DATA_DIRECTORY, major, minor);
+            if !has_udev || Path::new(&udev_data).exists() {
+                // Give the listeners of udev a moment to open the device
+                thread::sleep(
+                    READY_SETTLE_TIME.min(deadline.saturating_duration_since(Instant::now())),
+                );
+                return device;
+            }
+        }
+        if Instant::now() >= deadline {
+            return device;
+        }
+        thread::sleep(READY_POLL_INTERVAL);
+    }
+}
+
+/// Get the UI_GET_SYSNAME ioctl request for a buffer of the given length
+fn ui_get_sysname(length: usize) -> c_ulong {
+    // _IOC(_IOC_READ, 'U', 44, length)
+    (2 << 30) | ((length as c_ulong) << 16) | ((b'U' as c_ulong) << 8) | 44
+}
+
+/// Detect the size of the screen from the sizes of the outputs
+///
+/// If there are multiple outputs, they are assumed to be placed side by side
+fn screen_size() -> Option<(i32, i32)> {
+    output_sizes()
+        .into_iter()
+        .reduce(|(total_width, max_height), (width, height)| {
+            (total_width + width, max_height.max(height))
+        })
+}
+
+/// Detect the sizes of the connected outputs by reading
+/// their modes under /sys/class/drm
+pub(super) fn output_sizes() -> Vec<(i32, i32)> {
+    let mut sizes = Vec::new();
+    let status_paths = match glob("/sys/class/drm/*/status") {
+        Ok(status_paths) => status_paths,
+        Err(_) => return sizes,
+    };
+
+    for status_path in status_paths.flatten() {
+        match fs::read_to_string(&status_path) {
+            Ok(status) if status.trim() == "connected" => {}
+            _ => continue,
+        }
+
+        // The first mode is the preferred (current) mode of the output
+        let modes = match fs::read_to_string(status_path.with_file_name("modes")) {
+            Ok(modes) => modes,
+            Err(_) => continue,
+        };
+        let mode = match modes.lines().next() {
+            Some(mode) => mode,
+            None => continue,
+        };
+        let (width, height) = match mode.split_once('x') {
+            Some((width, height)) => (width.parse::<i32>(), height.parse::<i32>()),
+            None => continue,
+        };
+
+        if let (Ok(width), Ok(height)) = (width, height) {
+            sizes.push((width, height));
+        }
+    }
+
+    sizes
+}
+
 /// ioctl and uinput definitions
 const UI_SET_EVBIT: c_ulong = 1074025828;
 const UI_SET_KEYBIT: c_ulong = 1074025829;
 const UI_SET_RELBIT: c_ulong = 1074025830;
+const UI_SET_ABSBIT: c_ulong = 1074025831;
+const UI_ABS_SETUP: c_ulong = 1075598596;
 const UI_DEV_SETUP: c_ulong = 1079792899;
 const UI_DEV_CREATE: c_ulong = 21761;
 const UI_DEV_DESTROY: c_uint = 21762;
 
 pub const EV_KEY: c_int = 0x01;
 pub const EV_REL: c_int = 0x02;
+pub const EV_ABS: c_int = 0x03;
+pub const ABS_X: c_uint = 0x00;
+pub const ABS_Y: c_uint = 0x01;
 pub const REL_X: c_uint = 0x00;
 pub const REL_Y: c_uint = 0x01;
 pub const REL_WHEEL: c_uint = 0x08;
 pub const REL_HWHEEL: c_uint = 0x06;
+pub const REL_WHEEL_HI_RES: c_uint = 0x0b;
+pub const REL_HWHEEL_HI_RES: c_uint = 0x0c;
+/// The high resolution wheel events report a notch as 120 units
+pub const HI_RES_UNITS_PER_NOTCH: c_int = 120;
 pub const BTN_LEFT: c_int = 0x110;
 pub const BTN_RIGHT: c_int = 0x111;
 pub const BTN_MIDDLE: c_int = 0x112;
-const SYN_REPORT: c_int = 0x00;
-const EV_SYN: c_int = 0x00;
+pub const BTN_SIDE: c_int = 0x113;
+pub const BTN_EXTRA: c_int = 0x114;
+pub const BTN_FORWARD: c_int = 0x115;
+/// The first code after the mouse buttons
+pub const BTN_JOYSTICK: c_int = 0x120;
+pub const SYN_REPORT: c_int = 0x00;
+pub const SYN_DROPPED: c_int = 0x03;
+pub const EV_SYN: c_int = 0x00;
 const BUS_USB: c_ushort = 0x03;
 
 /// uinput types
@@ -263,6 +812,22 @@ struct UInputSetup {
     ff_effects_max: c_ulong,
 }
 
+#[repr(C)]
+struct UInputAbsSetup {
+    code: u16,
+    absinfo: InputAbsInfo,
+}
+
+#[repr(C)]
+struct InputAbsInfo {
+    value: i32,
+    minimum: i32,
+    maximum: i32,
+    fuzz: i32,
+    flat: i32,
+    resolution: i32,
+}
+
 #[repr(C)]
 struct InputId {
     bustype: c_ushort,
@@ -286,6 +851,5 @@ pub struct TimeVal {
 }
 
 extern "C" {
-    fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
-    fn write(fd: c_int, buf: *mut InputEvent, count: usize) -> c_long;
+    pub(super) fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
 }
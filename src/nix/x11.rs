@@ -2,69 +2,189 @@
 /// This module contains the mouse action functions
 /// for the unix-like systems that use X11
 ///
-use crate::common::{CallbackId, MouseActions, MouseButton, MouseEvent, ScrollDirection};
+use crate::builder::MouseBuilder;
+use crate::common::{
+    simulate_event, ButtonState, CallbackId, CallbackMap, DeviceId, EventCallback, MouseActions,
+    MouseButton, MouseEvent, Region, ScrollDirection, ScrollInversion, ScrollUnit,
+};
+use crate::display::{closest_monitor, Monitor};
 use crate::error::Error;
-use crate::nix::Callbacks;
-use std::collections::HashMap;
-use std::os::raw::{c_char, c_int, c_uint, c_ulong};
-use std::sync::{Arc, Mutex};
+use crate::nix::listener::{Listener, ListenerQueue};
+use crate::nix::{Callbacks, GrabbedDevices, UnreadableDevices};
+use crate::pacing::Pacer;
+use std::cell::{Cell, RefCell};
+use std::ffi::CString;
+use std::os::raw::{c_char, c_int, c_long, c_uchar, c_uint, c_ulong, c_ushort, c_void};
+use std::ptr::{null, null_mut};
+use std::rc::Rc;
+use std::slice;
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex, Once, OnceLock};
+
+/// The number of pixels that a wheel step scrolls in most of the X11 toolkits
+pub(super) const PIXELS_PER_WHEEL_STEP: i32 = 50;
+
+/// The state of the connections that the library opened, Xlib reports the
+/// errors of all the connections of the process to a single handler
+static CONNECTIONS: Mutex<Vec<Connection>> = Mutex::new(vec![]);
+/// The error handler of the process before the library installed its own,
+/// it still handles the errors of the connections of the other libraries
+static PREVIOUS_ERROR_HANDLER: OnceLock<XErrorHandler> = OnceLock::new();
+static INSTALL_ERROR_HANDLER: Once = Once::new();
+
+struct Connection {
+    /// The address of the display of the connection
+    display: usize,
+    /// The code of the first error since the last check
+    error: Option<u8>,
+    /// Xlib can not use the connection anymore once it is lost
+    lost: bool,
+}
 
 pub struct X11MouseManager {
-    display: *mut Display,
-    window: Window,
+    /// The connection is opened again once it is lost, e.g. because the X server restarted
+    connection: RefCell<Rc<DisplayConnection>>,
+    /// The name that the display is opened with, the listeners open it as well
+    display_name: Option<String>,
+    screen: c_int,
+    /// The root window of the screen
+    window: Cell<Window>,
+    // The vertical and horizontal pixel distances that did not add up to a wheel step yet
+    pixel_remainders: Cell<[i32; 2]>,
+    // XFixes counts the hide requests, so only the first one is sent
+    cursor_hidden: Cell<bool>,
+    // The pointer barriers around the region that the cursor is clipped to
+    barriers: RefCell<Vec<PointerBarrier>>,
+    // The monitors of the screen, they are queried again once XRandR reports a change of them
+    monitors: RefCell<Option<Vec<Monitor>>>,
     callbacks: Callbacks,
-    callback_counter: CallbackId,
-    is_listening: bool,
+    listener: Option<Listener>,
+    listener_queue: ListenerQueue,
+    grabbed_devices: GrabbedDevices,
+    unreadable_devices: UnreadableDevices,
+    pacer: Pacer,
+    scroll_inversion: ScrollInversion,
 }
 
 impl X11MouseManager {
-    pub fn new() -> Self {
-        unsafe {
-            let display = XOpenDisplay(&0);
-            let window = XDefaultRootWindow(display);
-            X11MouseManager {
-                display,
-                window,
-                callbacks: Arc::new(Mutex::new(HashMap::new())),
-                callback_counter: 0,
-                is_listening: false,
-            }
+    /// Connect to the given display (e.g. `:1`), or the one of the `DISPLAY` environment variable,
+    /// and control the pointer on the given screen of it, or on the default screen of the display
+    pub fn open(display_name: Option<&str>, screen: Option<i32>) -> Result<Self, Error> {
+        let (connection, screen, window) = connect(display_name, screen)?;
+        Ok(X11MouseManager {
+            connection: RefCell::new(connection),
+            display_name: display_name.map(String::from),
+            screen,
+            window: Cell::new(window),
+            pixel_remainders: Cell::new([0, 0]),
+            cursor_hidden: Cell::new(false),
+            barriers: RefCell::new(vec![]),
+            monitors: RefCell::new(None),
+            callbacks: Arc::new(Mutex::new(CallbackMap::new())),
+            listener: None,
+            listener_queue: ListenerQueue::new(&MouseBuilder::new()),
+            grabbed_devices: GrabbedDevices::default(),
+            unreadable_devices: UnreadableDevices::default(),
+            pacer: Pacer::default(),
+            scroll_inversion: ScrollInversion::NONE,
+        })
+    }
+
+    /// Get the display of the connection, a lost connection is opened again first
+    ///
+    /// The call that runs into the loss of the connection still fails, since
+    /// its requests can not be sent again once the X server restarted
+    fn display(&self) -> Result<*mut Display, Error> {
+        let mut connection = self.connection.borrow_mut();
+        if is_connection_lost(connection.as_ptr()) {
+            let (reconnected, _, window) =
+                connect(self.display_name.as_deref(), Some(self.screen))?;
+            *connection = reconnected;
+            self.window.set(window);
+            // The X server destroyed the barriers and showed the cursor with the lost connection
+            self.barriers.borrow_mut().clear();
+            self.cursor_hidden.set(false);
+            self.monitors.replace(None);
         }
+        Ok(connection.as_ptr())
     }
 
-    fn button_event(&self, button: &MouseButton, is_press: bool) -> Result<(), Error> {
-        let btn = match button {
-            MouseButton::Left => 1,
-            MouseButton::Middle => 2,
-            MouseButton::Right => 3,
+    /// Get the monitors of the screen, the layout is only queried again once
+    /// XRandR reports that it changed or the position is not on any of the monitors
+    fn monitors_at(&self, display: *mut Display, position: (i32, i32)) -> Vec<Monitor> {
+        let window = self.window.get();
+        let mut monitors = self.monitors.borrow_mut();
+        let changed = unsafe { screen_changed(display) };
+        let stale = match monitors.as_ref() {
+            Some(monitors) => {
+                changed
+                    || !monitors
+                        .iter()
+                        .any(|monitor| monitor.bounds.contains(position))
+            }
+            None => true,
         };
-        unsafe {
-            XTestFakeButtonEvent(self.display, btn, is_press, 0);
-            XFlush(self.display);
+        if stale {
+            *monitors = unsafe { xrandr_monitors(display, window) };
         }
-        Ok(())
+        monitors.clone().unwrap_or_default()
     }
-}
 
-impl MouseActions for X11MouseManager {
-    fn move_to(&self, x: usize, y: usize) -> Result<(), Error> {
+    /// Apply the pacing, the scroll inversion and the dispatch options of the given builder
+    pub(super) fn with_options(mut self, builder: &MouseBuilder) -> Self {
+        self.pacer = Pacer::new(&builder.pacing);
+        self.scroll_inversion = builder.scroll_inversion;
+        self.listener_queue = ListenerQueue::new(builder);
+        let mut callbacks = self.callbacks.lock().unwrap();
+        callbacks.set_execution(builder.callback_execution);
+        callbacks.set_move_coalescing(builder.move_coalescing);
+        drop(callbacks);
+        self
+    }
+
+    fn button_event(&self, button: &MouseButton, is_press: bool) -> Result<(), Error> {
+        let number = button_number(button)?;
+        let display = self.display()?;
+        self.pacer.wait();
         unsafe {
-            XWarpPointer(self.display, 0, self.window, 0, 0, 0, 0, x as i32, y as i32);
-            XFlush(self.display);
+            XTestFakeButtonEvent(display, number, is_press, 0);
         }
-        Ok(())
+        sync_display(display, "failed to fake the button event")
     }
 
-    fn get_position(&self) -> Result<(i32, i32), Error> {
+    /// Convert the given pixel distance to wheel steps, XTest can only fake the
+    /// wheel buttons so the remainders are kept to let the small distances add up
+    fn pixels_to_steps(&self, direction: &ScrollDirection, pixels: u32) -> u32 {
+        let (axis, sign) = match direction {
+            ScrollDirection::Up => (0, -1),
+            ScrollDirection::Down => (0, 1),
+            ScrollDirection::Left => (1, -1),
+            ScrollDirection::Right => (1, 1),
+        };
+
+        let mut remainders = self.pixel_remainders.get();
+        let total = remainders[axis].saturating_add(sign * pixels.min(i32::MAX as u32) as i32);
+        let steps = total / PIXELS_PER_WHEEL_STEP;
+        remainders[axis] = total % PIXELS_PER_WHEEL_STEP;
+        self.pixel_remainders.set(remainders);
+
+        // The steps are towards the opposite direction only if the
+        // distance did not cover the remainder of the previous scrolls
+        (steps * sign).max(0) as u32
+    }
+
+    /// Get the position of the pointer and the state of the buttons and the modifier keys
+    fn query_pointer(&self) -> Result<(i32, i32, c_uint), Error> {
         let mut x = 0;
         let mut y = 0;
         let mut void = 0;
         let mut mask = 0;
 
+        let display = self.display()?;
         unsafe {
             let out = XQueryPointer(
-                self.display,
-                self.window,
+                display,
+                self.window.get(),
                 &mut void,
                 &mut void,
                 &mut x,
@@ -76,14 +196,80 @@ impl MouseActions for X11MouseManager {
 
             // If XQueryPointer returns False (which is an enum value that corresponds to 0)
             // that means the pointer is not on the same screen as the specified window
+            check_display(display, "failed to query the pointer")?;
             if out == 0 {
                 return Err(Error::X11PointerWindowMismatch);
             }
         }
 
+        Ok((x, y, mask))
+    }
+
+    /// Fake a motion to the given position followed by the given button events,
+    /// all of them are flushed together so they reach the server at once
+    fn button_events_at(
+        &self,
+        x: usize,
+        y: usize,
+        button: &MouseButton,
+        presses: &[bool],
+    ) -> Result<(), Error> {
+        let number = button_number(button)?;
+        let display = self.display()?;
+        self.pacer.wait();
+        unsafe {
+            XTestFakeMotionEvent(display, self.screen, x as c_int, y as c_int, 0);
+            for is_press in presses {
+                XTestFakeButtonEvent(display, number, *is_press, 0);
+            }
+        }
+        sync_display(display, "failed to fake the button event")
+    }
+}
+
+impl MouseActions for X11MouseManager {
+    fn move_to(&self, x: usize, y: usize) -> Result<(), Error> {
+        // The parts of the screen that no monitor shows can not be reached,
+        // e.g. the area below a monitor that is smaller than its neighbour
+        let position = (
+            x.min(i32::MAX as usize) as i32,
+            y.min(i32::MAX as usize) as i32,
+        );
+        let display = self.display()?;
+        let window = self.window.get();
+        let monitors = self.monitors_at(display, position);
+        let (x, y) = match closest_monitor(&monitors, position) {
+            Some(monitor) => monitor.bounds.clamp(position),
+            None => position,
+        };
+        self.pacer.wait();
+        unsafe {
+            XWarpPointer(display, 0, window, 0, 0, 0, 0, x, y);
+        }
+        sync_display(display, "failed to move the pointer")
+    }
+
+    fn get_position(&self) -> Result<(i32, i32), Error> {
+        let (x, y, _) = self.query_pointer()?;
         Ok((x, y))
     }
 
+    fn get_pressed_buttons(&self) -> Result<ButtonState, Error> {
+        let (_, _, mask) = self.query_pointer()?;
+        // Button1Mask is 1 << 8, the following buttons have the following bits,
+        // the core protocol only has masks for the first five buttons so the
+        // state of the side buttons is not available
+        Ok([
+            (MouseButton::Left, 1 << 8),
+            (MouseButton::Middle, 1 << 9),
+            (MouseButton::Right, 1 << 10),
+        ]
+        .into_iter()
+        .filter(|(_, button_mask)| mask & button_mask != 0)
+        .map(|(button, _)| button)
+        .collect())
+    }
+
     fn press_button(&self, button: &MouseButton) -> Result<(), Error> {
         self.button_event(button, true)
     }
@@ -97,79 +283,622 @@ impl MouseActions for X11MouseManager {
         self.release_button(button)
     }
 
-    fn scroll_wheel(&self, direction: &ScrollDirection) -> Result<(), Error> {
+    fn press_at(&self, x: usize, y: usize, button: &MouseButton) -> Result<(), Error> {
+        self.button_events_at(x, y, button, &[true])
+    }
+
+    fn release_at(&self, x: usize, y: usize, button: &MouseButton) -> Result<(), Error> {
+        self.button_events_at(x, y, button, &[false])
+    }
+
+    fn click_at(&self, x: usize, y: usize, button: &MouseButton) -> Result<(), Error> {
+        self.button_events_at(x, y, button, &[true, false])
+    }
+
+    fn scroll_wheel(
+        &self,
+        direction: &ScrollDirection,
+        unit: ScrollUnit,
+        distance: u32,
+    ) -> Result<(), Error> {
+        let direction = &self.scroll_inversion.direction(direction);
         let btn = match direction {
             ScrollDirection::Up => 4,
             ScrollDirection::Down => 5,
             ScrollDirection::Left => 6,
             ScrollDirection::Right => 7,
         };
-        unsafe {
-            XTestFakeButtonEvent(self.display, btn, true, 0);
-            XTestFakeButtonEvent(self.display, btn, false, 0);
-            XFlush(self.display);
+        let steps = match unit {
+            ScrollUnit::Line => distance,
+            ScrollUnit::Pixel => self.pixels_to_steps(direction, distance),
+        };
+        // Each step is flushed on its own, so the pacing spaces the steps out
+        let display = self.display()?;
+        for _ in 0..steps {
+            self.pacer.wait();
+            unsafe {
+                XTestFakeButtonEvent(display, btn, true, 0);
+                XTestFakeButtonEvent(display, btn, false, 0);
+            }
+            sync_display(display, "failed to fake the wheel event")?;
+        }
+        Ok(())
+    }
+
+    fn hide_cursor(&self) -> Result<(), Error> {
+        // The cursor is shown again once the connection is closed
+        let display = self.display()?;
+        if !self.cursor_hidden.replace(true) {
+            unsafe {
+                XFixesHideCursor(display, self.window.get());
+            }
+            return sync_display(display, "failed to hide the cursor");
         }
         Ok(())
     }
 
-    fn hook(&mut self, callback: Box<dyn Fn(&MouseEvent) + Send>) -> Result<CallbackId, Error> {
-        if !self.is_listening {
-            super::start_nix_listener(&self.callbacks)?;
-            self.is_listening = true;
+    fn show_cursor(&self) -> Result<(), Error> {
+        let display = self.display()?;
+        if self.cursor_hidden.replace(false) {
+            unsafe {
+                XFixesShowCursor(display, self.window.get());
+            }
+            return sync_display(display, "failed to show the cursor");
+        }
+        Ok(())
+    }
+
+    fn clip_cursor(&self, region: Option<Region>) -> Result<(), Error> {
+        if region.is_some_and(|region| region.is_empty()) {
+            return Err(Error::CustomError("the region is empty"));
+        }
+
+        if let Some(region) = region {
+            // The barriers do not hold a cursor that is already outside
+            let (x, y) = self.get_position()?;
+            let (clamped_x, clamped_y) = region.clamp((x, y));
+            if (clamped_x, clamped_y) != (x, y) {
+                self.move_to(clamped_x as usize, clamped_y as usize)?;
+            }
+        }
+
+        let display = self.display()?;
+        let mut barriers = self.barriers.borrow_mut();
+        unsafe {
+            for barrier in barriers.drain(..) {
+                XFixesDestroyPointerBarrier(display, barrier);
+            }
+            if let Some(region) = region {
+                // Each edge only lets the cursor pass towards the inside of the region
+                let left = region.x;
+                let top = region.y;
+                let right = region
+                    .x
+                    .saturating_add(region.width.min(i32::MAX as u32) as i32);
+                let bottom = region
+                    .y
+                    .saturating_add(region.height.min(i32::MAX as u32) as i32);
+                for (x1, y1, x2, y2, direction) in [
+                    (left, top, left, bottom, BARRIER_POSITIVE_X),
+                    (right, top, right, bottom, BARRIER_NEGATIVE_X),
+                    (left, top, right, top, BARRIER_POSITIVE_Y),
+                    (left, bottom, right, bottom, BARRIER_NEGATIVE_Y),
+                ] {
+                    barriers.push(XFixesCreatePointerBarrier(
+                        display,
+                        self.window.get(),
+                        x1,
+                        y1,
+                        x2,
+                        y2,
+                        direction,
+                        0,
+                        null(),
+                    ));
+                }
+            }
+        }
+        sync_display(display, "failed to clip the cursor")
+    }
+
+    fn hook_with_info(&mut self, callback: EventCallback) -> Result<CallbackId, Error> {
+        // The listeners finish by themselves once their connection is lost, so
+        // they are started again with a new connection
+        if self
+            .listener
+            .as_ref()
+            .is_none_or(|listener| listener.is_finished())
+        {
+            // XInput2 raw events do not need any special privileges, fall back
+            // to XRecord and only read the event files if neither is available
+            let display_name = self.display_name.as_deref();
+            let listener = super::xinput2::start_xinput2_listener(&self.callbacks, display_name)
+                .or_else(|_| super::xrecord::start_xrecord_listener(&self.callbacks, display_name))
+                .or_else(|_| {
+                    super::start_nix_listener(
+                        &self.callbacks,
+                        &self.listener_queue,
+                        &self.unreadable_devices,
+                    )
+                })?;
+            self.listener = Some(listener);
         }
 
-        let id = self.callback_counter;
+        let id = CallbackId::next();
         self.callbacks.lock().unwrap().insert(id, callback);
-        self.callback_counter += 1;
         Ok(id)
     }
 
+    fn simulate_event(&self, event: &MouseEvent) -> Result<(), Error> {
+        simulate_event(&self.callbacks, event);
+        Ok(())
+    }
+
+    fn callback_panics(&mut self) -> Result<Receiver<Error>, Error> {
+        Ok(self.callbacks.lock().unwrap().panic_receiver())
+    }
+
+    fn dropped_events(&self) -> Result<u64, Error> {
+        Ok(self.listener_queue.dropped())
+    }
+
+    fn unreadable_devices(&self) -> Result<Vec<(String, Error)>, Error> {
+        Ok(self.unreadable_devices.list())
+    }
+
+    fn grab_device(&mut self, device_id: DeviceId) -> Result<(), Error> {
+        self.grabbed_devices
+            .grab(device_id, &self.callbacks, &self.listener_queue)
+    }
+
+    fn release_device(&mut self, device_id: DeviceId) -> Result<(), Error> {
+        self.grabbed_devices.release(device_id)
+    }
+
     fn unhook(&mut self, callback_id: CallbackId) -> Result<(), Error> {
-        match self.callbacks.lock().unwrap().remove(&callback_id) {
-            Some(_) => Ok(()),
+        let removed = self.callbacks.lock().unwrap().remove(&callback_id);
+        match removed {
+            Some(_) => {
+                super::stop_idle_listener(&mut self.listener, &self.callbacks);
+                Ok(())
+            }
             None => Err(Error::UnhookFailed),
         }
     }
 
     fn unhook_all(&mut self) -> Result<(), Error> {
         self.callbacks.lock().unwrap().clear();
+        super::stop_idle_listener(&mut self.listener, &self.callbacks);
         Ok(())
     }
+
+    fn pause_listening(&mut self) -> Result<(), Error> {
+        self.callbacks.lock().unwrap().paused = true;
+        Ok(())
+    }
+
+    fn resume_listening(&mut self) -> Result<(), Error> {
+        self.callbacks.lock().unwrap().paused = false;
+        Ok(())
+    }
+
+    fn stop_listening(&mut self) -> Result<(), Error> {
+        self.callbacks.lock().unwrap().clear();
+        if let Some(listener) = self.listener.take() {
+            listener.stop();
+        }
+        self.grabbed_devices.release_all();
+        Ok(())
+    }
+}
+
+/// Get the X11 button number of the given button, 4-7 are the scroll wheel
+fn button_number(button: &MouseButton) -> Result<c_uint, Error> {
+    match button {
+        MouseButton::Left => Ok(1),
+        MouseButton::Middle => Ok(2),
+        MouseButton::Right => Ok(3),
+        MouseButton::Back => Ok(8),
+        MouseButton::Forward => Ok(9),
+        MouseButton::Other(index) => match 10 + *index as c_uint {
+            number if number <= 255 => Ok(number),
+            _ => Err(Error::CustomError("the button has no X11 button number")),
+        },
+    }
+}
+
+/// Check whether the X server is Xwayland, which advertises the XWAYLAND extension
+pub(super) fn is_xwayland() -> bool {
+    with_display(|display| unsafe {
+        let mut opcode = 0;
+        let mut event = 0;
+        let mut error = 0;
+        let name = CString::new("XWAYLAND").unwrap();
+        XQueryExtension(display, name.as_ptr(), &mut opcode, &mut event, &mut error) != 0
+    })
+    .unwrap_or(false)
+}
+
+/// Check whether the X server accelerates the pointer
+pub(super) fn pointer_acceleration() -> Result<bool, Error> {
+    with_display(|display| unsafe {
+        let mut numerator = 0;
+        let mut denominator = 0;
+        let mut threshold = 0;
+        XGetPointerControl(display, &mut numerator, &mut denominator, &mut threshold);
+        // The pointer moves numerator / denominator times as fast past the threshold
+        numerator > denominator
+    })
+}
+
+/// Enable or disable the pointer acceleration of the X server
+pub(super) fn set_pointer_acceleration(enabled: bool) -> Result<(), Error> {
+    // These are the defaults of the X server, twice as fast past 4 pixels
+    let (numerator, threshold) = if enabled { (2, 4) } else { (1, 0) };
+    with_display(|display| unsafe {
+        XChangePointerControl(display, 1, 1, numerator, 1, threshold);
+        XFlush(display);
+    })
+}
+
+/// Get the monitors of the X server from XRandR, the whole screen is
+/// reported as a single monitor if XRandR is not available
+pub(super) fn monitors() -> Result<Vec<Monitor>, Error> {
+    with_display(|display| unsafe {
+        match xrandr_monitors(display, XDefaultRootWindow(display)) {
+            Some(monitors) if !monitors.is_empty() => monitors,
+            _ => {
+                let screen = XDefaultScreen(display);
+                vec![Monitor {
+                    id: 0,
+                    bounds: Region::new(
+                        0,
+                        0,
+                        XDisplayWidth(display, screen).max(0) as u32,
+                        XDisplayHeight(display, screen).max(0) as u32,
+                    ),
+                    // The root window is in physical pixels, Xft.dpi only scales the toolkits
+                    scale_factor: 1.,
+                    primary: true,
+                }]
+            }
+        }
+    })
+}
+
+/// The area of the screen that an enabled CRTC of XRandR shows
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct CrtcArea {
+    /// The area of the screen, the rotation is already applied to it
+    bounds: Region,
+    rotation: c_ushort,
+    /// The size of the mode of the CRTC in physical pixels, before the rotation
+    mode_size: Option<(u32, u32)>,
+    /// Whether the primary output is connected to the CRTC
+    primary: bool,
+}
+
+/// Whether XRandR reported a change of the screen since the last check, the
+/// events are only sent to the connections that selected them on the root window
+unsafe fn screen_changed(display: *mut Display) -> bool {
+    let mut event_base = 0;
+    let mut error_base = 0;
+    if XRRQueryExtension(display, &mut event_base, &mut error_base) == 0 {
+        return false;
+    }
+
+    // The queued events are drained, a single query covers all of them
+    let mut event: [c_long; 24] = [0; 24];
+    let mut changed = false;
+    while XCheckTypedEvent(
+        display,
+        event_base + RR_SCREEN_CHANGE_NOTIFY,
+        event.as_mut_ptr() as *mut c_void,
+    ) != 0
+    {
+        changed = true;
+    }
+    changed
+}
+
+/// Get the monitors of the screen of the given root window from the CRTCs
+/// of XRandR, `None` if the X server does not support XRandR
+unsafe fn xrandr_monitors(display: *mut Display, window: Window) -> Option<Vec<Monitor>> {
+    let mut event_base = 0;
+    let mut error_base = 0;
+    if XRRQueryExtension(display, &mut event_base, &mut error_base) == 0 {
+        return None;
+    }
+    // The changes of the layout are reported with RRScreenChangeNotify from now on
+    XRRSelectInput(display, window, RR_SCREEN_CHANGE_NOTIFY_MASK);
+    // The current resources are the ones that the server already knows, they do not probe the outputs
+    let resources = XRRGetScreenResourcesCurrent(display, window);
+    if resources.is_null() {
+        return None;
+    }
+
+    let primary = XRRGetOutputPrimary(display, window);
+    let modes = ffi_slice((*resources).modes, (*resources).nmode);
+    let mut areas = vec![];
+    for crtc in ffi_slice((*resources).crtcs, (*resources).ncrtc) {
+        let info = XRRGetCrtcInfo(display, resources, *crtc);
+        if info.is_null() {
+            continue;
+        }
+        // The disabled CRTCs have no mode and no outputs
+        let crtc = &*info;
+        if crtc.mode != 0 && crtc.noutput > 0 {
+            let mode = modes.iter().find(|mode| mode.id == crtc.mode);
+            areas.push(CrtcArea {
+                bounds: Region::new(crtc.x, crtc.y, crtc.width, crtc.height),
+                rotation: crtc.rotation,
+                mode_size: mode.map(|mode| (mode.width, mode.height)),
+                primary: ffi_slice(crtc.outputs, crtc.noutput).contains(&primary),
+            });
+        }
+        XRRFreeCrtcInfo(info);
+    }
+    XRRFreeScreenResources(resources);
+    Some(monitors_from_crtcs(&areas))
+}
+
+/// Get the slice of the given array of Xlib, which may be null when it is empty
+unsafe fn ffi_slice<'a, T>(data: *const T, length: c_int) -> &'a [T] {
+    if data.is_null() || length <= 0 {
+        return &[];
+    }
+    slice::from_raw_parts(data, length as usize)
+}
+
+/// Get the monitors that the given CRTCs show, the CRTCs that mirror the same area are a
+/// single monitor, and the first monitor is the primary one if no output is set as primary
+fn monitors_from_crtcs(areas: &[CrtcArea]) -> Vec<Monitor> {
+    let mut monitors: Vec<Monitor> = vec![];
+    for area in areas {
+        if area.bounds.is_empty() {
+            continue;
+        }
+        if let Some(monitor) = monitors
+            .iter_mut()
+            .find(|monitor| monitor.bounds == area.bounds)
+        {
+            monitor.primary |= area.primary;
+            continue;
+        }
+
+        // The mode is rotated along with the CRTC, and scaled by the transform of the CRTC
+        let scale_factor = match area.mode_size {
+            Some((width, height)) => {
+                let width = if area.rotation & (RR_ROTATE_90 | RR_ROTATE_270) != 0 {
+                    height
+                } else {
+                    width
+                };
+                width as f64 / area.bounds.width as f64
+            }
+            None => 1.,
+        };
+        monitors.push(Monitor {
+            id: monitors.len(),
+            bounds: area.bounds,
+            scale_factor,
+            primary: area.primary,
+        });
+    }
+    if !monitors.iter().any(|monitor| monitor.primary) {
+        if let Some(monitor) = monitors.first_mut() {
+            monitor.primary = true;
+        }
+    }
+    monitors
+}
+
+/// A connection that is opened with `open_display`, it is closed once the last reference to it is
+/// dropped. Xlib is not initialized for threads, so the connection is not shared between them
+pub(super) struct DisplayConnection(*mut Display);
+
+impl DisplayConnection {
+    pub(super) fn open(name: Option<&str>) -> Result<Rc<Self>, Error> {
+        Ok(Rc::new(DisplayConnection(open_display(name)?)))
+    }
+
+    pub(super) fn as_ptr(&self) -> *mut Display {
+        self.0
+    }
+}
+
+impl Drop for DisplayConnection {
+    fn drop(&mut self) {
+        close_display(self.0);
+    }
+}
+
+/// Connect to the given screen of the given display, or to the default screen of it,
+/// returns the connection along with the number and the root window of the screen
+fn connect(
+    display_name: Option<&str>,
+    screen: Option<i32>,
+) -> Result<(Rc<DisplayConnection>, c_int, Window), Error> {
+    let connection = DisplayConnection::open(display_name)?;
+    let display = connection.as_ptr();
+    unsafe {
+        let screen = screen.unwrap_or_else(|| XDefaultScreen(display));
+        if screen < 0 || screen >= XScreenCount(display) {
+            return Err(Error::CustomError("the X11 screen does not exist"));
+        }
+        let window = XRootWindow(display, screen);
+        Ok((connection, screen, window))
+    }
+}
+
+/// Run the given function with a new connection to the X server
+fn with_display<T>(f: impl FnOnce(*mut Display) -> T) -> Result<T, Error> {
+    let connection = DisplayConnection::open(None)?;
+    Ok(f(connection.as_ptr()))
+}
+
+/// Open a new connection to the given display or the default one, its errors are
+/// kept for `check_display` instead of being reported by the handler of Xlib,
+/// which exits the process
+pub(super) fn open_display(name: Option<&str>) -> Result<*mut Display, Error> {
+    let name = match name.map(CString::new).transpose() {
+        Ok(name) => name,
+        Err(_) => return Err(Error::CustomError("the X11 display name is not valid")),
+    };
+    INSTALL_ERROR_HANDLER.call_once(|| unsafe {
+        let previous = XSetErrorHandler(Some(handle_error));
+        let _ = PREVIOUS_ERROR_HANDLER.set(previous);
+    });
+
+    let display = unsafe { XOpenDisplay(name.as_ref().map_or(null(), |name| name.as_ptr())) };
+    if display.is_null() {
+        return Err(Error::CustomError("failed to open the X11 display"));
+    }
+    CONNECTIONS.lock().unwrap().push(Connection {
+        display: display as usize,
+        error: None,
+        lost: false,
+    });
+    unsafe { set_io_error_exit_handler(display) };
+    Ok(display)
+}
+
+/// Close the given connection that is opened with `open_display`
+pub(super) fn close_display(display: *mut Display) {
+    CONNECTIONS
+        .lock()
+        .unwrap()
+        .retain(|connection| connection.display != display as usize);
+    unsafe { XCloseDisplay(display) };
+}
+
+/// Get the first error of the given connection since the last check, the
+/// errors of the requests are only reported once the server processes them
+pub(super) fn check_display(display: *mut Display, context: &'static str) -> Result<(), Error> {
+    let mut connections = CONNECTIONS.lock().unwrap();
+    let connection = match connections
+        .iter_mut()
+        .find(|connection| connection.display == display as usize)
+    {
+        Some(connection) => connection,
+        None => return Ok(()),
+    };
+    if connection.lost {
+        return Err(Error::CustomError("the connection to the X server is lost"));
+    }
+    match connection.error.take() {
+        Some(code) => Err(Error::X11Error { context, code }),
+        None => Ok(()),
+    }
+}
+
+/// Wait until the X server processes the requests of the given connection, and
+/// get the first error of them
+pub(super) fn sync_display(display: *mut Display, context: &'static str) -> Result<(), Error> {
+    unsafe { XSync(display, 0) };
+    check_display(display, context)
+}
+
+/// Check whether the given connection is lost, e.g. because the X server quit
+pub(super) fn is_connection_lost(display: *mut Display) -> bool {
+    CONNECTIONS
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|connection| connection.display == display as usize && connection.lost)
+}
+
+unsafe extern "C" fn handle_error(display: *mut Display, event: *mut XErrorEvent) -> c_int {
+    let mut connections = CONNECTIONS.lock().unwrap();
+    match connections
+        .iter_mut()
+        .find(|connection| connection.display == display as usize)
+    {
+        Some(connection) => {
+            connection.error.get_or_insert((*event).error_code);
+            0
+        }
+        None => {
+            drop(connections);
+            match PREVIOUS_ERROR_HANDLER.get().copied().flatten() {
+                Some(handler) => handler(display, event),
+                None => 0,
+            }
+        }
+    }
+}
+
+/// Keep Xlib from exiting the process once the given connection is lost, the exit handlers
+/// are only available since libX11 1.7, so the function is looked up when it is needed
+unsafe fn set_io_error_exit_handler(display: *mut Display) {
+    let name = CString::new("XSetIOErrorExitHandler").unwrap();
+    let function = dlsym(RTLD_DEFAULT, name.as_ptr());
+    if !function.is_null() {
+        let set_handler: XSetIOErrorExitHandler = std::mem::transmute(function);
+        set_handler(display, handle_connection_loss, null_mut());
+    }
+}
+
+/// Xlib returns from the failed calls once the exit handler returns
+unsafe extern "C" fn handle_connection_loss(display: *mut Display, _data: *mut c_void) {
+    if let Some(connection) = CONNECTIONS
+        .lock()
+        .unwrap()
+        .iter_mut()
+        .find(|connection| connection.display == display as usize)
+    {
+        connection.lost = true;
+    }
 }
 
 /// Xlib type definitions
-enum _XDisplay {}
-type Display = _XDisplay;
-type Window = c_ulong;
+pub(super) enum _XDisplay {}
+pub(super) type Display = _XDisplay;
+pub(super) type Window = c_ulong;
 
-#[derive(Debug)]
 #[repr(C)]
-struct XEvent {
+struct XErrorEvent {
     r#type: c_int,
-    xbutton: XButtonEvent,
+    display: *mut Display,
+    resource_id: c_ulong,
+    serial: c_ulong,
+    error_code: c_uchar,
+    request_code: c_uchar,
+    minor_code: c_uchar,
 }
 
-#[derive(Debug)]
-#[repr(C)]
-struct XButtonEvent {
-    r#type: c_int,
-    window: Window,
-    root: Window,
-    subwindow: Window,
-    x: c_int,
-    y: c_int,
-    x_root: c_int,
-    y_root: c_int,
-    state: c_uint,
-    button: c_uint,
-    same_screen: bool,
+type XErrorHandler = Option<unsafe extern "C" fn(*mut Display, *mut XErrorEvent) -> c_int>;
+type XIOErrorExitHandler = unsafe extern "C" fn(*mut Display, *mut c_void);
+type XSetIOErrorExitHandler = unsafe extern "C" fn(*mut Display, XIOErrorExitHandler, *mut c_void);
+
+/// The pseudo handle of `dlsym` that searches the loaded libraries
+const RTLD_DEFAULT: *mut c_void = null_mut();
+
+extern "C" {
+    fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
 }
 
 // Xlib function definitions
 #[link(name = "X11")]
 extern "C" {
     fn XOpenDisplay(display: *const c_char) -> *mut Display;
-    fn XDefaultRootWindow(display: *mut Display) -> Window;
+    fn XCloseDisplay(display: *mut Display) -> c_int;
+    pub(super) fn XSync(display: *mut Display, discard: c_int) -> c_int;
+    fn XSetErrorHandler(handler: XErrorHandler) -> XErrorHandler;
+    pub(super) fn XConnectionNumber(display: *mut Display) -> c_int;
+    pub(super) fn XQueryExtension(
+        display: *mut Display,
+        name: *const c_char,
+        major_opcode_return: *mut c_int,
+        first_event_return: *mut c_int,
+        first_error_return: *mut c_int,
+    ) -> c_int;
+    pub(super) fn XDefaultRootWindow(display: *mut Display) -> Window;
+    pub(super) fn XDefaultScreen(display: *mut Display) -> c_int;
+    fn XScreenCount(display: *mut Display) -> c_int;
+    fn XRootWindow(display: *mut Display, screen_number: c_int) -> Window;
+    pub(super) fn XDisplayWidth(display: *mut Display, screen_number: c_int) -> c_int;
+    pub(super) fn XDisplayHeight(display: *mut Display, screen_number: c_int) -> c_int;
     fn XWarpPointer(
         display: *mut Display,
         src_w: Window,
@@ -182,8 +911,27 @@ extern "C" {
         dest_y: c_int,
     ) -> c_int;
 
-    fn XFlush(display: *mut Display) -> c_int;
-    fn XQueryPointer(
+    pub(super) fn XFlush(display: *mut Display) -> c_int;
+    fn XCheckTypedEvent(
+        display: *mut Display,
+        event_type: c_int,
+        event_return: *mut c_void,
+    ) -> c_int;
+    fn XGetPointerControl(
+        display: *mut Display,
+        accel_numerator_return: *mut c_int,
+        accel_denominator_return: *mut c_int,
+        threshold_return: *mut c_int,
+    ) -> c_int;
+    fn XChangePointerControl(
+        display: *mut Display,
+        do_accel: c_int,
+        do_threshold: c_int,
+        accel_numerator: c_int,
+        accel_denominator: c_int,
+        threshold: c_int,
+    ) -> c_int;
+    pub(super) fn XQueryPointer(
         display: *mut Display,
         window: Window,
         root_return: *mut Window,
@@ -196,6 +944,110 @@ extern "C" {
     ) -> c_int;
 }
 
+/// XRandR type definitions
+type RRCrtc = c_ulong;
+type RROutput = c_ulong;
+type RRMode = c_ulong;
+const RR_ROTATE_90: c_ushort = 2;
+const RR_ROTATE_270: c_ushort = 8;
+const RR_SCREEN_CHANGE_NOTIFY: c_int = 0;
+const RR_SCREEN_CHANGE_NOTIFY_MASK: c_int = 1 << 0;
+
+#[repr(C)]
+struct XRRScreenResources {
+    timestamp: c_ulong,
+    config_timestamp: c_ulong,
+    ncrtc: c_int,
+    crtcs: *mut RRCrtc,
+    noutput: c_int,
+    outputs: *mut RROutput,
+    nmode: c_int,
+    modes: *mut XRRModeInfo,
+}
+
+#[repr(C)]
+struct XRRModeInfo {
+    id: RRMode,
+    width: c_uint,
+    height: c_uint,
+    dot_clock: c_ulong,
+    h_sync_start: c_uint,
+    h_sync_end: c_uint,
+    h_total: c_uint,
+    h_skew: c_uint,
+    v_sync_start: c_uint,
+    v_sync_end: c_uint,
+    v_total: c_uint,
+    name: *mut c_char,
+    name_length: c_uint,
+    mode_flags: c_ulong,
+}
+
+#[repr(C)]
+struct XRRCrtcInfo {
+    timestamp: c_ulong,
+    x: c_int,
+    y: c_int,
+    width: c_uint,
+    height: c_uint,
+    mode: RRMode,
+    rotation: c_ushort,
+    noutput: c_int,
+    outputs: *mut RROutput,
+    rotations: c_ushort,
+    npossible: c_int,
+    possible: *mut RROutput,
+}
+
+// XRandR function definitions
+#[link(name = "Xrandr")]
+extern "C" {
+    fn XRRQueryExtension(
+        display: *mut Display,
+        event_base_return: *mut c_int,
+        error_base_return: *mut c_int,
+    ) -> c_int;
+    fn XRRGetScreenResourcesCurrent(
+        display: *mut Display,
+        window: Window,
+    ) -> *mut XRRScreenResources;
+    fn XRRFreeScreenResources(resources: *mut XRRScreenResources);
+    fn XRRGetCrtcInfo(
+        display: *mut Display,
+        resources: *mut XRRScreenResources,
+        crtc: RRCrtc,
+    ) -> *mut XRRCrtcInfo;
+    fn XRRFreeCrtcInfo(info: *mut XRRCrtcInfo);
+    fn XRRGetOutputPrimary(display: *mut Display, window: Window) -> RROutput;
+    fn XRRSelectInput(display: *mut Display, window: Window, mask: c_int);
+}
+
+/// XFixes type definitions
+type PointerBarrier = c_ulong;
+const BARRIER_POSITIVE_X: c_int = 1 << 0;
+const BARRIER_POSITIVE_Y: c_int = 1 << 1;
+const BARRIER_NEGATIVE_X: c_int = 1 << 2;
+const BARRIER_NEGATIVE_Y: c_int = 1 << 3;
+
+// XFixes function definitions
+#[link(name = "Xfixes")]
+extern "C" {
+    fn XFixesHideCursor(display: *mut Display, window: Window);
+    fn XFixesShowCursor(display: *mut Display, window: Window);
+    fn XFixesCreatePointerBarrier(
+        display: *mut Display,
+        window: Window,
+        x1: c_int,
+        y1: c_int,
+        x2: c_int,
+        y2: c_int,
+        directions: c_int,
+        num_devices: c_int,
+        devices: *const c_int,
+    ) -> PointerBarrier;
+    fn XFixesDestroyPointerBarrier(display: *mut Display, barrier: PointerBarrier);
+}
+
 // XTest function definitions
 #[link(name = "Xtst")]
 extern "C" {
@@ -205,4 +1057,102 @@ extern "C" {
         is_press: bool,
         delay: c_ulong,
     ) -> c_int;
+    fn XTestFakeMotionEvent(
+        dpy: *mut Display,
+        screen: c_int,
+        x: c_int,
+        y: c_int,
+        delay: c_ulong,
+    ) -> c_int;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        check_display, handle_error, monitors_from_crtcs, Connection, CrtcArea, Display,
+        XErrorEvent, CONNECTIONS, RR_ROTATE_90,
+    };
+    use crate::common::Region;
+    use crate::error::Error;
+    use std::ptr::null_mut;
+
+    #[test]
+    fn crtc_monitors() {
+        let area = |bounds, rotation, mode_size, primary| CrtcArea {
+            bounds,
+            rotation,
+            mode_size,
+            primary,
+        };
+        let monitors = monitors_from_crtcs(&[
+            area(Region::new(0, 0, 1920, 1080), 1, Some((1920, 1080)), false),
+            // A portrait monitor that shows twice its size with `xrandr --scale 2x2`
+            area(
+                Region::new(1920, 0, 4320, 7680),
+                RR_ROTATE_90,
+                Some((3840, 2160)),
+                true,
+            ),
+            // A projector that mirrors the first monitor
+            area(Region::new(0, 0, 1920, 1080), 1, Some((1920, 1080)), false),
+            area(Region::new(0, 0, 0, 0), 1, None, false),
+        ]);
+        assert_eq!(monitors.len(), 2);
+        assert_eq!(monitors[0].scale_factor, 1.);
+        assert!(!monitors[0].primary);
+        assert_eq!(monitors[1].id, 1);
+        assert_eq!(monitors[1].bounds, Region::new(1920, 0, 4320, 7680));
+        assert_eq!(monitors[1].scale_factor, 0.5);
+        assert!(monitors[1].primary);
+
+        // The first monitor is the primary one without a primary output
+        let monitors = monitors_from_crtcs(&[area(Region::new(0, 0, 800, 600), 1, None, false)]);
+        assert!(monitors[0].primary);
+    }
+
+    #[test]
+    fn kept_errors() {
+        // No request is sent to the display, so its address does not need to be valid
+        let display = 0x10 as *mut Display;
+        CONNECTIONS.lock().unwrap().push(Connection {
+            display: display as usize,
+            error: None,
+            lost: false,
+        });
+        let event = |code| XErrorEvent {
+            r#type: 0,
+            display,
+            resource_id: 0,
+            serial: 0,
+            error_code: code,
+            request_code: 0,
+            minor_code: 0,
+        };
+        // BadWindow is reported rather than the BadValue that follows it
+        unsafe {
+            handle_error(display, &mut event(3));
+            handle_error(display, &mut event(2));
+        }
+        assert_eq!(
+            check_display(display, "test"),
+            Err(Error::X11Error {
+                context: "test",
+                code: 3
+            })
+        );
+        assert_eq!(check_display(display, "test"), Ok(()));
+        assert_eq!(check_display(null_mut(), "test"), Ok(()));
+        let mut connections = CONNECTIONS.lock().unwrap();
+        let connection = connections
+            .iter_mut()
+            .find(|connection| connection.display == display as usize)
+            .unwrap();
+        connection.lost = true;
+        drop(connections);
+        assert!(check_display(display, "test").is_err());
+        CONNECTIONS
+            .lock()
+            .unwrap()
+            .retain(|connection| connection.display != display as usize);
+    }
 }
@@ -0,0 +1,654 @@
+///
+/// This module contains the event listener for the X11 sessions
+/// that consumes the raw events of the XInput2 extension
+///
+/// Raw events are delivered to any client that selects them on the root
+/// window, so unlike reading `/dev/input` this does not require root
+/// privileges or the membership of the input group
+///
+/// With XInput 2.1 the touchpads and the high-resolution wheels scroll
+/// through their scroll valuators, which are reported as pixel scrolls,
+/// and the wheel buttons that the server emulates for them are ignored
+///
+use crate::common::{
+    Device, DeviceId, DragTracker, EventInfo, MouseButton, MouseEvent, ScrollDirection, ScrollUnit,
+};
+use crate::error::Error;
+use crate::nix::listener::Listener;
+use crate::nix::x11::{
+    close_display, is_connection_lost, open_display, Display, Window, XConnectionNumber,
+    XDefaultRootWindow, XDefaultScreen, XDisplayHeight, XDisplayWidth, XFlush, XQueryExtension,
+    XQueryPointer, PIXELS_PER_WHEEL_STEP,
+};
+use crate::nix::Callbacks;
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int, c_long, c_uchar, c_uint, c_ulong, c_void};
+use std::slice;
+use std::sync::Arc;
+
+/// Start the XInput2 raw event listener
+pub(super) fn start_xinput2_listener(
+    callbacks: &Callbacks,
+    display_name: Option<&str>,
+) -> Result<Listener, Error> {
+    let (display, opcode, smooth_scroll) = unsafe {
+        // Use a dedicated connection, Xlib connections must not be
+        // shared between threads unless XInitThreads is called
+        let display = match open_display(display_name) {
+            Ok(display) => display,
+            Err(_) => return Err(Error::CustomError("xinput2: failed to open the display")),
+        };
+
+        let mut opcode = 0;
+        let mut event = 0;
+        let mut error = 0;
+        let name = CString::new("XInputExtension").unwrap();
+        if XQueryExtension(display, name.as_ptr(), &mut opcode, &mut event, &mut error) == 0 {
+            close_display(display);
+            return Err(Error::CustomError(
+                "xinput2: the XInput extension is not available",
+            ));
+        }
+
+        // The server only reports the emulated wheel buttons as such to the clients of
+        // XInput 2.1, and replies with the version that it supports if it is older
+        let mut major = 2;
+        let mut minor = 1;
+        if XIQueryVersion(display, &mut major, &mut minor) != SUCCESS {
+            close_display(display);
+            return Err(Error::CustomError("xinput2: XInput 2.0 is not supported"));
+        }
+        let smooth_scroll = (major, minor) >= (2, 1);
+
+        let mut mask = [0u8; 4];
+        for event_type in [XI_RAW_BUTTON_PRESS, XI_RAW_BUTTON_RELEASE, XI_RAW_MOTION] {
+            mask[(event_type >> 3) as usize] |= 1 << (event_type & 7);
+        }
+        // The cached devices are outdated once the devices are added or removed
+        let mut hierarchy_mask = [0u8; 4];
+        hierarchy_mask[(XI_HIERARCHY_CHANGED >> 3) as usize] |= 1 << (XI_HIERARCHY_CHANGED & 7);
+        let mut event_masks = [
+            XIEventMask {
+                deviceid: XI_ALL_MASTER_DEVICES,
+                mask_len: mask.len() as c_int,
+                mask: mask.as_mut_ptr(),
+            },
+            XIEventMask {
+                deviceid: XI_ALL_DEVICES,
+                mask_len: hierarchy_mask.len() as c_int,
+                mask: hierarchy_mask.as_mut_ptr(),
+            },
+        ];
+        XISelectEvents(
+            display,
+            XDefaultRootWindow(display),
+            event_masks.as_mut_ptr(),
+            event_masks.len() as c_int,
+        );
+        XFlush(display);
+
+        (display as usize, opcode, smooth_scroll)
+    };
+
+    let mut listener = Listener::new()?;
+    let callbacks = callbacks.clone();
+    listener.spawn(move |stop| {
+        let display = display as *mut Display;
+        let fd = unsafe { XConnectionNumber(display) };
+        let mut event = XEvent { pad: [0; 24] };
+
+        // XInput2 reports sub-pixel deltas, keep the remainders so that
+        // slow movements are not lost while rounding to whole pixels
+        let mut remainder = (0., 0.);
+        // The slave devices that produced the events, keyed by their ids
+        let mut devices: HashMap<c_int, SourceDevice> = HashMap::new();
+        let mut drags = DragTracker::default();
+
+        loop {
+            unsafe {
+                // Xlib may have already read the events from the connection,
+                // so the connection is only waited for when none are queued
+                while XPending(display) == 0 {
+                    // The connection stays readable once it is lost
+                    if !stop.wait(fd) || is_connection_lost(display) {
+                        close_display(display);
+                        return;
+                    }
+                }
+                XNextEvent(display, &mut event);
+
+                let cookie = &mut event.cookie;
+                if cookie.r#type != GENERIC_EVENT
+                    || cookie.extension != opcode
+                    || XGetEventData(display, cookie) == 0
+                {
+                    continue;
+                }
+                if cookie.evtype == XI_HIERARCHY_CHANGED {
+                    // The ids of the removed devices may be reused by the new ones
+                    devices.clear();
+                    XFreeEventData(display, cookie);
+                    continue;
+                }
+
+                let raw_event = &*(cookie.data as *const XIRawEvent);
+                let source = devices
+                    .entry(raw_event.sourceid)
+                    .or_insert_with(|| query_device(display, raw_event.sourceid, smooth_scroll));
+                let mouse_events = convert_event(cookie.evtype, raw_event, &mut remainder, source);
+                let device = source.device.clone();
+                XFreeEventData(display, cookie);
+
+                for mouse_event in mouse_events {
+                    let mouse_event = drags.track(mouse_event);
+                    // The raw events do not have a position, so it is only queried for
+                    // the buttons to avoid a round trip to the server for every motion
+                    let position = match mouse_event {
+                        MouseEvent::Press(_) | MouseEvent::Release(_) => query_pointer(display),
+                        _ => None,
+                    };
+                    // The injected events come from the XTEST devices of the server
+                    let injected = device
+                        .name
+                        .as_ref()
+                        .is_some_and(|name| name.contains("XTEST"));
+                    let info = EventInfo {
+                        device: Some(device.clone()),
+                        position,
+                        injected: Some(injected),
+                        ..EventInfo::default()
+                    };
+
+                    // Invoke all given callbacks with the constructed mouse event
+                    callbacks.lock().unwrap().invoke(&mouse_event, &info);
+                }
+            }
+        }
+    })?;
+
+    Ok(listener)
+}
+
+/// A slave device that produced the raw events
+struct SourceDevice {
+    device: Arc<Device>,
+    /// The scroll valuators of the device, empty without smooth scrolling
+    scroll_axes: Vec<ScrollAxis>,
+    /// The x and y valuators of the device
+    motion_axes: [MotionAxis; 2],
+}
+
+/// The x or y valuator of a device
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct MotionAxis {
+    /// Whether the valuator reports positions, like the ones of the tablets do
+    absolute: bool,
+    /// The pixels on the screen for a unit of an absolute valuator
+    scale: f64,
+    /// The value of an absolute valuator in the previous event
+    last_value: Option<f64>,
+}
+
+impl MotionAxis {
+    const RELATIVE: MotionAxis = MotionAxis {
+        absolute: false,
+        scale: 1.,
+        last_value: None,
+    };
+
+    /// Get the movement in pixels from the given value of the valuator
+    fn movement(&mut self, value: f64) -> f64 {
+        if !self.absolute {
+            return value;
+        }
+        // The first value of an absolute valuator only tells where it starts
+        match self.last_value.replace(value) {
+            Some(last_value) => (value - last_value) * self.scale,
+            None => 0.,
+        }
+    }
+}
+
+/// A valuator that a device scrolls with, see `XIScrollClassInfo`
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct ScrollAxis {
+    number: c_int,
+    vertical: bool,
+    /// The change of the value that scrolls as far as a wheel step
+    increment: f64,
+    /// The value of an absolute valuator in the previous event, the relative
+    /// valuators already report the change of the value
+    last_value: Option<f64>,
+    absolute: bool,
+}
+
+/// Get the identity and the scroll valuators of the XInput2 device with the given id
+unsafe fn query_device(
+    display: *mut Display,
+    device_id: c_int,
+    smooth_scroll: bool,
+) -> SourceDevice {
+    let mut count = 0;
+    let info = XIQueryDevice(display, device_id, &mut count);
+    let mut name = None;
+    let mut scroll_axes = vec![];
+    let mut motion_axes = [MotionAxis::RELATIVE; 2];
+    if !info.is_null() && count > 0 {
+        if !(*info).name.is_null() {
+            name = Some(CStr::from_ptr((*info).name).to_string_lossy().into_owned());
+        }
+
+        let classes = ffi_slice((*info).classes, (*info).num_classes);
+        for class in classes.iter().filter(|class| !class.is_null()) {
+            let class = *class;
+            if smooth_scroll && (*(class as *const XIAnyClassInfo)).r#type == XI_SCROLL_CLASS {
+                let scroll = &*(class as *const XIScrollClassInfo);
+                scroll_axes.push(ScrollAxis {
+                    number: scroll.number,
+                    vertical: scroll.scroll_type == XI_SCROLL_TYPE_VERTICAL,
+                    increment: scroll.increment,
+                    last_value: None,
+                    absolute: false,
+                });
+            }
+        }
+        // The absolute valuators span the whole screen
+        let screen = XDefaultScreen(display);
+        let screen_size = [
+            XDisplayWidth(display, screen) as f64,
+            XDisplayHeight(display, screen) as f64,
+        ];
+        // The mode of the valuators is in their own classes
+        for class in classes.iter().filter(|class| !class.is_null()) {
+            let class = *class;
+            if (*(class as *const XIAnyClassInfo)).r#type == XI_VALUATOR_CLASS {
+                let valuator = &*(class as *const XIValuatorClassInfo);
+                for axis in scroll_axes.iter_mut() {
+                    if axis.number == valuator.number {
+                        axis.absolute = valuator.mode == XI_MODE_ABSOLUTE;
+                    }
+                }
+                // The first two valuators are the x and y axes
+                if valuator.mode == XI_MODE_ABSOLUTE && (0..2).contains(&valuator.number) {
+                    let index = valuator.number as usize;
+                    let range = valuator.max - valuator.min;
+                    motion_axes[index] = MotionAxis {
+                        absolute: true,
+                        scale: if range > 0. {
+                            screen_size[index] / range
+                        } else {
+                            1.
+                        },
+                        last_value: None,
+                    };
+                }
+            }
+        }
+    }
+    if !info.is_null() {
+        XIFreeDeviceInfo(info);
+    }
+
+    // The increment is negative for the inverted axes, and zero would not scroll at all
+    scroll_axes.retain(|axis| axis.increment != 0.);
+    SourceDevice {
+        device: Arc::new(Device {
+            id: DeviceId(device_id as u64),
+            name,
+        }),
+        scroll_axes,
+        motion_axes,
+    }
+}
+
+/// Get the slice of the given array of Xlib, which may be null when it is empty
+unsafe fn ffi_slice<'a, T>(data: *const T, length: c_int) -> &'a [T] {
+    if data.is_null() || length <= 0 {
+        return &[];
+    }
+    slice::from_raw_parts(data, length as usize)
+}
+
+/// Get the numbers and the values of the valuators that are set in the mask of the given state
+unsafe fn valuator_values(valuators: &XIValuatorState, values: *const f64) -> Vec<(c_int, f64)> {
+    let mask = ffi_slice(valuators.mask, valuators.mask_len);
+    let mut set = vec![];
+    // The values are only there for the valuators that are set in the mask, in their order
+    let mut value = values;
+    for (index, bits) in mask.iter().enumerate() {
+        for bit in 0..8 {
+            if bits & (1 << bit) != 0 {
+                set.push(((index * 8 + bit) as c_int, *value));
+                value = value.add(1);
+            }
+        }
+    }
+    set
+}
+
+/// Get the pixel scrolls from the given values of the valuators of a device
+fn scroll_events(values: &[(c_int, f64)], axes: &mut [ScrollAxis]) -> Vec<MouseEvent> {
+    let mut events = vec![];
+    for axis in axes.iter_mut() {
+        let value = match values.iter().find(|(number, _)| *number == axis.number) {
+            Some((_, value)) => *value,
+            None => continue,
+        };
+        let change = if axis.absolute {
+            // The first value of an absolute valuator only tells where it starts
+            match axis.last_value.replace(value) {
+                Some(last_value) => value - last_value,
+                None => continue,
+            }
+        } else {
+            value
+        };
+
+        let distance = change / axis.increment * PIXELS_PER_WHEEL_STEP as f64;
+        if distance == 0. || !distance.is_finite() {
+            continue;
+        }
+        let direction = match (axis.vertical, distance > 0.) {
+            (true, true) => ScrollDirection::Down,
+            (true, false) => ScrollDirection::Up,
+            (false, true) => ScrollDirection::Right,
+            (false, false) => ScrollDirection::Left,
+        };
+        events.push(MouseEvent::Scroll {
+            direction,
+            unit: ScrollUnit::Pixel,
+            distance: distance.abs(),
+        });
+    }
+    events
+}
+
+/// Get the position of the pointer on the root window
+unsafe fn query_pointer(display: *mut Display) -> Option<(i32, i32)> {
+    let mut window = 0;
+    let (mut x, mut y, mut window_x, mut window_y, mut mask) = (0, 0, 0, 0, 0);
+    let same_screen = XQueryPointer(
+        display,
+        XDefaultRootWindow(display),
+        &mut window,
+        &mut window,
+        &mut x,
+        &mut y,
+        &mut window_x,
+        &mut window_y,
+        &mut mask,
+    );
+    if same_screen == 0 {
+        return None;
+    }
+    Some((x, y))
+}
+
+/// Construct the library's MouseEvents from the given raw event of the given device
+unsafe fn convert_event(
+    event_type: c_int,
+    event: &XIRawEvent,
+    remainder: &mut (f64, f64),
+    source: &mut SourceDevice,
+) -> Vec<MouseEvent> {
+    match event_type {
+        XI_RAW_MOTION => {
+            // The first two valuators are the x and y axes
+            let values = valuator_values(&event.valuators, event.raw_values);
+            let mut delta = (0., 0.);
+            for (number, value) in &values {
+                match number {
+                    0 => delta.0 = source.motion_axes[0].movement(*value),
+                    1 => delta.1 = source.motion_axes[1].movement(*value),
+                    _ => {}
+                }
+            }
+
+            let dx = delta.0 + remainder.0;
+            let dy = delta.1 + remainder.1;
+            let (x, y) = (dx.trunc(), dy.trunc());
+            *remainder = (dx - x, dy - y);
+
+            let mut events = vec![];
+            if x != 0. || y != 0. {
+                events.push(MouseEvent::Move {
+                    position: None,
+                    delta: (x as i32, y as i32),
+                });
+            }
+            events.extend(scroll_events(&values, &mut source.scroll_axes));
+            events
+        }
+        XI_RAW_BUTTON_PRESS | XI_RAW_BUTTON_RELEASE => {
+            let is_press = event_type == XI_RAW_BUTTON_PRESS;
+            let button = match event.detail {
+                1 => MouseButton::Left,
+                2 => MouseButton::Middle,
+                3 => MouseButton::Right,
+                // The scroll wheel is reported as buttons 4-7, one press and
+                // release pair for each step, so only report the presses
+                4..=7 if !is_press => return vec![],
+                // The scroll valuators already reported the scrolls of the emulated buttons
+                4..=7 if event.flags & XI_POINTER_EMULATED != 0 => return vec![],
+                4..=7 => {
+                    let direction = match event.detail {
+                        4 => ScrollDirection::Up,
+                        5 => ScrollDirection::Down,
+                        6 => ScrollDirection::Left,
+                        _ => ScrollDirection::Right,
+                    };
+                    return vec![MouseEvent::Scroll {
+                        direction,
+                        unit: ScrollUnit::Line,
+                        distance: 1.,
+                    }];
+                }
+                8 => MouseButton::Back,
+                9 => MouseButton::Forward,
+                10..=255 => MouseButton::Other((event.detail - 10) as u8),
+                // Ignore the unknown mouse buttons
+                _ => return vec![],
+            };
+
+            if is_press {
+                vec![MouseEvent::Press(button)]
+            } else {
+                vec![MouseEvent::Release(button)]
+            }
+        }
+        _ => vec![],
+    }
+}
+
+/// Xlib and XInput2 definitions
+const SUCCESS: c_int = 0;
+const GENERIC_EVENT: c_int = 35;
+const XI_ALL_DEVICES: c_int = 0;
+const XI_ALL_MASTER_DEVICES: c_int = 1;
+const XI_HIERARCHY_CHANGED: c_int = 11;
+const XI_RAW_BUTTON_PRESS: c_int = 15;
+const XI_RAW_BUTTON_RELEASE: c_int = 16;
+const XI_RAW_MOTION: c_int = 17;
+const XI_POINTER_EMULATED: c_int = 1 << 16;
+const XI_VALUATOR_CLASS: c_int = 2;
+const XI_SCROLL_CLASS: c_int = 3;
+const XI_SCROLL_TYPE_VERTICAL: c_int = 1;
+const XI_MODE_ABSOLUTE: c_int = 1;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct XGenericEventCookie {
+    r#type: c_int,
+    serial: c_ulong,
+    send_event: c_int,
+    display: *mut Display,
+    extension: c_int,
+    evtype: c_int,
+    cookie: c_uint,
+    data: *mut c_void,
+}
+
+#[repr(C)]
+union XEvent {
+    cookie: XGenericEventCookie,
+    pad: [c_long; 24],
+}
+
+#[repr(C)]
+struct XIEventMask {
+    deviceid: c_int,
+    mask_len: c_int,
+    mask: *mut c_uchar,
+}
+
+#[repr(C)]
+struct XIValuatorState {
+    mask_len: c_int,
+    mask: *mut c_uchar,
+    values: *mut f64,
+}
+
+#[repr(C)]
+struct XIDeviceInfo {
+    deviceid: c_int,
+    name: *mut c_char,
+    r#use: c_int,
+    attachment: c_int,
+    enabled: c_int,
+    num_classes: c_int,
+    classes: *mut *mut XIAnyClassInfo,
+}
+
+#[repr(C)]
+struct XIAnyClassInfo {
+    r#type: c_int,
+    sourceid: c_int,
+}
+
+#[repr(C)]
+struct XIValuatorClassInfo {
+    r#type: c_int,
+    sourceid: c_int,
+    number: c_int,
+    label: c_ulong,
+    min: f64,
+    max: f64,
+    value: f64,
+    resolution: c_int,
+    mode: c_int,
+}
+
+#[repr(C)]
+struct XIScrollClassInfo {
+    r#type: c_int,
+    sourceid: c_int,
+    number: c_int,
+    scroll_type: c_int,
+    increment: f64,
+    flags: c_int,
+}
+
+#[repr(C)]
+struct XIRawEvent {
+    r#type: c_int,
+    serial: c_ulong,
+    send_event: c_int,
+    display: *mut Display,
+    extension: c_int,
+    evtype: c_int,
+    time: c_ulong,
+    deviceid: c_int,
+    sourceid: c_int,
+    detail: c_int,
+    flags: c_int,
+    valuators: XIValuatorState,
+    raw_values: *mut f64,
+}
+
+#[link(name = "X11")]
+extern "C" {
+    fn XPending(display: *mut Display) -> c_int;
+    fn XNextEvent(display: *mut Display, event: *mut XEvent) -> c_int;
+    fn XGetEventData(display: *mut Display, cookie: *mut XGenericEventCookie) -> c_int;
+    fn XFreeEventData(display: *mut Display, cookie: *mut XGenericEventCookie);
+}
+
+#[link(name = "Xi")]
+extern "C" {
+    fn XIQueryVersion(
+        display: *mut Display,
+        major_version_inout: *mut c_int,
+        minor_version_inout: *mut c_int,
+    ) -> c_int;
+    fn XIQueryDevice(
+        display: *mut Display,
+        deviceid: c_int,
+        ndevices_return: *mut c_int,
+    ) -> *mut XIDeviceInfo;
+    fn XIFreeDeviceInfo(info: *mut XIDeviceInfo);
+    fn XISelectEvents(
+        display: *mut Display,
+        window: Window,
+        masks: *mut XIEventMask,
+        num_masks: c_int,
+    ) -> c_int;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{scroll_events, MotionAxis, ScrollAxis};
+    use crate::common::{MouseEvent, ScrollDirection, ScrollUnit};
+
+    #[test]
+    fn scroll_valuators() {
+        let axis = |number, vertical, increment, absolute| ScrollAxis {
+            number,
+            vertical,
+            increment,
+            last_value: None,
+            absolute,
+        };
+        let scroll = |direction, distance| MouseEvent::Scroll {
+            direction,
+            unit: ScrollUnit::Pixel,
+            distance,
+        };
+        let mut axes = [axis(2, false, 15., false), axis(3, true, -120., false)];
+
+        // A wheel step is as far as the increment, which is negative for the inverted axes
+        assert_eq!(
+            scroll_events(&[(0, 4.), (2, 7.5), (3, -60.)], &mut axes),
+            [
+                scroll(ScrollDirection::Right, 25.),
+                scroll(ScrollDirection::Down, 25.)
+            ]
+        );
+        assert_eq!(scroll_events(&[(0, 4.), (2, 0.)], &mut axes), []);
+
+        // The absolute valuators scroll by the change of their values
+        let mut axes = [axis(3, true, 10., true)];
+        assert_eq!(scroll_events(&[(3, 100.)], &mut axes), []);
+        assert_eq!(
+            scroll_events(&[(3, 96.)], &mut axes),
+            [scroll(ScrollDirection::Up, 20.)]
+        );
+    }
+
+    #[test]
+    fn absolute_motion() {
+        let mut relative = MotionAxis::RELATIVE;
+        assert_eq!(relative.movement(3.5), 3.5);
+        assert_eq!(relative.movement(-2.), -2.);
+
+        // The absolute valuators move by the change of their values on the screen
+        let mut absolute = MotionAxis {
+            absolute: true,
+            scale: 0.5,
+            last_value: None,
+        };
+        assert_eq!(absolute.movement(1000.), 0.);
+        assert_eq!(absolute.movement(1040.), 20.);
+        assert_eq!(absolute.movement(1000.), -20.);
+    }
+}
@@ -0,0 +1,360 @@
+///
+/// This module contains the event listener for the X11 sessions
+/// that is built on the XRecord extension
+///
+/// It is used as a fallback when the XInput2 raw events are not available,
+/// for example on remote X sessions where `/dev/input` is not even present
+///
+use crate::common::{DragTracker, EventInfo, MouseButton, MouseEvent, ScrollDirection, ScrollUnit};
+use crate::error::Error;
+use crate::nix::listener::Listener;
+use crate::nix::x11::{
+    close_display, is_connection_lost, open_display, Display, XConnectionNumber, XSync,
+};
+use crate::nix::Callbacks;
+use std::os::raw::{c_char, c_int, c_uchar, c_ulong, c_ushort, c_void};
+use std::slice;
+
+struct ListenerState {
+    callbacks: Callbacks,
+    last_position: Option<(i32, i32)>,
+    drags: DragTracker,
+}
+
+/// Start the XRecord event listener
+pub(super) fn start_xrecord_listener(
+    callbacks: &Callbacks,
+    display_name: Option<&str>,
+) -> Result<Listener, Error> {
+    let (control_display, data_display, context) = unsafe {
+        // XRecord needs two connections, the control connection creates
+        // the context and the data connection blocks while receiving the events
+        let control_display = match open_display(display_name) {
+            Ok(display) => display,
+            Err(_) => return Err(Error::CustomError("xrecord: failed to open the display")),
+        };
+        let data_display = match open_display(display_name) {
+            Ok(display) => display,
+            Err(_) => {
+                close_display(control_display);
+                return Err(Error::CustomError("xrecord: failed to open the display"));
+            }
+        };
+
+        let mut major = 0;
+        let mut minor = 0;
+        if XRecordQueryVersion(control_display, &mut major, &mut minor) == 0 {
+            close_display(data_display);
+            close_display(control_display);
+            return Err(Error::CustomError(
+                "xrecord: the RECORD extension is not available",
+            ));
+        }
+
+        let range = XRecordAllocRange();
+        if range.is_null() {
+            close_display(data_display);
+            close_display(control_display);
+            return Err(Error::CustomError("xrecord: failed to allocate the range"));
+        }
+        (*range).device_events = XRecordRange8 {
+            first: BUTTON_PRESS,
+            last: MOTION_NOTIFY,
+        };
+
+        let mut clients = XRECORD_ALL_CLIENTS;
+        let mut ranges = range;
+        let context = XRecordCreateContext(control_display, 0, &mut clients, 1, &mut ranges, 1);
+        XFree(range as *mut _);
+        if context == 0 {
+            close_display(data_display);
+            close_display(control_display);
+            return Err(Error::CustomError("xrecord: failed to create the context"));
+        }
+
+        // Make sure the context is created before the data connection uses it,
+        // the control connection is kept open since it owns the context
+        XSync(control_display, 0);
+
+        (control_display as usize, data_display as usize, context)
+    };
+
+    let mut listener = Listener::new()?;
+    let state = Box::new(ListenerState {
+        callbacks: callbacks.clone(),
+        last_position: None,
+        drags: DragTracker::default(),
+    });
+    listener.spawn(move |stop| unsafe {
+        let control_display = control_display as *mut Display;
+        let data_display = data_display as *mut Display;
+        let state = Box::into_raw(state);
+        // The context is enabled asynchronously, so that the data connection
+        // can be waited for along with the stop signal of the listener
+        if XRecordEnableContextAsync(data_display, context, intercept, state as *mut c_char) != 0 {
+            let fd = XConnectionNumber(data_display);
+            loop {
+                // Invokes `intercept` for every recorded event that is received
+                XRecordProcessReplies(data_display);
+                // The connection stays readable once it is lost
+                if !stop.wait(fd) || is_connection_lost(data_display) {
+                    break;
+                }
+            }
+            XRecordDisableContext(control_display, context);
+        }
+        XRecordFreeContext(control_display, context);
+        close_display(data_display);
+        close_display(control_display);
+        drop(Box::from_raw(state));
+    })?;
+
+    Ok(listener)
+}
+
+unsafe extern "C" fn intercept(closure: *mut c_char, data: *mut XRecordInterceptData) {
+    let state = &mut *(closure as *mut ListenerState);
+
+    if (*data).category == XRECORD_FROM_SERVER && !(*data).data.is_null() {
+        // The data length is reported in 4-byte units
+        let bytes = slice::from_raw_parts((*data).data, (*data).data_len as usize * 4);
+        if let Some(mouse_event) = convert_event(bytes, &mut state.last_position) {
+            let mouse_event = state.drags.track(mouse_event);
+            let info = EventInfo {
+                position: Some(root_position(bytes)),
+                ..EventInfo::default()
+            };
+
+            // Invoke all given callbacks with the constructed mouse event
+            state.callbacks.lock().unwrap().invoke(&mouse_event, &info);
+        }
+    }
+
+    XRecordFreeData(data);
+}
+
+/// Get the root window coordinates of the given pointer event
+fn root_position(bytes: &[u8]) -> (i32, i32) {
+    let x = i16::from_ne_bytes([bytes[20], bytes[21]]) as i32;
+    let y = i16::from_ne_bytes([bytes[22], bytes[23]]) as i32;
+    (x, y)
+}
+
+/// Construct the library's MouseEvent from the given core protocol event
+fn convert_event(bytes: &[u8], last_position: &mut Option<(i32, i32)>) -> Option<MouseEvent> {
+    if bytes.len() < 32 {
+        return None;
+    }
+
+    // The highest bit of the type is set for the events sent by SendEvent
+    let event_type = bytes[0] & 0x7f;
+    let detail = bytes[1];
+
+    match event_type {
+        MOTION_NOTIFY => {
+            // The core protocol only reports the absolute root coordinates,
+            // the delta is calculated from the previous motion
+            let (x, y) = root_position(bytes);
+            let previous = last_position.replace((x, y))?;
+            if previous == (x, y) {
+                return None;
+            }
+            Some(MouseEvent::Move {
+                position: Some((x, y)),
+                delta: (x - previous.0, y - previous.1),
+            })
+        }
+        BUTTON_PRESS | BUTTON_RELEASE => {
+            let is_press = event_type == BUTTON_PRESS;
+            let button = match detail {
+                1 => MouseButton::Left,
+                2 => MouseButton::Middle,
+                3 => MouseButton::Right,
+                // The scroll wheel is reported as buttons 4-7, one press and
+                // release pair for each step, so only report the presses
+                4..=7 if !is_press => return None,
+                4..=7 => {
+                    let direction = match detail {
+                        4 => ScrollDirection::Up,
+                        5 => ScrollDirection::Down,
+                        6 => ScrollDirection::Left,
+                        _ => ScrollDirection::Right,
+                    };
+                    return Some(MouseEvent::Scroll {
+                        direction,
+                        unit: ScrollUnit::Line,
+                        distance: 1.,
+                    });
+                }
+                8 => MouseButton::Back,
+                9 => MouseButton::Forward,
+                10.. => MouseButton::Other(detail - 10),
+                // Ignore the unknown mouse buttons
+                _ => return None,
+            };
+
+            if is_press {
+                Some(MouseEvent::Press(button))
+            } else {
+                Some(MouseEvent::Release(button))
+            }
+        }
+        _ => None,
+    }
+}
+
+/// XRecord definitions
+const BUTTON_PRESS: c_uchar = 4;
+const BUTTON_RELEASE: c_uchar = 5;
+const MOTION_NOTIFY: c_uchar = 6;
+const XRECORD_FROM_SERVER: c_int = 0;
+const XRECORD_ALL_CLIENTS: XRecordClientSpec = 3;
+
+type XRecordContext = c_ulong;
+type XRecordClientSpec = c_ulong;
+
+#[repr(C)]
+struct XRecordRange8 {
+    first: c_uchar,
+    last: c_uchar,
+}
+
+#[repr(C)]
+struct XRecordRange16 {
+    first: c_ushort,
+    last: c_ushort,
+}
+
+#[repr(C)]
+struct XRecordExtRange {
+    ext_major: XRecordRange8,
+    ext_minor: XRecordRange16,
+}
+
+#[repr(C)]
+struct XRecordRange {
+    core_requests: XRecordRange8,
+    core_replies: XRecordRange8,
+    ext_requests: XRecordExtRange,
+    ext_replies: XRecordExtRange,
+    delivered_events: XRecordRange8,
+    device_events: XRecordRange8,
+    errors: XRecordRange8,
+    client_started: c_int,
+    client_died: c_int,
+}
+
+#[repr(C)]
+struct XRecordInterceptData {
+    id_base: c_ulong,
+    server_time: c_ulong,
+    client_seq: c_ulong,
+    category: c_int,
+    client_swapped: c_int,
+    data: *mut c_uchar,
+    data_len: c_ulong,
+}
+
+type XRecordInterceptProc =
+    unsafe extern "C" fn(closure: *mut c_char, data: *mut XRecordInterceptData);
+
+#[link(name = "X11")]
+extern "C" {
+    fn XFree(data: *mut c_void) -> c_int;
+}
+
+#[link(name = "Xtst")]
+extern "C" {
+    fn XRecordQueryVersion(
+        display: *mut Display,
+        major_version: *mut c_int,
+        minor_version: *mut c_int,
+    ) -> c_int;
+    fn XRecordAllocRange() -> *mut XRecordRange;
+    fn XRecordCreateContext(
+        display: *mut Display,
+        datum_flags: c_int,
+        clients: *mut XRecordClientSpec,
+        nclients: c_int,
+        ranges: *mut *mut XRecordRange,
+        nranges: c_int,
+    ) -> XRecordContext;
+    fn XRecordEnableContextAsync(
+        display: *mut Display,
+        context: XRecordContext,
+        callback: XRecordInterceptProc,
+        closure: *mut c_char,
+    ) -> c_int;
+    fn XRecordProcessReplies(display: *mut Display);
+    fn XRecordDisableContext(display: *mut Display, context: XRecordContext) -> c_int;
+    fn XRecordFreeContext(display: *mut Display, context: XRecordContext) -> c_int;
+    fn XRecordFreeData(data: *mut XRecordInterceptData);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{convert_event, root_position};
+    use crate::common::{MouseButton, MouseEvent, ScrollDirection, ScrollUnit};
+
+    fn core_event(event_type: u8, detail: u8, x: i16, y: i16) -> [u8; 32] {
+        let mut bytes = [0; 32];
+        bytes[0] = event_type;
+        bytes[1] = detail;
+        bytes[20..22].copy_from_slice(&x.to_ne_bytes());
+        bytes[22..24].copy_from_slice(&y.to_ne_bytes());
+        bytes
+    }
+
+    #[test]
+    fn convert_buttons() {
+        let mut position = None;
+        assert_eq!(
+            convert_event(&core_event(4, 1, 0, 0), &mut position),
+            Some(MouseEvent::Press(MouseButton::Left))
+        );
+        assert_eq!(
+            convert_event(&core_event(5, 3, 0, 0), &mut position),
+            Some(MouseEvent::Release(MouseButton::Right))
+        );
+        assert_eq!(
+            convert_event(&core_event(4, 5, 0, 0), &mut position),
+            Some(MouseEvent::Scroll {
+                direction: ScrollDirection::Down,
+                unit: ScrollUnit::Line,
+                distance: 1.
+            })
+        );
+        assert_eq!(convert_event(&core_event(5, 5, 0, 0), &mut position), None);
+        assert_eq!(
+            convert_event(&core_event(4, 8, 0, 0), &mut position),
+            Some(MouseEvent::Press(MouseButton::Back))
+        );
+        assert_eq!(
+            convert_event(&core_event(4, 12, 0, 0), &mut position),
+            Some(MouseEvent::Press(MouseButton::Other(2)))
+        );
+        // The buttons are reported at the root coordinates of the event
+        assert_eq!(root_position(&core_event(4, 1, 640, -20)), (640, -20));
+    }
+
+    #[test]
+    fn convert_motion() {
+        let mut position = None;
+        assert_eq!(
+            convert_event(&core_event(6, 0, 100, 100), &mut position),
+            None
+        );
+        assert_eq!(
+            convert_event(&core_event(6, 0, 90, 120), &mut position),
+            Some(MouseEvent::Move {
+                position: Some((90, 120)),
+                delta: (-10, 20)
+            })
+        );
+        assert_eq!(
+            convert_event(&core_event(6, 0, 90, 120), &mut position),
+            None
+        );
+        assert_eq!(convert_event(&[6, 0], &mut position), None);
+    }
+}
@@ -0,0 +1,177 @@
+///
+/// This module contains a control socket that speaks the
+/// ydotoold protocol, so the existing ydotool clients and scripts
+/// can drive the mouse through mouce
+///
+/// ydotool clients send raw `input_event` structs as datagrams to the
+/// daemon socket, which are written to a uinput device as they are
+///
+/// - Unsupported events
+///     - Keyboard events are ignored, since the uinput device only has mouse capabilities
+///
+use crate::builder::MouseBuilder;
+use crate::error::Error;
+use crate::nix::uinput::{InputEvent, TimeVal, UInputMouseManager};
+use std::io::ErrorKind;
+use std::mem::size_of;
+use std::os::raw::c_int;
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::UnixDatagram;
+use std::path::{Path, PathBuf};
+
+pub struct YdotoolServer {
+    manager: UInputMouseManager,
+}
+
+impl YdotoolServer {
+    /// Create a new uinput device that receives the events of the ydotool clients
+    pub fn new() -> Result<Self, Error> {
+        Self::from_builder(&MouseBuilder::new())
+    }
+
+    /// Create a new uinput device with the identity configured on the given builder
+    pub fn from_builder(builder: &MouseBuilder) -> Result<Self, Error> {
+        Ok(YdotoolServer {
+            manager: UInputMouseManager::new(builder)?,
+        })
+    }
+
+    /// The socket path that ydotool clients connect to by default,
+    /// `$YDOTOOL_SOCKET` if it is set, otherwise `/tmp/.ydotool_socket`
+    pub fn default_socket_path() -> PathBuf {
+        match std::env::var_os("YDOTOOL_SOCKET") {
+            Some(path) => PathBuf::from(path),
+            None => PathBuf::from("/tmp/.ydotool_socket"),
+        }
+    }
+
+    /// Receive the events on the given socket path, this function blocks forever
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::nix::ydotool::YdotoolServer;
+    ///
+    /// let server = YdotoolServer::new().unwrap();
+    /// server.serve(YdotoolServer::default_socket_path()).unwrap();
+    /// ```
+    pub fn serve<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        remove_stale_socket(path.as_ref())?;
+        let socket = match UnixDatagram::bind(&path) {
+            Ok(socket) => socket,
+            Err(err) if err.kind() == ErrorKind::PermissionDenied => {
+                return Err(Error::PermissionDenied)
+            }
+            Err(err) => return Err(Error::from_io("ydotool: failed to bind the socket", err)),
+        };
+
+        // The buffer has room for one more byte, so the datagrams that
+        // are larger than an input event are not truncated to one
+        let mut buffer = [0; size_of::<InputEvent>() + 1];
+        loop {
+            let length = socket
+                .recv(&mut buffer)
+                .map_err(|err| Error::from_io("ydotool: failed to read the socket", err))?;
+
+            // Ignore anything that is not a complete input event
+            if let Some((r#type, code, value)) = decode_event(&buffer[..length]) {
+                // The kernel drops the events that the device does not support
+                let _ = self
+                    .manager
+                    .emit(r#type as c_int, code as c_int, value as c_int);
+            }
+        }
+    }
+}
+
+/// Remove the socket of a previous run that nothing receives on anymore, like ydotoold
+/// does, the other files and the sockets of the running servers are left in place
+fn remove_stale_socket(path: &Path) -> Result<(), Error> {
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => {}
+        _ => return Ok(()),
+    }
+
+    let probe = UnixDatagram::unbound()
+        .map_err(|err| Error::from_io("ydotool: failed to create a socket", err))?;
+    match probe.connect(path) {
+        Ok(()) => Err(Error::CustomError(
+            "ydotool: another server is already receiving on the socket",
+        )),
+        Err(err) if err.kind() == ErrorKind::ConnectionRefused => std::fs::remove_file(path)
+            .map_err(|err| Error::from_io("ydotool: failed to remove the stale socket", err)),
+        // Binding reports the problem with the path
+        Err(_) => Ok(()),
+    }
+}
+
+/// Get the type, code and value of the given raw `input_event`
+fn decode_event(bytes: &[u8]) -> Option<(u16, u16, i32)> {
+    if bytes.len() != size_of::<InputEvent>() {
+        return None;
+    }
+
+    // The timestamp is ignored, the kernel fills it in when the event is written
+    let offset = size_of::<TimeVal>();
+    let r#type = u16::from_ne_bytes([bytes[offset], bytes[offset + 1]]);
+    let code = u16::from_ne_bytes([bytes[offset + 2], bytes[offset + 3]]);
+    let value = i32::from_ne_bytes([
+        bytes[offset + 4],
+        bytes[offset + 5],
+        bytes[offset + 6],
+        bytes[offset + 7],
+    ]);
+    Some((r#type, code, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_event, remove_stale_socket};
+    use crate::nix::uinput::{InputEvent, TimeVal};
+    use std::mem::size_of;
+    use std::os::unix::net::UnixDatagram;
+
+    #[test]
+    fn decode_raw_events() {
+        let event = InputEvent {
+            time: TimeVal {
+                tv_sec: 12,
+                tv_usec: 34,
+            },
+            r#type: 0x02,
+            code: 0x08,
+            value: -1,
+        };
+        let bytes = unsafe {
+            std::slice::from_raw_parts(
+                &event as *const InputEvent as *const u8,
+                size_of::<InputEvent>(),
+            )
+        };
+
+        assert_eq!(decode_event(bytes), Some((0x02, 0x08, -1)));
+        assert_eq!(decode_event(&bytes[1..]), None);
+        assert_eq!(decode_event(&[bytes, &[0]].concat()), None);
+    }
+
+    #[test]
+    fn remove_stale_sockets() {
+        let path = std::env::temp_dir().join(format!("mouce-ydotool-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        // The socket of a running server is kept
+        let socket = UnixDatagram::bind(&path).unwrap();
+        assert!(remove_stale_socket(&path).is_err());
+        assert!(path.exists());
+
+        drop(socket);
+        assert_eq!(remove_stale_socket(&path), Ok(()));
+        assert!(!path.exists());
+
+        // The other files are not removed
+        std::fs::write(&path, "").unwrap();
+        assert_eq!(remove_stale_socket(&path), Ok(()));
+        assert!(path.exists());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
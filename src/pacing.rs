@@ -0,0 +1,149 @@
+///
+/// This module contains the pacing of the injected events
+///
+/// The display servers and the kernel coalesce the events that arrive in
+/// a burst, so e.g. many `move_relative` calls in a row can end up as a
+/// single move. A pacing that is set with `MouseBuilder::pacing` spaces
+/// the injected events out, so they are replayed the same way every time
+///
+/// An event is what the backend injects at once, e.g. a uinput report or
+/// a single `SendInput` call, so a click at a position counts as one event
+/// on Windows while a click counts as two events on the other platforms
+///
+/// The pacing is not applied in the browsers, since the main thread can not sleep
+///
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The limits of the rate of the injected events, see `MouseBuilder::pacing`
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use mouce::pacing::Pacing;
+/// use mouce::Mouse;
+/// use std::time::Duration;
+///
+/// let pacing = Pacing::new()
+///     .max_events_per_second(250.)
+///     .min_gap(Duration::from_millis(2));
+/// let manager = Mouse::builder().pacing(pacing).build().unwrap();
+/// // The moves are injected 4 milliseconds apart
+/// for _ in 0..100 {
+///     manager.move_relative(1, 0).unwrap();
+/// }
+/// ```
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Pacing {
+    /// The time between two events at the maximum rate
+    rate_interval: Duration,
+    min_gap: Duration,
+}
+
+impl Pacing {
+    /// Create a pacing without any limits, the events are injected as soon as possible
+    pub fn new() -> Self {
+        Pacing::default()
+    }
+
+    /// Limit the number of the injected events per second, the events are spaced
+    /// out evenly rather than injected in bursts of the given size
+    ///
+    /// The rates that are not positive numbers remove the limit
+    pub fn max_events_per_second(mut self, rate: f64) -> Self {
+        self.rate_interval = if rate.is_finite() && rate > 0. {
+            Duration::from_secs_f64(1. / rate)
+        } else {
+            Duration::ZERO
+        };
+        self
+    }
+
+    /// Set the minimum time between two injected events
+    pub fn min_gap(mut self, gap: Duration) -> Self {
+        self.min_gap = gap;
+        self
+    }
+
+    /// Get the minimum time between two injected events that satisfies both of the limits
+    pub fn interval(&self) -> Duration {
+        self.rate_interval.max(self.min_gap)
+    }
+}
+
+/// Delays the injected events of a manager according to its pacing
+#[derive(Debug, Default)]
+#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+pub(crate) struct Pacer {
+    interval: Duration,
+    /// The earliest time that the next event can be injected at
+    next: Mutex<Option<Instant>>,
+}
+
+#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+impl Pacer {
+    pub(crate) fn new(pacing: &Pacing) -> Self {
+        Pacer {
+            interval: pacing.interval(),
+            next: Mutex::new(None),
+        }
+    }
+
+    /// Wait until the next event can be injected, the callers that
+    /// inject at the same time are paced one after another
+    pub(crate) fn wait(&self) {
+        if self.interval.is_zero() {
+            return;
+        }
+        let mut next = self.next.lock().unwrap();
+        let now = Instant::now();
+        let at = match *next {
+            Some(at) if at > now => {
+                thread::sleep(at - now);
+                at
+            }
+            _ => now,
+        };
+        *next = Some(at + self.interval);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Pacer, Pacing};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn intervals() {
+        assert_eq!(Pacing::new().interval(), Duration::ZERO);
+        let pacing = Pacing::new().max_events_per_second(100.);
+        assert_eq!(pacing.interval(), Duration::from_millis(10));
+        assert_eq!(
+            pacing.min_gap(Duration::from_millis(25)).interval(),
+            Duration::from_millis(25)
+        );
+        assert_eq!(
+            pacing.min_gap(Duration::from_millis(5)).interval(),
+            Duration::from_millis(10)
+        );
+        assert_eq!(pacing.max_events_per_second(0.).interval(), Duration::ZERO);
+    }
+
+    #[test]
+    fn spaced_events() {
+        let pacer = Pacer::new(&Pacing::new().min_gap(Duration::from_millis(5)));
+        let start = Instant::now();
+        for _ in 0..5 {
+            pacer.wait();
+        }
+        // The first event is not delayed
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        // The events after a pause are not delayed
+        std::thread::sleep(Duration::from_millis(10));
+        let start = Instant::now();
+        pacer.wait();
+        assert!(start.elapsed() < Duration::from_millis(5));
+    }
+}
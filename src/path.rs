@@ -0,0 +1,303 @@
+///
+/// This module contains the paths that can be traced with the mouse
+/// by `MouseActions::trace_path`
+///
+/// A path starts at a point and is extended with lines, Bezier curves,
+/// arcs and Catmull-Rom splines, then it is flattened into waypoints
+///
+use std::f64::consts::PI;
+
+type Point = (f64, f64);
+
+/// The number of samples used to estimate the length of a curved segment
+const LENGTH_SAMPLES: usize = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Line(Point),
+    Quadratic(Point, Point),
+    Cubic(Point, Point, Point),
+    Arc {
+        center: Point,
+        sweep: f64,
+    },
+    /// A span of a Catmull-Rom spline with its neighbouring points and the end point
+    CatmullRom(Point, Point, Point),
+}
+
+impl Segment {
+    /// Get the point at `t` (0.0..=1.0) of the segment that starts at `start`
+    fn point_at(&self, start: Point, t: f64) -> Point {
+        match self {
+            Segment::Line(to) => lerp(start, *to, t),
+            Segment::Quadratic(control, to) => {
+                let u = 1. - t;
+                (
+                    u * u * start.0 + 2. * u * t * control.0 + t * t * to.0,
+                    u * u * start.1 + 2. * u * t * control.1 + t * t * to.1,
+                )
+            }
+            Segment::Cubic(control1, control2, to) => {
+                let u = 1. - t;
+                let (a, b, c, d) = (u * u * u, 3. * u * u * t, 3. * u * t * t, t * t * t);
+                (
+                    a * start.0 + b * control1.0 + c * control2.0 + d * to.0,
+                    a * start.1 + b * control1.1 + c * control2.1 + d * to.1,
+                )
+            }
+            Segment::Arc { center, sweep } => {
+                let radius = distance(start, *center);
+                let angle = (start.1 - center.1).atan2(start.0 - center.0) + sweep * t;
+                (
+                    center.0 + radius * angle.cos(),
+                    center.1 + radius * angle.sin(),
+                )
+            }
+            Segment::CatmullRom(previous, to, next) => catmull_rom(*previous, start, *to, *next, t),
+        }
+    }
+
+    /// Estimate the length of the segment that starts at `start`
+    fn length(&self, start: Point) -> f64 {
+        match self {
+            Segment::Line(to) => distance(start, *to),
+            Segment::Arc { center, sweep } => distance(start, *center) * sweep.abs(),
+            _ => {
+                let mut length = 0.;
+                let mut previous = start;
+                for sample in 1..=LENGTH_SAMPLES {
+                    let point = self.point_at(start, sample as f64 / LENGTH_SAMPLES as f64);
+                    length += distance(previous, point);
+                    previous = point;
+                }
+                length
+            }
+        }
+    }
+}
+
+/// A path for the mouse to follow
+///
+/// # Examples
+///
+/// ```rust
+/// use mouce::path::Path;
+///
+/// let path = Path::new((0, 0))
+///     .line_to((100, 0))
+///     .cubic_to((150, 0), (200, 50), (200, 100))
+///     .arc((100, 100), 180.);
+/// assert_eq!(path.end(), (0, 100));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Path {
+    start: Point,
+    segments: Vec<Segment>,
+}
+
+impl Path {
+    /// Start a new path at the given point
+    pub fn new(start: (i32, i32)) -> Self {
+        Path {
+            start: to_point(start),
+            segments: vec![],
+        }
+    }
+
+    /// Add a straight line to the given point
+    pub fn line_to(mut self, to: (i32, i32)) -> Self {
+        self.segments.push(Segment::Line(to_point(to)));
+        self
+    }
+
+    /// Add straight lines through all of the given points
+    pub fn polyline(mut self, points: &[(i32, i32)]) -> Self {
+        for point in points {
+            self = self.line_to(*point);
+        }
+        self
+    }
+
+    /// Add a quadratic Bezier curve to the given point
+    pub fn quadratic_to(mut self, control: (i32, i32), to: (i32, i32)) -> Self {
+        self.segments
+            .push(Segment::Quadratic(to_point(control), to_point(to)));
+        self
+    }
+
+    /// Add a cubic Bezier curve to the given point
+    pub fn cubic_to(mut self, control1: (i32, i32), control2: (i32, i32), to: (i32, i32)) -> Self {
+        self.segments.push(Segment::Cubic(
+            to_point(control1),
+            to_point(control2),
+            to_point(to),
+        ));
+        self
+    }
+
+    /// Add a circular arc around the given center, starting from the current point
+    ///
+    /// `degrees` is the angle to sweep, positive values sweep clockwise on the screen
+    pub fn arc(mut self, center: (i32, i32), degrees: f64) -> Self {
+        self.segments.push(Segment::Arc {
+            center: to_point(center),
+            sweep: degrees * PI / 180.,
+        });
+        self
+    }
+
+    /// Add a Catmull-Rom spline that smoothly passes through all of the given points
+    pub fn catmull_rom(mut self, points: &[(i32, i32)]) -> Self {
+        // The end points are duplicated so that the spline
+        // passes through the first and the last points
+        let mut points: Vec<Point> = points.iter().map(|point| to_point(*point)).collect();
+        points.insert(0, self.current());
+
+        for index in 1..points.len() {
+            let previous = points[index.saturating_sub(2)];
+            let next = points[(index + 1).min(points.len() - 1)];
+            self.segments
+                .push(Segment::CatmullRom(previous, points[index], next));
+        }
+        self
+    }
+
+    /// Get the start point of the path
+    pub fn start(&self) -> (i32, i32) {
+        to_pixel(self.start)
+    }
+
+    /// Get the end point of the path
+    pub fn end(&self) -> (i32, i32) {
+        to_pixel(self.current())
+    }
+
+    /// Get the approximate length of the path in pixels
+    pub fn length(&self) -> f64 {
+        let mut current = self.start;
+        let mut length = 0.;
+        for segment in &self.segments {
+            length += segment.length(current);
+            current = segment.point_at(current, 1.);
+        }
+        length
+    }
+
+    /// Flatten the path into waypoints that are about `spacing` pixels apart
+    ///
+    /// The waypoints start with the start point and end with the end point
+    /// of the path, consecutive duplicates are removed
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use mouce::path::Path;
+    ///
+    /// let path = Path::new((0, 0)).line_to((10, 0));
+    /// assert_eq!(path.waypoints(5.), vec![(0, 0), (5, 0), (10, 0)]);
+    /// ```
+    pub fn waypoints(&self, spacing: f64) -> Vec<(i32, i32)> {
+        let spacing = spacing.max(1.);
+        let mut waypoints = vec![to_pixel(self.start)];
+        let mut current = self.start;
+
+        for segment in &self.segments {
+            let steps = (segment.length(current) / spacing).ceil().max(1.) as usize;
+            for step in 1..=steps {
+                let waypoint = to_pixel(segment.point_at(current, step as f64 / steps as f64));
+                if waypoints.last() != Some(&waypoint) {
+                    waypoints.push(waypoint);
+                }
+            }
+            current = segment.point_at(current, 1.);
+        }
+
+        waypoints
+    }
+
+    /// Get the end point of the last segment, where the next segment starts
+    fn current(&self) -> Point {
+        let mut current = self.start;
+        for segment in &self.segments {
+            current = segment.point_at(current, 1.);
+        }
+        current
+    }
+}
+
+fn to_point((x, y): (i32, i32)) -> Point {
+    (x as f64, y as f64)
+}
+
+fn to_pixel((x, y): Point) -> (i32, i32) {
+    (x.round() as i32, y.round() as i32)
+}
+
+fn lerp(from: Point, to: Point, t: f64) -> Point {
+    (from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t)
+}
+
+fn distance(from: Point, to: Point) -> f64 {
+    (to.0 - from.0).hypot(to.1 - from.1)
+}
+
+/// Evaluate the uniform Catmull-Rom spline between `p1` and `p2`
+fn catmull_rom(p0: Point, p1: Point, p2: Point, p3: Point, t: f64) -> Point {
+    let (t2, t3) = (t * t, t * t * t);
+    let axis = |a: f64, b: f64, c: f64, d: f64| {
+        0.5 * (2. * b
+            + (c - a) * t
+            + (2. * a - 5. * b + 4. * c - d) * t2
+            + (3. * b - a - 3. * c + d) * t3)
+    };
+    (axis(p0.0, p1.0, p2.0, p3.0), axis(p0.1, p1.1, p2.1, p3.1))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::path::Path;
+
+    #[test]
+    fn polyline_waypoints() {
+        let path = Path::new((0, 0)).polyline(&[(4, 0), (4, 3)]);
+        assert_eq!(path.length(), 7.);
+        assert_eq!(
+            path.waypoints(2.),
+            vec![(0, 0), (2, 0), (4, 0), (4, 2), (4, 3)]
+        );
+        // A path without segments only has the start point
+        assert_eq!(Path::new((5, 5)).waypoints(1.), vec![(5, 5)]);
+    }
+
+    #[test]
+    fn curves_end_at_their_targets() {
+        let path = Path::new((0, 0))
+            .quadratic_to((50, 100), (100, 0))
+            .cubic_to((100, 100), (200, 100), (200, 0));
+        assert_eq!(path.end(), (200, 0));
+        assert_eq!(path.waypoints(1.).last(), Some(&(200, 0)));
+        // The quadratic curve peaks at half of the control point height
+        assert!(path.waypoints(1.).contains(&(50, 50)));
+    }
+
+    #[test]
+    fn arc() {
+        let path = Path::new((100, 0)).arc((0, 0), 90.);
+        assert_eq!(path.end(), (0, 100));
+        for (x, y) in path.waypoints(5.) {
+            let radius = ((x * x + y * y) as f64).sqrt();
+            assert!((radius - 100.).abs() < 1.);
+        }
+    }
+
+    #[test]
+    fn catmull_rom_passes_through_points() {
+        let points = [(50, 80), (100, 0), (150, 80)];
+        let path = Path::new((0, 0)).catmull_rom(&points);
+        let waypoints = path.waypoints(1.);
+        for point in points {
+            assert!(waypoints.contains(&point));
+        }
+        assert_eq!(path.end(), (150, 80));
+    }
+}
@@ -0,0 +1,575 @@
+///
+/// This module contains the recording of the hooked events and their replay
+///
+/// A `Recorder` collects the events of a manager along with the time they
+/// happened at, and a `Player` injects them again with the original timing
+///
+/// The sessions are saved as text with `to_string` and loaded with `parse`,
+/// each line holds the time of an event in microseconds and the event itself:
+///
+/// ```text
+/// 0 move 100,200 3,-1
+/// 15250 press left
+/// 98000 release left
+/// 120500 scroll down line 1
+/// ```
+///
+use crate::common::{
+    ButtonState, CallbackId, MouseActions, MouseEvent, ScrollDirection, ScrollUnit,
+};
+use crate::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// An event of a session and the time it happened at, relative to the start of the recording
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RecordedEvent {
+    pub time: Duration,
+    pub event: MouseEvent,
+}
+
+/// The recorded events, in the order they happened
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Session {
+    pub events: Vec<RecordedEvent>,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Session::default()
+    }
+
+    /// Get the time of the last event of the session
+    pub fn duration(&self) -> Duration {
+        self.events
+            .last()
+            .map(|event| event.time)
+            .unwrap_or_default()
+    }
+}
+
+impl fmt::Display for Session {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for RecordedEvent { time, event } in &self.events {
+            write!(f, "{} ", time.as_micros())?;
+            match event {
+                MouseEvent::Move { position, delta } => {
+                    write!(f, "move {} {}", Position(*position), Pair(*delta))?
+                }
+                MouseEvent::Press(button) => write!(f, "press {}", button)?,
+                MouseEvent::Release(button) => write!(f, "release {}", button)?,
+                MouseEvent::Drag {
+                    button,
+                    position,
+                    delta,
+                } => write!(
+                    f,
+                    "drag {} {} {}",
+                    button,
+                    Position(*position),
+                    Pair(*delta)
+                )?,
+                MouseEvent::DoubleClick { button, position } => {
+                    write!(f, "double-click {} {}", button, Position(*position))?
+                }
+                MouseEvent::Scroll {
+                    direction,
+                    unit,
+                    distance,
+                } => write!(f, "scroll {} {} {}", direction, unit, distance)?,
+                MouseEvent::ListenerInterrupted => write!(f, "interrupted")?,
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Session {
+    type Err = Error;
+
+    /// Parse a session that is saved with `to_string`, the empty
+    /// lines and the lines that start with `#` are skipped
+    fn from_str(session: &str) -> Result<Self, Self::Err> {
+        let mut events = vec![];
+        for line in session.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let time = parse_field::<u64>(fields.next())?;
+            let event = parse_event(&mut fields)?;
+            if fields.next().is_some() {
+                return Err(Error::CustomError("the session has an unexpected field"));
+            }
+            events.push(RecordedEvent {
+                time: Duration::from_micros(time),
+                event,
+            });
+        }
+        Ok(Session { events })
+    }
+}
+
+fn parse_event<'a>(fields: &mut impl Iterator<Item = &'a str>) -> Result<MouseEvent, Error> {
+    let event = match fields.next() {
+        Some("move") => MouseEvent::Move {
+            position: parse_position(fields.next())?,
+            delta: parse_pair(fields.next())?,
+        },
+        Some("press") => MouseEvent::Press(parse_field(fields.next())?),
+        Some("release") => MouseEvent::Release(parse_field(fields.next())?),
+        Some("drag") => MouseEvent::Drag {
+            button: parse_field(fields.next())?,
+            position: parse_position(fields.next())?,
+            delta: parse_pair(fields.next())?,
+        },
+        Some("double-click") => MouseEvent::DoubleClick {
+            button: parse_field(fields.next())?,
+            position: parse_position(fields.next())?,
+        },
+        Some("scroll") => MouseEvent::Scroll {
+            direction: parse_field(fields.next())?,
+            unit: parse_field(fields.next())?,
+            distance: parse_field(fields.next())?,
+        },
+        Some("interrupted") => MouseEvent::ListenerInterrupted,
+        _ => return Err(Error::CustomError("the session has an unknown event")),
+    };
+    Ok(event)
+}
+
+fn parse_field<T: FromStr>(field: Option<&str>) -> Result<T, Error> {
+    field
+        .and_then(|field| field.parse().ok())
+        .ok_or(Error::CustomError("the session has an invalid field"))
+}
+
+fn parse_pair(field: Option<&str>) -> Result<(i32, i32), Error> {
+    let (x, y) = field
+        .and_then(|field| field.split_once(','))
+        .ok_or(Error::CustomError("the session has an invalid field"))?;
+    Ok((parse_field(Some(x))?, parse_field(Some(y))?))
+}
+
+fn parse_position(field: Option<&str>) -> Result<Option<(i32, i32)>, Error> {
+    match field {
+        Some("-") => Ok(None),
+        field => parse_pair(field).map(Some),
+    }
+}
+
+/// Formats a pair of coordinates as `x,y`
+struct Pair((i32, i32));
+
+impl fmt::Display for Pair {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{},{}", self.0 .0, self.0 .1)
+    }
+}
+
+/// Formats an optional position as `x,y` or `-`
+struct Position(Option<(i32, i32)>);
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Some(position) => write!(f, "{}", Pair(position)),
+            None => write!(f, "-"),
+        }
+    }
+}
+
+/// Records the hooked events of a manager into a session
+#[derive(Debug)]
+pub struct Recorder {
+    callback_id: CallbackId,
+    events: Arc<Mutex<Vec<RecordedEvent>>>,
+}
+
+impl Recorder {
+    /// Start recording the events of the given manager, the times
+    /// of the events are relative to the start of the recording
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::recorder::{PlayOptions, Player, Recorder};
+    /// use mouce::Mouse;
+    /// use std::thread;
+    /// use std::time::Duration;
+    ///
+    /// let mut manager = Mouse::new();
+    /// let recorder = Recorder::start(manager.as_mut()).unwrap();
+    /// thread::sleep(Duration::from_secs(10));
+    /// let session = recorder.stop(manager.as_mut()).unwrap();
+    ///
+    /// std::fs::write("session.txt", session.to_string()).unwrap();
+    /// Player::new(manager.as_ref())
+    ///     .play(&session, &PlayOptions::new())
+    ///     .unwrap();
+    /// ```
+    pub fn start(manager: &mut dyn MouseActions) -> Result<Self, Error> {
+        let events = Arc::new(Mutex::new(vec![]));
+        let recorded = events.clone();
+        let start = Instant::now();
+        let callback_id = manager.hook(Box::new(move |event| {
+            recorded.lock().unwrap().push(RecordedEvent {
+                time: start.elapsed(),
+                event: *event,
+            });
+        }))?;
+        Ok(Recorder {
+            callback_id,
+            events,
+        })
+    }
+
+    /// Get the session of the events that are recorded so far
+    pub fn session(&self) -> Session {
+        Session {
+            events: self.events.lock().unwrap().clone(),
+        }
+    }
+
+    /// Stop recording, the given manager must be the one the recording is started with
+    pub fn stop(self, manager: &mut dyn MouseActions) -> Result<Session, Error> {
+        manager.unhook(self.callback_id)?;
+        Ok(self.session())
+    }
+}
+
+/// The options of the replay of a session
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PlayOptions {
+    pub(crate) speed: f64,
+    pub(crate) repeat: u32,
+}
+
+impl PlayOptions {
+    /// Play the session once with the original timing
+    pub fn new() -> Self {
+        PlayOptions {
+            speed: 1.,
+            repeat: 1,
+        }
+    }
+
+    /// Set the multiplier of the playback speed, e.g. `2.0` plays the session twice as fast
+    pub fn speed(mut self, speed: f64) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Set how many times the session is played in a row
+    pub fn repeat(mut self, repeat: u32) -> Self {
+        self.repeat = repeat;
+        self
+    }
+}
+
+impl Default for PlayOptions {
+    fn default() -> Self {
+        PlayOptions::new()
+    }
+}
+
+/// Injects the events of a session through a manager
+pub struct Player<'a> {
+    manager: &'a dyn MouseActions,
+}
+
+impl<'a> Player<'a> {
+    pub fn new(manager: &'a dyn MouseActions) -> Self {
+        Player { manager }
+    }
+
+    /// Play the given session, blocks until all of its events are injected
+    ///
+    /// The moves with a position are replayed with `move_to` and the others with
+    /// `move_relative`, the double-clicks are skipped since their presses are
+    /// recorded as well. The buttons that are held down by the session are
+    /// released if an event fails to be injected
+    pub fn play(&self, session: &Session, options: &PlayOptions) -> Result<(), Error> {
+        if options.speed.is_nan() || options.speed <= 0. {
+            return Err(Error::CustomError("the speed must be a positive number"));
+        }
+
+        let mut pressed = ButtonState::new();
+        for _ in 0..options.repeat {
+            if let Err(err) = self.play_once(session, options.speed, &mut pressed) {
+                for button in pressed.iter() {
+                    let _ = self.manager.release_button(&button);
+                }
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    fn play_once(
+        &self,
+        session: &Session,
+        speed: f64,
+        pressed: &mut ButtonState,
+    ) -> Result<(), Error> {
+        let start = Instant::now();
+        // The fractions of the scroll distances that are not injected yet
+        let mut scroll_remainders: Vec<((ScrollDirection, ScrollUnit), f64)> = vec![];
+        for RecordedEvent { time, event } in &session.events {
+            // Wait for the deadline of the event, so that the delays do not add up
+            let deadline = start + time.div_f64(speed);
+            let now = Instant::now();
+            if deadline > now {
+                thread::sleep(deadline - now);
+            }
+
+            match event {
+                MouseEvent::Move { position, delta }
+                | MouseEvent::Drag {
+                    position, delta, ..
+                } => match position {
+                    Some((x, y)) => self
+                        .manager
+                        .move_to((*x).max(0) as usize, (*y).max(0) as usize)?,
+                    None => self.manager.move_relative(delta.0, delta.1)?,
+                },
+                MouseEvent::Press(button) => {
+                    self.manager.press_button(button)?;
+                    pressed.insert(button);
+                }
+                MouseEvent::Release(button) => {
+                    self.manager.release_button(button)?;
+                    pressed.remove(button);
+                }
+                MouseEvent::DoubleClick { .. } | MouseEvent::ListenerInterrupted => {}
+                MouseEvent::Scroll {
+                    direction,
+                    unit,
+                    distance,
+                } => {
+                    let key = (*direction, *unit);
+                    let index = match scroll_remainders.iter().position(|(k, _)| *k == key) {
+                        Some(index) => index,
+                        None => {
+                            scroll_remainders.push((key, 0.));
+                            scroll_remainders.len() - 1
+                        }
+                    };
+                    let remainder = &mut scroll_remainders[index].1;
+                    let total = *remainder + distance;
+                    let steps = total.trunc();
+                    *remainder = total - steps;
+                    if steps >= 1. {
+                        self.manager.scroll_wheel(direction, *unit, steps as u32)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PlayOptions, Player, RecordedEvent, Recorder, Session};
+    use crate::common::{
+        CallbackId, Callbacks, EventCallback, EventInfo, MouseActions, MouseButton, MouseEvent,
+        ScrollDirection, ScrollUnit,
+    };
+    use crate::error::Error;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    /// A manager that logs the injected actions and passes
+    /// the emitted events to the hooked callbacks
+    #[derive(Default)]
+    struct TestMouseManager {
+        log: Mutex<Vec<String>>,
+        callbacks: Callbacks,
+        /// Whether the scrolls fail to be injected
+        failing_scroll: bool,
+    }
+
+    impl TestMouseManager {
+        fn emit(&self, event: &MouseEvent) {
+            self.callbacks
+                .lock()
+                .unwrap()
+                .invoke(event, &EventInfo::default());
+        }
+
+        fn log(&self) -> Vec<String> {
+            self.log.lock().unwrap().clone()
+        }
+    }
+
+    impl MouseActions for TestMouseManager {
+        fn move_to(&self, x: usize, y: usize) -> Result<(), Error> {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("move_to {} {}", x, y));
+            Ok(())
+        }
+
+        fn move_relative(&self, x_offset: i32, y_offset: i32) -> Result<(), Error> {
+            let action = format!("move_relative {} {}", x_offset, y_offset);
+            self.log.lock().unwrap().push(action);
+            Ok(())
+        }
+
+        fn get_position(&self) -> Result<(i32, i32), Error> {
+            Err(Error::NotImplemented)
+        }
+
+        fn press_button(&self, button: &MouseButton) -> Result<(), Error> {
+            self.log.lock().unwrap().push(format!("press {}", button));
+            Ok(())
+        }
+
+        fn release_button(&self, button: &MouseButton) -> Result<(), Error> {
+            self.log.lock().unwrap().push(format!("release {}", button));
+            Ok(())
+        }
+
+        fn scroll_wheel(
+            &self,
+            direction: &ScrollDirection,
+            unit: ScrollUnit,
+            distance: u32,
+        ) -> Result<(), Error> {
+            if self.failing_scroll {
+                return Err(Error::WriteFailed);
+            }
+            let action = format!("scroll {} {} {}", direction, unit, distance);
+            self.log.lock().unwrap().push(action);
+            Ok(())
+        }
+
+        fn hook_with_info(&mut self, callback: EventCallback) -> Result<CallbackId, Error> {
+            let id = CallbackId::next();
+            self.callbacks.lock().unwrap().insert(id, callback);
+            Ok(id)
+        }
+
+        fn unhook(&mut self, callback_id: CallbackId) -> Result<(), Error> {
+            match self.callbacks.lock().unwrap().remove(&callback_id) {
+                Some(_) => Ok(()),
+                None => Err(Error::UnhookFailed),
+            }
+        }
+
+        fn unhook_all(&mut self) -> Result<(), Error> {
+            self.callbacks.lock().unwrap().clear();
+            Ok(())
+        }
+    }
+
+    fn session() -> Session {
+        let events = [
+            MouseEvent::Move {
+                position: Some((100, 200)),
+                delta: (3, -1),
+            },
+            MouseEvent::Press(MouseButton::Left),
+            MouseEvent::Drag {
+                button: MouseButton::Left,
+                position: None,
+                delta: (-5, 2),
+            },
+            MouseEvent::Release(MouseButton::Left),
+            MouseEvent::Scroll {
+                direction: ScrollDirection::Down,
+                unit: ScrollUnit::Pixel,
+                distance: 0.75,
+            },
+            MouseEvent::Scroll {
+                direction: ScrollDirection::Down,
+                unit: ScrollUnit::Pixel,
+                distance: 1.5,
+            },
+            MouseEvent::DoubleClick {
+                button: MouseButton::Other(2),
+                position: None,
+            },
+        ];
+        Session {
+            events: events
+                .into_iter()
+                .enumerate()
+                .map(|(index, event)| RecordedEvent {
+                    time: Duration::from_micros(index as u64 * 1500),
+                    event,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn session_round_trip() {
+        let session = session();
+        let text = session.to_string();
+        assert!(text.starts_with("0 move 100,200 3,-1\n1500 press left\n"));
+        assert_eq!(text.parse(), Ok(session.clone()));
+        assert_eq!(session.duration(), Duration::from_micros(9000));
+
+        let commented = format!("# recorded by mouce\n\n{}", text);
+        assert_eq!(commented.parse(), Ok(session));
+        assert!("10 press".parse::<Session>().is_err());
+        assert!("10 jump 1,2".parse::<Session>().is_err());
+        assert!("10 press left right".parse::<Session>().is_err());
+    }
+
+    #[test]
+    fn record_and_play() {
+        let mut manager = TestMouseManager::default();
+        let recorder = Recorder::start(&mut manager).unwrap();
+        for RecordedEvent { event, .. } in session().events {
+            manager.emit(&event);
+        }
+        let recorded = recorder.stop(&mut manager).unwrap();
+        let events: Vec<MouseEvent> = recorded.events.iter().map(|e| e.event).collect();
+        let expected: Vec<MouseEvent> = session().events.iter().map(|e| e.event).collect();
+        assert_eq!(events, expected);
+        // The recording is unhooked once it is stopped
+        assert_eq!(manager.callbacks.lock().unwrap().values().count(), 0);
+
+        let player = Player::new(&manager);
+        let options = PlayOptions::new().speed(100.).repeat(2);
+        assert_eq!(player.play(&session(), &options), Ok(()));
+        let once = [
+            "move_to 100 200",
+            "press left",
+            "move_relative -5 2",
+            "release left",
+            // The fractions of the scrolls add up to a whole pixel
+            "scroll down pixel 2",
+        ];
+        let twice: Vec<&str> = once.iter().chain(once.iter()).copied().collect();
+        assert_eq!(manager.log(), twice);
+    }
+
+    #[test]
+    fn failed_play_releases_the_buttons() {
+        let manager = TestMouseManager {
+            failing_scroll: true,
+            ..TestMouseManager::default()
+        };
+        let mut session = session();
+        // End the session while the button is held down
+        session.events.remove(3);
+
+        let player = Player::new(&manager);
+        let options = PlayOptions::new().speed(100.);
+        assert_eq!(player.play(&session, &options), Err(Error::WriteFailed));
+        assert_eq!(
+            manager.log().last().map(String::as_str),
+            Some("release left")
+        );
+        assert!(player.play(&session, &options.speed(0.)).is_err());
+    }
+}
@@ -0,0 +1,408 @@
+///
+/// This module contains the remapping of the mouse buttons
+///
+/// A `Remapper` installs a rewriting hook (see `MouseActions::hook_rewrite`)
+/// that swallows the events of the remapped buttons and injects the events
+/// of their targets instead, e.g. to swap the left and the right buttons or
+/// to double-click the left button with the middle one
+///
+/// Only the events of the devices are remapped, the injected events (e.g.
+/// the clicks of a target) pass through as they are. A button can only be
+/// remapped by one running remapper of the process at a time
+///
+use crate::builder::MouseBuilder;
+use crate::common::{ButtonState, CallbackId, EventInfo, MouseActions, MouseButton, MouseEvent};
+use crate::error::Error;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+/// The buttons that are remapped by the running remappers
+static REMAPPED_BUTTONS: Mutex<ButtonState> = Mutex::new(ButtonState::new());
+
+/// The sending end of the channel of the clicks, it is shared with the hook
+type ClickSender = Mutex<Option<Sender<(MouseButton, u32)>>>;
+
+/// What a remapped button does instead
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Target {
+    /// Press and release the given button along with the remapped one
+    Button(MouseButton),
+    /// Click the given button the given number of times on each press,
+    /// e.g. `Click(MouseButton::Left, 2)` for a double-click
+    Click(MouseButton, u32),
+    /// Do nothing, the button is disabled
+    Disabled,
+}
+
+/// The table of the remapped buttons and their targets
+///
+/// # Examples
+///
+/// ```rust
+/// use mouce::common::MouseButton;
+/// use mouce::remap::{ButtonMap, Target};
+///
+/// let map = ButtonMap::new()
+///     .swap(MouseButton::Left, MouseButton::Right)
+///     .map(MouseButton::Middle, Target::Click(MouseButton::Left, 2));
+/// assert_eq!(map.target(&MouseButton::Left), Some(Target::Button(MouseButton::Right)));
+/// assert_eq!(map.target(&MouseButton::Back), None);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ButtonMap {
+    mappings: Vec<(MouseButton, Target)>,
+}
+
+impl ButtonMap {
+    /// Create an empty table, the buttons that are not remapped pass through as they are
+    pub fn new() -> Self {
+        ButtonMap::default()
+    }
+
+    /// Remap the given button to the given target
+    pub fn map(mut self, button: MouseButton, target: Target) -> Self {
+        self.mappings.push((button, target));
+        self
+    }
+
+    /// Remap the given buttons to each other
+    pub fn swap(self, first: MouseButton, second: MouseButton) -> Self {
+        self.map(first, Target::Button(second))
+            .map(second, Target::Button(first))
+    }
+
+    /// Get the target of the given button, `None` if it is not remapped
+    pub fn target(&self, button: &MouseButton) -> Option<Target> {
+        self.mappings
+            .iter()
+            .find(|(remapped, _)| remapped == button)
+            .map(|(_, target)| *target)
+    }
+
+    /// Get the set of the remapped buttons, fails if a button is remapped more than once
+    fn buttons(&self) -> Result<ButtonState, Error> {
+        let mut buttons = ButtonState::new();
+        for (button, _) in &self.mappings {
+            if buttons.is_pressed(button) {
+                return Err(Error::CustomError("the button is remapped more than once"));
+            }
+            buttons.insert(button);
+        }
+        Ok(buttons)
+    }
+
+    /// Get the event that replaces the given one, the clicks of the
+    /// targets are sent to `clicks` since they can not replace an event
+    fn rewrite(
+        &self,
+        event: &MouseEvent,
+        info: &EventInfo,
+        clicks: &ClickSender,
+    ) -> Option<MouseEvent> {
+        if info.injected == Some(true) {
+            return Some(*event);
+        }
+        let (button, is_press) = match event {
+            MouseEvent::Press(button) => (button, true),
+            MouseEvent::Release(button) => (button, false),
+            _ => return Some(*event),
+        };
+
+        match self.target(button) {
+            None => Some(*event),
+            Some(Target::Button(target)) if is_press => Some(MouseEvent::Press(target)),
+            Some(Target::Button(target)) => Some(MouseEvent::Release(target)),
+            Some(Target::Click(target, count)) => {
+                if let (true, Some(clicks)) = (is_press, clicks.lock().unwrap().as_ref()) {
+                    let _ = clicks.send((target, count));
+                }
+                None
+            }
+            Some(Target::Disabled) => None,
+        }
+    }
+}
+
+/// Remaps the buttons of a manager until it is stopped
+///
+/// The remapper must be stopped with `stop`, dropping it only stops the clicks of the targets
+#[derive(Debug)]
+pub struct Remapper {
+    callback_id: CallbackId,
+    buttons: ButtonState,
+    /// The thread of the clicks stops once the channel is dropped, the
+    /// hook keeps sharing it until the manager drops the hook
+    clicks: Arc<ClickSender>,
+    thread: Option<JoinHandle<Result<(), Error>>>,
+}
+
+impl Remapper {
+    /// Start remapping the buttons of the given manager with the given table, the
+    /// clicks of the targets are injected by a mouse manager of the current platform
+    ///
+    /// Fails if the table remaps a button more than once, or if a button is
+    /// already remapped by another remapper
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::common::MouseButton;
+    /// use mouce::remap::{ButtonMap, Remapper, Target};
+    /// use mouce::Mouse;
+    /// use std::thread;
+    /// use std::time::Duration;
+    ///
+    /// let mut manager = Mouse::builder().tap_listen_only(false).build().unwrap();
+    /// let map = ButtonMap::new()
+    ///     .swap(MouseButton::Left, MouseButton::Right)
+    ///     .map(MouseButton::Middle, Target::Click(MouseButton::Left, 2));
+    /// let remapper = Remapper::start(manager.as_mut(), map).unwrap();
+    /// thread::sleep(Duration::from_secs(60));
+    /// remapper.stop(manager.as_mut()).unwrap();
+    /// ```
+    pub fn start(manager: &mut dyn MouseActions, map: ButtonMap) -> Result<Self, Error> {
+        Self::spawn(manager, map, || MouseBuilder::new().build())
+    }
+
+    /// Start remapping the buttons of the given manager with the given table,
+    /// the clicks of the targets are injected by the given injector
+    pub fn start_with(
+        manager: &mut dyn MouseActions,
+        map: ButtonMap,
+        injector: Box<dyn MouseActions + Send>,
+    ) -> Result<Self, Error> {
+        Self::spawn(manager, map, move || Ok(injector))
+    }
+
+    fn spawn<F>(manager: &mut dyn MouseActions, map: ButtonMap, injector: F) -> Result<Self, Error>
+    where
+        F: FnOnce() -> Result<Box<dyn MouseActions>, Error> + Send + 'static,
+    {
+        let buttons = map.buttons()?;
+        {
+            let mut remapped = REMAPPED_BUTTONS.lock().unwrap();
+            if remapped.bits() & buttons.bits() != 0 {
+                return Err(Error::CustomError(
+                    "the button is already remapped by another remapper",
+                ));
+            }
+            for button in buttons.iter() {
+                remapped.insert(&button);
+            }
+        }
+
+        // The clicks are injected on their own thread, so the hook is not blocked
+        // while they are injected, and the manager is created on that thread
+        let (clicks, targets) = mpsc::channel::<(MouseButton, u32)>();
+        let thread = thread::spawn(move || {
+            let injector = injector()?;
+            for (button, count) in targets {
+                injector.click_n(&button, count)?;
+            }
+            Ok(())
+        });
+
+        let clicks = Arc::new(Mutex::new(Some(clicks)));
+        let sender = clicks.clone();
+        let hooked = manager.hook_rewrite(Box::new(move |event, info| {
+            map.rewrite(event, info, &sender)
+        }));
+        let callback_id = match hooked {
+            Ok(callback_id) => callback_id,
+            Err(err) => {
+                clicks.lock().unwrap().take();
+                release_buttons(&buttons);
+                return Err(err);
+            }
+        };
+        Ok(Remapper {
+            callback_id,
+            buttons,
+            clicks,
+            thread: Some(thread),
+        })
+    }
+
+    /// Get the set of the buttons that are remapped
+    pub fn buttons(&self) -> ButtonState {
+        self.buttons
+    }
+
+    /// Stop remapping, the given manager must be the one the remapping is started with
+    ///
+    /// Returns the error that stopped the clicks of the targets before, if there is any
+    pub fn stop(mut self, manager: &mut dyn MouseActions) -> Result<(), Error> {
+        manager.unhook(self.callback_id)?;
+        self.join()
+    }
+
+    fn join(&mut self) -> Result<(), Error> {
+        self.clicks.lock().unwrap().take();
+        match self.thread.take() {
+            Some(thread) => thread
+                .join()
+                .map_err(|_| Error::CustomError("the remapper thread panicked"))?,
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Remapper {
+    fn drop(&mut self) {
+        release_buttons(&self.buttons);
+        let _ = self.join();
+    }
+}
+
+/// Let the other remappers remap the given buttons again
+fn release_buttons(buttons: &ButtonState) {
+    let mut remapped = REMAPPED_BUTTONS.lock().unwrap();
+    for button in buttons.iter() {
+        remapped.remove(&button);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ButtonMap, Remapper, Target};
+    use crate::common::{
+        CallbackId, EventCallback, EventInfo, MouseActions, MouseButton, MouseEvent,
+        RewriteCallback, ScrollDirection, ScrollUnit,
+    };
+    use crate::error::Error;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    /// A manager that keeps the rewriting callback and logs the clicks
+    #[derive(Default)]
+    struct TestMouseManager {
+        rewriter: Option<RewriteCallback>,
+        clicks: Arc<Mutex<Vec<(MouseButton, u32)>>>,
+    }
+
+    impl TestMouseManager {
+        fn rewrite(&self, event: MouseEvent, injected: bool) -> Option<MouseEvent> {
+            let info = EventInfo {
+                injected: Some(injected),
+                ..EventInfo::default()
+            };
+            self.rewriter.as_ref().unwrap()(&event, &info)
+        }
+    }
+
+    impl MouseActions for TestMouseManager {
+        fn move_to(&self, _x: usize, _y: usize) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn get_position(&self) -> Result<(i32, i32), Error> {
+            Ok((0, 0))
+        }
+
+        fn press_button(&self, _button: &MouseButton) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn release_button(&self, _button: &MouseButton) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn click_n(&self, button: &MouseButton, count: u32) -> Result<(), Error> {
+            self.clicks.lock().unwrap().push((*button, count));
+            Ok(())
+        }
+
+        fn scroll_wheel(
+            &self,
+            _direction: &ScrollDirection,
+            _unit: ScrollUnit,
+            _distance: u32,
+        ) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn hook_with_info(&mut self, _callback: EventCallback) -> Result<CallbackId, Error> {
+            Err(Error::NotImplemented)
+        }
+
+        fn hook_rewrite(&mut self, callback: RewriteCallback) -> Result<CallbackId, Error> {
+            self.rewriter = Some(callback);
+            Ok(CallbackId::next())
+        }
+
+        fn unhook(&mut self, _callback_id: CallbackId) -> Result<(), Error> {
+            self.rewriter = None;
+            Ok(())
+        }
+
+        fn unhook_all(&mut self) -> Result<(), Error> {
+            Err(Error::NotImplemented)
+        }
+    }
+
+    #[test]
+    fn remapped_events() {
+        let mut manager = TestMouseManager::default();
+        let injector = TestMouseManager::default();
+        let clicks = injector.clicks.clone();
+        let map = ButtonMap::new()
+            .swap(MouseButton::Left, MouseButton::Right)
+            .map(MouseButton::Middle, Target::Click(MouseButton::Left, 2))
+            .map(MouseButton::Back, Target::Disabled);
+        let remapper = Remapper::start_with(&mut manager, map, Box::new(injector)).unwrap();
+
+        let press = MouseEvent::Press;
+        let release = MouseEvent::Release;
+        assert_eq!(
+            manager.rewrite(press(MouseButton::Left), false),
+            Some(press(MouseButton::Right))
+        );
+        assert_eq!(
+            manager.rewrite(release(MouseButton::Right), false),
+            Some(release(MouseButton::Left))
+        );
+        assert_eq!(manager.rewrite(press(MouseButton::Middle), false), None);
+        assert_eq!(manager.rewrite(release(MouseButton::Middle), false), None);
+        assert_eq!(manager.rewrite(press(MouseButton::Back), false), None);
+        assert_eq!(
+            manager.rewrite(press(MouseButton::Forward), false),
+            Some(press(MouseButton::Forward))
+        );
+        // The injected events are not remapped
+        assert_eq!(
+            manager.rewrite(press(MouseButton::Left), true),
+            Some(press(MouseButton::Left))
+        );
+
+        while clicks.lock().unwrap().is_empty() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(remapper.stop(&mut manager), Ok(()));
+        assert!(manager.rewriter.is_none());
+        assert_eq!(*clicks.lock().unwrap(), [(MouseButton::Left, 2)]);
+    }
+
+    #[test]
+    fn conflicting_maps() {
+        let mut manager = TestMouseManager::default();
+        let map = ButtonMap::new()
+            .map(MouseButton::Other(5), Target::Disabled)
+            .map(MouseButton::Other(5), Target::Button(MouseButton::Left));
+        let injector = Box::new(TestMouseManager::default());
+        assert!(Remapper::start_with(&mut manager, map, injector).is_err());
+
+        // A button can not be remapped by two remappers at once
+        let map = ButtonMap::new().map(MouseButton::Other(6), Target::Disabled);
+        let injector = Box::new(TestMouseManager::default());
+        let remapper = Remapper::start_with(&mut manager, map.clone(), injector).unwrap();
+        let injector = Box::new(TestMouseManager::default());
+        assert!(Remapper::start_with(&mut manager, map.clone(), injector).is_err());
+
+        assert_eq!(remapper.stop(&mut manager), Ok(()));
+        let injector = Box::new(TestMouseManager::default());
+        let remapper = Remapper::start_with(&mut manager, map, injector).unwrap();
+        assert_eq!(remapper.stop(&mut manager), Ok(()));
+    }
+}
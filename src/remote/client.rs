@@ -0,0 +1,242 @@
+use crate::common::{
+    simulate_event, CallbackId, CallbackMap, Callbacks, EventCallback, MouseActions, MouseButton,
+    MouseEvent, ScrollDirection, ScrollUnit,
+};
+use crate::display::Monitor;
+use crate::error::Error;
+use crate::remote::{
+    decode_error, decode_event, encode_request, read_frame, read_u32, write_frame, Request, Stream,
+    STATUS_ERROR, STATUS_OK,
+};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+/// Where the server is listening
+enum Address {
+    Tcp(SocketAddr),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl Address {
+    fn connect(&self) -> Result<Stream, Error> {
+        let stream = match self {
+            Address::Tcp(address) => TcpStream::connect(address).map(|stream| {
+                // Requests are tiny, do not wait to fill a packet
+                let _ = stream.set_nodelay(true);
+                Stream::Tcp(stream)
+            }),
+            #[cfg(unix)]
+            Address::Unix(path) => UnixStream::connect(path).map(Stream::Unix),
+        };
+        stream.map_err(|_| Error::CustomError("remote: failed to connect to the server"))
+    }
+}
+
+pub struct RemoteMouseManager {
+    address: Address,
+    stream: Mutex<Stream>,
+    callbacks: Callbacks,
+    /// The connection that receives the events and the thread that reads it
+    listener: Option<(Stream, JoinHandle<()>)>,
+}
+
+impl RemoteMouseManager {
+    /// Connect to a server that is listening on the given TCP address
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::remote::RemoteMouseManager;
+    /// use mouce::MouseActions;
+    ///
+    /// let manager = RemoteMouseManager::connect_tcp("192.168.1.20:7070").unwrap();
+    /// assert_eq!(manager.move_to(100, 100), Ok(()));
+    /// ```
+    pub fn connect_tcp<A: ToSocketAddrs>(address: A) -> Result<Self, Error> {
+        let address = address
+            .to_socket_addrs()
+            .ok()
+            .and_then(|mut addresses| addresses.next())
+            .ok_or(Error::CustomError("remote: failed to resolve the address"))?;
+        Self::connect(Address::Tcp(address))
+    }
+
+    /// Connect to a server that is listening on the given Unix socket
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::remote::RemoteMouseManager;
+    /// use mouce::MouseActions;
+    ///
+    /// let manager = RemoteMouseManager::connect_unix("/tmp/mouce.sock").unwrap();
+    /// assert_eq!(manager.move_to(100, 100), Ok(()));
+    /// ```
+    #[cfg(unix)]
+    pub fn connect_unix<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::connect(Address::Unix(path.as_ref().to_path_buf()))
+    }
+
+    fn connect(address: Address) -> Result<Self, Error> {
+        let stream = address.connect()?;
+        Ok(RemoteMouseManager {
+            address,
+            stream: Mutex::new(stream),
+            callbacks: Arc::new(Mutex::new(CallbackMap::new())),
+            listener: None,
+        })
+    }
+
+    /// Send the request and return the payload of a successful response
+    fn send(&self, request: Request) -> Result<Vec<u8>, Error> {
+        let mut stream = self.stream.lock().unwrap();
+        write_frame(&mut *stream, &encode_request(&request))?;
+        let response = read_frame(&mut *stream)?;
+
+        match response.split_first() {
+            Some((&STATUS_OK, payload)) => Ok(payload.to_vec()),
+            Some((&STATUS_ERROR, payload)) => Err(decode_error(*payload.first().unwrap_or(&0))),
+            _ => Err(Error::CustomError("remote: received an invalid response")),
+        }
+    }
+
+    /// Open a second connection that receives the mouse events of the server
+    fn start_listener(&self) -> Result<(Stream, JoinHandle<()>), Error> {
+        let mut stream = self.address.connect()?;
+        write_frame(&mut stream, &encode_request(&Request::Subscribe))?;
+        let connection = stream.try_clone()?;
+
+        let callbacks = self.callbacks.clone();
+        let thread = thread::spawn(move || {
+            // The thread finishes when the server closes the connection
+            while let Ok(payload) = read_frame(&mut stream) {
+                if let Some((event, info)) = decode_event(&payload) {
+                    // Invoke all given callbacks with the received mouse event
+                    callbacks.lock().unwrap().invoke(&event, &info);
+                }
+            }
+        });
+
+        Ok((connection, thread))
+    }
+}
+
+impl MouseActions for RemoteMouseManager {
+    fn move_to(&self, x: usize, y: usize) -> Result<(), Error> {
+        // The protocol sends the coordinates as u32
+        match (u32::try_from(x), u32::try_from(y)) {
+            (Ok(x), Ok(y)) => {
+                self.send(Request::MoveTo(x, y))?;
+                Ok(())
+            }
+            _ => Err(Error::CustomError(
+                "remote: the position does not fit into the protocol",
+            )),
+        }
+    }
+
+    fn move_relative(&self, x_offset: i32, y_offset: i32) -> Result<(), Error> {
+        self.send(Request::MoveRelative(x_offset, y_offset))?;
+        Ok(())
+    }
+
+    fn move_to_monitor(&self, _monitor_id: usize, _x: u32, _y: u32) -> Result<(), Error> {
+        // The local monitors say nothing about the monitors of the remote machine
+        Err(Error::NotImplemented)
+    }
+
+    fn current_monitor(&self) -> Result<Monitor, Error> {
+        Err(Error::NotImplemented)
+    }
+
+    fn get_position(&self) -> Result<(i32, i32), Error> {
+        let payload = self.send(Request::GetPosition)?;
+        match (read_u32(&payload, 0), read_u32(&payload, 4)) {
+            (Some(x), Some(y)) => Ok((x as i32, y as i32)),
+            _ => Err(Error::CustomError("remote: received an invalid response")),
+        }
+    }
+
+    fn press_button(&self, button: &MouseButton) -> Result<(), Error> {
+        self.send(Request::PressButton(*button))?;
+        Ok(())
+    }
+
+    fn release_button(&self, button: &MouseButton) -> Result<(), Error> {
+        self.send(Request::ReleaseButton(*button))?;
+        Ok(())
+    }
+
+    fn click_button(&self, button: &MouseButton) -> Result<(), Error> {
+        self.send(Request::ClickButton(*button))?;
+        Ok(())
+    }
+
+    fn scroll_wheel(
+        &self,
+        direction: &ScrollDirection,
+        unit: ScrollUnit,
+        distance: u32,
+    ) -> Result<(), Error> {
+        self.send(Request::ScrollWheel(*direction, unit, distance))?;
+        Ok(())
+    }
+
+    fn hook_with_info(&mut self, callback: EventCallback) -> Result<CallbackId, Error> {
+        if self.listener.is_none() {
+            self.listener = Some(self.start_listener()?);
+        }
+
+        let id = CallbackId::next();
+        self.callbacks.lock().unwrap().insert(id, callback);
+        Ok(id)
+    }
+
+    fn simulate_event(&self, event: &MouseEvent) -> Result<(), Error> {
+        simulate_event(&self.callbacks, event);
+        Ok(())
+    }
+
+    fn callback_panics(&mut self) -> Result<Receiver<Error>, Error> {
+        Ok(self.callbacks.lock().unwrap().panic_receiver())
+    }
+
+    fn unhook(&mut self, callback_id: CallbackId) -> Result<(), Error> {
+        match self.callbacks.lock().unwrap().remove(&callback_id) {
+            Some(_) => Ok(()),
+            None => Err(Error::UnhookFailed),
+        }
+    }
+
+    fn unhook_all(&mut self) -> Result<(), Error> {
+        self.callbacks.lock().unwrap().clear();
+        Ok(())
+    }
+
+    fn pause_listening(&mut self) -> Result<(), Error> {
+        self.callbacks.lock().unwrap().paused = true;
+        Ok(())
+    }
+
+    fn resume_listening(&mut self) -> Result<(), Error> {
+        self.callbacks.lock().unwrap().paused = false;
+        Ok(())
+    }
+
+    fn stop_listening(&mut self) -> Result<(), Error> {
+        self.callbacks.lock().unwrap().clear();
+        if let Some((connection, thread)) = self.listener.take() {
+            // The server unsubscribes once the connection is closed
+            connection.shutdown();
+            let _ = thread.join();
+        }
+        Ok(())
+    }
+}
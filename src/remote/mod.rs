@@ -0,0 +1,546 @@
+///
+/// This module contains a small length-prefixed protocol for
+/// controlling the mouse of another machine over TCP or Unix sockets
+///
+/// - `RemoteMouseManager` implements `MouseActions` by sending requests to a server
+/// - `RemoteServer` executes the received requests with a local mouse manager
+///
+/// Every frame starts with the length of its payload as a big-endian u32, the
+/// first byte of the payload is the opcode and the rest are its big-endian arguments
+///
+/// The streamed events may be followed by the position of the cursor when the event
+/// happened, the device of the event is not sent since its id is only meaningful locally
+///
+/// The protocol is neither authenticated nor encrypted, anyone who can connect to a
+/// server controls its mouse and receives its mouse events, so only listen on trusted
+/// interfaces (e.g. localhost) or tunnel the connections through SSH
+///
+use crate::common::{EventInfo, MouseButton, MouseEvent, ScrollDirection, ScrollUnit};
+use crate::error::Error;
+use std::io::{Read, Write};
+use std::net::{Shutdown, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+
+mod client;
+mod server;
+
+pub use client::RemoteMouseManager;
+pub use server::RemoteServer;
+
+/// The maximum accepted payload size, frames are tiny so anything
+/// bigger than this is a corrupted or malicious stream
+const MAX_FRAME_SIZE: u32 = 1024;
+
+/// Request opcodes
+const OP_MOVE_TO: u8 = 0x01;
+const OP_MOVE_RELATIVE: u8 = 0x02;
+const OP_GET_POSITION: u8 = 0x03;
+const OP_PRESS_BUTTON: u8 = 0x04;
+const OP_RELEASE_BUTTON: u8 = 0x05;
+const OP_CLICK_BUTTON: u8 = 0x06;
+const OP_SCROLL_WHEEL: u8 = 0x07;
+const OP_SUBSCRIBE: u8 = 0x08;
+
+/// Response statuses
+const STATUS_OK: u8 = 0x00;
+const STATUS_ERROR: u8 = 0x01;
+
+/// Event kinds that are streamed to the subscribed clients
+const EVENT_PRESS: u8 = 0x02;
+const EVENT_RELEASE: u8 = 0x03;
+const EVENT_MOVE: u8 = 0x05;
+const EVENT_SCROLL_DISTANCE: u8 = 0x06;
+const EVENT_DOUBLE_CLICK: u8 = 0x07;
+const EVENT_DRAG: u8 = 0x08;
+const EVENT_LISTENER_INTERRUPTED: u8 = 0x09;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Request {
+    MoveTo(u32, u32),
+    MoveRelative(i32, i32),
+    GetPosition,
+    PressButton(MouseButton),
+    ReleaseButton(MouseButton),
+    ClickButton(MouseButton),
+    ScrollWheel(ScrollDirection, ScrollUnit, u32),
+    Subscribe,
+}
+
+/// A connection to either a TCP or a Unix socket
+enum Stream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Stream {
+    fn try_clone(&self) -> Result<Stream, Error> {
+        let stream = match self {
+            Stream::Tcp(stream) => stream.try_clone().map(Stream::Tcp),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.try_clone().map(Stream::Unix),
+        };
+        stream.map_err(|_| Error::CustomError("remote: failed to clone the connection"))
+    }
+
+    /// Close the connection, the blocked reads of its clones return as well
+    fn shutdown(&self) {
+        let _ = match self {
+            Stream::Tcp(stream) => stream.shutdown(Shutdown::Both),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.shutdown(Shutdown::Both),
+        };
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.read(buf),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.write(buf),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.flush(),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.flush(),
+        }
+    }
+}
+
+/// Write the given payload as a single frame
+fn write_frame<W: Write>(writer: &mut W, payload: &[u8]) -> Result<(), Error> {
+    let mut frame = Vec::with_capacity(payload.len() + 4);
+    frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    frame.extend_from_slice(payload);
+    writer.write_all(&frame).map_err(|_| Error::WriteFailed)
+}
+
+/// Read a single frame and return its payload
+fn read_frame<R: Read>(reader: &mut R) -> Result<Vec<u8>, Error> {
+    let mut length = [0; 4];
+    reader
+        .read_exact(&mut length)
+        .map_err(|_| Error::CustomError("remote: the connection is closed"))?;
+
+    let length = u32::from_be_bytes(length);
+    if length > MAX_FRAME_SIZE {
+        return Err(Error::CustomError("remote: received an oversized frame"));
+    }
+
+    let mut payload = vec![0; length as usize];
+    reader
+        .read_exact(&mut payload)
+        .map_err(|_| Error::CustomError("remote: the connection is closed"))?;
+    Ok(payload)
+}
+
+fn encode_request(request: &Request) -> Vec<u8> {
+    let mut payload = vec![];
+    match request {
+        Request::MoveTo(x, y) => {
+            payload.push(OP_MOVE_TO);
+            payload.extend_from_slice(&x.to_be_bytes());
+            payload.extend_from_slice(&y.to_be_bytes());
+        }
+        Request::MoveRelative(x, y) => {
+            payload.push(OP_MOVE_RELATIVE);
+            payload.extend_from_slice(&x.to_be_bytes());
+            payload.extend_from_slice(&y.to_be_bytes());
+        }
+        Request::GetPosition => payload.push(OP_GET_POSITION),
+        Request::PressButton(button) => {
+            payload.push(OP_PRESS_BUTTON);
+            payload.extend_from_slice(&encode_button(button));
+        }
+        Request::ReleaseButton(button) => {
+            payload.push(OP_RELEASE_BUTTON);
+            payload.extend_from_slice(&encode_button(button));
+        }
+        Request::ClickButton(button) => {
+            payload.push(OP_CLICK_BUTTON);
+            payload.extend_from_slice(&encode_button(button));
+        }
+        Request::ScrollWheel(direction, unit, distance) => {
+            payload.push(OP_SCROLL_WHEEL);
+            payload.push(encode_direction(direction));
+            payload.push(encode_unit(unit));
+            payload.extend_from_slice(&distance.to_be_bytes());
+        }
+        Request::Subscribe => payload.push(OP_SUBSCRIBE),
+    }
+    payload
+}
+
+fn decode_request(payload: &[u8]) -> Option<Request> {
+    let (opcode, args) = payload.split_first()?;
+    let request = match *opcode {
+        OP_MOVE_TO => Request::MoveTo(read_u32(args, 0)?, read_u32(args, 4)?),
+        OP_MOVE_RELATIVE => {
+            Request::MoveRelative(read_u32(args, 0)? as i32, read_u32(args, 4)? as i32)
+        }
+        OP_GET_POSITION => Request::GetPosition,
+        OP_PRESS_BUTTON => Request::PressButton(decode_button(args)?),
+        OP_RELEASE_BUTTON => Request::ReleaseButton(decode_button(args)?),
+        OP_CLICK_BUTTON => Request::ClickButton(decode_button(args)?),
+        OP_SCROLL_WHEEL => Request::ScrollWheel(
+            decode_direction(*args.first()?)?,
+            decode_unit(*args.get(1)?)?,
+            read_u32(args, 2)?,
+        ),
+        OP_SUBSCRIBE => Request::Subscribe,
+        _ => return None,
+    };
+    Some(request)
+}
+
+fn encode_event(event: &MouseEvent, info: &EventInfo) -> Vec<u8> {
+    let mut payload = vec![];
+    match event {
+        MouseEvent::Move { position, delta } => {
+            // The position of the move is followed by a flag that tells whether it is known
+            let (x, y) = position.unwrap_or_default();
+            payload.push(EVENT_MOVE);
+            payload.extend_from_slice(&delta.0.to_be_bytes());
+            payload.extend_from_slice(&delta.1.to_be_bytes());
+            payload.extend_from_slice(&x.to_be_bytes());
+            payload.extend_from_slice(&y.to_be_bytes());
+            payload.push(position.is_some() as u8);
+        }
+        MouseEvent::Press(button) => {
+            payload.push(EVENT_PRESS);
+            payload.extend_from_slice(&encode_button(button));
+        }
+        MouseEvent::Release(button) => {
+            payload.push(EVENT_RELEASE);
+            payload.extend_from_slice(&encode_button(button));
+        }
+        MouseEvent::Drag {
+            button,
+            position,
+            delta,
+        } => {
+            let (x, y) = position.unwrap_or_default();
+            payload.push(EVENT_DRAG);
+            payload.extend_from_slice(&encode_button(button));
+            payload.extend_from_slice(&delta.0.to_be_bytes());
+            payload.extend_from_slice(&delta.1.to_be_bytes());
+            payload.extend_from_slice(&x.to_be_bytes());
+            payload.extend_from_slice(&y.to_be_bytes());
+            payload.push(position.is_some() as u8);
+        }
+        MouseEvent::DoubleClick { button, position } => {
+            let (x, y) = position.unwrap_or_default();
+            payload.push(EVENT_DOUBLE_CLICK);
+            payload.extend_from_slice(&encode_button(button));
+            payload.extend_from_slice(&x.to_be_bytes());
+            payload.extend_from_slice(&y.to_be_bytes());
+            payload.push(position.is_some() as u8);
+        }
+        MouseEvent::Scroll {
+            direction,
+            unit,
+            distance,
+        } => {
+            payload.push(EVENT_SCROLL_DISTANCE);
+            payload.push(encode_direction(direction));
+            payload.push(encode_unit(unit));
+            payload.extend_from_slice(&distance.to_be_bytes());
+        }
+        MouseEvent::ListenerInterrupted => payload.push(EVENT_LISTENER_INTERRUPTED),
+    }
+    if let Some((x, y)) = info.position {
+        payload.extend_from_slice(&x.to_be_bytes());
+        payload.extend_from_slice(&y.to_be_bytes());
+    }
+    payload
+}
+
+fn decode_event(payload: &[u8]) -> Option<(MouseEvent, EventInfo)> {
+    let (kind, args) = payload.split_first()?;
+    let (event, length) = match *kind {
+        EVENT_MOVE => {
+            let position = (read_u32(args, 8)? as i32, read_u32(args, 12)? as i32);
+            let event = MouseEvent::Move {
+                position: (*args.get(16)? != 0).then_some(position),
+                delta: (read_u32(args, 0)? as i32, read_u32(args, 4)? as i32),
+            };
+            (event, 17)
+        }
+        EVENT_PRESS => (MouseEvent::Press(decode_button(args)?), 2),
+        EVENT_RELEASE => (MouseEvent::Release(decode_button(args)?), 2),
+        EVENT_DRAG => {
+            let position = (read_u32(args, 10)? as i32, read_u32(args, 14)? as i32);
+            let event = MouseEvent::Drag {
+                button: decode_button(args)?,
+                position: (*args.get(18)? != 0).then_some(position),
+                delta: (read_u32(args, 2)? as i32, read_u32(args, 6)? as i32),
+            };
+            (event, 19)
+        }
+        EVENT_DOUBLE_CLICK => {
+            let position = (read_u32(args, 2)? as i32, read_u32(args, 6)? as i32);
+            let event = MouseEvent::DoubleClick {
+                button: decode_button(args)?,
+                position: (*args.get(10)? != 0).then_some(position),
+            };
+            (event, 11)
+        }
+        EVENT_SCROLL_DISTANCE => {
+            let event = MouseEvent::Scroll {
+                direction: decode_direction(*args.first()?)?,
+                unit: decode_unit(*args.get(1)?)?,
+                distance: read_f64(args, 2)?,
+            };
+            (event, 10)
+        }
+        EVENT_LISTENER_INTERRUPTED => (MouseEvent::ListenerInterrupted, 0),
+        _ => return None,
+    };
+
+    // The position of the pointer is only sent when it is known
+    let position = match args.len().checked_sub(length)? {
+        0 => None,
+        8 => Some((
+            read_u32(args, length)? as i32,
+            read_u32(args, length + 4)? as i32,
+        )),
+        _ => return None,
+    };
+    let info = EventInfo {
+        position,
+        ..EventInfo::default()
+    };
+    Some((event, info))
+}
+
+fn encode_error(error: &Error) -> u8 {
+    match error {
+        Error::NotImplemented => 0x01,
+        Error::WriteFailed => 0x02,
+        Error::UnhookFailed => 0x03,
+        Error::X11PointerWindowMismatch => 0x04,
+        Error::InputIsBlocked => 0x05,
+        Error::CGCouldNotCreateEvent => 0x06,
+        Error::PermissionDenied => 0x07,
+        Error::AccessibilityNotTrusted => 0x08,
+        // The context and the code of the system are not sent
+        Error::CustomError(_)
+        | Error::OsError { .. }
+        | Error::CGError { .. }
+        | Error::X11Error { .. }
+        | Error::Io { .. }
+        | Error::CallbackPanicked { .. } => 0xff,
+    }
+}
+
+fn decode_error(code: u8) -> Error {
+    match code {
+        0x01 => Error::NotImplemented,
+        0x02 => Error::WriteFailed,
+        0x03 => Error::UnhookFailed,
+        0x04 => Error::X11PointerWindowMismatch,
+        0x05 => Error::InputIsBlocked,
+        0x06 => Error::CGCouldNotCreateEvent,
+        0x07 => Error::PermissionDenied,
+        0x08 => Error::AccessibilityNotTrusted,
+        _ => Error::CustomError("remote: the operation failed on the server"),
+    }
+}
+
+/// Encode the given button as a code and the index of the `Other` buttons
+fn encode_button(button: &MouseButton) -> [u8; 2] {
+    match button {
+        MouseButton::Left => [0x00, 0],
+        MouseButton::Middle => [0x01, 0],
+        MouseButton::Right => [0x02, 0],
+        MouseButton::Back => [0x03, 0],
+        MouseButton::Forward => [0x04, 0],
+        MouseButton::Other(index) => [0x05, *index],
+    }
+}
+
+fn decode_button(args: &[u8]) -> Option<MouseButton> {
+    match *args.first()? {
+        0x00 => Some(MouseButton::Left),
+        0x01 => Some(MouseButton::Middle),
+        0x02 => Some(MouseButton::Right),
+        0x03 => Some(MouseButton::Back),
+        0x04 => Some(MouseButton::Forward),
+        0x05 => Some(MouseButton::Other(*args.get(1)?)),
+        _ => None,
+    }
+}
+
+fn encode_direction(direction: &ScrollDirection) -> u8 {
+    match direction {
+        ScrollDirection::Up => 0x00,
+        ScrollDirection::Down => 0x01,
+        ScrollDirection::Right => 0x02,
+        ScrollDirection::Left => 0x03,
+    }
+}
+
+fn decode_direction(code: u8) -> Option<ScrollDirection> {
+    match code {
+        0x00 => Some(ScrollDirection::Up),
+        0x01 => Some(ScrollDirection::Down),
+        0x02 => Some(ScrollDirection::Right),
+        0x03 => Some(ScrollDirection::Left),
+        _ => None,
+    }
+}
+
+fn encode_unit(unit: &ScrollUnit) -> u8 {
+    match unit {
+        ScrollUnit::Line => 0x00,
+        ScrollUnit::Pixel => 0x01,
+    }
+}
+
+fn decode_unit(code: u8) -> Option<ScrollUnit> {
+    match code {
+        0x00 => Some(ScrollUnit::Line),
+        0x01 => Some(ScrollUnit::Pixel),
+        _ => None,
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    let bytes = bytes.get(offset..offset + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn read_f64(bytes: &[u8], offset: usize) -> Option<f64> {
+    let bytes = bytes.get(offset..offset + 8)?;
+    Some(f64::from_be_bytes(bytes.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        decode_event, decode_request, encode_event, encode_request, read_frame, write_frame,
+        Request,
+    };
+    use crate::common::{EventInfo, MouseButton, MouseEvent, ScrollDirection, ScrollUnit};
+
+    #[test]
+    fn request_round_trip() {
+        let requests = [
+            Request::MoveTo(1920, 1080),
+            Request::MoveRelative(-15, 30),
+            Request::GetPosition,
+            Request::PressButton(MouseButton::Left),
+            Request::ReleaseButton(MouseButton::Middle),
+            Request::ClickButton(MouseButton::Right),
+            Request::PressButton(MouseButton::Back),
+            Request::ClickButton(MouseButton::Other(7)),
+            Request::ScrollWheel(ScrollDirection::Left, ScrollUnit::Line, 3),
+            Request::ScrollWheel(ScrollDirection::Up, ScrollUnit::Pixel, 120),
+            Request::Subscribe,
+        ];
+
+        for request in requests {
+            assert_eq!(decode_request(&encode_request(&request)), Some(request));
+        }
+    }
+
+    #[test]
+    fn event_round_trip() {
+        let events = [
+            MouseEvent::Move {
+                position: None,
+                delta: (-3, 4),
+            },
+            MouseEvent::Move {
+                position: Some((100, 200)),
+                delta: (1, -1),
+            },
+            MouseEvent::Press(MouseButton::Middle),
+            MouseEvent::Release(MouseButton::Right),
+            MouseEvent::Press(MouseButton::Forward),
+            MouseEvent::Scroll {
+                direction: ScrollDirection::Down,
+                unit: ScrollUnit::Pixel,
+                distance: 12.5,
+            },
+            MouseEvent::Drag {
+                button: MouseButton::Left,
+                position: None,
+                delta: (0, -8),
+            },
+            MouseEvent::DoubleClick {
+                button: MouseButton::Other(3),
+                position: Some((-5, 7)),
+            },
+            MouseEvent::ListenerInterrupted,
+        ];
+
+        for event in events {
+            let info = EventInfo::default();
+            assert_eq!(
+                decode_event(&encode_event(&event, &info)),
+                Some((event, info))
+            );
+
+            let info = EventInfo {
+                position: Some((-20, 1080)),
+                ..EventInfo::default()
+            };
+            assert_eq!(
+                decode_event(&encode_event(&event, &info)),
+                Some((event, info))
+            );
+        }
+    }
+
+    #[test]
+    fn reject_malformed_payloads() {
+        assert_eq!(decode_request(&[]), None);
+        assert_eq!(decode_request(&[0x01, 0x00]), None);
+        assert_eq!(decode_request(&[0x04, 0x09]), None);
+        // The Other buttons need their index
+        assert_eq!(decode_request(&[0x04, 0x05]), None);
+        assert_eq!(decode_request(&[0x7f]), None);
+        assert_eq!(decode_event(&[0x02]), None);
+        assert_eq!(decode_event(&[0x02, 0x00]), None);
+        assert_eq!(decode_event(&[0x05, 0, 0, 0, 1]), None);
+        // The events of the older servers are not accepted anymore
+        assert_eq!(decode_event(&[0x00, 0, 0, 0, 1, 0, 0, 0, 2]), None);
+        assert_eq!(decode_event(&[0x04, 0x01]), None);
+        // The position is either sent whole or not at all
+        assert_eq!(decode_event(&[0x02, 0x00, 0, 0, 0, 0, 1]), None);
+    }
+
+    #[test]
+    fn frame_round_trip() {
+        let mut buffer = vec![];
+        write_frame(&mut buffer, &[1, 2, 3]).unwrap();
+        write_frame(&mut buffer, &[]).unwrap();
+
+        let mut reader = buffer.as_slice();
+        assert_eq!(read_frame(&mut reader), Ok(vec![1, 2, 3]));
+        assert_eq!(read_frame(&mut reader), Ok(vec![]));
+        assert!(read_frame(&mut reader).is_err());
+    }
+
+    #[test]
+    fn reject_oversized_frame() {
+        let buffer = u32::MAX.to_be_bytes();
+        assert!(read_frame(&mut buffer.as_slice()).is_err());
+    }
+}
@@ -0,0 +1,363 @@
+use crate::common::{CallbackId, MouseActions};
+use crate::error::Error;
+use crate::remote::{
+    decode_request, encode_error, encode_event, read_frame, write_frame, Request, Stream,
+    STATUS_ERROR, STATUS_OK,
+};
+use std::collections::HashMap;
+use std::net::{TcpListener, ToSocketAddrs};
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
+#[cfg(unix)]
+use std::path::Path;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+/// The number of event frames that are queued for a subscribed client, the
+/// events are dropped while the queue is full so a slow client does not
+/// hold up the hooks
+const SUBSCRIPTION_QUEUE_SIZE: usize = 1024;
+
+enum Message {
+    Connected(Stream),
+    Request(usize, Vec<u8>),
+    Disconnected(usize),
+}
+
+pub struct RemoteServer {
+    manager: Box<dyn MouseActions>,
+    connections: HashMap<usize, Stream>,
+    subscriptions: HashMap<usize, CallbackId>,
+    connection_counter: usize,
+}
+
+impl RemoteServer {
+    /// Create a server that executes the received requests with the given manager
+    pub fn new(manager: Box<dyn MouseActions>) -> Self {
+        RemoteServer {
+            manager,
+            connections: HashMap::new(),
+            subscriptions: HashMap::new(),
+            connection_counter: 0,
+        }
+    }
+
+    /// Accept clients on the given TCP address, this function blocks forever
+    ///
+    /// The clients are not authenticated, so anyone who can reach the address
+    /// can control the mouse, prefer a loopback address and forward it over SSH
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::remote::RemoteServer;
+    /// use mouce::Mouse;
+    ///
+    /// let server = RemoteServer::new(Mouse::new());
+    /// server.serve_tcp("127.0.0.1:7070").unwrap();
+    /// ```
+    pub fn serve_tcp<A: ToSocketAddrs>(self, address: A) -> Result<(), Error> {
+        let listener = TcpListener::bind(address)
+            .map_err(|_| Error::CustomError("remote: failed to bind the address"))?;
+        self.serve_tcp_listener(listener)
+    }
+
+    /// Accept clients on the given Unix socket, this function blocks forever
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::remote::RemoteServer;
+    /// use mouce::Mouse;
+    ///
+    /// let server = RemoteServer::new(Mouse::new());
+    /// server.serve_unix("/tmp/mouce.sock").unwrap();
+    /// ```
+    #[cfg(unix)]
+    pub fn serve_unix<P: AsRef<Path>>(self, path: P) -> Result<(), Error> {
+        let listener = UnixListener::bind(path)
+            .map_err(|_| Error::CustomError("remote: failed to bind the socket"))?;
+
+        let (tx, rx) = mpsc::channel();
+        let accept_tx = tx.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if accept_tx
+                    .send(Message::Connected(Stream::Unix(stream)))
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+        self.run(tx, rx)
+    }
+
+    fn serve_tcp_listener(self, listener: TcpListener) -> Result<(), Error> {
+        let (tx, rx) = mpsc::channel();
+        let accept_tx = tx.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let _ = stream.set_nodelay(true);
+                if accept_tx
+                    .send(Message::Connected(Stream::Tcp(stream)))
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+        self.run(tx, rx)
+    }
+
+    /// Execute the incoming requests one by one on the current thread
+    fn run(mut self, tx: Sender<Message>, rx: mpsc::Receiver<Message>) -> Result<(), Error> {
+        for message in rx {
+            match message {
+                Message::Connected(stream) => {
+                    let id = self.connection_counter;
+                    self.connection_counter += 1;
+
+                    let mut reader = match stream.try_clone() {
+                        Ok(reader) => reader,
+                        Err(_) => continue,
+                    };
+                    self.connections.insert(id, stream);
+
+                    let tx = tx.clone();
+                    thread::spawn(move || {
+                        while let Ok(payload) = read_frame(&mut reader) {
+                            if tx.send(Message::Request(id, payload)).is_err() {
+                                return;
+                            }
+                        }
+                        let _ = tx.send(Message::Disconnected(id));
+                    });
+                }
+                Message::Request(id, payload) => self.handle_request(id, &payload),
+                Message::Disconnected(id) => {
+                    self.connections.remove(&id);
+                    if let Some(callback_id) = self.subscriptions.remove(&id) {
+                        let _ = self.manager.unhook(callback_id);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_request(&mut self, id: usize, payload: &[u8]) {
+        let result = match decode_request(payload) {
+            Some(Request::Subscribe) => {
+                self.subscribe(id);
+                return;
+            }
+            Some(request) => self.execute(request),
+            None => Err(Error::CustomError("remote: received an invalid request")),
+        };
+
+        let response = match result {
+            Ok(mut payload) => {
+                payload.insert(0, STATUS_OK);
+                payload
+            }
+            Err(err) => vec![STATUS_ERROR, encode_error(&err)],
+        };
+
+        if let Some(stream) = self.connections.get_mut(&id) {
+            let _ = write_frame(stream, &response);
+        }
+    }
+
+    fn execute(&mut self, request: Request) -> Result<Vec<u8>, Error> {
+        match request {
+            Request::MoveTo(x, y) => self.manager.move_to(x as usize, y as usize)?,
+            Request::MoveRelative(x, y) => self.manager.move_relative(x, y)?,
+            Request::GetPosition => {
+                let (x, y) = self.manager.get_position()?;
+                let mut payload = x.to_be_bytes().to_vec();
+                payload.extend_from_slice(&y.to_be_bytes());
+                return Ok(payload);
+            }
+            Request::PressButton(button) => self.manager.press_button(&button)?,
+            Request::ReleaseButton(button) => self.manager.release_button(&button)?,
+            Request::ClickButton(button) => self.manager.click_button(&button)?,
+            Request::ScrollWheel(direction, unit, distance) => {
+                self.manager.scroll_wheel(&direction, unit, distance)?
+            }
+            Request::Subscribe => {}
+        }
+        Ok(vec![])
+    }
+
+    /// Stream the mouse events of this machine to the given connection
+    fn subscribe(&mut self, id: usize) {
+        if self.subscriptions.contains_key(&id) {
+            return;
+        }
+
+        let mut writer = match self.connections.get(&id).map(Stream::try_clone) {
+            Some(Ok(writer)) => writer,
+            _ => return,
+        };
+
+        // The frames are written on a thread of the connection, so the hook does not
+        // block on the socket, the thread ends once the callback is unhooked
+        let (tx, rx) = mpsc::sync_channel::<Vec<u8>>(SUBSCRIPTION_QUEUE_SIZE);
+        thread::spawn(move || {
+            for frame in rx {
+                if write_frame(&mut writer, &frame).is_err() {
+                    return;
+                }
+            }
+        });
+
+        let callback_id = self.manager.hook_with_info(Box::new(move |event, info| {
+            let _ = tx.try_send(encode_event(event, info));
+        }));
+
+        match callback_id {
+            Ok(callback_id) => {
+                self.subscriptions.insert(id, callback_id);
+            }
+            Err(_) => {
+                // The client can not be notified on this connection, so drop it
+                self.connections.remove(&id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RemoteServer;
+    use crate::common::{
+        CallbackId, CallbackMap, Callbacks, EventCallback, EventInfo, MouseActions, MouseButton,
+        MouseEvent, ScrollDirection, ScrollUnit,
+    };
+    use crate::error::Error;
+    use crate::remote::RemoteMouseManager;
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    /// A manager that only keeps track of the position and
+    /// reports every move to the hooked callbacks
+    struct TestMouseManager {
+        position: Mutex<(i32, i32)>,
+        callbacks: Callbacks,
+    }
+
+    impl MouseActions for TestMouseManager {
+        fn move_to(&self, x: usize, y: usize) -> Result<(), Error> {
+            *self.position.lock().unwrap() = (x as i32, y as i32);
+            let event = MouseEvent::Move {
+                position: Some((x as i32, y as i32)),
+                delta: (0, 0),
+            };
+            self.callbacks
+                .lock()
+                .unwrap()
+                .invoke(&event, &EventInfo::default());
+            Ok(())
+        }
+
+        fn get_position(&self) -> Result<(i32, i32), Error> {
+            Ok(*self.position.lock().unwrap())
+        }
+
+        fn press_button(&self, _button: &MouseButton) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn release_button(&self, _button: &MouseButton) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn scroll_wheel(
+            &self,
+            _direction: &ScrollDirection,
+            _unit: ScrollUnit,
+            _distance: u32,
+        ) -> Result<(), Error> {
+            Err(Error::NotImplemented)
+        }
+
+        fn hook_with_info(&mut self, callback: EventCallback) -> Result<CallbackId, Error> {
+            let id = CallbackId::next();
+            self.callbacks.lock().unwrap().insert(id, callback);
+            Ok(id)
+        }
+
+        fn unhook(&mut self, callback_id: CallbackId) -> Result<(), Error> {
+            match self.callbacks.lock().unwrap().remove(&callback_id) {
+                Some(_) => Ok(()),
+                None => Err(Error::UnhookFailed),
+            }
+        }
+
+        fn unhook_all(&mut self) -> Result<(), Error> {
+            self.callbacks.lock().unwrap().clear();
+            Ok(())
+        }
+    }
+
+    fn start_server() -> RemoteMouseManager {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let manager = TestMouseManager {
+                position: Mutex::new((0, 0)),
+                callbacks: Arc::new(Mutex::new(CallbackMap::new())),
+            };
+            RemoteServer::new(Box::new(manager))
+                .serve_tcp_listener(listener)
+                .unwrap();
+        });
+        RemoteMouseManager::connect_tcp(address).unwrap()
+    }
+
+    #[test]
+    fn remote_actions() {
+        let manager = start_server();
+        assert_eq!(manager.move_to(30, 40), Ok(()));
+        assert_eq!(manager.move_relative(-10, 5), Ok(()));
+        assert_eq!(manager.get_position(), Ok((20, 45)));
+        assert_eq!(manager.click_button(&MouseButton::Left), Ok(()));
+        assert_eq!(
+            manager.scroll_wheel(&ScrollDirection::Up, ScrollUnit::Pixel, 10),
+            Err(Error::NotImplemented)
+        );
+    }
+
+    #[test]
+    fn remote_hook() {
+        let mut manager = start_server();
+        let (tx, rx) = mpsc::channel();
+        manager
+            .hook(Box::new(move |e| {
+                let _ = tx.send(*e);
+            }))
+            .unwrap();
+
+        // The subscription is handled asynchronously on the server,
+        // so keep moving until the first event arrives
+        let mut received = None;
+        for _ in 0..50 {
+            manager.move_to(12, 34).unwrap();
+            if let Ok(event) = rx.recv_timeout(Duration::from_millis(100)) {
+                received = Some(event);
+                break;
+            }
+        }
+        assert_eq!(
+            received,
+            Some(MouseEvent::Move {
+                position: Some((12, 34)),
+                delta: (0, 0)
+            })
+        );
+    }
+}
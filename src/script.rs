@@ -0,0 +1,293 @@
+///
+/// This module contains a tiny scripting language for the mouse actions
+///
+/// A script is a list of commands that are separated by `;` or new lines,
+/// the text after a `#` is a comment:
+///
+/// ```text
+/// move 100 200; click left
+/// sleep 250           # milliseconds
+/// scroll down 3       # lines, `scroll down 30 pixel` scrolls in pixels
+/// ```
+///
+/// The commands are `move x y`, `move_relative dx dy`, `press button`,
+/// `release button`, `click button [count]`, `double_click button`,
+/// `scroll direction [distance] [line|pixel]` and `sleep milliseconds`
+///
+use crate::common::{ButtonState, MouseActions, MouseButton, ScrollDirection, ScrollUnit};
+use crate::error::Error;
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
+
+/// A command of a script
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Command {
+    Move(usize, usize),
+    MoveRelative(i32, i32),
+    Press(MouseButton),
+    Release(MouseButton),
+    Click(MouseButton, u32),
+    DoubleClick(MouseButton),
+    Scroll(ScrollDirection, ScrollUnit, u32),
+    Sleep(Duration),
+}
+
+impl Command {
+    /// Execute the command with the given manager
+    pub fn run(&self, manager: &dyn MouseActions) -> Result<(), Error> {
+        match self {
+            Command::Move(x, y) => manager.move_to(*x, *y),
+            Command::MoveRelative(x, y) => manager.move_relative(*x, *y),
+            Command::Press(button) => manager.press_button(button),
+            Command::Release(button) => manager.release_button(button),
+            Command::Click(button, count) => manager.click_n(button, *count),
+            Command::DoubleClick(button) => manager.double_click(button),
+            Command::Scroll(direction, unit, distance) => {
+                manager.scroll_wheel(direction, *unit, *distance)
+            }
+            Command::Sleep(duration) => {
+                thread::sleep(*duration);
+                Ok(())
+            }
+        }
+    }
+}
+
+impl FromStr for Command {
+    type Err = Error;
+
+    fn from_str(command: &str) -> Result<Self, Self::Err> {
+        let mut fields = command.split_whitespace();
+        let name = fields.next().unwrap_or_default().to_ascii_lowercase();
+        let command = match name.as_str() {
+            "move" => Command::Move(parse_field(&mut fields)?, parse_field(&mut fields)?),
+            "move_relative" => {
+                Command::MoveRelative(parse_field(&mut fields)?, parse_field(&mut fields)?)
+            }
+            "press" => Command::Press(parse_field(&mut fields)?),
+            "release" => Command::Release(parse_field(&mut fields)?),
+            "click" => Command::Click(
+                parse_field(&mut fields)?,
+                parse_optional(&mut fields)?.unwrap_or(1),
+            ),
+            "double_click" => Command::DoubleClick(parse_field(&mut fields)?),
+            // The distance comes before the unit, since it is given more often
+            "scroll" => {
+                let direction = parse_field(&mut fields)?;
+                let distance = parse_optional(&mut fields)?.unwrap_or(1);
+                let unit = parse_optional(&mut fields)?.unwrap_or(ScrollUnit::Line);
+                Command::Scroll(direction, unit, distance)
+            }
+            "sleep" => Command::Sleep(Duration::from_millis(parse_field(&mut fields)?)),
+            _ => return Err(Error::CustomError("the script has an unknown command")),
+        };
+        if fields.next().is_some() {
+            return Err(Error::CustomError("the script has an unexpected argument"));
+        }
+        Ok(command)
+    }
+}
+
+fn parse_field<'a, T: FromStr>(fields: &mut impl Iterator<Item = &'a str>) -> Result<T, Error> {
+    parse_optional(fields)?.ok_or(Error::CustomError("the script misses an argument"))
+}
+
+fn parse_optional<'a, T: FromStr>(
+    fields: &mut impl Iterator<Item = &'a str>,
+) -> Result<Option<T>, Error> {
+    match fields.next() {
+        Some(field) => field
+            .parse()
+            .map(Some)
+            .map_err(|_| Error::CustomError("the script has an invalid argument")),
+        None => Ok(None),
+    }
+}
+
+/// A parsed script, see the module documentation for its format
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Script {
+    pub commands: Vec<Command>,
+}
+
+impl Script {
+    /// Execute the commands of the script in order with the given manager
+    ///
+    /// The buttons that are held down by the script are released if a command fails
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::script::Script;
+    /// use mouce::Mouse;
+    ///
+    /// let manager = Mouse::new();
+    /// let script: Script = "move 100 200; click left; sleep 250; scroll down 3"
+    ///     .parse()
+    ///     .unwrap();
+    /// assert_eq!(script.run(manager.as_ref()), Ok(()));
+    /// ```
+    pub fn run(&self, manager: &dyn MouseActions) -> Result<(), Error> {
+        let mut pressed = ButtonState::new();
+        for command in &self.commands {
+            if let Err(err) = command.run(manager) {
+                for button in pressed.iter() {
+                    let _ = manager.release_button(&button);
+                }
+                return Err(err);
+            }
+            match command {
+                Command::Press(button) => pressed.insert(button),
+                Command::Release(button) => pressed.remove(button),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Script {
+    type Err = Error;
+
+    fn from_str(script: &str) -> Result<Self, Self::Err> {
+        let mut commands = vec![];
+        for line in script.lines() {
+            let line = line.split('#').next().unwrap_or_default();
+            for command in line.split(';').map(str::trim) {
+                if !command.is_empty() {
+                    commands.push(command.parse()?);
+                }
+            }
+        }
+        Ok(Script { commands })
+    }
+}
+
+/// Parse and execute the given script with the given manager
+pub fn run(script: &str, manager: &dyn MouseActions) -> Result<(), Error> {
+    script.parse::<Script>()?.run(manager)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Command, Script};
+    use crate::common::{
+        CallbackId, EventCallback, MouseActions, MouseButton, ScrollDirection, ScrollUnit,
+    };
+    use crate::error::Error;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    /// A manager that logs the actions, the moves past 1000 fail
+    #[derive(Default)]
+    struct TestMouseManager {
+        log: Mutex<Vec<String>>,
+    }
+
+    impl MouseActions for TestMouseManager {
+        fn move_to(&self, x: usize, y: usize) -> Result<(), Error> {
+            if x > 1000 || y > 1000 {
+                return Err(Error::CustomError("the position is off the screen"));
+            }
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("move_to {} {}", x, y));
+            Ok(())
+        }
+
+        fn get_position(&self) -> Result<(i32, i32), Error> {
+            Ok((0, 0))
+        }
+
+        fn press_button(&self, button: &MouseButton) -> Result<(), Error> {
+            self.log.lock().unwrap().push(format!("press {}", button));
+            Ok(())
+        }
+
+        fn release_button(&self, button: &MouseButton) -> Result<(), Error> {
+            self.log.lock().unwrap().push(format!("release {}", button));
+            Ok(())
+        }
+
+        fn scroll_wheel(
+            &self,
+            direction: &ScrollDirection,
+            unit: ScrollUnit,
+            distance: u32,
+        ) -> Result<(), Error> {
+            let action = format!("scroll {} {} {}", direction, unit, distance);
+            self.log.lock().unwrap().push(action);
+            Ok(())
+        }
+
+        fn hook_with_info(&mut self, _callback: EventCallback) -> Result<CallbackId, Error> {
+            Err(Error::NotImplemented)
+        }
+
+        fn unhook(&mut self, _callback_id: CallbackId) -> Result<(), Error> {
+            Err(Error::NotImplemented)
+        }
+
+        fn unhook_all(&mut self) -> Result<(), Error> {
+            Err(Error::NotImplemented)
+        }
+    }
+
+    #[test]
+    fn parse_scripts() {
+        let script: Script = "move 100 200; click left\n\
+            sleep 250 # milliseconds\n\
+            ; scroll down 3; SCROLL left 30 pixel\n\
+            click right 2; double_click middle; move_relative -5 5\n\
+            press 8; release 8"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            script.commands,
+            vec![
+                Command::Move(100, 200),
+                Command::Click(MouseButton::Left, 1),
+                Command::Sleep(Duration::from_millis(250)),
+                Command::Scroll(ScrollDirection::Down, ScrollUnit::Line, 3),
+                Command::Scroll(ScrollDirection::Left, ScrollUnit::Pixel, 30),
+                Command::Click(MouseButton::Right, 2),
+                Command::DoubleClick(MouseButton::Middle),
+                Command::MoveRelative(-5, 5),
+                Command::Press(MouseButton::Other(2)),
+                Command::Release(MouseButton::Other(2)),
+            ]
+        );
+
+        assert_eq!("".parse(), Ok(Script::default()));
+        assert!("jump 1 2".parse::<Script>().is_err());
+        assert!("move 100".parse::<Script>().is_err());
+        assert!("move 100 two".parse::<Script>().is_err());
+        assert!("sleep 10 20".parse::<Script>().is_err());
+    }
+
+    #[test]
+    fn run_scripts() {
+        let manager = TestMouseManager::default();
+        let script = "press left; move 10 20; scroll up; release left; move 2000 0; press right";
+        assert!(super::run(script, &manager).is_err());
+        assert_eq!(
+            *manager.log.lock().unwrap(),
+            [
+                "press left",
+                "move_to 10 20",
+                "scroll up line 1",
+                "release left"
+            ]
+        );
+
+        // The held buttons are released when a command fails
+        manager.log.lock().unwrap().clear();
+        assert!(super::run("press right; move 2000 0; move 10 10", &manager).is_err());
+        assert_eq!(
+            *manager.log.lock().unwrap(),
+            ["press right", "release right"]
+        );
+    }
+}
@@ -0,0 +1,49 @@
+///
+/// This module contains the functions to read and change the pointer
+/// speed and acceleration of the system
+///
+/// The systems use different scales for the speed, so it is mapped to the
+/// range from `0.0` for the slowest to `1.0` for the fastest speed
+///
+use crate::error::Error;
+
+/// Get the pointer speed of the system, from `0.0` to `1.0`
+pub fn pointer_speed() -> Result<f64, Error> {
+    crate::Mouse::pointer_speed()
+}
+
+/// Set the pointer speed of the system, the speed is clamped to the range from `0.0` to `1.0`
+///
+/// On macOS the speed and the acceleration are a single setting,
+/// so setting the speed enables the acceleration as well
+pub fn set_pointer_speed(speed: f64) -> Result<(), Error> {
+    if speed.is_nan() {
+        return Err(Error::CustomError("the pointer speed is not a number"));
+    }
+    crate::Mouse::set_pointer_speed(speed.clamp(0., 1.))
+}
+
+/// Check if the pointer acceleration of the system is enabled
+pub fn pointer_acceleration() -> Result<bool, Error> {
+    crate::Mouse::pointer_acceleration()
+}
+
+/// Enable or disable the pointer acceleration of the system
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use mouce::{Mouse, MouseActions};
+///
+/// // The relative movements only add up to the same distance without the acceleration
+/// let accelerated = mouce::settings::pointer_acceleration().unwrap();
+/// mouce::settings::set_pointer_acceleration(false).unwrap();
+///
+/// let manager = Mouse::new();
+/// manager.move_relative(100, 0).unwrap();
+///
+/// mouce::settings::set_pointer_acceleration(accelerated).unwrap();
+/// ```
+pub fn set_pointer_acceleration(enabled: bool) -> Result<(), Error> {
+    crate::Mouse::set_pointer_acceleration(enabled)
+}
@@ -0,0 +1,32 @@
+///
+/// This module contains the queries of the mouse settings of the system
+///
+/// Automation code can use them to pace the synthetic events like the
+/// user's own, e.g. to double-click within the double-click time
+///
+use std::time::Duration;
+
+/// Get the maximum time between the clicks of a double-click
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use mouce::common::MouseButton;
+/// use mouce::{Mouse, MouseActions};
+/// use std::thread;
+///
+/// let manager = Mouse::new();
+/// manager.click_button(&MouseButton::Left).unwrap();
+/// // Still a double-click, even on the machines with a short double-click time
+/// thread::sleep(mouce::system::double_click_time() / 2);
+/// manager.click_button(&MouseButton::Left).unwrap();
+/// ```
+pub fn double_click_time() -> Duration {
+    crate::Mouse::double_click_settings().0
+}
+
+/// Get the distance in pixels that the cursor can move with a held button
+/// before the system starts a drag
+pub fn drag_threshold() -> u32 {
+    crate::Mouse::drag_threshold()
+}
@@ -0,0 +1,471 @@
+///
+/// This module contains the mouse action functions
+/// for the browsers (wasm32-unknown-unknown)
+///
+/// Mouse actions dispatch synthetic DOM events on the element under the
+/// pointer, and the hooks listen to the DOM events of the document
+///
+/// - Limitations
+///     - Synthetic events are not trusted by the browser, so they do not move
+///       the real cursor or trigger the default actions (e.g. scrolling the page)
+///     - Coordinates are relative to the viewport of the page
+///
+use crate::builder::MouseBuilder;
+use crate::common::{
+    simulate_event, ButtonState, CallbackId, CallbackMap, Callbacks, EventCallback, EventInfo,
+    MouseActions, MouseButton, MouseEvent, Region, ScrollDirection, ScrollInversion, ScrollUnit,
+};
+use crate::display::Monitor;
+use crate::error::Error;
+use std::cell::Cell;
+use std::rc::Rc;
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{
+    Document, EventTarget, MouseEventInit, PointerEvent, PointerEventInit, WheelEvent,
+    WheelEventInit,
+};
+
+type EventListener = Closure<dyn FnMut(web_sys::Event)>;
+
+pub struct WasmMouseManager {
+    document: Document,
+    position: Rc<Cell<(i32, i32)>>,
+    buttons: Cell<u16>,
+    callbacks: Callbacks,
+    /// The event listeners that are added to the document, along with their event types
+    listeners: Vec<(&'static str, EventListener)>,
+    scroll_inversion: ScrollInversion,
+}
+
+impl WasmMouseManager {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new() -> Box<dyn MouseActions> {
+        Self::from_builder(&MouseBuilder::new()).expect("failed to create the mouse manager")
+    }
+
+    /// Get a builder to customize the mouse manager
+    pub fn builder() -> MouseBuilder {
+        MouseBuilder::new()
+    }
+
+    /// None of the builder options apply to the browsers
+    /// Get the double-click interval and distance, the browsers do not expose
+    /// the settings of the system so the usual defaults are used
+    pub(crate) fn double_click_settings() -> (Duration, u32) {
+        (Duration::from_millis(500), 4)
+    }
+
+    /// Get the distance the pointer moves with a held button before it is a drag,
+    /// the browsers do not expose it either so the usual default is used
+    pub(crate) fn drag_threshold() -> u32 {
+        4
+    }
+
+    /// Get the viewport of the page as the only monitor, the positions
+    /// of the events are relative to it
+    pub(crate) fn monitors() -> Result<Vec<Monitor>, Error> {
+        let window = web_sys::window().ok_or(Error::CustomError("the window is not available"))?;
+        let size = |value: Result<JsValue, JsValue>| {
+            value.ok().and_then(|value| value.as_f64()).unwrap_or(0.) as u32
+        };
+        Ok(vec![Monitor {
+            id: 0,
+            bounds: Region::new(
+                0,
+                0,
+                size(window.inner_width()),
+                size(window.inner_height()),
+            ),
+            scale_factor: window.device_pixel_ratio(),
+            primary: true,
+        }])
+    }
+
+    /// The browsers do not let the pages read or change the pointer settings
+    pub(crate) fn pointer_speed() -> Result<f64, Error> {
+        Err(Error::NotImplemented)
+    }
+
+    pub(crate) fn set_pointer_speed(_speed: f64) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
+
+    pub(crate) fn pointer_acceleration() -> Result<bool, Error> {
+        Err(Error::NotImplemented)
+    }
+
+    pub(crate) fn set_pointer_acceleration(_enabled: bool) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
+
+    pub(crate) fn from_builder(builder: &MouseBuilder) -> Result<Box<dyn MouseActions>, Error> {
+        let document = web_sys::window()
+            .and_then(|window| window.document())
+            .ok_or(Error::CustomError("the document is not available"))?;
+
+        // Keep track of the real pointer as well, so that the synthetic
+        // events continue from where the user left the pointer
+        let position = Rc::new(Cell::new((0, 0)));
+        let position_clone = position.clone();
+        let tracker = Closure::<dyn FnMut(PointerEvent)>::new(move |event: PointerEvent| {
+            position_clone.set((event.client_x(), event.client_y()));
+        });
+        let _ = document
+            .add_event_listener_with_callback("pointermove", tracker.as_ref().unchecked_ref());
+        tracker.forget();
+
+        Ok(Box::new(WasmMouseManager {
+            document,
+            position,
+            buttons: Cell::new(0),
+            callbacks: Arc::new(Mutex::new(CallbackMap::new())),
+            listeners: vec![],
+            scroll_inversion: builder.scroll_inversion,
+        }))
+    }
+
+    /// The element under the pointer, or the document if there is none
+    fn target(&self) -> EventTarget {
+        let (x, y) = self.position.get();
+        match self.document.element_from_point(x as f32, y as f32) {
+            Some(element) => element.into(),
+            None => self.document.clone().into(),
+        }
+    }
+
+    /// Dispatch a pointer event, followed by its compatibility mouse event
+    fn dispatch(&self, pointer_type: &str, mouse_type: &str, button: i16) -> Result<(), Error> {
+        let (x, y) = self.position.get();
+        let target = self.target();
+
+        let pointer_init = PointerEventInit::new();
+        pointer_init.set_bubbles(true);
+        pointer_init.set_cancelable(true);
+        pointer_init.set_composed(true);
+        pointer_init.set_client_x(x);
+        pointer_init.set_client_y(y);
+        pointer_init.set_button(button);
+        pointer_init.set_buttons(self.buttons.get());
+        pointer_init.set_pointer_id(1);
+        pointer_init.set_pointer_type("mouse");
+        pointer_init.set_is_primary(true);
+        let pointer_event = PointerEvent::new_with_event_init_dict(pointer_type, &pointer_init)
+            .map_err(|_| Error::CustomError("failed to create the pointer event"))?;
+        target
+            .dispatch_event(&pointer_event)
+            .map_err(|_| Error::WriteFailed)?;
+
+        let mouse_init = MouseEventInit::new();
+        mouse_init.set_bubbles(true);
+        mouse_init.set_cancelable(true);
+        mouse_init.set_composed(true);
+        mouse_init.set_client_x(x);
+        mouse_init.set_client_y(y);
+        mouse_init.set_button(button);
+        mouse_init.set_buttons(self.buttons.get());
+        let mouse_event =
+            web_sys::MouseEvent::new_with_mouse_event_init_dict(mouse_type, &mouse_init)
+                .map_err(|_| Error::CustomError("failed to create the mouse event"))?;
+        target
+            .dispatch_event(&mouse_event)
+            .map_err(|_| Error::WriteFailed)?;
+
+        Ok(())
+    }
+
+    fn start_listener(&mut self) -> Result<(), Error> {
+        for event_type in ["pointermove", "pointerdown", "pointerup", "wheel"] {
+            let callbacks = self.callbacks.clone();
+            let listener = EventListener::new(move |event: web_sys::Event| {
+                if let Some(mouse_event) = convert_event(&event) {
+                    // The pointer and wheel events are mouse events as well
+                    let info = EventInfo {
+                        position: event
+                            .dyn_ref::<web_sys::MouseEvent>()
+                            .map(|event| (event.client_x(), event.client_y())),
+                        // The events that are dispatched by the scripts are not trusted
+                        injected: Some(!event.is_trusted()),
+                        ..EventInfo::default()
+                    };
+
+                    // Invoke all given callbacks with the constructed mouse event
+                    callbacks.lock().unwrap().invoke(&mouse_event, &info);
+                }
+            });
+
+            self.document
+                .add_event_listener_with_callback(event_type, listener.as_ref().unchecked_ref())
+                .map_err(|_| Error::CustomError("failed to add the event listener"))?;
+            // The listener is kept until the listening is stopped
+            self.listeners.push((event_type, listener));
+        }
+        Ok(())
+    }
+
+    /// Remove the added event listeners from the document
+    fn remove_listeners(&mut self) {
+        for (event_type, listener) in self.listeners.drain(..) {
+            let _ = self
+                .document
+                .remove_event_listener_with_callback(event_type, listener.as_ref().unchecked_ref());
+        }
+    }
+}
+
+impl Drop for WasmMouseManager {
+    fn drop(&mut self) {
+        // The dropped closures must not be invoked by the document anymore
+        self.remove_listeners();
+    }
+}
+
+impl MouseActions for WasmMouseManager {
+    fn move_to(&self, x: usize, y: usize) -> Result<(), Error> {
+        self.position.set((x as i32, y as i32));
+        self.dispatch("pointermove", "mousemove", 0)
+    }
+
+    fn get_position(&self) -> Result<(i32, i32), Error> {
+        Ok(self.position.get())
+    }
+
+    fn get_pressed_buttons(&self) -> Result<ButtonState, Error> {
+        // Only the synthetic presses are known, the page can not query the real buttons
+        let buttons = self.buttons.get();
+        Ok((0..u16::BITS as i16)
+            .filter_map(button_from_dom)
+            .filter(|button| dom_button(button).is_ok_and(|(_, mask)| buttons & mask != 0))
+            .collect())
+    }
+
+    fn press_button(&self, button: &MouseButton) -> Result<(), Error> {
+        let (button, mask) = dom_button(button)?;
+        self.buttons.set(self.buttons.get() | mask);
+        self.dispatch("pointerdown", "mousedown", button)
+    }
+
+    fn release_button(&self, button: &MouseButton) -> Result<(), Error> {
+        let (button, mask) = dom_button(button)?;
+        self.buttons.set(self.buttons.get() & !mask);
+        self.dispatch("pointerup", "mouseup", button)
+    }
+
+    fn click_button(&self, button: &MouseButton) -> Result<(), Error> {
+        self.press_button(button)?;
+        self.release_button(button)?;
+
+        // The browser does not synthesize clicks for untrusted events
+        let (x, y) = self.position.get();
+        let (dom_button, _) = dom_button(button)?;
+        let init = MouseEventInit::new();
+        init.set_bubbles(true);
+        init.set_cancelable(true);
+        init.set_composed(true);
+        init.set_client_x(x);
+        init.set_client_y(y);
+        init.set_button(dom_button);
+        let click_type = match button {
+            MouseButton::Left => "click",
+            _ => "auxclick",
+        };
+        let event = web_sys::MouseEvent::new_with_mouse_event_init_dict(click_type, &init)
+            .map_err(|_| Error::CustomError("failed to create the mouse event"))?;
+        self.target()
+            .dispatch_event(&event)
+            .map_err(|_| Error::WriteFailed)?;
+        Ok(())
+    }
+
+    fn scroll_wheel(
+        &self,
+        direction: &ScrollDirection,
+        unit: ScrollUnit,
+        distance: u32,
+    ) -> Result<(), Error> {
+        // The DOM uses positive deltas for scrolling down and right
+        let distance = distance as f64;
+        match direction {
+            ScrollDirection::Up => self.scroll_by(0., -distance, unit),
+            ScrollDirection::Down => self.scroll_by(0., distance, unit),
+            ScrollDirection::Left => self.scroll_by(-distance, 0., unit),
+            ScrollDirection::Right => self.scroll_by(distance, 0., unit),
+        }
+    }
+
+    fn scroll_by(&self, dx: f64, dy: f64, unit: ScrollUnit) -> Result<(), Error> {
+        // The wheel events of `scroll_wheel` are inverted here as well
+        let (dx, dy) = self.scroll_inversion.deltas(dx, dy);
+        let (x, y) = self.position.get();
+        let init = WheelEventInit::new();
+        init.set_bubbles(true);
+        init.set_cancelable(true);
+        init.set_composed(true);
+        init.set_client_x(x);
+        init.set_client_y(y);
+        init.set_delta_mode(match unit {
+            ScrollUnit::Line => DOM_DELTA_LINE,
+            ScrollUnit::Pixel => DOM_DELTA_PIXEL,
+        });
+        init.set_delta_x(dx);
+        init.set_delta_y(dy);
+
+        let event = WheelEvent::new_with_event_init_dict("wheel", &init)
+            .map_err(|_| Error::CustomError("failed to create the wheel event"))?;
+        self.target()
+            .dispatch_event(&event)
+            .map_err(|_| Error::WriteFailed)?;
+        Ok(())
+    }
+
+    fn hook_with_info(&mut self, callback: EventCallback) -> Result<CallbackId, Error> {
+        if self.listeners.is_empty() {
+            self.start_listener()?;
+        }
+
+        let id = CallbackId::next();
+        self.callbacks.lock().unwrap().insert(id, callback);
+        Ok(id)
+    }
+
+    fn simulate_event(&self, event: &MouseEvent) -> Result<(), Error> {
+        simulate_event(&self.callbacks, event);
+        Ok(())
+    }
+
+    fn callback_panics(&mut self) -> Result<Receiver<Error>, Error> {
+        Ok(self.callbacks.lock().unwrap().panic_receiver())
+    }
+
+    fn unhook(&mut self, callback_id: CallbackId) -> Result<(), Error> {
+        match self.callbacks.lock().unwrap().remove(&callback_id) {
+            Some(_) => Ok(()),
+            None => Err(Error::UnhookFailed),
+        }
+    }
+
+    fn unhook_all(&mut self) -> Result<(), Error> {
+        self.callbacks.lock().unwrap().clear();
+        Ok(())
+    }
+
+    fn pause_listening(&mut self) -> Result<(), Error> {
+        self.callbacks.lock().unwrap().paused = true;
+        Ok(())
+    }
+
+    fn resume_listening(&mut self) -> Result<(), Error> {
+        self.callbacks.lock().unwrap().paused = false;
+        Ok(())
+    }
+
+    fn stop_listening(&mut self) -> Result<(), Error> {
+        self.callbacks.lock().unwrap().clear();
+        self.remove_listeners();
+        Ok(())
+    }
+}
+
+/// Get the DOM button number and the `buttons` mask of the given button
+fn dom_button(button: &MouseButton) -> Result<(i16, u16), Error> {
+    let number = match button {
+        MouseButton::Left => 0,
+        MouseButton::Middle => 1,
+        MouseButton::Right => 2,
+        MouseButton::Back => 3,
+        MouseButton::Forward => 4,
+        MouseButton::Other(index) => 5 + *index as i16,
+    };
+    let bit = mask_bit(number);
+    if bit >= u16::BITS as i16 {
+        return Err(Error::CustomError(
+            "the button does not fit the DOM buttons mask",
+        ));
+    }
+    Ok((number, 1 << bit))
+}
+
+/// Get the button of the given DOM button number
+/// Get the bit of the given button number in the `buttons` mask, or the other way around
+fn mask_bit(number: i16) -> i16 {
+    // The `buttons` mask swaps the bits of the middle and the right buttons
+    match number {
+        1 => 2,
+        2 => 1,
+        number => number,
+    }
+}
+
+fn button_from_dom(number: i16) -> Option<MouseButton> {
+    match number {
+        0 => Some(MouseButton::Left),
+        1 => Some(MouseButton::Middle),
+        2 => Some(MouseButton::Right),
+        3 => Some(MouseButton::Back),
+        4 => Some(MouseButton::Forward),
+        5.. => u8::try_from(number - 5).ok().map(MouseButton::Other),
+        _ => None,
+    }
+}
+
+/// Construct the library's MouseEvent from the given DOM event
+fn convert_event(event: &web_sys::Event) -> Option<MouseEvent> {
+    if let Some(wheel) = event.dyn_ref::<WheelEvent>() {
+        let (direction, distance) = if wheel.delta_y() < 0. {
+            (ScrollDirection::Up, -wheel.delta_y())
+        } else if wheel.delta_y() > 0. {
+            (ScrollDirection::Down, wheel.delta_y())
+        } else if wheel.delta_x() < 0. {
+            (ScrollDirection::Left, -wheel.delta_x())
+        } else if wheel.delta_x() > 0. {
+            (ScrollDirection::Right, wheel.delta_x())
+        } else {
+            return None;
+        };
+        // The rarely used page mode is reported as lines
+        let unit = if wheel.delta_mode() == WheelEvent::DOM_DELTA_PIXEL {
+            ScrollUnit::Pixel
+        } else {
+            ScrollUnit::Line
+        };
+        return Some(MouseEvent::Scroll {
+            direction,
+            unit,
+            distance,
+        });
+    }
+
+    let pointer = event.dyn_ref::<PointerEvent>()?;
+    let button = button_from_dom(pointer.button());
+
+    match event.type_().as_str() {
+        "pointermove" => {
+            let position = Some((pointer.client_x(), pointer.client_y()));
+            let delta = (pointer.movement_x(), pointer.movement_y());
+
+            let held: ButtonState = (0..u16::BITS as i16)
+                .filter(|bit| pointer.buttons() & (1 << bit) != 0)
+                .filter_map(|bit| button_from_dom(mask_bit(bit)))
+                .collect();
+            let button = held.iter().next();
+            match button {
+                Some(button) => Some(MouseEvent::Drag {
+                    button,
+                    position,
+                    delta,
+                }),
+                None => Some(MouseEvent::Move { position, delta }),
+            }
+        }
+        "pointerdown" => button.map(MouseEvent::Press),
+        "pointerup" => button.map(MouseEvent::Release),
+        _ => None,
+    }
+}
+
+/// DOM definitions
+const DOM_DELTA_PIXEL: u32 = 0x00;
+const DOM_DELTA_LINE: u32 = 0x01;
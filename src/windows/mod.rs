@@ -0,0 +1,1621 @@
+///
+/// This module contains the mouse action functions
+/// for the windows opearting system
+/// Uses the User32 system library
+///
+use crate::builder::MouseBuilder;
+use crate::common::{
+    rewrite_event, simulate_event, Action, ButtonState, CallbackId, CallbackMap, Callbacks, Device,
+    DeviceId, DragTracker, EventCallback, EventInfo, MouseActions, MouseButton, MouseEvent, Region,
+    RewriteCallback, Rewriters, ScrollDirection, ScrollInversion, ScrollUnit,
+};
+use crate::display::Monitor;
+use crate::error::Error;
+use crate::hotplug::{DeviceTracker, DeviceWatcher};
+use crate::pacing::Pacer;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use std::mem::{size_of, zeroed};
+use std::os::raw::{c_char, c_int, c_long, c_short, c_uint, c_ulong, c_ushort, c_void};
+use std::ptr::{null, null_mut};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+pub mod pointer;
+
+thread_local! {
+    /// The state of the manager whose listener runs on this thread, the low level
+    /// hooks get no user data so each hook finds the state of its manager here
+    static HOOK_STATE: RefCell<Option<Arc<HookState>>> = const { RefCell::new(None) };
+    /// The hook that the listener of this thread installed
+    static HOOK: Cell<HHook> = const { Cell::new(null_mut()) };
+    /// The raw input of the listener thread, the low level hook does not
+    /// tell which device produced the input but the raw input does
+    static RAW_INPUT: RefCell<RawInput> = RefCell::new(RawInput {
+        window: null_mut(),
+        latest_device: None,
+        devices: HashMap::new(),
+    });
+    /// The position of the previous move, the hook only reports the absolute
+    /// position so the delta of a move is calculated from it
+    static LAST_POSITION: Cell<Option<(i32, i32)>> = const { Cell::new(None) };
+    /// The hook does not tell the held buttons of a move
+    static DRAGS: RefCell<DragTracker> = RefCell::new(DragTracker::default());
+}
+
+struct RawInput {
+    /// The message-only window that receives the raw input
+    window: HWND,
+    /// The device of the latest raw input, `None` for the injected input
+    latest_device: Option<Arc<Device>>,
+    /// The devices that produced raw input, keyed by their handles
+    devices: HashMap<usize, Arc<Device>>,
+}
+
+impl RawInput {
+    /// Start receiving the raw input of all the mice, even while in the background
+    unsafe fn register(&mut self) {
+        let class = CString::new("STATIC").unwrap();
+        self.window = CreateWindowExA(
+            0,
+            class.as_ptr(),
+            null(),
+            0,
+            0,
+            0,
+            0,
+            0,
+            HWND_MESSAGE,
+            null_mut(),
+            null_mut(),
+            null_mut(),
+        );
+        if self.window.is_null() {
+            return;
+        }
+
+        let device = RawInputDevice {
+            us_usage_page: HID_USAGE_PAGE_GENERIC,
+            us_usage: HID_USAGE_GENERIC_MOUSE,
+            dw_flags: RIDEV_INPUTSINK,
+            hwnd_target: self.window,
+        };
+        RegisterRawInputDevices(&device, 1, size_of::<RawInputDevice>() as c_uint);
+    }
+
+    /// Stop receiving the raw input and destroy the window
+    unsafe fn unregister(&mut self) {
+        if self.window.is_null() {
+            return;
+        }
+
+        let device = RawInputDevice {
+            us_usage_page: HID_USAGE_PAGE_GENERIC,
+            us_usage: HID_USAGE_GENERIC_MOUSE,
+            dw_flags: RIDEV_REMOVE,
+            hwnd_target: null_mut(),
+        };
+        RegisterRawInputDevices(&device, 1, size_of::<RawInputDevice>() as c_uint);
+        DestroyWindow(self.window);
+        self.window = null_mut();
+        self.latest_device = None;
+        self.devices.clear();
+    }
+
+    /// Remember the device of the given WM_INPUT message
+    unsafe fn record(&mut self, message: &Msg) {
+        let mut header: RawInputHeader = zeroed();
+        let mut size = size_of::<RawInputHeader>() as c_uint;
+        let result = GetRawInputData(
+            message.l_param as HRawInput,
+            RID_HEADER,
+            &mut header as *mut RawInputHeader as *mut c_void,
+            &mut size,
+            size_of::<RawInputHeader>() as c_uint,
+        );
+        if result == c_uint::MAX {
+            return;
+        }
+
+        // The input that is injected with SendInput does not have a device
+        self.latest_device = if header.h_device.is_null() {
+            None
+        } else {
+            let device = self
+                .devices
+                .entry(header.h_device as usize)
+                .or_insert_with(|| Arc::new(raw_input_device(header.h_device)));
+            Some(device.clone())
+        };
+    }
+
+    /// Process the pending raw input and get the device of the latest one
+    unsafe fn latest_device(&mut self) -> Option<Arc<Device>> {
+        let mut message: Msg = zeroed();
+        // Only the input messages are processed, so that the sent
+        // messages (e.g. the next hook call) are not handled recursively
+        while PeekMessageA(
+            &mut message,
+            self.window,
+            WM_INPUT,
+            WM_INPUT,
+            PM_REMOVE | PM_QS_INPUT,
+        ) != 0
+        {
+            self.record(&message);
+            DispatchMessageA(&message);
+        }
+        self.latest_device.clone()
+    }
+}
+
+pub struct WindowsMouseManager {
+    hook_module_handle: bool,
+    listener: Option<JoinHandle<()>>,
+    hook_state: Arc<HookState>,
+    pacer: Pacer,
+    scroll_inversion: ScrollInversion,
+}
+
+/// The state that the listener thread of a manager shares with it
+struct HookState {
+    callbacks: Callbacks,
+    rewriters: Mutex<Rewriters>,
+    scroll_inversion: ScrollInversion,
+    ignore_own_events: AtomicBool,
+    paused: AtomicBool,
+    /// The id of the thread that installs the hook, it is 0 while no listener is running
+    listener_thread: AtomicU32,
+}
+
+impl WindowsMouseManager {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new() -> Box<dyn MouseActions> {
+        Self::from_builder(&MouseBuilder::new()).expect("failed to create the mouse manager")
+    }
+
+    /// Get a builder to customize the mouse manager
+    pub fn builder() -> MouseBuilder {
+        MouseBuilder::new()
+    }
+
+    /// Get the double-click interval and distance of the system
+    pub(crate) fn double_click_settings() -> (Duration, u32) {
+        unsafe {
+            // The second click must be inside a rectangle that is centered on the first one
+            let width = GetSystemMetrics(SM_CXDOUBLECLK).max(0) as u32;
+            let height = GetSystemMetrics(SM_CYDOUBLECLK).max(0) as u32;
+            let interval = Duration::from_millis(GetDoubleClickTime() as u64);
+            (interval, width.max(height) / 2)
+        }
+    }
+
+    /// Get the distance the cursor moves with a held button before it is a drag
+    pub(crate) fn drag_threshold() -> u32 {
+        unsafe {
+            // Unlike the double-click rectangle, these are the distances on either side
+            let x = GetSystemMetrics(SM_CXDRAG).max(0) as u32;
+            let y = GetSystemMetrics(SM_CYDRAG).max(0) as u32;
+            x.max(y)
+        }
+    }
+
+    /// Get the monitors in the order that the system enumerates them
+    pub(crate) fn monitors() -> Result<Vec<Monitor>, Error> {
+        unsafe extern "system" fn monitor_enum_proc(
+            monitor: HMonitor,
+            _hdc: Handle,
+            _rect: *mut Rect,
+            data: LParam,
+        ) -> c_int {
+            let monitors = &mut *(data as *mut Vec<Monitor>);
+            let mut info: MonitorInfo = zeroed();
+            info.cb_size = size_of::<MonitorInfo>() as DWord;
+            if GetMonitorInfoA(monitor, &mut info) != 0 {
+                let rect = info.rc_monitor;
+                monitors.push(Monitor {
+                    id: monitors.len(),
+                    bounds: Region::new(
+                        rect.left as i32,
+                        rect.top as i32,
+                        (rect.right - rect.left) as u32,
+                        (rect.bottom - rect.top) as u32,
+                    ),
+                    scale_factor: monitor_scale_factor(monitor),
+                    primary: info.dw_flags & MONITORINFOF_PRIMARY != 0,
+                });
+            }
+            // Continue the enumeration
+            1
+        }
+
+        let mut monitors: Vec<Monitor> = Vec::new();
+        let result = unsafe {
+            EnumDisplayMonitors(
+                null_mut(),
+                null(),
+                Some(monitor_enum_proc),
+                &mut monitors as *mut Vec<Monitor> as LParam,
+            )
+        };
+        if result == 0 {
+            return Err(Error::CustomError("failed to enumerate the monitors"));
+        }
+        Ok(monitors)
+    }
+
+    /// Get the pointer speed, Windows keeps it from 1 to 20
+    pub(crate) fn pointer_speed() -> Result<f64, Error> {
+        let mut speed: c_int = 0;
+        let result = unsafe {
+            SystemParametersInfoA(
+                SPI_GETMOUSESPEED,
+                0,
+                &mut speed as *mut c_int as *mut c_void,
+                0,
+            )
+        };
+        if result == 0 {
+            return Err(Error::last_os_error("failed to read the pointer speed"));
+        }
+        Ok((speed - 1) as f64 / 19.)
+    }
+
+    /// Set the pointer speed, the change is not saved to the user profile
+    /// so it lasts until the user logs off
+    pub(crate) fn set_pointer_speed(speed: f64) -> Result<(), Error> {
+        // The speed is passed as the value of the pointer itself
+        let speed = 1 + (speed * 19.).round() as usize;
+        let result = unsafe {
+            SystemParametersInfoA(SPI_SETMOUSESPEED, 0, speed as *mut c_void, SPIF_SENDCHANGE)
+        };
+        if result == 0 {
+            return Err(Error::last_os_error("failed to set the pointer speed"));
+        }
+        Ok(())
+    }
+
+    /// Check if the pointer acceleration, "Enhance pointer precision", is enabled
+    pub(crate) fn pointer_acceleration() -> Result<bool, Error> {
+        // The two thresholds and the acceleration, which is zero when disabled
+        let mut params: [c_int; 3] = [0; 3];
+        let result = unsafe {
+            SystemParametersInfoA(SPI_GETMOUSE, 0, params.as_mut_ptr() as *mut c_void, 0)
+        };
+        if result == 0 {
+            return Err(Error::last_os_error(
+                "failed to read the pointer acceleration",
+            ));
+        }
+        Ok(params[2] != 0)
+    }
+
+    /// Enable or disable the pointer acceleration, like the speed it lasts until the user logs off
+    pub(crate) fn set_pointer_acceleration(enabled: bool) -> Result<(), Error> {
+        // These are the defaults of Windows when the acceleration is enabled
+        let mut params: [c_int; 3] = if enabled { [6, 10, 1] } else { [0, 0, 0] };
+        let result = unsafe {
+            SystemParametersInfoA(
+                SPI_SETMOUSE,
+                0,
+                params.as_mut_ptr() as *mut c_void,
+                SPIF_SENDCHANGE,
+            )
+        };
+        if result == 0 {
+            return Err(Error::last_os_error(
+                "failed to set the pointer acceleration",
+            ));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn from_builder(builder: &MouseBuilder) -> Result<Box<dyn MouseActions>, Error> {
+        let mut callbacks = CallbackMap::new();
+        callbacks.set_execution(builder.callback_execution);
+        callbacks.set_move_coalescing(builder.move_coalescing);
+        let hook_state = HookState {
+            callbacks: Arc::new(Mutex::new(callbacks)),
+            rewriters: Mutex::new(Rewriters::new()),
+            scroll_inversion: builder.scroll_inversion,
+            ignore_own_events: AtomicBool::new(false),
+            paused: AtomicBool::new(false),
+            listener_thread: AtomicU32::new(0),
+        };
+        Ok(Box::new(WindowsMouseManager {
+            hook_module_handle: builder.hook_module_handle,
+            listener: None,
+            hook_state: Arc::new(hook_state),
+            pacer: Pacer::new(&builder.pacing),
+            scroll_inversion: builder.scroll_inversion,
+        }))
+    }
+
+    /// Send the given button or wheel event at the current position of the cursor,
+    /// `dx` and `dy` are only read with `MOUSEEVENTF_MOVE` so no position is needed
+    fn send_input(&self, event: WindowsMouseEvent, mouse_data: i32) -> Result<(), Error> {
+        self.send_inputs(&mut [mouse_input(event as DWord, mouse_data, 0, 0)])
+    }
+
+    /// Send the button events at the given position, the position is embedded
+    /// into the events so the move and the button events are injected at once,
+    /// and a concurrent movement of the cursor can not come in between them
+    fn send_inputs_at(
+        &self,
+        x: usize,
+        y: usize,
+        events: &[(WindowsMouseEvent, i32)],
+    ) -> Result<(), Error> {
+        let (dx, dy) = to_absolute(x as c_long, y as c_long);
+        let mut inputs: Vec<Input> = events
+            .iter()
+            .map(|(event, data)| mouse_input(*event as DWord | ABSOLUTE_MOVE, *data, dx, dy))
+            .collect();
+        self.send_inputs(&mut inputs)
+    }
+
+    fn send_inputs(&self, inputs: &mut [Input]) -> Result<(), Error> {
+        self.pacer.wait();
+        unsafe {
+            let result = SendInput(
+                inputs.len() as c_uint,
+                inputs.as_mut_ptr(),
+                size_of::<Input>() as i32,
+            );
+            // SendInput stops at the first input that is blocked by another thread,
+            // e.g. by UIPI, and returns the number of the inputs that it inserted
+            if result != inputs.len() as c_uint {
+                return Err(Error::InputIsBlocked);
+            }
+        }
+        Ok(())
+    }
+
+    fn start_listener(&mut self) -> Result<(), Error> {
+        let hook_module_handle = self.hook_module_handle;
+        let hook_state = self.hook_state.clone();
+        // The thread reports back once its message queue is created
+        let (sender, receiver) = mpsc::channel();
+        let thread = thread::spawn(move || {
+            unsafe extern "system" fn low_level_mouse_handler(
+                code: c_int,
+                param: WParam,
+                lpdata: LParam,
+            ) -> LResult {
+                let state = match HOOK_STATE.with(|state| state.borrow().clone()) {
+                    Some(state) => state,
+                    None => return CallNextHookEx(HOOK.get(), code, param, lpdata),
+                };
+                // Construct the library's MouseEvent
+                let w_param = param as u32;
+
+                let mouse_event = match w_param {
+                    WM_MOUSEMOVE => {
+                        let (x, y) = get_point(lpdata);
+                        let position: (i32, i32) = (
+                            x.try_into().expect("Can't fit i64 into i32"),
+                            y.try_into().expect("Can't fit i64 into i32"),
+                        );
+                        let previous = LAST_POSITION.with(|last| last.replace(Some(position)));
+                        let (previous_x, previous_y) = previous.unwrap_or(position);
+                        Some(MouseEvent::Move {
+                            position: Some(position),
+                            delta: (position.0 - previous_x, position.1 - previous_y),
+                        })
+                    }
+                    WM_LBUTTONDOWN => Some(MouseEvent::Press(MouseButton::Left)),
+                    WM_MBUTTONDOWN => Some(MouseEvent::Press(MouseButton::Middle)),
+                    WM_RBUTTONDOWN => Some(MouseEvent::Press(MouseButton::Right)),
+                    WM_LBUTTONUP => Some(MouseEvent::Release(MouseButton::Left)),
+                    WM_MBUTTONUP => Some(MouseEvent::Release(MouseButton::Middle)),
+                    WM_RBUTTONUP => Some(MouseEvent::Release(MouseButton::Right)),
+                    WM_XBUTTONDOWN => get_x_button(lpdata).map(MouseEvent::Press),
+                    WM_XBUTTONUP => get_x_button(lpdata).map(MouseEvent::Release),
+                    WM_MOUSEWHEEL | WM_MOUSEHWHEEL => {
+                        // The regular wheels send a WHEEL_DELTA for each notch, while the
+                        // high-precision wheels and the touchpads send fractions of it
+                        let delta = get_delta(lpdata) as i16;
+                        let direction = match (w_param == WM_MOUSEWHEEL, delta > 0) {
+                            (true, true) => ScrollDirection::Up,
+                            (true, false) => ScrollDirection::Down,
+                            (false, true) => ScrollDirection::Right,
+                            (false, false) => ScrollDirection::Left,
+                        };
+                        (delta != 0).then(|| MouseEvent::Scroll {
+                            direction,
+                            unit: ScrollUnit::Line,
+                            distance: (delta as f64 / WHEEL_DELTA as f64).abs(),
+                        })
+                    }
+                    _ => None,
+                };
+
+                let mouse_event =
+                    mouse_event.map(|event| DRAGS.with(|drags| drags.borrow_mut().track(event)));
+                let hook = *(lpdata as *const MSLLHookStruct);
+                let is_ignored = hook.dw_extra_info == OWN_INPUT
+                    && state.ignore_own_events.load(Ordering::Relaxed);
+                if let (Some(event), false) = (mouse_event, is_ignored) {
+                    let (x, y) = get_point(lpdata);
+                    let info = EventInfo {
+                        device: RAW_INPUT.with(|raw_input| raw_input.borrow_mut().latest_device()),
+                        position: Some((x as i32, y as i32)),
+                        injected: Some(hook.flags & LLMHF_INJECTED != 0),
+                        // The time is the tick count, which wraps around after 49.7 days
+                        timestamp: Some(Duration::from_millis(hook.time as u64)),
+                        extra_info: Some(hook.dw_extra_info),
+                    };
+
+                    // The replacements pass through the hook again, they are not rewritten twice
+                    if hook.dw_extra_info != REWRITTEN_INPUT {
+                        let rewritten =
+                            rewrite_event(&state.rewriters.lock().unwrap(), event, &info)
+                                .map(|event| state.scroll_inversion.hooked_event(event));
+                        if rewritten != Some(event) {
+                            // Swallow the original event and inject the replacement, if any
+                            if let Some(mut input) =
+                                rewritten.and_then(|event| rewritten_input(&event))
+                            {
+                                SendInput(1, &mut input, size_of::<Input>() as i32);
+                            }
+                            return 1 as LResult;
+                        }
+                    }
+
+                    state.callbacks.lock().unwrap().invoke(&event, &info);
+                }
+
+                CallNextHookEx(HOOK.get(), code, param, lpdata)
+            }
+            unsafe {
+                let module = if hook_module_handle {
+                    GetModuleHandleA(null_mut())
+                } else {
+                    null_mut()
+                };
+                let install_hook = || {
+                    if HOOK.get().is_null() {
+                        HOOK.set(SetWindowsHookExA(
+                            WH_MOUSE_LL,
+                            Some(low_level_mouse_handler),
+                            module,
+                            0,
+                        ));
+                    }
+                };
+                let remove_hook = || {
+                    let hook = HOOK.replace(null_mut());
+                    if !hook.is_null() {
+                        UnhookWindowsHookEx(hook);
+                    }
+                };
+                HOOK_STATE.with(|state| *state.borrow_mut() = Some(hook_state.clone()));
+
+                // Create the message queue before publishing the thread id, so that
+                // the pause, resume and quit messages can be posted to it
+                let mut message: Msg = zeroed();
+                PeekMessageA(&mut message, null_mut(), 0, 0, PM_NOREMOVE);
+                hook_state
+                    .listener_thread
+                    .store(GetCurrentThreadId() as u32, Ordering::SeqCst);
+                let _ = sender.send(());
+                if !hook_state.paused.load(Ordering::SeqCst) {
+                    install_hook();
+                }
+                RAW_INPUT.with(|raw_input| raw_input.borrow_mut().register());
+
+                while GetMessageA(&mut message, null_mut(), 0, 0) > 0 {
+                    match message.message {
+                        WM_INPUT => {
+                            RAW_INPUT.with(|raw_input| raw_input.borrow_mut().record(&message));
+                        }
+                        WM_PAUSE_LISTENING => remove_hook(),
+                        WM_RESUME_LISTENING => install_hook(),
+                        _ => {}
+                    }
+                    DispatchMessageA(&message);
+                }
+
+                // The loop ends with the WM_QUIT of `stop_listener`
+                remove_hook();
+                RAW_INPUT.with(|raw_input| raw_input.borrow_mut().unregister());
+                HOOK_STATE.with(|state| state.borrow_mut().take());
+                hook_state.listener_thread.store(0, Ordering::SeqCst);
+            }
+        });
+
+        if receiver.recv().is_err() {
+            return Err(Error::CustomError("failed to start the listener thread"));
+        }
+        self.listener = Some(thread);
+        Ok(())
+    }
+
+    /// Quit the message loop of the listener thread and wait for it to remove the hook
+    fn stop_listener(&mut self) -> Result<(), Error> {
+        if let Some(thread) = self.listener.take() {
+            let thread_id = self.hook_state.listener_thread.load(Ordering::SeqCst);
+            unsafe {
+                if PostThreadMessageA(thread_id as DWord, WM_QUIT, 0, null_mut()) == 0 {
+                    return Err(Error::last_os_error("failed to notify the listener thread"));
+                }
+            }
+            let _ = thread.join();
+        }
+        Ok(())
+    }
+
+    /// Install or remove the hook on the listener thread, the callbacks stay intact
+    fn set_paused(&self, paused: bool) -> Result<(), Error> {
+        self.hook_state.paused.store(paused, Ordering::SeqCst);
+        let thread_id = self.hook_state.listener_thread.load(Ordering::SeqCst);
+        // The listener thread checks the flag before installing the hook
+        if thread_id == 0 {
+            return Ok(());
+        }
+        let message = if paused {
+            WM_PAUSE_LISTENING
+        } else {
+            WM_RESUME_LISTENING
+        };
+        unsafe {
+            if PostThreadMessageA(thread_id as DWord, message, 0, null_mut()) == 0 {
+                return Err(Error::last_os_error("failed to notify the listener thread"));
+            }
+        }
+        Ok(())
+    }
+
+    // Return the mouse position (c_long, c_long), but it does not directly
+    // comply with mouce interface, so we first fetch the positions here
+    // then try to convert it to (i32, i32) within the trait implementation
+    fn set_cursor_pos(&self, x: c_int, y: c_int) -> Result<(), Error> {
+        self.pacer.wait();
+        unsafe {
+            let result = SetCursorPos(x, y);
+            if result == 0 {
+                return Err(Error::last_os_error("failed to set the cursor position"));
+            }
+        }
+        Ok(())
+    }
+
+    fn get_position_raw(&self) -> Result<(c_long, c_long), Error> {
+        let mut out = Point { x: 0, y: 0 };
+        unsafe {
+            let result = GetCursorPos(&mut out);
+            if result == 0 {
+                return Err(Error::last_os_error("failed to get the cursor position"));
+            }
+        }
+        return Ok((out.x, out.y));
+    }
+}
+
+impl Drop for WindowsMouseManager {
+    fn drop(&mut self) {
+        // Remove the procedure installed in the hook chain
+        let _ = self.stop_listener();
+    }
+}
+
+impl MouseActions for WindowsMouseManager {
+    fn move_to(&self, x: usize, y: usize) -> Result<(), Error> {
+        self.set_cursor_pos(x as c_int, y as c_int)
+    }
+
+    fn move_relative(&self, x_offset: i32, y_offset: i32) -> Result<(), Error> {
+        // The target is not passed through the usize of move_to,
+        // the monitors left of or above the primary monitor are negative
+        let position = self.get_position()?;
+        let (x, y) = relative_position(position, (x_offset, y_offset));
+        self.set_cursor_pos(x, y)
+    }
+
+    fn move_to_monitor(&self, monitor_id: usize, x: u32, y: u32) -> Result<(), Error> {
+        // The monitors left of or above the primary monitor have negative coordinates
+        let (x, y) = crate::display::monitor(monitor_id)?.to_global(x, y)?;
+        self.set_cursor_pos(x, y)
+    }
+
+    fn get_position(&self) -> Result<(i32, i32), Error> {
+        match self.get_position_raw() {
+            Ok((x, y)) => Ok((
+                x.try_into().expect("Can't fit i64 into i32"),
+                y.try_into().expect("Can't fit i64 into i32"),
+            )),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn get_pressed_buttons(&self) -> Result<ButtonState, Error> {
+        // GetAsyncKeyState reports the physical buttons, so the left
+        // and right buttons have to be swapped for left-handed setups
+        let (left, right) = if unsafe { GetSystemMetrics(SM_SWAPBUTTON) != 0 } {
+            (VK_RBUTTON, VK_LBUTTON)
+        } else {
+            (VK_LBUTTON, VK_RBUTTON)
+        };
+
+        Ok([
+            (MouseButton::Left, left),
+            (MouseButton::Middle, VK_MBUTTON),
+            (MouseButton::Right, right),
+            (MouseButton::Back, VK_XBUTTON1),
+            (MouseButton::Forward, VK_XBUTTON2),
+        ]
+        .into_iter()
+        // The most significant bit is set if the button is down
+        .filter(|(_, virtual_key)| unsafe { GetAsyncKeyState(*virtual_key) } < 0)
+        .map(|(button, _)| button)
+        .collect())
+    }
+
+    fn press_button(&self, button: &MouseButton) -> Result<(), Error> {
+        let (event, data) = press_event(button)?;
+        self.send_input(event, data)
+    }
+
+    fn release_button(&self, button: &MouseButton) -> Result<(), Error> {
+        let (event, data) = release_event(button)?;
+        self.send_input(event, data)
+    }
+
+    fn click_button(&self, button: &MouseButton) -> Result<(), Error> {
+        self.click_n(button, 1)
+    }
+
+    fn click_n(&self, button: &MouseButton, count: u32) -> Result<(), Error> {
+        // All the clicks are injected by a single call, so the clicks of the
+        // other injectors can not come in between them
+        let (press, press_data) = press_event(button)?;
+        let (release, release_data) = release_event(button)?;
+        let mut inputs = vec![];
+        for _ in 0..count {
+            inputs.push(mouse_input(press as DWord, press_data, 0, 0));
+            inputs.push(mouse_input(release as DWord, release_data, 0, 0));
+        }
+        if inputs.is_empty() {
+            return Ok(());
+        }
+        self.send_inputs(&mut inputs)
+    }
+
+    fn press_at(&self, x: usize, y: usize, button: &MouseButton) -> Result<(), Error> {
+        self.send_inputs_at(x, y, &[press_event(button)?])
+    }
+
+    fn release_at(&self, x: usize, y: usize, button: &MouseButton) -> Result<(), Error> {
+        self.send_inputs_at(x, y, &[release_event(button)?])
+    }
+
+    fn click_at(&self, x: usize, y: usize, button: &MouseButton) -> Result<(), Error> {
+        self.send_inputs_at(x, y, &[press_event(button)?, release_event(button)?])
+    }
+
+    fn scroll_wheel(
+        &self,
+        direction: &ScrollDirection,
+        unit: ScrollUnit,
+        distance: u32,
+    ) -> Result<(), Error> {
+        let direction = self.scroll_inversion.direction(direction);
+        let (event, scroll_amount) = scroll_event(&direction, distance as f64, unit);
+        self.send_input(event, scroll_amount)
+    }
+
+    fn scroll_by(&self, dx: f64, dy: f64, unit: ScrollUnit) -> Result<(), Error> {
+        let (dx, dy) = self.scroll_inversion.deltas(dx, dy);
+        // A positive wheel amount scrolls up, but a positive horizontal one scrolls right
+        let (vertical, horizontal) = (-wheel_amount(dy, unit), wheel_amount(dx, unit));
+        // The axes that do not scroll are left out, so no empty wheel messages are sent
+        let mut inputs: Vec<Input> = [
+            (WindowsMouseEvent::Wheel, vertical),
+            (WindowsMouseEvent::HWheel, horizontal),
+        ]
+        .into_iter()
+        .filter(|(_, amount)| *amount != 0)
+        .map(|(event, amount)| mouse_input(event as DWord, amount, 0, 0))
+        .collect();
+        if inputs.is_empty() {
+            return Ok(());
+        }
+        self.send_inputs(&mut inputs)
+    }
+
+    fn send_batch(&self, actions: &[Action]) -> Result<(), Error> {
+        let input =
+            |(event, data): (WindowsMouseEvent, i32)| mouse_input(event as DWord, data, 0, 0);
+
+        // The relative moves are injected as absolute ones like `move_relative`
+        // does, so the pointer acceleration does not apply to them
+        let mut position = None;
+        let mut inputs = vec![];
+        for action in actions {
+            match action {
+                Action::MoveTo(x, y) => position = Some((*x as c_long, *y as c_long)),
+                Action::MoveRelative(x, y) => {
+                    let (current_x, current_y) = match position {
+                        Some(position) => position,
+                        None => self.get_position_raw()?,
+                    };
+                    position = Some((current_x + *x as c_long, current_y + *y as c_long));
+                }
+                Action::Press(button) => inputs.push(input(press_event(button)?)),
+                Action::Release(button) => inputs.push(input(release_event(button)?)),
+                Action::Click(button) => {
+                    inputs.push(input(press_event(button)?));
+                    inputs.push(input(release_event(button)?));
+                }
+                Action::Scroll(direction, unit, distance) => {
+                    let direction = self.scroll_inversion.direction(direction);
+                    inputs.push(input(scroll_event(&direction, *distance as f64, *unit)))
+                }
+            }
+            if let (Action::MoveTo(..) | Action::MoveRelative(..), Some((x, y))) =
+                (action, position)
+            {
+                let (dx, dy) = to_absolute(x, y);
+                inputs.push(mouse_input(ABSOLUTE_MOVE, 0, dx, dy));
+            }
+        }
+
+        if inputs.is_empty() {
+            return Ok(());
+        }
+        self.send_inputs(&mut inputs)
+    }
+
+    fn hide_cursor(&self) -> Result<(), Error> {
+        // ShowCursor counts the calls, the cursor is hidden while the count is negative
+        unsafe { while ShowCursor(0) >= 0 {} }
+        Ok(())
+    }
+
+    fn show_cursor(&self) -> Result<(), Error> {
+        unsafe {
+            let mut count = ShowCursor(1);
+            if count > 0 {
+                // The cursor was not hidden, keep the count as it was
+                ShowCursor(0);
+            }
+            while count < 0 {
+                count = ShowCursor(1);
+            }
+        }
+        Ok(())
+    }
+
+    fn clip_cursor(&self, region: Option<Region>) -> Result<(), Error> {
+        let edge = |start: i32, length: u32| (start as i64 + length as i64).min(i32::MAX as i64);
+        let rect = match region {
+            Some(region) if region.is_empty() => {
+                return Err(Error::CustomError("the region is empty"));
+            }
+            // The right and the bottom edges are outside of the rectangle
+            Some(region) => Some(Rect {
+                left: region.x as c_long,
+                top: region.y as c_long,
+                right: edge(region.x, region.width) as c_long,
+                bottom: edge(region.y, region.height) as c_long,
+            }),
+            None => None,
+        };
+        let rect_ptr = rect.as_ref().map_or(null(), |rect| rect as *const Rect);
+        if unsafe { ClipCursor(rect_ptr) } == 0 {
+            return Err(Error::last_os_error("failed to clip the cursor"));
+        }
+        Ok(())
+    }
+
+    fn hook_with_info(&mut self, callback: EventCallback) -> Result<CallbackId, Error> {
+        if self.listener.is_none() {
+            self.start_listener()?;
+        }
+
+        let id = CallbackId::next();
+        self.hook_state
+            .callbacks
+            .lock()
+            .unwrap()
+            .insert(id, callback);
+        Ok(id)
+    }
+
+    fn hook_rewrite(&mut self, callback: RewriteCallback) -> Result<CallbackId, Error> {
+        if self.listener.is_none() {
+            self.start_listener()?;
+        }
+
+        let id = CallbackId::next();
+        self.hook_state
+            .rewriters
+            .lock()
+            .unwrap()
+            .insert(id, callback);
+        Ok(id)
+    }
+
+    fn set_ignore_own_events(&mut self, ignore: bool) -> Result<(), Error> {
+        self.hook_state
+            .ignore_own_events
+            .store(ignore, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn pause_listening(&mut self) -> Result<(), Error> {
+        self.set_paused(true)
+    }
+
+    fn resume_listening(&mut self) -> Result<(), Error> {
+        self.set_paused(false)
+    }
+
+    fn stop_listening(&mut self) -> Result<(), Error> {
+        self.unhook_all()?;
+        self.stop_listener()
+    }
+
+    fn simulate_event(&self, event: &MouseEvent) -> Result<(), Error> {
+        // The listener does not invoke the callbacks while it is paused
+        if self.hook_state.paused.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        simulate_event(&self.hook_state.callbacks, event);
+        Ok(())
+    }
+
+    fn callback_panics(&mut self) -> Result<Receiver<Error>, Error> {
+        Ok(self.hook_state.callbacks.lock().unwrap().panic_receiver())
+    }
+
+    fn unhook(&mut self, callback_id: CallbackId) -> Result<(), Error> {
+        if self
+            .hook_state
+            .rewriters
+            .lock()
+            .unwrap()
+            .remove(&callback_id)
+            .is_some()
+        {
+            return Ok(());
+        }
+        match self
+            .hook_state
+            .callbacks
+            .lock()
+            .unwrap()
+            .remove(&callback_id)
+        {
+            Some(_) => Ok(()),
+            None => Err(Error::UnhookFailed),
+        }
+    }
+
+    fn unhook_all(&mut self) -> Result<(), Error> {
+        self.hook_state.rewriters.lock().unwrap().clear();
+        self.hook_state.callbacks.lock().unwrap().clear();
+        Ok(())
+    }
+}
+
+/// Get the number of physical pixels per unit of the cursor coordinates on the given monitor
+unsafe fn monitor_scale_factor(monitor: HMonitor) -> f64 {
+    // The coordinates of the DPI aware processes are not scaled
+    if IsProcessDPIAware() != 0 {
+        return 1.;
+    }
+    let mut dpi_x = 0;
+    let mut dpi_y = 0;
+    if GetDpiForMonitor(monitor, MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y) != 0 {
+        return 1.;
+    }
+    dpi_x as f64 / USER_DEFAULT_SCREEN_DPI
+}
+
+/// Get the event and the mouse data that press the given button,
+/// the X buttons are told apart by the mouse data
+fn press_event(button: &MouseButton) -> Result<(WindowsMouseEvent, i32), Error> {
+    match button {
+        MouseButton::Left => Ok((WindowsMouseEvent::LeftDown, 0)),
+        MouseButton::Middle => Ok((WindowsMouseEvent::MiddleDown, 0)),
+        MouseButton::Right => Ok((WindowsMouseEvent::RightDown, 0)),
+        MouseButton::Back => Ok((WindowsMouseEvent::XDown, XBUTTON1)),
+        MouseButton::Forward => Ok((WindowsMouseEvent::XDown, XBUTTON2)),
+        // Windows only knows about the two X buttons
+        MouseButton::Other(_) => Err(Error::NotImplemented),
+    }
+}
+
+/// Get the event and the mouse data that release the given button
+fn release_event(button: &MouseButton) -> Result<(WindowsMouseEvent, i32), Error> {
+    match button {
+        MouseButton::Left => Ok((WindowsMouseEvent::LeftUp, 0)),
+        MouseButton::Middle => Ok((WindowsMouseEvent::MiddleUp, 0)),
+        MouseButton::Right => Ok((WindowsMouseEvent::RightUp, 0)),
+        MouseButton::Back => Ok((WindowsMouseEvent::XUp, XBUTTON1)),
+        MouseButton::Forward => Ok((WindowsMouseEvent::XUp, XBUTTON2)),
+        MouseButton::Other(_) => Err(Error::NotImplemented),
+    }
+}
+
+/// Get the event and the wheel amount that scroll towards the given direction
+fn scroll_event(
+    direction: &ScrollDirection,
+    distance: f64,
+    unit: ScrollUnit,
+) -> (WindowsMouseEvent, i32) {
+    let amount = wheel_amount(distance, unit);
+    match direction {
+        ScrollDirection::Up => (WindowsMouseEvent::Wheel, amount),
+        ScrollDirection::Down => (WindowsMouseEvent::Wheel, -amount),
+        ScrollDirection::Right => (WindowsMouseEvent::HWheel, amount),
+        ScrollDirection::Left => (WindowsMouseEvent::HWheel, -amount),
+    }
+}
+
+/// Get the input that injects the given rewritten event, `None` if
+/// the event can not be injected (e.g. the `Other` buttons)
+fn rewritten_input(event: &MouseEvent) -> Option<Input> {
+    let mut input = match event {
+        MouseEvent::Move { position, delta }
+        | MouseEvent::Drag {
+            position, delta, ..
+        } => match position {
+            Some((x, y)) => {
+                let (dx, dy) = to_absolute(*x as c_long, *y as c_long);
+                mouse_input(ABSOLUTE_MOVE, 0, dx, dy)
+            }
+            None => {
+                let (dx, dy) = (delta.0 as c_long, delta.1 as c_long);
+                mouse_input(WindowsMouseEvent::Move as DWord, 0, dx, dy)
+            }
+        },
+        MouseEvent::Press(button) | MouseEvent::DoubleClick { button, .. } => {
+            let (event, data) = press_event(button).ok()?;
+            mouse_input(event as DWord, data, 0, 0)
+        }
+        MouseEvent::Release(button) => {
+            let (event, data) = release_event(button).ok()?;
+            mouse_input(event as DWord, data, 0, 0)
+        }
+        MouseEvent::Scroll {
+            direction,
+            unit,
+            distance,
+        } => {
+            let (event, amount) = scroll_event(direction, *distance, *unit);
+            mouse_input(event as DWord, amount, 0, 0)
+        }
+        MouseEvent::ListenerInterrupted => return None,
+    };
+    input.mi.dw_extra_info = REWRITTEN_INPUT as *mut c_ulong;
+    Some(input)
+}
+
+fn mouse_input(dw_flags: DWord, mouse_data: i32, dx: c_long, dy: c_long) -> Input {
+    Input {
+        r#type: INPUT_MOUSE,
+        mi: MouseInput {
+            dx,
+            dy,
+            mouse_data,
+            dw_flags,
+            time: 0,
+            dw_extra_info: OWN_INPUT as *mut c_ulong,
+        },
+    }
+}
+
+/// Convert the given scroll distance to the wheel amount, the applications that
+/// support smooth scrolling accept the fractions of WHEEL_DELTA so they are kept
+fn wheel_amount(distance: f64, unit: ScrollUnit) -> i32 {
+    let amount = match unit {
+        ScrollUnit::Line => distance * WHEEL_DELTA as f64,
+        ScrollUnit::Pixel => distance * WHEEL_DELTA as f64 / PIXELS_PER_WHEEL_DELTA,
+    };
+    // Do not round the small distances down to nothing
+    let amount = if amount.abs() < 1. && amount != 0. {
+        amount.signum()
+    } else {
+        amount.round()
+    };
+    amount.clamp(i32::MIN as f64, i32::MAX as f64) as i32
+}
+
+/// Convert the given coordinates of the virtual desktop to the normalized absolute
+/// coordinates (0..=65535) that are expected by SendInput with `ABSOLUTE_MOVE`
+fn to_absolute(x: c_long, y: c_long) -> (c_long, c_long) {
+    let bounds = screen_metrics();
+    let (width, height) = (bounds.width.max(2) as i64, bounds.height.max(2) as i64);
+    (
+        ((x as i64 - bounds.x as i64) * 65535 / (width - 1)) as c_long,
+        ((y as i64 - bounds.y as i64) * 65535 / (height - 1)) as c_long,
+    )
+}
+
+/// Get the bounds of the virtual desktop that spans all the monitors
+pub(crate) fn virtual_screen_bounds() -> Result<Region, Error> {
+    let bounds = screen_metrics();
+    if bounds.is_empty() {
+        return Err(Error::last_os_error(
+            "failed to get the bounds of the virtual screen",
+        ));
+    }
+    Ok(bounds)
+}
+
+fn screen_metrics() -> Region {
+    unsafe {
+        Region::new(
+            GetSystemMetrics(SM_XVIRTUALSCREEN),
+            GetSystemMetrics(SM_YVIRTUALSCREEN),
+            GetSystemMetrics(SM_CXVIRTUALSCREEN).max(0) as u32,
+            GetSystemMetrics(SM_CYVIRTUALSCREEN).max(0) as u32,
+        )
+    }
+}
+
+/// Get the position at the given offsets from the given position
+fn relative_position(position: (i32, i32), offset: (i32, i32)) -> (i32, i32) {
+    (
+        position.0.saturating_add(offset.0),
+        position.1.saturating_add(offset.1),
+    )
+}
+
+unsafe fn get_point(lpdata: LParam) -> (c_long, c_long) {
+    let mouse = *(lpdata as *const MSLLHookStruct);
+    (mouse.pt.x, mouse.pt.y)
+}
+
+unsafe fn get_delta(lpdata: LParam) -> Word {
+    let mouse = *(lpdata as *const MSLLHookStruct);
+    ((mouse.mouse_data >> 16) & 0xffff) as Word
+}
+
+/// Get the identity of the raw input device with the given handle
+unsafe fn raw_input_device(handle: Handle) -> Device {
+    let mut length: c_uint = 0;
+    GetRawInputDeviceInfoA(handle, RIDI_DEVICENAME, null_mut(), &mut length);
+
+    // The name is the device interface path, e.g. `\\?\HID#VID_046D&PID_C077#...`
+    let mut name = vec![0u8; length as usize];
+    let name = if length > 0
+        && GetRawInputDeviceInfoA(
+            handle,
+            RIDI_DEVICENAME,
+            name.as_mut_ptr() as *mut c_void,
+            &mut length,
+        ) != c_uint::MAX
+    {
+        CStr::from_bytes_until_nul(&name)
+            .ok()
+            .map(|name| name.to_string_lossy().into_owned())
+    } else {
+        None
+    };
+
+    Device {
+        id: DeviceId(handle as u64),
+        name,
+    }
+}
+
+/// Watch the mice that are connected or removed, see `DeviceWatcher`
+///
+/// The raw input notifications of the devices are not used, since only
+/// one window of a process can receive the raw input of the mice and the
+/// listener uses it already
+pub(crate) fn watch_devices(mut tracker: DeviceTracker) -> Result<DeviceWatcher, Error> {
+    let (tx, rx) = mpsc::channel();
+    let thread = thread::spawn(move || unsafe {
+        let class = CString::new("mouce_device_watcher").unwrap();
+        let instance = GetModuleHandleA(null_mut());
+        let window_class = WndClassA {
+            style: 0,
+            lpfn_wnd_proc: device_change_proc,
+            cb_cls_extra: 0,
+            cb_wnd_extra: 0,
+            h_instance: instance,
+            h_icon: null_mut(),
+            h_cursor: null_mut(),
+            hbr_background: null_mut(),
+            lpsz_menu_name: null(),
+            lpsz_class_name: class.as_ptr(),
+        };
+        // The class is already registered if a watcher was started before
+        RegisterClassA(&window_class);
+
+        let window = CreateWindowExA(
+            0,
+            class.as_ptr(),
+            null(),
+            0,
+            0,
+            0,
+            0,
+            0,
+            HWND_MESSAGE,
+            null_mut(),
+            instance,
+            null_mut(),
+        );
+        if window.is_null() {
+            tx.send(Err(Error::CustomError(
+                "failed to create the window of the device watcher",
+            )))
+            .unwrap();
+            return;
+        }
+
+        let filter = DevBroadcastDeviceInterface {
+            dbcc_size: size_of::<DevBroadcastDeviceInterface>() as DWord,
+            dbcc_devicetype: DBT_DEVTYP_DEVICEINTERFACE,
+            dbcc_reserved: 0,
+            dbcc_classguid: GUID_DEVINTERFACE_MOUSE,
+            dbcc_name: [0],
+        };
+        let notification = RegisterDeviceNotificationA(
+            window,
+            &filter as *const DevBroadcastDeviceInterface as *mut c_void,
+            DEVICE_NOTIFY_WINDOW_HANDLE,
+        );
+        if notification.is_null() {
+            DestroyWindow(window);
+            tx.send(Err(Error::CustomError(
+                "failed to register for the device notifications",
+            )))
+            .unwrap();
+            return;
+        }
+        tx.send(Ok(GetCurrentThreadId())).unwrap();
+
+        scan_devices(&mut tracker);
+        let mut message: Msg = zeroed();
+        // The notifications are sent to the window procedure while waiting for a message
+        while GetMessageA(&mut message, null_mut(), 0, 0) > 0 {
+            if message.message == WM_DEVICES_CHANGED {
+                scan_devices(&mut tracker);
+            }
+        }
+
+        UnregisterDeviceNotification(notification);
+        DestroyWindow(window);
+    });
+
+    match rx.recv() {
+        Ok(Ok(thread_id)) => Ok(DeviceWatcher::new(move || {
+            unsafe { PostThreadMessageA(thread_id, WM_QUIT, 0, null_mut()) };
+            let _ = thread.join();
+        })),
+        Ok(Err(err)) => {
+            let _ = thread.join();
+            Err(err)
+        }
+        Err(_) => Err(Error::CustomError("the device watcher thread died")),
+    }
+}
+
+unsafe extern "system" fn device_change_proc(
+    window: HWND,
+    message: c_uint,
+    w_param: WParam,
+    l_param: LParam,
+) -> LResult {
+    // The devices are scanned on the message loop rather than in the window procedure
+    if message == WM_DEVICECHANGE
+        && (w_param == DBT_DEVICEARRIVAL || w_param == DBT_DEVICEREMOVECOMPLETE)
+    {
+        PostThreadMessageA(GetCurrentThreadId(), WM_DEVICES_CHANGED, 0, null_mut());
+    }
+    DefWindowProcA(window, message, w_param, l_param)
+}
+
+/// Report the changes of the connected mice to the given tracker
+unsafe fn scan_devices(tracker: &mut DeviceTracker) {
+    let size = size_of::<RawInputDeviceList>() as c_uint;
+    let mut count: c_uint = 0;
+    if GetRawInputDeviceList(null_mut(), &mut count, size) == c_uint::MAX {
+        return;
+    }
+
+    let mut list = vec![
+        RawInputDeviceList {
+            h_device: null_mut(),
+            dw_type: 0,
+        };
+        count as usize
+    ];
+    let count = GetRawInputDeviceList(list.as_mut_ptr(), &mut count, size);
+    // The list can grow between the two calls, it is scanned again on the next notification
+    if count == c_uint::MAX {
+        return;
+    }
+    list.truncate(count as usize);
+
+    let connected = list
+        .into_iter()
+        .filter(|device| device.dw_type == RIM_TYPEMOUSE)
+        .map(|device| (DeviceId(device.h_device as u64), device.h_device))
+        .collect();
+    tracker.update(connected, |_, handle| raw_input_device(handle));
+}
+
+/// Get the X button of a WM_XBUTTONDOWN or a WM_XBUTTONUP message
+unsafe fn get_x_button(lpdata: LParam) -> Option<MouseButton> {
+    match get_delta(lpdata) as i32 {
+        XBUTTON1 => Some(MouseButton::Back),
+        XBUTTON2 => Some(MouseButton::Forward),
+        _ => None,
+    }
+}
+
+/// User32 type definitions
+type LParam = *mut c_long;
+type LPInput = *mut Input;
+type DWord = c_ulong;
+type LResult = *mut c_int;
+type WParam = usize;
+type HHook = *mut Hhook__;
+type HInstance = *mut HInstance__;
+type HookProc =
+    Option<unsafe extern "system" fn(code: c_int, w_param: WParam, l_param: LParam) -> LResult>;
+type LPMsg = *mut Msg;
+type HWND = *mut HWND__;
+type Word = c_ushort;
+type Handle = *mut c_void;
+type HRawInput = Handle;
+type HMonitor = Handle;
+type WndProc = unsafe extern "system" fn(HWND, c_uint, WParam, LParam) -> LResult;
+type MonitorEnumProc =
+    Option<unsafe extern "system" fn(HMonitor, Handle, *mut Rect, LParam) -> c_int>;
+const HWND_MESSAGE: HWND = -3isize as HWND;
+const WM_QUIT: c_uint = 0x0012;
+const WM_INPUT: c_uint = 0x00FF;
+// WM_APP based messages for the listener thread
+const WM_PAUSE_LISTENING: c_uint = 0x8000;
+const WM_RESUME_LISTENING: c_uint = 0x8001;
+/// Posted to the thread of the device watcher when a mouse is connected or removed
+const WM_DEVICES_CHANGED: c_uint = 0x8002;
+const PM_NOREMOVE: c_uint = 0x0000;
+const PM_REMOVE: c_uint = 0x0001;
+// (QS_MOUSE | QS_KEY | QS_RAWINPUT) << 16
+const PM_QS_INPUT: c_uint = 0x0407 << 16;
+const RIDEV_REMOVE: DWord = 0x0001;
+const RIDEV_INPUTSINK: DWord = 0x0100;
+const RID_HEADER: c_uint = 0x10000005;
+const RIDI_DEVICENAME: c_uint = 0x20000007;
+const RIM_TYPEMOUSE: DWord = 0;
+const WM_DEVICECHANGE: c_uint = 0x0219;
+const DBT_DEVICEARRIVAL: WParam = 0x8000;
+const DBT_DEVICEREMOVECOMPLETE: WParam = 0x8004;
+const DBT_DEVTYP_DEVICEINTERFACE: DWord = 0x0005;
+const DEVICE_NOTIFY_WINDOW_HANDLE: DWord = 0x0000;
+/// The device interface class of the mice, {378DE44C-56EF-11D1-BC8C-00A0C91405DD}
+const GUID_DEVINTERFACE_MOUSE: Guid = Guid {
+    data1: 0x378de44c,
+    data2: 0x56ef,
+    data3: 0x11d1,
+    data4: [0xbc, 0x8c, 0x00, 0xa0, 0xc9, 0x14, 0x05, 0xdd],
+};
+const HID_USAGE_PAGE_GENERIC: c_ushort = 0x01;
+const HID_USAGE_GENERIC_MOUSE: c_ushort = 0x02;
+const WM_MOUSEMOVE: c_uint = 0x0200;
+const WM_LBUTTONDOWN: c_uint = 0x0201;
+const WM_LBUTTONUP: c_uint = 0x0202;
+const WM_RBUTTONDOWN: c_uint = 0x0204;
+const WM_RBUTTONUP: c_uint = 0x0205;
+const WM_MBUTTONDOWN: c_uint = 0x0207;
+const WM_MBUTTONUP: c_uint = 0x0208;
+const WM_XBUTTONDOWN: c_uint = 0x020B;
+const WM_XBUTTONUP: c_uint = 0x020C;
+const WM_MOUSEWHEEL: c_uint = 0x020A;
+const WM_MOUSEHWHEEL: c_uint =  0x020E;
+const WHEEL_DELTA: c_short = 120;
+// A WHEEL_DELTA scrolls 3 lines by default, which is about 100 pixels
+const PIXELS_PER_WHEEL_DELTA: f64 = 100.;
+const WH_MOUSE_LL: c_int = 14;
+// Also set for LLMHF_LOWER_IL_INJECTED
+const LLMHF_INJECTED: DWord = 0x01;
+// The extra info of the input that is injected by the mouse managers
+const OWN_INPUT: usize = 0x6d6f7563;
+// The extra info of the input that is injected in place of a rewritten event
+const REWRITTEN_INPUT: usize = 0x6d6f7564;
+const SM_XVIRTUALSCREEN: c_int = 76;
+const SM_YVIRTUALSCREEN: c_int = 77;
+const SM_CXVIRTUALSCREEN: c_int = 78;
+const SM_CYVIRTUALSCREEN: c_int = 79;
+const SM_CXDOUBLECLK: c_int = 36;
+const SM_CYDOUBLECLK: c_int = 37;
+const SM_CXDRAG: c_int = 68;
+const SM_CYDRAG: c_int = 69;
+const SM_SWAPBUTTON: c_int = 23;
+const MONITORINFOF_PRIMARY: DWord = 0x0001;
+const MDT_EFFECTIVE_DPI: c_int = 0;
+// The DPI of a monitor at the 100% scale
+const USER_DEFAULT_SCREEN_DPI: f64 = 96.;
+const SPI_GETMOUSE: c_uint = 0x0003;
+const SPI_SETMOUSE: c_uint = 0x0004;
+const SPI_GETMOUSESPEED: c_uint = 0x0070;
+const SPI_SETMOUSESPEED: c_uint = 0x0071;
+const SPIF_SENDCHANGE: c_uint = 0x0002;
+const VK_LBUTTON: c_int = 0x01;
+const VK_RBUTTON: c_int = 0x02;
+const VK_MBUTTON: c_int = 0x04;
+const VK_XBUTTON1: c_int = 0x05;
+const VK_XBUTTON2: c_int = 0x06;
+const XBUTTON1: i32 = 0x0001;
+const XBUTTON2: i32 = 0x0002;
+enum Hhook__ {}
+enum HInstance__ {}
+enum HWND__ {}
+const INPUT_MOUSE: DWord = 0;
+#[repr(C)]
+struct MouseInput {
+    dx: c_long,
+    dy: c_long,
+    mouse_data: c_int,
+    dw_flags: DWord,
+    time: DWord,
+    dw_extra_info: *mut c_ulong,
+}
+#[repr(C)]
+struct Input {
+    r#type: DWord,
+    mi: MouseInput,
+}
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Point {
+    x: c_long,
+    y: c_long,
+}
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Rect {
+    left: c_long,
+    top: c_long,
+    right: c_long,
+    bottom: c_long,
+}
+#[repr(C)]
+struct MonitorInfo {
+    cb_size: DWord,
+    rc_monitor: Rect,
+    rc_work: Rect,
+    dw_flags: DWord,
+}
+#[repr(C)]
+#[derive(Clone, Copy)]
+enum WindowsMouseEvent {
+    Move = 0x0001,
+    LeftDown = 0x0002,
+    LeftUp = 0x0004,
+    RightDown = 0x0008,
+    RightUp = 0x0010,
+    MiddleDown = 0x0020,
+    MiddleUp = 0x0040,
+    XDown = 0x0080,
+    XUp = 0x0100,
+    Wheel = 0x0800,
+    HWheel = 0x01000,
+    VirtualDesk = 0x4000,
+    Absolute = 0x8000,
+}
+
+/// The flags of an absolute move, the coordinates span the virtual desktop rather than the
+/// primary monitor so the monitors left of or above it can be reached as well
+const ABSOLUTE_MOVE: DWord = WindowsMouseEvent::Move as DWord
+    | WindowsMouseEvent::VirtualDesk as DWord
+    | WindowsMouseEvent::Absolute as DWord;
+
+#[repr(C)]
+struct Msg {
+    hwnd: HWND,
+    message: c_uint,
+    w_param: WParam,
+    l_param: LParam,
+    time: DWord,
+    pt: Point,
+}
+
+#[repr(C)]
+struct RawInputDevice {
+    us_usage_page: c_ushort,
+    us_usage: c_ushort,
+    dw_flags: DWord,
+    hwnd_target: HWND,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct RawInputDeviceList {
+    h_device: Handle,
+    dw_type: DWord,
+}
+
+#[repr(C)]
+struct Guid {
+    data1: u32,
+    data2: c_ushort,
+    data3: c_ushort,
+    data4: [u8; 8],
+}
+
+#[repr(C)]
+struct DevBroadcastDeviceInterface {
+    dbcc_size: DWord,
+    dbcc_devicetype: DWord,
+    dbcc_reserved: DWord,
+    dbcc_classguid: Guid,
+    dbcc_name: [c_char; 1],
+}
+
+#[repr(C)]
+struct WndClassA {
+    style: c_uint,
+    lpfn_wnd_proc: WndProc,
+    cb_cls_extra: c_int,
+    cb_wnd_extra: c_int,
+    h_instance: HInstance,
+    h_icon: Handle,
+    h_cursor: Handle,
+    hbr_background: Handle,
+    lpsz_menu_name: *const c_char,
+    lpsz_class_name: *const c_char,
+}
+
+#[repr(C)]
+struct RawInputHeader {
+    dw_type: DWord,
+    dw_size: DWord,
+    h_device: Handle,
+    w_param: WParam,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct MSLLHookStruct {
+    pt: Point,
+    mouse_data: DWord,
+    flags: DWord,
+    time: DWord,
+    dw_extra_info: usize,
+}
+
+// User32 function definitions
+#[link(name = "user32")]
+extern "system" {
+    fn SetCursorPos(x: c_int, y: c_int) -> c_int;
+    fn GetCursorPos(lp_point: *mut Point) -> c_int;
+    fn SendInput(c_inputs: c_uint, p_inputs: LPInput, cb_size: c_int) -> c_uint;
+    fn GetSystemMetrics(n_index: c_int) -> c_int;
+    fn GetDoubleClickTime() -> c_uint;
+    fn ShowCursor(b_show: c_int) -> c_int;
+    fn ClipCursor(lp_rect: *const Rect) -> c_int;
+    fn EnumDisplayMonitors(
+        hdc: Handle,
+        lprc_clip: *const Rect,
+        lpfn_enum: MonitorEnumProc,
+        dw_data: LParam,
+    ) -> c_int;
+    fn GetMonitorInfoA(h_monitor: HMonitor, lpmi: *mut MonitorInfo) -> c_int;
+    fn IsProcessDPIAware() -> c_int;
+    fn SystemParametersInfoA(
+        ui_action: c_uint,
+        ui_param: c_uint,
+        pv_param: *mut c_void,
+        f_win_ini: c_uint,
+    ) -> c_int;
+    fn GetAsyncKeyState(v_key: c_int) -> c_short;
+    fn SetWindowsHookExA(
+        idHook: c_int,
+        lpfn: HookProc,
+        hmod: HInstance,
+        dwThreadId: DWord,
+    ) -> HHook;
+    fn CallNextHookEx(hhk: HHook, n_code: c_int, w_param: WParam, l_param: LParam) -> LResult;
+    fn GetMessageA(
+        lp_msg: LPMsg,
+        h_wnd: HWND,
+        w_msg_filter_min: c_uint,
+        w_msg_filter_max: c_uint,
+    ) -> c_int;
+    fn PeekMessageA(
+        lp_msg: LPMsg,
+        h_wnd: HWND,
+        w_msg_filter_min: c_uint,
+        w_msg_filter_max: c_uint,
+        w_remove_msg: c_uint,
+    ) -> c_int;
+    fn DispatchMessageA(lp_msg: *const Msg) -> LResult;
+    fn PostThreadMessageA(id_thread: DWord, msg: c_uint, w_param: WParam, l_param: LParam)
+        -> c_int;
+    fn CreateWindowExA(
+        dw_ex_style: DWord,
+        lp_class_name: *const c_char,
+        lp_window_name: *const c_char,
+        dw_style: DWord,
+        x: c_int,
+        y: c_int,
+        n_width: c_int,
+        n_height: c_int,
+        h_wnd_parent: HWND,
+        h_menu: *mut c_void,
+        h_instance: HInstance,
+        lp_param: *mut c_void,
+    ) -> HWND;
+    fn DestroyWindow(h_wnd: HWND) -> c_int;
+    fn RegisterRawInputDevices(
+        p_raw_input_devices: *const RawInputDevice,
+        ui_num_devices: c_uint,
+        cb_size: c_uint,
+    ) -> c_int;
+    fn GetRawInputData(
+        h_raw_input: HRawInput,
+        ui_command: c_uint,
+        p_data: *mut c_void,
+        pcb_size: *mut c_uint,
+        cb_size_header: c_uint,
+    ) -> c_uint;
+    fn GetRawInputDeviceInfoA(
+        h_device: Handle,
+        ui_command: c_uint,
+        p_data: *mut c_void,
+        pcb_size: *mut c_uint,
+    ) -> c_uint;
+    fn GetRawInputDeviceList(
+        p_raw_input_device_list: *mut RawInputDeviceList,
+        pui_num_devices: *mut c_uint,
+        cb_size: c_uint,
+    ) -> c_uint;
+    fn RegisterClassA(lp_wnd_class: *const WndClassA) -> Word;
+    fn DefWindowProcA(h_wnd: HWND, msg: c_uint, w_param: WParam, l_param: LParam) -> LResult;
+    fn RegisterDeviceNotificationA(
+        h_recipient: HWND,
+        notification_filter: *mut c_void,
+        flags: DWord,
+    ) -> Handle;
+    fn UnregisterDeviceNotification(handle: Handle) -> c_int;
+    fn UnhookWindowsHookEx(hhk: HHook) -> bool;
+}
+
+// Shcore function definitions
+#[link(name = "shcore")]
+extern "system" {
+    fn GetDpiForMonitor(
+        hmonitor: HMonitor,
+        dpi_type: c_int,
+        dpi_x: *mut c_uint,
+        dpi_y: *mut c_uint,
+    ) -> c_long;
+}
+
+// Kernel32 function definitions
+#[link(name = "kernel32")]
+extern "system" {
+    fn GetModuleHandleA(lp_module_name: *mut c_char) -> HInstance;
+    fn GetCurrentThreadId() -> DWord;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::relative_position;
+
+    #[test]
+    fn relative_position_negative() {
+        // A monitor left of and above the primary monitor
+        assert_eq!(relative_position((-1920, -100), (-10, -20)), (-1930, -120));
+        assert_eq!(relative_position((5, 5), (-10, -10)), (-5, -5));
+        assert_eq!(relative_position((-5, 0), (10, 0)), (5, 0));
+        assert_eq!(
+            relative_position((i32::MIN + 1, 0), (-10, 0)),
+            (i32::MIN, 0)
+        );
+    }
+}
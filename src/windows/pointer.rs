@@ -0,0 +1,298 @@
+///
+/// This module contains the synthetic pointer functions
+/// for the windows operating system, they inject touch and pen
+/// input rather than mouse input
+///
+/// Synthetic pointers require Windows 10 version 1809 or newer
+///
+use super::{DWord, Point, Rect, HWND};
+use crate::common::{line_point, line_steps};
+use crate::error::Error;
+use std::os::raw::{c_int, c_long, c_void};
+use std::ptr::null_mut;
+use std::thread;
+use std::time::Duration;
+
+/// The kind of the synthetic pointer device
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PointerKind {
+    Touch,
+    Pen,
+}
+
+pub struct SyntheticPointer {
+    device: HSyntheticPointerDevice,
+    kind: PointerKind,
+}
+
+impl SyntheticPointer {
+    /// Create a synthetic pointer device of the given kind
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::windows::pointer::{PointerKind, SyntheticPointer};
+    ///
+    /// let touch = SyntheticPointer::new(PointerKind::Touch).unwrap();
+    /// assert_eq!(touch.tap(500, 500), Ok(()));
+    /// ```
+    pub fn new(kind: PointerKind) -> Result<Self, Error> {
+        let pointer_type = match kind {
+            PointerKind::Touch => PT_TOUCH,
+            PointerKind::Pen => PT_PEN,
+        };
+
+        let device =
+            unsafe { CreateSyntheticPointerDevice(pointer_type, 1, POINTER_FEEDBACK_DEFAULT) };
+        if device.is_null() {
+            return Err(Error::last_os_error(
+                "failed to create the synthetic pointer device",
+            ));
+        }
+
+        Ok(SyntheticPointer { device, kind })
+    }
+
+    /// Put the pointer in contact with the screen at the given coordinates
+    pub fn down(&self, x: i32, y: i32) -> Result<(), Error> {
+        self.inject(
+            x,
+            y,
+            POINTER_FLAG_DOWN | POINTER_FLAG_INRANGE | POINTER_FLAG_INCONTACT,
+        )
+    }
+
+    /// Move the pointer while it is in contact with the screen
+    pub fn move_to(&self, x: i32, y: i32) -> Result<(), Error> {
+        self.inject(
+            x,
+            y,
+            POINTER_FLAG_UPDATE | POINTER_FLAG_INRANGE | POINTER_FLAG_INCONTACT,
+        )
+    }
+
+    /// Lift the pointer from the screen at the given coordinates
+    pub fn up(&self, x: i32, y: i32) -> Result<(), Error> {
+        self.inject(x, y, POINTER_FLAG_UP)
+    }
+
+    /// Hover the pen over the given coordinates without touching the screen
+    ///
+    /// Touch pointers can not hover, so this returns `Error::NotImplemented` for them
+    pub fn hover(&self, x: i32, y: i32) -> Result<(), Error> {
+        if self.kind == PointerKind::Touch {
+            return Err(Error::NotImplemented);
+        }
+        self.inject(x, y, POINTER_FLAG_UPDATE | POINTER_FLAG_INRANGE)
+    }
+
+    /// Tap the screen at the given coordinates
+    pub fn tap(&self, x: i32, y: i32) -> Result<(), Error> {
+        self.down(x, y)?;
+        self.up(x, y)
+    }
+
+    /// Swipe from one point to another within the given duration
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mouce::windows::pointer::{PointerKind, SyntheticPointer};
+    /// use std::time::Duration;
+    ///
+    /// let touch = SyntheticPointer::new(PointerKind::Touch).unwrap();
+    /// touch
+    ///     .swipe((100, 500), (900, 500), Duration::from_millis(300))
+    ///     .unwrap();
+    /// ```
+    pub fn swipe(&self, from: (i32, i32), to: (i32, i32), duration: Duration) -> Result<(), Error> {
+        // Send an update roughly every 10 milliseconds
+        let steps = line_steps(duration, Duration::from_millis(10));
+        let interval = duration / steps;
+
+        self.down(from.0, from.1)?;
+        for step in 1..=steps {
+            thread::sleep(interval);
+            let (x, y) = line_point(from, to, step, steps);
+            self.move_to(x, y)?;
+        }
+        self.up(to.0, to.1)
+    }
+
+    fn inject(&self, x: i32, y: i32, flags: u32) -> Result<(), Error> {
+        let in_contact = flags & POINTER_FLAG_INCONTACT != 0;
+        let pointer_info = PointerInfo {
+            pointer_type: match self.kind {
+                PointerKind::Touch => PT_TOUCH,
+                PointerKind::Pen => PT_PEN,
+            },
+            pointer_id: 0,
+            frame_id: 0,
+            pointer_flags: flags,
+            source_device: null_mut(),
+            hwnd_target: null_mut(),
+            pt_pixel_location: Point {
+                x: x as c_long,
+                y: y as c_long,
+            },
+            pt_himetric_location: Point { x: 0, y: 0 },
+            pt_pixel_location_raw: Point { x: 0, y: 0 },
+            pt_himetric_location_raw: Point { x: 0, y: 0 },
+            dw_time: 0,
+            history_count: 0,
+            input_data: 0,
+            dw_key_states: 0,
+            performance_count: 0,
+            button_change_type: 0,
+        };
+
+        let info = match self.kind {
+            PointerKind::Touch => PointerTypeInfo {
+                r#type: PT_TOUCH,
+                info: PointerTypeInfoUnion {
+                    touch_info: PointerTouchInfo {
+                        pointer_info,
+                        touch_flags: 0,
+                        touch_mask: TOUCH_MASK_CONTACTAREA
+                            | TOUCH_MASK_ORIENTATION
+                            | TOUCH_MASK_PRESSURE,
+                        // A small contact area around the touch point
+                        rc_contact: Rect {
+                            left: x as c_long - 2,
+                            top: y as c_long - 2,
+                            right: x as c_long + 2,
+                            bottom: y as c_long + 2,
+                        },
+                        rc_contact_raw: Rect {
+                            left: 0,
+                            top: 0,
+                            right: 0,
+                            bottom: 0,
+                        },
+                        orientation: 90,
+                        pressure: if in_contact { DEFAULT_PRESSURE } else { 0 },
+                    },
+                },
+            },
+            PointerKind::Pen => PointerTypeInfo {
+                r#type: PT_PEN,
+                info: PointerTypeInfoUnion {
+                    pen_info: PointerPenInfo {
+                        pointer_info,
+                        pen_flags: 0,
+                        pen_mask: PEN_MASK_PRESSURE,
+                        pressure: if in_contact { DEFAULT_PRESSURE } else { 0 },
+                        rotation: 0,
+                        tilt_x: 0,
+                        tilt_y: 0,
+                    },
+                },
+            },
+        };
+
+        unsafe {
+            if InjectSyntheticPointerInput(self.device, &info, 1) == 0 {
+                return Err(Error::InputIsBlocked);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for SyntheticPointer {
+    fn drop(&mut self) {
+        unsafe {
+            DestroySyntheticPointerDevice(self.device);
+        }
+    }
+}
+
+/// User32 pointer type definitions
+type HSyntheticPointerDevice = *mut c_void;
+const PT_TOUCH: u32 = 2;
+const PT_PEN: u32 = 3;
+const POINTER_FEEDBACK_DEFAULT: u32 = 1;
+const POINTER_FLAG_INRANGE: u32 = 0x0000_0002;
+const POINTER_FLAG_INCONTACT: u32 = 0x0000_0004;
+const POINTER_FLAG_DOWN: u32 = 0x0001_0000;
+const POINTER_FLAG_UPDATE: u32 = 0x0002_0000;
+const POINTER_FLAG_UP: u32 = 0x0004_0000;
+const TOUCH_MASK_CONTACTAREA: u32 = 0x0000_0001;
+const TOUCH_MASK_ORIENTATION: u32 = 0x0000_0002;
+const TOUCH_MASK_PRESSURE: u32 = 0x0000_0004;
+const PEN_MASK_PRESSURE: u32 = 0x0000_0001;
+/// Both touch and pen pressures range from 0 to 1024
+const DEFAULT_PRESSURE: u32 = 512;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct PointerInfo {
+    pointer_type: u32,
+    pointer_id: u32,
+    frame_id: u32,
+    pointer_flags: u32,
+    source_device: *mut c_void,
+    hwnd_target: HWND,
+    pt_pixel_location: Point,
+    pt_himetric_location: Point,
+    pt_pixel_location_raw: Point,
+    pt_himetric_location_raw: Point,
+    dw_time: DWord,
+    history_count: u32,
+    input_data: i32,
+    dw_key_states: DWord,
+    performance_count: u64,
+    button_change_type: c_int,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct PointerTouchInfo {
+    pointer_info: PointerInfo,
+    touch_flags: u32,
+    touch_mask: u32,
+    rc_contact: Rect,
+    rc_contact_raw: Rect,
+    orientation: u32,
+    pressure: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct PointerPenInfo {
+    pointer_info: PointerInfo,
+    pen_flags: u32,
+    pen_mask: u32,
+    pressure: u32,
+    rotation: u32,
+    tilt_x: i32,
+    tilt_y: i32,
+}
+
+#[repr(C)]
+union PointerTypeInfoUnion {
+    touch_info: PointerTouchInfo,
+    pen_info: PointerPenInfo,
+}
+
+#[repr(C)]
+struct PointerTypeInfo {
+    r#type: u32,
+    info: PointerTypeInfoUnion,
+}
+
+// User32 function definitions
+#[link(name = "user32")]
+extern "system" {
+    fn CreateSyntheticPointerDevice(
+        pointer_type: u32,
+        max_count: u32,
+        mode: u32,
+    ) -> HSyntheticPointerDevice;
+    fn InjectSyntheticPointerInput(
+        device: HSyntheticPointerDevice,
+        pointer_info: *const PointerTypeInfo,
+        count: u32,
+    ) -> c_int;
+    fn DestroySyntheticPointerDevice(device: HSyntheticPointerDevice);
+}